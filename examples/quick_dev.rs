@@ -18,5 +18,37 @@ async fn main() -> Result<()> {
 	);
 	req_login.await?.print().await?;
 
+	let req_create_task = hc.do_post(
+		"/api/rpc",
+		json!({
+			"id": 1,
+			"method": "create_task",
+			"params": {
+				"data": {
+					"title": "task AAA"
+				}
+			}
+		}),
+	);
+	req_create_task.await?.print().await?;
+
+	let req_list_tasks = hc.do_post(
+		"/api/rpc",
+		json!({
+			"id": 1,
+			"method": "list_tasks",
+			"params": {
+				"filters": {
+					"title": {"$contains": "AAA"}
+				},
+				"list_options": {
+					"order_bys": ["!id"],
+					"limit": 10
+				}
+			}
+		}),
+	);
+	req_list_tasks.await?.print().await?;
+
 	Ok(())
 }
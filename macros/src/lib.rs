@@ -0,0 +1,121 @@
+//! The derive macros of the app.
+//!
+//! - `#[derive(Fields)]` implements `crate::model::fields::HasFields` for a struct
+//!   with named fields, the db column of each field being its name.
+//!   `#[field(skip)]` leaves a field out (e.g., a field the Bmc handles itself).
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Type};
+
+#[proc_macro_derive(Fields, attributes(field))]
+pub fn derive_fields(input: TokenStream) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+
+	match expand_fields(&input) {
+		Ok(tokens) => tokens.into(),
+		Err(err) => err.to_compile_error().into(),
+	}
+}
+
+fn expand_fields(input: &DeriveInput) -> syn::Result<TokenStream2> {
+	let Data::Struct(data) = &input.data else {
+		return Err(syn::Error::new_spanned(
+			input,
+			"Fields can only be derived for a struct",
+		));
+	};
+	let Fields::Named(named) = &data.fields else {
+		return Err(syn::Error::new_spanned(
+			input,
+			"Fields can only be derived for a struct with named fields",
+		));
+	};
+
+	let mut not_none_pushes = Vec::new();
+	let mut all_pushes = Vec::new();
+	let mut names = Vec::new();
+	for field in named.named.iter() {
+		if is_skipped(field)? {
+			continue;
+		}
+		// Note: The field is named (see above).
+		let Some(ident) = &field.ident else {
+			continue;
+		};
+		let name = ident.to_string();
+		let name = name.strip_prefix("r#").unwrap_or(&name).to_string();
+
+		if is_option(&field.ty) {
+			not_none_pushes.push(quote! {
+				if let Some(val) = self.#ident {
+					fields.push(crate::model::fields::Field::new(#name, val));
+				}
+			});
+		} else {
+			not_none_pushes.push(quote! {
+				fields.push(crate::model::fields::Field::new(#name, self.#ident));
+			});
+		}
+		all_pushes.push(quote! {
+			fields.push(crate::model::fields::Field::new(#name, self.#ident));
+		});
+		names.push(name);
+	}
+
+	let ident = &input.ident;
+	let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+	Ok(quote! {
+		impl #impl_generics crate::model::fields::HasFields for #ident #ty_generics #where_clause {
+			fn not_none_fields(self) -> Vec<crate::model::fields::Field> {
+				let mut fields = Vec::new();
+				#(#not_none_pushes)*
+				fields
+			}
+
+			fn all_fields(self) -> Vec<crate::model::fields::Field> {
+				let mut fields = Vec::new();
+				#(#all_pushes)*
+				fields
+			}
+
+			fn field_names() -> &'static [&'static str] {
+				&[#(#names),*]
+			}
+		}
+	})
+}
+
+/// `true` for `#[field(skip)]`.
+fn is_skipped(field: &syn::Field) -> syn::Result<bool> {
+	let mut skip = false;
+	for attr in field.attrs.iter().filter(|a| a.path().is_ident("field")) {
+		attr.parse_nested_meta(|meta| {
+			if meta.path.is_ident("skip") {
+				skip = true;
+				Ok(())
+			} else {
+				Err(meta.error("unsupported field attribute, expected `skip`"))
+			}
+		})?;
+	}
+
+	Ok(skip)
+}
+
+/// Note: Syntactic, so only for the `Option<..>` spelled as such
+///       (e.g., not through a type alias).
+fn is_option(ty: &Type) -> bool {
+	let Type::Path(path) = ty else {
+		return false;
+	};
+
+	path.qself.is_none()
+		&& path
+			.path
+			.segments
+			.last()
+			.is_some_and(|segment| segment.ident == "Option")
+}
@@ -1,9 +1,12 @@
 use tokio::sync::OnceCell;
 
 use tracing::{debug, info};
+use uuid::Uuid;
 
+use crate::model::project::{ProjectBmc, ProjectForCreate};
+use crate::model::tag::{TagBmc, TagForCreate};
 use crate::model::task::{Task, TaskBmc, TaskForCreate};
-use crate::model::user::UserBmc;
+use crate::model::user::{UserBmc, UserForCreate};
 use crate::model::{Error, Result};
 use crate::{ctx::Ctx, model::ModelManager};
 
@@ -25,24 +28,36 @@ pub async fn init_dev() {
 		let mm = ModelManager::new().await.unwrap();
 		let user_id = 1000;
 
-		let result = UserBmc::update_pwd(&ctx, &mm, user_id, &PASSWORD_SAMPLE).await;
+		let result = UserBmc::update_pwd(&ctx, &mm, user_id, PASSWORD_SAMPLE).await;
 		debug!("{result:?}");
 	})
 	.await;
 }
 
+/// Note: A new `ModelManager` for each call, as its pool is bound to the runtime
+///       of the test (each `#[tokio::test]` has its own), the dev db being initialized once.
 pub async fn init_test() -> ModelManager {
-	static INIT: OnceCell<ModelManager> = OnceCell::const_new();
+	init_dev().await;
 
-	let mm = INIT
-		.get_or_init(|| async {
-			init_dev().await;
-
-			ModelManager::new().await.unwrap()
-		})
-		.await;
+	ModelManager::new().await.unwrap()
+}
 
-	mm.clone()
+/// Same as `init_test`, on a new in-memory SQLite db of its own
+/// (see `store::check_db_url`), with the dev values.
+/// Note: Not shared, so the tests using it do not need `#[serial]`
+///       (the db is dropped with the last clone of the `ModelManager`).
+pub async fn init_test_sqlite() -> ModelManager {
+	let db_url = format!("sqlite:/test-{}?vfs=memdb", Uuid::new_v4());
+	let mm = ModelManager::new_for_db_url(&db_url).await.unwrap();
+
+	dev_db::init_sqlite_db(&db_url)
+		.await
+		.unwrap_or_else(|ex| panic!("Cannot init test sqlite db! Error: {ex}"));
+	UserBmc::update_pwd(&Ctx::root_ctx(), &mm, 1000, PASSWORD_SAMPLE)
+		.await
+		.unwrap();
+
+	mm
 }
 
 pub async fn seed_test(ctx: &Ctx, mm: &ModelManager, list: &[&str]) -> Result<Vec<Task>> {
@@ -50,6 +65,7 @@ pub async fn seed_test(ctx: &Ctx, mm: &ModelManager, list: &[&str]) -> Result<Ve
 	for task in list {
 		let task = TaskForCreate {
 			title: task.to_string(),
+			..Default::default()
 		};
 
 		let id = TaskBmc::create(ctx, mm, task).await?;
@@ -59,3 +75,106 @@ pub async fn seed_test(ctx: &Ctx, mm: &ModelManager, list: &[&str]) -> Result<Ve
 
 	Ok(list_task)
 }
+
+pub async fn seed_project(ctx: &Ctx, mm: &ModelManager, name: &str) -> Result<i64> {
+	ProjectBmc::create(
+		ctx,
+		mm,
+		ProjectForCreate {
+			name: name.to_string(),
+		},
+	)
+	.await
+}
+
+pub async fn seed_tasks_for_project(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	project_id: i64,
+	titles: &[&str],
+) -> Result<Vec<i64>> {
+	let mut ids = Vec::new();
+	for title in titles {
+		let task_c = TaskForCreate {
+			title: title.to_string(),
+			project_id: Some(project_id),
+			..Default::default()
+		};
+		ids.push(TaskBmc::create(ctx, mm, task_c).await?);
+	}
+
+	Ok(ids)
+}
+
+pub async fn seed_subtasks(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	parent_id: i64,
+	titles: &[&str],
+) -> Result<Vec<i64>> {
+	let mut ids = Vec::new();
+	for title in titles {
+		let task_c = TaskForCreate {
+			title: title.to_string(),
+			parent_id: Some(parent_id),
+			..Default::default()
+		};
+		ids.push(TaskBmc::create(ctx, mm, task_c).await?);
+	}
+
+	Ok(ids)
+}
+
+pub async fn seed_tag(ctx: &Ctx, mm: &ModelManager, name: &str) -> Result<i64> {
+	TagBmc::create(
+		ctx,
+		mm,
+		TagForCreate {
+			name: name.to_string(),
+		},
+	)
+	.await
+}
+
+pub async fn seed_user(ctx: &Ctx, mm: &ModelManager, username: &str) -> Result<i64> {
+	UserBmc::create(
+		ctx,
+		mm,
+		UserForCreate {
+			username: username.to_string(),
+			pwd: PASSWORD_SAMPLE.to_string(),
+		},
+	)
+	.await
+}
+
+/// Generate the tests of the test bodies (`async fn name(mm: ModelManager) -> Result<()>`)
+/// against both backends: in a `postgres` module with `init_test` (serial, shared db),
+/// and in a `sqlite` module with `init_test_sqlite` (a db per test, so in parallel).
+#[cfg(test)]
+macro_rules! test_backends {
+	($($name:ident),+ $(,)?) => {
+		mod postgres {
+			use super::*;
+			$(
+				#[serial_test::serial]
+				#[tokio::test]
+				async fn $name() -> anyhow::Result<()> {
+					super::$name($crate::_dev_utils::init_test().await).await
+				}
+			)+
+		}
+
+		mod sqlite {
+			use super::*;
+			$(
+				#[tokio::test]
+				async fn $name() -> anyhow::Result<()> {
+					super::$name($crate::_dev_utils::init_test_sqlite().await).await
+				}
+			)+
+		}
+	};
+}
+#[cfg(test)]
+pub(crate) use test_backends;
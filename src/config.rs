@@ -1,4 +1,7 @@
 use crate::error::{Error, Result};
+use crate::model::blob::BlobStoreKind;
+use crate::model::project::ProjectDeletePolicy;
+use crate::model::task::TaskDeletePolicy;
 use std::{env, str::FromStr, sync::OnceLock};
 
 pub fn config() -> &'static Config {
@@ -27,6 +30,26 @@ pub struct Config {
 
 	// html folder
 	pub WEB_FOLDER: String,
+
+	// Model
+	pub PROJECT_DELETE_POLICY: ProjectDeletePolicy,
+	pub TASK_DELETE_POLICY: TaskDeletePolicy,
+	pub TRASH_RETENTION_SEC: f64,
+	pub TRASH_PURGE_INTERVAL_SEC: f64,
+	pub ATTACHMENT_SIZE_MAX: u64,
+
+	// Entity cache (see `model::cache`)
+	pub CACHE_TTL_SEC: f64,
+	pub CACHE_SIZE_MAX: usize,
+
+	// Blob store (the settings of the other kind are not needed)
+	pub BLOB_STORE: BlobStoreKind,
+	pub BLOB_FS_DIR: Option<String>,
+	pub BLOB_S3_ENDPOINT: Option<String>,
+	pub BLOB_S3_REGION: Option<String>,
+	pub BLOB_S3_BUCKET: Option<String>,
+	pub BLOB_S3_ACCESS_KEY_ID: Option<String>,
+	pub BLOB_S3_SECRET_ACCESS_KEY: Option<String>,
 }
 
 impl Config {
@@ -37,6 +60,20 @@ impl Config {
 			TOKEN_DURATION: get_from_parse("SERVICE_TOKEN_DURATION").unwrap(),
 			DB_URL: get_env("SERVICE_DB_URL").unwrap(),
 			WEB_FOLDER: get_env("SERVICE_WEB_FOLDER").unwrap(),
+			PROJECT_DELETE_POLICY: get_from_parse("SERVICE_PROJECT_DELETE_POLICY").unwrap(),
+			TASK_DELETE_POLICY: get_from_parse("SERVICE_TASK_DELETE_POLICY").unwrap(),
+			TRASH_RETENTION_SEC: get_from_parse("SERVICE_TRASH_RETENTION_SEC").unwrap(),
+			TRASH_PURGE_INTERVAL_SEC: get_from_parse("SERVICE_TRASH_PURGE_INTERVAL_SEC").unwrap(),
+			ATTACHMENT_SIZE_MAX: get_from_parse("SERVICE_ATTACHMENT_SIZE_MAX").unwrap(),
+			CACHE_TTL_SEC: get_from_parse("SERVICE_CACHE_TTL_SEC").unwrap(),
+			CACHE_SIZE_MAX: get_from_parse("SERVICE_CACHE_SIZE_MAX").unwrap(),
+			BLOB_STORE: get_from_parse("SERVICE_BLOB_STORE").unwrap(),
+			BLOB_FS_DIR: get_env("SERVICE_BLOB_FS_DIR").ok(),
+			BLOB_S3_ENDPOINT: get_env("SERVICE_BLOB_S3_ENDPOINT").ok(),
+			BLOB_S3_REGION: get_env("SERVICE_BLOB_S3_REGION").ok(),
+			BLOB_S3_BUCKET: get_env("SERVICE_BLOB_S3_BUCKET").ok(),
+			BLOB_S3_ACCESS_KEY_ID: get_env("SERVICE_BLOB_S3_ACCESS_KEY_ID").ok(),
+			BLOB_S3_SECRET_ACCESS_KEY: get_env("SERVICE_BLOB_S3_SECRET_ACCESS_KEY").ok(),
 		})
 	}
 }
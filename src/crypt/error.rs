@@ -6,6 +6,7 @@ pub type Result<T> = core::result::Result<T, Error>;
 pub enum Error {
 	KeyFailedHmac,
 	PwdNotMatching,
+	SignNotMatching,
 }
 
 impl std::fmt::Display for Error {
@@ -30,6 +30,21 @@ pub fn encrypt_into_b64u(key: &[u8], encrypt_content: &EncryptContent) -> Result
 	Ok(result)
 }
 
+/// Check that `sign_b64u` is the `encrypt_into_b64u` of `encrypt_content`,
+/// in constant time (`Mac::verify_slice`), rather than comparing the strings.
+pub fn verify_b64u(key: &[u8], encrypt_content: &EncryptContent, sign_b64u: &str) -> Result<()> {
+	let EncryptContent { content, salt } = encrypt_content;
+	let sign = base64_url::decode(sign_b64u).map_err(|_| Error::SignNotMatching)?;
+
+	let mut hmac_sha512 = Hmac::<Sha512>::new_from_slice(key).map_err(|_| Error::KeyFailedHmac)?;
+	hmac_sha512.update(content.as_bytes());
+	hmac_sha512.update(salt.as_bytes());
+
+	hmac_sha512
+		.verify_slice(&sign)
+		.map_err(|_| Error::SignNotMatching)
+}
+
 #[cfg(test)]
 mod crypt_test {
 	use super::*;
@@ -51,4 +66,32 @@ mod crypt_test {
 
 		Ok(())
 	}
+
+	#[test]
+	fn test_verify_b64u_ok() -> Result<()> {
+		let mut key = [0u8; 100];
+		rand::thread_rng().fill_bytes(&mut key);
+		let encrypt_content = EncryptContent {
+			content: "test".to_string(),
+			salt: "Sau".to_string(),
+		};
+		let fx_sign = encrypt_into_b64u(&key, &encrypt_content)?;
+
+		verify_b64u(&key, &encrypt_content, &fx_sign)?;
+
+		let other_content = EncryptContent {
+			content: "test2".to_string(),
+			salt: "Sau".to_string(),
+		};
+		assert!(matches!(
+			verify_b64u(&key, &other_content, &fx_sign),
+			Err(Error::SignNotMatching)
+		));
+		assert!(matches!(
+			verify_b64u(&key, &encrypt_content, "not base64url!"),
+			Err(Error::SignNotMatching)
+		));
+
+		Ok(())
+	}
 }
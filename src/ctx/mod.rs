@@ -4,26 +4,54 @@ mod error;
 
 pub use self::error::{Error, Result};
 
+use uuid::Uuid;
+
 // endregion: --- Modules
 
 #[derive(Clone, Debug)]
 pub struct Ctx {
 	user_id: i64,
+	/// The active workspace (tenant), see `DbBmc::WORKSPACE_SCOPED`.
+	workspace_id: Option<i64>,
+	req_uuid: Option<Uuid>,
 }
 
 // Constructor.
 impl Ctx {
 	pub fn root_ctx() -> Self {
-		Ctx { user_id: 0 }
+		Ctx {
+			user_id: 0,
+			workspace_id: None,
+			req_uuid: None,
+		}
 	}
 
 	pub fn new(user_id: i64) -> Result<Self> {
 		if user_id == 0 {
 			Err(Error::CtxCannotNewRootCtx)
 		} else {
-			Ok(Self { user_id })
+			Ok(Self {
+				user_id,
+				workspace_id: None,
+				req_uuid: None,
+			})
 		}
 	}
+
+	/// The same ctx, scoped to the `workspace_id` rows
+	/// (e.g., from the `X-Workspace-Id` header, see `mw_ctx_resolve`).
+	/// Note: The membership is checked by the caller.
+	pub fn with_workspace_id(mut self, workspace_id: i64) -> Self {
+		self.workspace_id = Some(workspace_id);
+		self
+	}
+
+	/// The same ctx, for the web request `req_uuid`
+	/// (e.g., recorded in the audit trail).
+	pub fn with_req_uuid(mut self, req_uuid: Uuid) -> Self {
+		self.req_uuid = Some(req_uuid);
+		self
+	}
 }
 
 // Property Accessors.
@@ -31,4 +59,12 @@ impl Ctx {
 	pub fn user_id(&self) -> i64 {
 		self.user_id
 	}
+
+	pub fn workspace_id(&self) -> Option<i64> {
+		self.workspace_id
+	}
+
+	pub fn req_uuid(&self) -> Option<Uuid> {
+		self.req_uuid
+	}
 }
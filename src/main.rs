@@ -7,15 +7,16 @@ pub mod ctx;
 pub mod error;
 pub mod log;
 pub mod model;
+mod utils;
 pub mod web;
 
 pub mod _dev_utils;
 
 pub use self::error::{Error, Result};
 use crate::model::ModelManager;
-use crate::web::mw_auth::mw_ctx_resolve;
+use crate::web::mw_auth::{mw_ctx_require, mw_ctx_resolve};
 use crate::web::mw_res_map::mw_reponse_map;
-use crate::web::{routes_login, routes_static};
+use crate::web::{routes_attachment, routes_login, routes_static, rpc};
 use axum::extract::State;
 use axum::{middleware, Router};
 use std::net::SocketAddr;
@@ -34,19 +35,30 @@ async fn main() -> Result<()> {
 		.with_env_filter(EnvFilter::from_default_env())
 		.init();
 
+	// -- `rust-web-app migrate` only applies the schema migrations.
+	if std::env::args().nth(1).as_deref() == Some("migrate") {
+		let versions = ModelManager::migrate().await?;
+		info!(" {:<12} - {} applied\n", "MIGRATION", versions.len());
+		return Ok(());
+	}
+
 	_dev_utils::init_dev().await;
 
-	// Initialize ModelManager.
+	// Initialize ModelManager (applies the pending migrations).
 	let mm = ModelManager::new().await?;
 
+	// -- Start the trash purge job.
+	tokio::spawn(model::trash::purge_job(mm.clone()));
+
 	// -- Define Routes
-	// let routes_rpc = rpc::routes(mm.clone())
-	//   .route_layer(middleware::from_fn(mw_ctx_require));
+	let routes_rpc = rpc::routes(mm.clone())
+		.merge(routes_attachment::routes(mm.clone()))
+		.route_layer(middleware::from_fn(mw_ctx_require));
 
 	let routes_all = Router::new()
 		.merge(routes_login::routes(mm.clone()))
 		.merge(routes_login::home_routes())
-		// .nest("/api", routes_rpc)
+		.nest("/api", routes_rpc)
 		.layer(middleware::map_response(mw_reponse_map))
 		.layer(middleware::from_fn_with_state(mm.clone(), mw_ctx_resolve))
 		.layer(CookieManagerLayer::new())
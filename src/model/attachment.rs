@@ -0,0 +1,699 @@
+use std::path::Path;
+
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use sqlx::prelude::FromRow;
+use time::format_description::well_known::Rfc3339;
+use time::{Duration, OffsetDateTime};
+use tokio::fs::{self, OpenOptions};
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+use crate::config::config;
+use crate::ctx::Ctx;
+use crate::model::error::{Error, Result};
+use crate::utils::now_utc;
+
+use super::blob::{self, BlobStream};
+use super::common::DbBmc;
+use super::fields::Fields;
+use super::store::{with_db, with_pool, Sql, SqlValue};
+use super::task::TaskBmc;
+use super::{common, ModelManager};
+
+/// The first bytes of the content, enough for the sniffing of the content type.
+const SNIFF_LEN: usize = 8192;
+/// In chars, as the `attachment.filename` column.
+const FILENAME_LEN_MAX: usize = 256;
+/// The time for an upload to be committed, after which its content is purged
+/// when it has no attachment (see `AttachmentBmc::purge_orphans`).
+const UPLOAD_COMMIT_MAX: Duration = Duration::DAY;
+
+// Model: Attachment struct
+#[serde_as]
+#[derive(Clone, Debug, Serialize, FromRow, Fields)]
+pub struct Attachment {
+	pub id: i64,
+	/// `None` once the task is purged (the attachment is then purged too).
+	pub task_id: Option<i64>,
+	pub filename: String,
+	/// Sniffed from the content (see `AttachmentBmc::create`).
+	pub content_type: String,
+	pub size: i64,
+	#[serde(skip)]
+	pub blob_key: String,
+
+	// -- Timestamps
+	pub cid: i64,
+	#[serde_as(as = "Rfc3339")]
+	pub ctime: OffsetDateTime,
+	pub mid: i64,
+	#[serde_as(as = "Rfc3339")]
+	pub mtime: OffsetDateTime,
+}
+
+// Attachment entity for creating method
+// Note: The content is streamed aside (see `AttachmentBmc::create`).
+#[derive(Deserialize)]
+pub struct AttachmentForCreate {
+	pub task_id: i64,
+	pub filename: String,
+}
+
+/// The content of an attachment, stored in the blob store but not attached yet
+/// (see `AttachmentBmc::put_content`).
+pub struct AttachmentContent {
+	task_id: i64,
+	filename: String,
+	content_type: &'static str,
+	size: u64,
+	blob_key: String,
+}
+
+#[derive(Fields)]
+struct AttachmentForInsert {
+	task_id: i64,
+	/// The workspace of the task.
+	workspace_id: Option<i64>,
+	filename: String,
+	content_type: String,
+	size: i64,
+	blob_key: String,
+}
+
+// Attachment Backend Model Controller
+// Note: All the methods fail with `Error::TaskAccessDenied` unless the ctx user
+//       can access the task of the attachment (see `TaskBmc::get_accessible`).
+pub struct AttachmentBmc;
+
+impl AttachmentBmc {
+	/// Store `body`, of at most `SERVICE_ATTACHMENT_SIZE_MAX` bytes
+	/// (fails with `Error::AttachmentTooLarge` otherwise).
+	/// The filename is only informative, from 1 to 256 chars.
+	/// The content type is sniffed from the content, the client one is not trusted.
+	/// Note: `put_content` then `create_from_content`, with the same `mm`.
+	pub async fn create(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		attachment_c: AttachmentForCreate,
+		body: BlobStream,
+	) -> Result<i64> {
+		let size_max = config().ATTACHMENT_SIZE_MAX;
+		Self::create_with_size_max(ctx, mm, attachment_c, body, size_max).await
+	}
+
+	pub async fn create_with_size_max(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		attachment_c: AttachmentForCreate,
+		body: BlobStream,
+		size_max: u64,
+	) -> Result<i64> {
+		let content = Self::put_content(ctx, mm, attachment_c, body, size_max).await?;
+		Self::create_from_content(ctx, mm, content).await
+	}
+
+	/// Store `body` in the blob store, for the attachment to be created
+	/// with `create_from_content` (see `create`).
+	/// Note: `mm` should not have a transaction, as the body may be slow to come
+	///       (e.g., `upload_handler` opens it for `create_from_content` only).
+	/// Note: The content is recorded as a blob orphan until the attachment is created,
+	///       so that it is purged if it never is (e.g., the transaction fails).
+	pub async fn put_content(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		attachment_c: AttachmentForCreate,
+		body: BlobStream,
+		size_max: u64,
+	) -> Result<AttachmentContent> {
+		let AttachmentForCreate { task_id, filename } = attachment_c;
+		if filename.is_empty() || filename.chars().count() > FILENAME_LEN_MAX {
+			return Err(Error::AttachmentFilenameInvalid { filename });
+		}
+		TaskBmc::get_accessible(ctx, mm, task_id).await?;
+
+		let blob_key = format!("task/{task_id}/{}", Uuid::new_v4());
+		// Note: Not in the transaction of `mm`, if any, to be kept on a rollback.
+		with_pool!(ctx, mm, |db| sqlx::query(
+			"INSERT INTO blob_orphan (blob_key, purge_after) VALUES ($1, $2)"
+		)
+		.bind(&blob_key)
+		.bind(SqlValue::from(now_utc() + UPLOAD_COMMIT_MAX))
+		.execute(db)
+		.await?
+		.rows_affected());
+
+		let spool_path = std::env::temp_dir().join(format!("attachment-{}.tmp", Uuid::new_v4()));
+		let stored = Self::put_blob(mm, &blob_key, body, size_max, &spool_path).await;
+		let _ = fs::remove_file(&spool_path).await;
+		let (content_type, size) = stored?;
+
+		Ok(AttachmentContent {
+			task_id,
+			filename,
+			content_type,
+			size,
+			blob_key,
+		})
+	}
+
+	/// Create the attachment of the `content` stored by `put_content`.
+	/// The content is deleted from the blob store when the attachment cannot be created.
+	pub async fn create_from_content(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		content: AttachmentContent,
+	) -> Result<i64> {
+		let AttachmentContent {
+			task_id,
+			filename,
+			content_type,
+			size,
+			blob_key,
+		} = content;
+
+		let id = Self::create_row(ctx, mm, task_id, filename, content_type, size, &blob_key).await;
+		if id.is_err() {
+			let _ = Self::delete_blob(ctx, mm, &blob_key).await;
+		}
+
+		id
+	}
+
+	/// Create the attachment row, no longer a blob orphan (both committed together).
+	async fn create_row(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		task_id: i64,
+		filename: String,
+		content_type: &str,
+		size: u64,
+		blob_key: &str,
+	) -> Result<i64> {
+		let txn_mm = common::own_txn(ctx, mm).await?;
+		let mm = txn_mm.as_ref().unwrap_or(mm);
+
+		// Note: Checked again, as the task may have changed since `put_content`.
+		let task = TaskBmc::get_accessible(ctx, mm, task_id).await?;
+		let attachment_i = AttachmentForInsert {
+			task_id,
+			workspace_id: task.workspace_id,
+			filename,
+			content_type: content_type.to_string(),
+			size: size as i64,
+			blob_key: blob_key.to_string(),
+		};
+		let id = common::create::<Self, _>(ctx, mm, attachment_i).await?;
+		with_db!(ctx, mm, |db| sqlx::query(
+			"DELETE FROM blob_orphan WHERE blob_key = $1"
+		)
+		.bind(blob_key)
+		.execute(db)
+		.await?
+		.rows_affected());
+
+		common::commit_own_txn(txn_mm).await?;
+
+		Ok(id)
+	}
+
+	/// The attachments of the task, oldest first.
+	pub async fn list_by_task(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		task_id: i64,
+	) -> Result<Vec<Attachment>> {
+		TaskBmc::get_accessible(ctx, mm, task_id).await?;
+
+		let attachments = with_db!(ctx, mm, |db| sqlx::query_as(
+			"SELECT * FROM attachment WHERE task_id = $1 ORDER BY id"
+		)
+		.bind(task_id)
+		.fetch_all(db)
+		.await?);
+
+		Ok(attachments)
+	}
+
+	pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Attachment> {
+		let attachment: Attachment = common::get::<Self, _>(ctx, mm, id).await?;
+		let task_id = attachment.task_id.ok_or(Error::EntityNotFound {
+			entity: Self::TABLE,
+			id,
+		})?;
+		TaskBmc::get_accessible(ctx, mm, task_id).await?;
+
+		Ok(attachment)
+	}
+
+	/// The attachment, and its content streamed from the blob store.
+	pub async fn get_content(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: i64,
+	) -> Result<(Attachment, BlobStream)> {
+		let attachment = Self::get(ctx, mm, id).await?;
+		let content = mm.blob().get(&attachment.blob_key).await?;
+
+		Ok((attachment, content))
+	}
+
+	/// Note: The content is deleted from the blob store once the delete is committed:
+	///       right away when `mm` has no transaction, otherwise by `purge_orphans`
+	///       (so that it is kept on a rollback).
+	pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+		let attachment = Self::get(ctx, mm, id).await?;
+
+		Self::delete_row(ctx, mm, &attachment).await?;
+		if mm.txn().is_none() {
+			Self::delete_blob(ctx, mm, &attachment.blob_key).await?;
+		}
+
+		Ok(())
+	}
+
+	/// Delete the attachment row, its content becoming a blob orphan (both committed together).
+	async fn delete_row(ctx: &Ctx, mm: &ModelManager, attachment: &Attachment) -> Result<()> {
+		let txn_mm = common::own_txn(ctx, mm).await?;
+		let mm = txn_mm.as_ref().unwrap_or(mm);
+
+		common::delete::<Self, Attachment>(ctx, mm, attachment.id).await?;
+		with_db!(ctx, mm, |db| sqlx::query(
+			"INSERT INTO blob_orphan (blob_key, purge_after) VALUES ($1, $2)"
+		)
+		.bind(&attachment.blob_key)
+		.bind(SqlValue::from(now_utc()))
+		.execute(db)
+		.await?
+		.rows_affected());
+
+		common::commit_own_txn(txn_mm).await?;
+
+		Ok(())
+	}
+
+	/// Delete the attachments of the purged tasks and their content, and the blob orphans
+	/// due at `now` without attachment (see `trash::purge`).
+	/// Returns the number of deleted attachments and blob orphans.
+	/// Note: Not audited, as the purge of their task.
+	pub async fn purge_orphans(ctx: &Ctx, mm: &ModelManager, now: OffsetDateTime) -> Result<u64> {
+		let orphans: Vec<(i64, String)> = with_db!(ctx, mm, |db| sqlx::query_as(
+			"SELECT id, blob_key FROM attachment WHERE task_id IS NULL"
+		)
+		.fetch_all(db)
+		.await?);
+
+		let mut ids = Vec::new();
+		for (id, blob_key) in orphans {
+			mm.blob().delete(&blob_key).await?;
+			ids.push(id);
+		}
+
+		let mut sql = Sql::new(mm.backend(), "DELETE FROM attachment WHERE ");
+		sql.push_in_list("id", ids);
+		let count = with_db!(ctx, mm, |db| sql.query().execute(db).await?.rows_affected());
+
+		// -- The blob orphans (e.g., failed uploads, deleted attachments).
+		// Note: Kept in the blob store when an attachment has it after all.
+		let blob_orphans: Vec<(String, bool)> = with_db!(ctx, mm, |db| {
+			sqlx::query_as(
+			"SELECT blob_key, EXISTS (SELECT 1 FROM attachment WHERE attachment.blob_key = blob_orphan.blob_key) \
+			 FROM blob_orphan WHERE purge_after <= $1"
+		)
+		.bind(SqlValue::from(now))
+		.fetch_all(db)
+		.await?
+		});
+
+		let mut blob_count = 0;
+		for (blob_key, has_attachment) in blob_orphans {
+			if has_attachment {
+				with_db!(ctx, mm, |db| sqlx::query(
+					"DELETE FROM blob_orphan WHERE blob_key = $1"
+				)
+				.bind(&blob_key)
+				.execute(db)
+				.await?
+				.rows_affected());
+			} else {
+				Self::delete_blob(ctx, mm, &blob_key).await?;
+				blob_count += 1;
+			}
+		}
+
+		Ok(count + blob_count)
+	}
+
+	/// Delete the content from the blob store, then its blob orphan row, if any.
+	/// Note: Not in the transaction of `mm`, if any (the blob store is not transactional).
+	async fn delete_blob(ctx: &Ctx, mm: &ModelManager, blob_key: &str) -> Result<()> {
+		mm.blob().delete(blob_key).await?;
+		with_pool!(ctx, mm, |db| sqlx::query(
+			"DELETE FROM blob_orphan WHERE blob_key = $1"
+		)
+		.bind(blob_key)
+		.execute(db)
+		.await?
+		.rows_affected());
+
+		Ok(())
+	}
+
+	/// Spool `body` to the `spool_path` file, so that its size is known before
+	/// it is stored (e.g., S3 needs the `Content-Length`), then store it at `blob_key`.
+	/// Returns the sniffed content type and the size.
+	async fn put_blob(
+		mm: &ModelManager,
+		blob_key: &str,
+		mut body: BlobStream,
+		size_max: u64,
+		spool_path: &Path,
+	) -> Result<(&'static str, u64)> {
+		let mut file = OpenOptions::new()
+			.read(true)
+			.write(true)
+			.create_new(true)
+			.open(spool_path)
+			.await
+			.map_err(blob::Error::from)?;
+		let mut head = Vec::new();
+		let mut size = 0;
+		while let Some(chunk) = body.next().await {
+			let chunk = chunk.map_err(blob::Error::from)?;
+			size += chunk.len() as u64;
+			if size > size_max {
+				return Err(Error::AttachmentTooLarge { max: size_max });
+			}
+			if head.len() < SNIFF_LEN {
+				let len = chunk.len().min(SNIFF_LEN - head.len());
+				head.extend_from_slice(&chunk[..len]);
+			}
+			file.write_all(&chunk).await.map_err(blob::Error::from)?;
+		}
+		file.flush().await.map_err(blob::Error::from)?;
+		file.rewind().await.map_err(blob::Error::from)?;
+
+		let content_type = sniff_content_type(&head);
+		let content = Box::pin(ReaderStream::new(file));
+		mm.blob().put(blob_key, content_type, size, content).await?;
+
+		Ok((content_type, size))
+	}
+}
+
+// Impl Trait Dbmc for Attachment model
+impl DbBmc for AttachmentBmc {
+	const TABLE: &'static str = "attachment";
+	const WORKSPACE_SCOPED: bool = true;
+}
+
+/// The content type of the content starting with `head`:
+/// the detected one, else plain text for utf-8 text, else `application/octet-stream`.
+fn sniff_content_type(head: &[u8]) -> &'static str {
+	if let Some(kind) = infer::get(head) {
+		return kind.mime_type();
+	}
+
+	// Note: `head` may end in the middle of a character.
+	let is_text = match std::str::from_utf8(head) {
+		Ok(_) => true,
+		Err(ex) => ex.error_len().is_none(),
+	};
+	if is_text && !head.contains(&0) {
+		"text/plain; charset=utf-8"
+	} else {
+		"application/octet-stream"
+	}
+}
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	#![allow(unused)]
+	use super::*;
+	use crate::_dev_utils;
+	use crate::model::blob::{read_to_vec, stream_from_bytes};
+	use crate::model::trash;
+	use crate::utils::now_utc;
+	use anyhow::Result;
+	use serial_test::serial;
+
+	const FX_PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+	#[test]
+	fn test_sniff_content_type_ok() {
+		assert_eq!(sniff_content_type(FX_PNG), "image/png");
+		assert_eq!(sniff_content_type(b"%PDF-1.7\n"), "application/pdf");
+		assert_eq!(
+			sniff_content_type("été".as_bytes()),
+			"text/plain; charset=utf-8"
+		);
+		// Cut in the middle of the `é`.
+		assert_eq!(
+			sniff_content_type(&"é".as_bytes()[..1]),
+			"text/plain; charset=utf-8"
+		);
+		assert_eq!(
+			sniff_content_type(b"\xff\xfe\0\x01"),
+			"application/octet-stream"
+		);
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_create_get_content_ok() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+		let task_id =
+			_dev_utils::seed_test(&ctx, &mm, &["test_create_get_content_ok"]).await?[0].id;
+		let fx_content = [FX_PNG, &[7; 100]].concat();
+
+		// -- Exec
+		// The declared content type (e.g., the filename extension) is not used.
+		let attachment_c = AttachmentForCreate {
+			task_id,
+			filename: "fx-image.txt".to_string(),
+		};
+		let id = AttachmentBmc::create(
+			&ctx,
+			&mm,
+			attachment_c,
+			stream_from_bytes(fx_content.clone()),
+		)
+		.await?;
+
+		// -- Check
+		let (attachment, content) = AttachmentBmc::get_content(&ctx, &mm, id).await?;
+		assert_eq!(attachment.task_id, Some(task_id));
+		assert_eq!(attachment.filename, "fx-image.txt");
+		assert_eq!(attachment.content_type, "image/png");
+		assert_eq!(attachment.size, fx_content.len() as i64);
+		assert_eq!(read_to_vec(content).await?, fx_content);
+
+		let attachments = AttachmentBmc::list_by_task(&ctx, &mm, task_id).await?;
+		assert_eq!(attachments.len(), 1);
+		assert_eq!(attachments[0].id, id);
+
+		// -- Clean
+		AttachmentBmc::delete(&ctx, &mm, id).await?;
+		let result = mm.blob().get(&attachment.blob_key).await;
+		assert!(matches!(result, Err(blob::Error::BlobNotFound { .. })));
+		TaskBmc::delete(&ctx, &mm, task_id).await?;
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_create_err_too_large() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+		let task_id = _dev_utils::seed_test(&ctx, &mm, &["test_create_err_too_large"]).await?[0].id;
+		let attachment_c = || AttachmentForCreate {
+			task_id,
+			filename: "fx-file".to_string(),
+		};
+
+		// -- Exec
+		let id = AttachmentBmc::create_with_size_max(
+			&ctx,
+			&mm,
+			attachment_c(),
+			stream_from_bytes("0123456789"),
+			10,
+		)
+		.await?;
+		let result = AttachmentBmc::create_with_size_max(
+			&ctx,
+			&mm,
+			attachment_c(),
+			stream_from_bytes("0123456789A"),
+			10,
+		)
+		.await;
+
+		// -- Check
+		assert!(matches!(result, Err(Error::AttachmentTooLarge { max: 10 })));
+		let attachments = AttachmentBmc::list_by_task(&ctx, &mm, task_id).await?;
+		assert_eq!(attachments.len(), 1);
+
+		// -- Clean
+		AttachmentBmc::delete(&ctx, &mm, id).await?;
+		TaskBmc::delete(&ctx, &mm, task_id).await?;
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_access_err_denied() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+		// Not the creator of the task, not an admin.
+		let other_ctx = Ctx::new(1999)?;
+		let task_id = _dev_utils::seed_test(&ctx, &mm, &["test_access_err_denied"]).await?[0].id;
+		let attachment_c = || AttachmentForCreate {
+			task_id,
+			filename: "fx-file".to_string(),
+		};
+		let id = AttachmentBmc::create(&ctx, &mm, attachment_c(), stream_from_bytes("fx")).await?;
+
+		// -- Check
+		let result =
+			AttachmentBmc::create(&other_ctx, &mm, attachment_c(), stream_from_bytes("fx")).await;
+		assert!(matches!(
+			result,
+			Err(Error::TaskAccessDenied { user_id: 1999, .. })
+		));
+		let result = AttachmentBmc::list_by_task(&other_ctx, &mm, task_id).await;
+		assert!(matches!(result, Err(Error::TaskAccessDenied { .. })));
+		let result = AttachmentBmc::get_content(&other_ctx, &mm, id).await;
+		assert!(matches!(result, Err(Error::TaskAccessDenied { .. })));
+		let result = AttachmentBmc::delete(&other_ctx, &mm, id).await;
+		assert!(matches!(result, Err(Error::TaskAccessDenied { .. })));
+		AttachmentBmc::get(&ctx, &mm, id).await?;
+
+		// -- Clean
+		AttachmentBmc::delete(&ctx, &mm, id).await?;
+		TaskBmc::delete(&ctx, &mm, task_id).await?;
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_purge_orphans_ok() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+		let task_id = _dev_utils::seed_test(&ctx, &mm, &["test_purge_orphans_ok"]).await?[0].id;
+		let attachment_c = AttachmentForCreate {
+			task_id,
+			filename: "fx-file".to_string(),
+		};
+		let id = AttachmentBmc::create(&ctx, &mm, attachment_c, stream_from_bytes("fx")).await?;
+		let blob_key = AttachmentBmc::get(&ctx, &mm, id).await?.blob_key;
+
+		// -- Exec
+		TaskBmc::delete(&ctx, &mm, task_id).await?;
+		TaskBmc::purge_deleted(&Ctx::root_ctx(), &mm, now_utc()).await?;
+		let count = AttachmentBmc::purge_orphans(&Ctx::root_ctx(), &mm, now_utc()).await?;
+
+		// -- Check
+		assert!(count >= 1);
+		let result = AttachmentBmc::get(&ctx, &mm, id).await;
+		assert!(matches!(result, Err(Error::EntityNotFound { .. })));
+		let result = mm.blob().get(&blob_key).await;
+		assert!(matches!(result, Err(blob::Error::BlobNotFound { .. })));
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_purge_orphans_upload_ok() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+		let task_id =
+			_dev_utils::seed_test(&ctx, &mm, &["test_purge_orphans_upload_ok"]).await?[0].id;
+		let attachment_c = AttachmentForCreate {
+			task_id,
+			filename: "fx-file".to_string(),
+		};
+
+		// -- Exec
+		// The content is stored, but the transaction of the attachment fails.
+		let content =
+			AttachmentBmc::put_content(&ctx, &mm, attachment_c, stream_from_bytes("fx"), 10)
+				.await?;
+		let blob_key = content.blob_key.clone();
+		let txn_mm = mm.new_with_ctx_txn(&ctx).await?;
+		AttachmentBmc::create_from_content(&ctx, &txn_mm, content).await?;
+		txn_mm.rollback().await?;
+
+		// -- Check
+		// Not before the upload could be committed.
+		AttachmentBmc::purge_orphans(&Ctx::root_ctx(), &mm, now_utc()).await?;
+		mm.blob().get(&blob_key).await?;
+
+		let count = AttachmentBmc::purge_orphans(
+			&Ctx::root_ctx(),
+			&mm,
+			now_utc() + UPLOAD_COMMIT_MAX + Duration::SECOND,
+		)
+		.await?;
+		assert!(count >= 1);
+		let result = mm.blob().get(&blob_key).await;
+		assert!(matches!(result, Err(blob::Error::BlobNotFound { .. })));
+
+		// -- Clean
+		TaskBmc::delete(&ctx, &mm, task_id).await?;
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_delete_txn_ok() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+		let task_id = _dev_utils::seed_test(&ctx, &mm, &["test_delete_txn_ok"]).await?[0].id;
+		let attachment_c = AttachmentForCreate {
+			task_id,
+			filename: "fx-file".to_string(),
+		};
+		let id = AttachmentBmc::create(&ctx, &mm, attachment_c, stream_from_bytes("fx")).await?;
+		let blob_key = AttachmentBmc::get(&ctx, &mm, id).await?.blob_key;
+
+		// -- Exec & Check
+		// Rolled back, the content is kept.
+		let txn_mm = mm.new_with_ctx_txn(&ctx).await?;
+		AttachmentBmc::delete(&ctx, &txn_mm, id).await?;
+		txn_mm.rollback().await?;
+		AttachmentBmc::purge_orphans(&Ctx::root_ctx(), &mm, now_utc()).await?;
+		let (_, content) = AttachmentBmc::get_content(&ctx, &mm, id).await?;
+		assert_eq!(read_to_vec(content).await?, b"fx");
+
+		// Committed, the content is purged.
+		let txn_mm = mm.new_with_ctx_txn(&ctx).await?;
+		AttachmentBmc::delete(&ctx, &txn_mm, id).await?;
+		txn_mm.commit().await?;
+		mm.blob().get(&blob_key).await?;
+		AttachmentBmc::purge_orphans(&Ctx::root_ctx(), &mm, now_utc()).await?;
+		let result = mm.blob().get(&blob_key).await;
+		assert!(matches!(result, Err(blob::Error::BlobNotFound { .. })));
+
+		// -- Clean
+		TaskBmc::delete(&ctx, &mm, task_id).await?;
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
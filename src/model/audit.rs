@@ -0,0 +1,400 @@
+//! Change-audit trail of the `DbBmc` entities.
+//!
+//! - The `common` create, update and delete functions (and their bulk variants)
+//!   write one `audit` row per changed row, in the same transaction as the change.
+//! - The `diff` only has the changed fields, as `{"<field>": {"old": .., "new": ..}}`,
+//!   without `old` on create, and without `new` on a (hard) delete.
+//! - The trail can only be read by the admin users (see `AuditBmc::list`).
+//! - Out of scope, the writes made outside of the `common` functions, which are not audited:
+//!   - the tags of the tasks (`TaskBmc::set_tags`, `add_tags`, `remove_tags`),
+//!     and the tag merge (`TagBmc::merge`, the delete of the merged tag included),
+//!   - the task watchers (`TaskBmc::watch`, `unwatch`) and the comment mentions,
+//!   - the ranks of the other tasks spread again on a move (`TaskBmc::move_before`,
+//!     `move_after`, the moved task is audited),
+//!   - the workspace members (`WorkspaceBmc::add_member`, `remove_member`),
+//!   - the purge of the trash (`trash::purge`, the rows were audited when deleted).
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use serde_with::{serde_as, DisplayFromStr};
+use sqlx::prelude::FromRow;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::ctx::Ctx;
+use crate::model::error::Result;
+use crate::utils::now_utc;
+
+use super::common::DbBmc;
+use super::fields::Fields;
+use super::store::{sql_enum, with_db, Backend, SqlValue};
+use super::user::UserBmc;
+use super::{
+	common, FilterNode, FilterNodes, ListOptions, ModelManager, OpValsInt64, OpValsString, OrderBy,
+};
+
+// Audited operation, stored as the `audit_op` postgres enum
+#[derive(
+	Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, strum_macros::AsRefStr,
+)]
+#[sqlx(type_name = "audit_op", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum AuditOp {
+	Create,
+	Update,
+	Delete,
+	/// Restore of a soft deleted row (see `common::restore`).
+	Restore,
+}
+
+sql_enum!(AuditOp);
+
+/// The `{"<field>": {"old": .., "new": ..}}` json of the changed fields.
+#[derive(Clone, Debug, Serialize, sqlx::Type)]
+#[serde(transparent)]
+#[sqlx(transparent)]
+pub struct AuditDiff(pub Value);
+
+impl From<AuditDiff> for SqlValue {
+	fn from(val: AuditDiff) -> Self {
+		val.0.into()
+	}
+}
+
+// Model: Audit struct
+#[serde_as]
+#[derive(Clone, Debug, Serialize, FromRow, Fields)]
+pub struct Audit {
+	pub id: i64,
+	/// The `DbBmc::TABLE` of the changed row (e.g., `task`).
+	pub entity: String,
+	pub entity_id: i64,
+	pub op: AuditOp,
+	pub diff: AuditDiff,
+
+	pub actor_id: i64,
+	/// The web request of the change, if any.
+	#[serde_as(as = "Option<DisplayFromStr>")]
+	pub req_uuid: Option<Uuid>,
+	#[serde_as(as = "Rfc3339")]
+	pub ctime: OffsetDateTime,
+}
+
+// Audit filter for list method
+// (e.g., `{"entity": {"$eq": "task"}, "entity_id": {"$eq": 1000}}`)
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuditFilter {
+	pub entity: Option<OpValsString>,
+	pub entity_id: Option<OpValsInt64>,
+	pub op: Option<OpValsString>,
+	pub actor_id: Option<OpValsInt64>,
+	pub req_uuid: Option<OpValsString>,
+}
+
+impl FilterNodes for AuditFilter {
+	fn filter_nodes(self) -> Vec<FilterNode> {
+		let mut nodes = Vec::new();
+		if let Some(entity) = self.entity {
+			nodes.extend(entity.into_filter_nodes("entity"));
+		}
+		if let Some(entity_id) = self.entity_id {
+			nodes.extend(entity_id.into_filter_nodes("entity_id"));
+		}
+		if let Some(op) = self.op {
+			nodes.extend(op.into_filter_nodes("op"));
+		}
+		if let Some(actor_id) = self.actor_id {
+			nodes.extend(actor_id.into_filter_nodes("actor_id"));
+		}
+		if let Some(req_uuid) = self.req_uuid {
+			nodes.extend(req_uuid.into_filter_nodes("req_uuid"));
+		}
+		nodes
+	}
+}
+
+// Audit Backend Model Controller
+// Note: The audit rows are only written by `common`, and never updated
+//       (see the module doc for the writes which are not audited).
+pub struct AuditBmc;
+
+impl AuditBmc {
+	/// The audit trail, most recent first by default. Admin only.
+	pub async fn list(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		filter: Option<AuditFilter>,
+		list_options: Option<ListOptions>,
+	) -> Result<Vec<Audit>> {
+		UserBmc::ensure_admin(ctx, mm).await?;
+
+		let mut list_options = list_options.unwrap_or_default();
+		list_options
+			.order_bys
+			.get_or_insert_with(|| vec![OrderBy::Desc("id".to_string())]);
+
+		common::list::<Self, _, _>(ctx, mm, filter, Some(list_options)).await
+	}
+
+	/// Write the audit rows of a change, one per `(entity_id, diff)`, with one insert.
+	/// Note: Must run in the transaction of the change.
+	pub(in crate::model) async fn log(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		entity: &str,
+		op: AuditOp,
+		diffs: Vec<(i64, Value)>,
+	) -> Result<()> {
+		if diffs.is_empty() {
+			return Ok(());
+		}
+		let (entity_ids, diffs): (Vec<i64>, Vec<Value>) = diffs.into_iter().unzip();
+
+		// Note: The ids and the diffs are zipped by their position.
+		let rows_sql = match mm.backend() {
+			Backend::Postgres => {
+				"SELECT $1, e.id, $3, d.diff, $5, $6, $7 \
+				 FROM unnest($2::bigint[]) WITH ORDINALITY AS e (id, n) \
+				 JOIN jsonb_array_elements($4) WITH ORDINALITY AS d (diff, n) USING (n)"
+			}
+			Backend::Sqlite => {
+				"SELECT $1, e.value, $3, d.value, $5, $6, $7 \
+				 FROM json_each($2) AS e JOIN json_each($4) AS d ON d.key = e.key"
+			}
+		};
+		let sql = format!(
+			"INSERT INTO audit (entity, entity_id, op, diff, actor_id, req_uuid, ctime) {rows_sql}"
+		);
+		with_db!(ctx, mm, |db| sqlx::query(&sql)
+			.bind(entity)
+			.bind(SqlValue::from(entity_ids))
+			.bind(SqlValue::from(op))
+			.bind(SqlValue::from(Value::from(diffs)))
+			.bind(ctx.user_id())
+			.bind(ctx.req_uuid())
+			.bind(SqlValue::from(now_utc()))
+			.execute(db)
+			.await?
+			.rows_affected());
+
+		Ok(())
+	}
+}
+
+// Impl Trait Dbmc for Audit model
+impl DbBmc for AuditBmc {
+	const TABLE: &'static str = "audit";
+}
+
+/// The audit diff of the `names` fields between the `old` and `new` row json,
+/// skipping the fields with the same old and new value.
+/// All the fields when `names` is `None` (e.g., for a hard delete).
+pub(in crate::model) fn diff(
+	old: Option<&Value>,
+	new: Option<&Value>,
+	names: Option<&[String]>,
+) -> Value {
+	let old = old.and_then(Value::as_object);
+	let new = new.and_then(Value::as_object);
+
+	let all_names: Vec<String>;
+	let names = match names {
+		Some(names) => names,
+		None => {
+			all_names = old
+				.into_iter()
+				.chain(new)
+				.flat_map(|row| row.keys().cloned())
+				.collect();
+			&all_names
+		}
+	};
+
+	let mut diff = Map::new();
+	for name in names {
+		let old_val = old.and_then(|row| row.get(name));
+		let new_val = new.and_then(|row| row.get(name));
+		if diff.contains_key(name) || (old.is_some() && new.is_some() && old_val == new_val) {
+			continue;
+		}
+
+		let mut change = Map::new();
+		if old.is_some() {
+			change.insert("old".to_string(), old_val.cloned().unwrap_or(Value::Null));
+		}
+		if new.is_some() {
+			change.insert("new".to_string(), new_val.cloned().unwrap_or(Value::Null));
+		}
+		diff.insert(name.to_string(), Value::Object(change));
+	}
+
+	json!(diff)
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(unused)]
+	use crate::_dev_utils;
+	use crate::model::project::{ProjectBmc, ProjectDeletePolicy, ProjectForUpdate};
+	use crate::model::task::{TaskBmc, TaskForUpdate};
+	use crate::model::Error;
+
+	use super::*;
+	use anyhow::Result;
+	use serial_test::serial;
+
+	#[test]
+	fn test_diff_ok() -> Result<()> {
+		let old = json!({"id": 1, "title": "a", "priority": 0});
+		let new = json!({"id": 1, "title": "b", "priority": 0});
+		let names = ["title".to_string(), "priority".to_string()];
+
+		assert_eq!(
+			diff(Some(&old), Some(&new), Some(&names)),
+			json!({"title": {"old": "a", "new": "b"}})
+		);
+		assert_eq!(
+			diff(None, Some(&new), Some(&names)),
+			json!({"title": {"new": "b"}, "priority": {"new": 0}})
+		);
+		assert_eq!(
+			diff(Some(&old), None, None),
+			json!({"id": {"old": 1}, "title": {"old": "a"}, "priority": {"old": 0}})
+		);
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_audit_crud_ok() -> Result<()> {
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+		let fx_req_uuid = Uuid::new_v4();
+		let fx_ctx = Ctx::new(1000)?.with_req_uuid(fx_req_uuid);
+		let fx_id = _dev_utils::seed_project(&fx_ctx, &mm, "test_audit_crud_ok").await?;
+
+		let project_u = ProjectForUpdate {
+			name: Some("test_audit_crud_ok renamed".to_string()),
+		};
+		ProjectBmc::update(&fx_ctx, &mm, fx_id, project_u).await?;
+		ProjectBmc::delete_with_policy(&fx_ctx, &mm, fx_id, ProjectDeletePolicy::Reject).await?;
+
+		// -- Check, oldest first.
+		let filter = AuditFilter {
+			entity: Some(OpValsString {
+				eq: Some("project".to_string()),
+				..Default::default()
+			}),
+			entity_id: Some(OpValsInt64 {
+				eq: Some(fx_id),
+				..Default::default()
+			}),
+			..Default::default()
+		};
+		let list_options = ListOptions {
+			order_bys: Some(vec![OrderBy::Asc("id".to_string())]),
+			..Default::default()
+		};
+		let audits = AuditBmc::list(&ctx, &mm, Some(filter), Some(list_options)).await?;
+
+		let ops: Vec<AuditOp> = audits.iter().map(|a| a.op).collect();
+		assert_eq!(ops, [AuditOp::Create, AuditOp::Update, AuditOp::Delete]);
+		assert!(audits.iter().all(|a| a.actor_id == 1000));
+		assert!(audits.iter().all(|a| a.req_uuid == Some(fx_req_uuid)));
+		assert_eq!(
+			audits[0].diff.0,
+			json!({"name": {"new": "test_audit_crud_ok"}})
+		);
+		assert_eq!(
+			audits[1].diff.0,
+			json!({"name": {"old": "test_audit_crud_ok", "new": "test_audit_crud_ok renamed"}})
+		);
+		assert_eq!(
+			audits[2].diff.0["name"],
+			json!({"old": "test_audit_crud_ok renamed"})
+		);
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_audit_soft_delete_ok() -> Result<()> {
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+		let fx_id = _dev_utils::seed_test(&ctx, &mm, &["test_audit_soft_delete_ok"]).await?[0].id;
+
+		TaskBmc::delete(&ctx, &mm, fx_id).await?;
+		TaskBmc::restore(&ctx, &mm, fx_id).await?;
+
+		let filter = AuditFilter {
+			entity: Some(OpValsString {
+				eq: Some("task".to_string()),
+				..Default::default()
+			}),
+			entity_id: Some(OpValsInt64 {
+				eq: Some(fx_id),
+				..Default::default()
+			}),
+			..Default::default()
+		};
+		let audits = AuditBmc::list(&ctx, &mm, Some(filter), None).await?;
+
+		// Most recent first.
+		let ops: Vec<AuditOp> = audits.iter().map(|a| a.op).collect();
+		assert_eq!(ops, [AuditOp::Restore, AuditOp::Delete, AuditOp::Create]);
+		assert!(audits[0].diff.0["deleted_at"]["new"].is_null());
+		assert!(audits[1].diff.0["deleted_at"]["old"].is_null());
+		assert!(audits[1].diff.0["deleted_at"]["new"].is_string());
+
+		// -- Clean
+		TaskBmc::delete(&ctx, &mm, fx_id).await?;
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_audit_rollback_ok() -> Result<()> {
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+
+		let txn_mm = mm.new_with_ctx_txn(&ctx).await?;
+		let id = _dev_utils::seed_project(&ctx, &txn_mm, "test_audit_rollback_ok").await?;
+		txn_mm.rollback().await?;
+
+		let filter = AuditFilter {
+			entity: Some(OpValsString {
+				eq: Some("project".to_string()),
+				..Default::default()
+			}),
+			entity_id: Some(OpValsInt64 {
+				eq: Some(id),
+				..Default::default()
+			}),
+			..Default::default()
+		};
+		let audits = AuditBmc::list(&ctx, &mm, Some(filter), None).await?;
+		assert!(audits.is_empty());
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_list_err_not_admin() -> Result<()> {
+		let mm = _dev_utils::init_test().await;
+		// Note: Not a user.
+		let ctx = Ctx::new(100)?;
+
+		let result = AuditBmc::list(&ctx, &mm, None, None).await;
+
+		assert!(matches!(result, Err(Error::UserNotAdmin { user_id: 100 })));
+
+		Ok(())
+	}
+}
@@ -0,0 +1,53 @@
+use serde::Serialize;
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug, Serialize)]
+pub enum Error {
+	BlobStoreConfigMissing(&'static str),
+	BlobKeyInvalid {
+		key: String,
+	},
+	BlobNotFound {
+		key: String,
+	},
+	BlobSizeMismatch {
+		key: String,
+		expected: u64,
+		actual: u64,
+	},
+
+	// -- S3
+	S3EndpointInvalid(String),
+	S3BucketInvalid(String),
+	S3Status {
+		key: String,
+		status: u16,
+	},
+
+	// -- External Modules
+	Io(String),
+	Http(String),
+}
+
+impl From<std::io::Error> for Error {
+	fn from(err: std::io::Error) -> Self {
+		Self::Io(err.to_string())
+	}
+}
+
+impl From<reqwest::Error> for Error {
+	fn from(err: reqwest::Error) -> Self {
+		Self::Http(err.to_string())
+	}
+}
+
+// region:    --- Error Boilerplate
+impl core::fmt::Display for Error {
+	fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
+		write!(fmt, "{self:?}")
+	}
+}
+
+impl std::error::Error for Error {}
+// endregion: --- Error Boilerplate
@@ -0,0 +1,181 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use tokio::fs::{self, File};
+use tokio::io::AsyncWriteExt;
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+use super::{check_key, BlobStore, BlobStream, Error, Result};
+
+/// Blobs stored as the files of a local directory, the key being the relative path.
+pub struct FsBlobStore {
+	dir: PathBuf,
+}
+
+impl FsBlobStore {
+	/// Note: `dir` is created on the first `put`.
+	pub fn new(dir: impl Into<PathBuf>) -> Self {
+		Self { dir: dir.into() }
+	}
+
+	fn path(&self, key: &str) -> Result<PathBuf> {
+		check_key(key)?;
+		Ok(self.dir.join(key))
+	}
+}
+
+#[async_trait]
+impl BlobStore for FsBlobStore {
+	async fn put(
+		&self,
+		key: &str,
+		_content_type: &str,
+		size: u64,
+		mut body: BlobStream,
+	) -> Result<()> {
+		let path = self.path(key)?;
+		if let Some(parent) = path.parent() {
+			fs::create_dir_all(parent).await?;
+		}
+
+		// Note: Written to a temporary file first, so that a reader never sees
+		//       a partial blob. Hidden, so that it is never a key.
+		let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+		let tmp_path = path.with_file_name(format!(".{file_name}.{}.tmp", Uuid::new_v4()));
+
+		let result = match write_file(&tmp_path, &mut body).await {
+			Ok(written) if written == size => Ok(()),
+			Ok(actual) => Err(Error::BlobSizeMismatch {
+				key: key.to_string(),
+				expected: size,
+				actual,
+			}),
+			Err(ex) => Err(ex.into()),
+		};
+		if result.is_err() {
+			let _ = fs::remove_file(&tmp_path).await;
+			return result;
+		}
+
+		fs::rename(&tmp_path, &path).await?;
+
+		Ok(())
+	}
+
+	async fn get(&self, key: &str) -> Result<BlobStream> {
+		let file = File::open(self.path(key)?)
+			.await
+			.map_err(|ex| match ex.kind() {
+				ErrorKind::NotFound => Error::BlobNotFound {
+					key: key.to_string(),
+				},
+				_ => ex.into(),
+			})?;
+
+		Ok(Box::pin(ReaderStream::new(file)))
+	}
+
+	async fn delete(&self, key: &str) -> Result<()> {
+		match fs::remove_file(self.path(key)?).await {
+			Err(ex) if ex.kind() != ErrorKind::NotFound => Err(ex.into()),
+			_ => Ok(()),
+		}
+	}
+}
+
+/// Returns the number of bytes written.
+async fn write_file(path: &Path, body: &mut BlobStream) -> std::io::Result<u64> {
+	let mut file = File::create(path).await?;
+	let mut written = 0;
+	while let Some(chunk) = body.next().await {
+		let chunk = chunk?;
+		file.write_all(&chunk).await?;
+		written += chunk.len() as u64;
+	}
+	file.sync_all().await?;
+
+	Ok(written)
+}
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	use super::super::{read_to_vec, stream_from_bytes};
+	use super::*;
+	use anyhow::Result;
+
+	#[tokio::test]
+	async fn test_fs_put_get_delete_ok() -> Result<()> {
+		// -- Setup & Fixtures
+		let dir =
+			std::env::temp_dir().join(format!("test_fs_put_get_delete_ok-{}", Uuid::new_v4()));
+		let store = FsBlobStore::new(&dir);
+		let fx_key = "task/1000/fx-blob";
+
+		// -- Exec
+		store
+			.put(fx_key, "text/plain", 5, stream_from_bytes("hello"))
+			.await?;
+		store
+			.put(fx_key, "text/plain", 6, stream_from_bytes("hello!"))
+			.await?;
+
+		// -- Check
+		let content = read_to_vec(store.get(fx_key).await?).await?;
+		assert_eq!(content, b"hello!");
+		// Only the blob, no temporary file left.
+		let mut entries = fs::read_dir(dir.join("task/1000")).await?;
+		let mut names = Vec::new();
+		while let Some(entry) = entries.next_entry().await? {
+			names.push(entry.file_name());
+		}
+		assert_eq!(names, ["fx-blob"]);
+
+		store.delete(fx_key).await?;
+		let result = store.get(fx_key).await;
+		assert!(matches!(result, Err(Error::BlobNotFound { .. })));
+		// No error when already deleted.
+		store.delete(fx_key).await?;
+
+		// -- Clean
+		fs::remove_dir_all(&dir).await?;
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_fs_put_err() -> Result<()> {
+		// -- Setup & Fixtures
+		let dir = std::env::temp_dir().join(format!("test_fs_put_err-{}", Uuid::new_v4()));
+		let store = FsBlobStore::new(&dir);
+
+		// -- Check
+		let result = store
+			.put("../fx-blob", "text/plain", 5, stream_from_bytes("hello"))
+			.await;
+		assert!(matches!(result, Err(Error::BlobKeyInvalid { .. })));
+
+		let result = store
+			.put("fx-blob", "text/plain", 10, stream_from_bytes("hello"))
+			.await;
+		assert!(matches!(
+			result,
+			Err(Error::BlobSizeMismatch {
+				expected: 10,
+				actual: 5,
+				..
+			})
+		));
+		let result = store.get("fx-blob").await;
+		assert!(matches!(result, Err(Error::BlobNotFound { .. })));
+
+		// -- Clean
+		fs::remove_dir_all(&dir).await?;
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
@@ -0,0 +1,143 @@
+//! Blob storage of the attachment contents (see `AttachmentBmc`).
+//!
+//! - `BlobStore` is the storage backend held by the `ModelManager`,
+//!   selected by `SERVICE_BLOB_STORE`.
+//! - `FsBlobStore` stores the blobs as files of a local directory.
+//! - `S3BlobStore` stores the blobs in the bucket of an S3 compatible service
+//!   (e.g., AWS S3, MinIO).
+//! - Keys are `/` separated paths of `[A-Za-z0-9._-]` segments,
+//!   not starting with a `.` (e.g., `task/1000/<uuid>`).
+
+// region:    --- Modules
+
+mod error;
+mod fs;
+mod s3;
+
+pub use self::error::{Error, Result};
+pub use self::fs::FsBlobStore;
+pub use self::s3::{S3BlobStore, S3Config};
+
+use std::io;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::{stream, Stream, TryStreamExt};
+use lazy_regex::regex_is_match;
+
+use crate::config::config;
+
+// endregion: --- Modules
+
+/// The content of a blob, streamed by chunks.
+pub type BlobStream = Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send>>;
+
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+	/// Store the `size` bytes of `body` at `key`, replacing the blob already there, if any.
+	/// Fails with `Error::BlobSizeMismatch` when `body` is not `size` bytes.
+	async fn put(&self, key: &str, content_type: &str, size: u64, body: BlobStream) -> Result<()>;
+
+	/// Fails with `Error::BlobNotFound` when there is no blob at `key`.
+	async fn get(&self, key: &str) -> Result<BlobStream>;
+
+	/// Succeeds when there is no blob at `key`.
+	async fn delete(&self, key: &str) -> Result<()>;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum BlobStoreKind {
+	/// `FsBlobStore` in `SERVICE_BLOB_FS_DIR`.
+	Fs,
+	/// `S3BlobStore` with the `SERVICE_BLOB_S3_*` settings.
+	S3,
+}
+
+impl FromStr for BlobStoreKind {
+	type Err = ();
+
+	fn from_str(val: &str) -> core::result::Result<Self, Self::Err> {
+		match val {
+			"fs" => Ok(Self::Fs),
+			"s3" => Ok(Self::S3),
+			_ => Err(()),
+		}
+	}
+}
+
+/// The blob store configured by the `SERVICE_BLOB_*` env.
+pub fn new_blob_store() -> Result<Arc<dyn BlobStore>> {
+	let config = config();
+
+	let store: Arc<dyn BlobStore> = match config.BLOB_STORE {
+		BlobStoreKind::Fs => {
+			let dir = config
+				.BLOB_FS_DIR
+				.as_ref()
+				.ok_or(Error::BlobStoreConfigMissing("SERVICE_BLOB_FS_DIR"))?;
+			Arc::new(FsBlobStore::new(dir))
+		}
+		BlobStoreKind::S3 => Arc::new(S3BlobStore::new(S3Config::from_config()?)?),
+	};
+
+	Ok(store)
+}
+
+/// A stream of `bytes`, in one chunk (e.g., small contents, tests).
+pub fn stream_from_bytes(bytes: impl Into<Bytes>) -> BlobStream {
+	Box::pin(stream::once(futures_util::future::ready(Ok(bytes.into()))))
+}
+
+/// The whole content of `stream`, in memory.
+pub async fn read_to_vec(stream: BlobStream) -> io::Result<Vec<u8>> {
+	stream
+		.try_fold(Vec::new(), |mut content, chunk| async move {
+			content.extend_from_slice(&chunk);
+			Ok(content)
+		})
+		.await
+}
+
+/// Fails with `Error::BlobKeyInvalid` unless `key` is a valid key
+/// (so that it is a safe relative path and needs no url encoding).
+fn check_key(key: &str) -> Result<()> {
+	if regex_is_match!(
+		r"^[A-Za-z0-9_-][A-Za-z0-9._-]*(/[A-Za-z0-9_-][A-Za-z0-9._-]*)*$",
+		key
+	) {
+		Ok(())
+	} else {
+		Err(Error::BlobKeyInvalid {
+			key: key.to_string(),
+		})
+	}
+}
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_check_key_ok() {
+		for key in ["task/1000/4f0c-a1", "a", "a.b/_c-d"] {
+			assert!(check_key(key).is_ok(), "{key}");
+		}
+	}
+
+	#[test]
+	fn test_check_key_err() {
+		for key in [
+			"", "/a", "a/", "a//b", "../a", "a/../b", ".a", "a/.b", "a b", "a\\b",
+		] {
+			assert!(
+				matches!(check_key(key), Err(Error::BlobKeyInvalid { .. })),
+				"{key}"
+			);
+		}
+	}
+}
+// endregion: --- Tests
@@ -0,0 +1,391 @@
+//! Read-through cache of the entities read with `common::get` (e.g., the task of each task
+//! method), for the `DbBmc::CACHED` tables only.
+//!
+//! - Keyed by table and id, then by entity type and ctx workspace scope.
+//! - Entries expire after `SERVICE_CACHE_TTL_SEC`, and at most `SERVICE_CACHE_SIZE_MAX` rows
+//!   are cached (the least recently used are evicted). `0` disables the cache.
+//! - Invalidated by the writes of the model layer (see `common::invalidate_cached`), and by
+//!   the `entity_changed` notifications of the update/delete triggers, sent on commit
+//!   by all the service instances (see `listen_job`, Postgres only).
+//! - Also read in a transaction (e.g., the ctx transaction of a web request), but for the rows
+//!   it wrote, which it must read from the db (see `ModelManager::cache_for`).
+
+use std::any::{Any, TypeId};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use sqlx::postgres::PgListener;
+use tracing::{debug, error, warn};
+
+use crate::config::config;
+use crate::model::{Error, Result};
+
+/// The channel of the `notify_entity_changed` trigger (see the migrations).
+const NOTIFY_CHANNEL: &str = "entity_changed";
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct CacheStats {
+	pub hits: u64,
+	pub misses: u64,
+	/// The cached rows.
+	pub entries: usize,
+}
+
+type CachedValue = Arc<dyn Any + Send + Sync>;
+
+/// The entity type, and the workspace condition of the ctx (see `common::workspace_cond`).
+type VariantKey = (TypeId, Option<String>);
+
+struct CachedRow {
+	variants: HashMap<VariantKey, (CachedValue, Instant)>,
+	last_used: u64,
+}
+
+type RowKey = (String, i64);
+
+#[derive(Default)]
+struct State {
+	rows: HashMap<RowKey, CachedRow>,
+	/// The rows by `last_used`, the least recently used first (for the eviction).
+	lru: BTreeMap<u64, RowKey>,
+	/// Bumped by each invalidation (see `EntityCache::put`).
+	generation: u64,
+	/// For the least recently used eviction.
+	tick: u64,
+}
+
+impl State {
+	/// Mark the row `key` as used now (it must be in `rows`).
+	fn touch(&mut self, key: &RowKey) {
+		self.tick += 1;
+		let tick = self.tick;
+		if let Some(row) = self.rows.get_mut(key) {
+			self.lru.remove(&row.last_used);
+			row.last_used = tick;
+			self.lru.insert(tick, key.clone());
+		}
+	}
+
+	fn remove(&mut self, key: &RowKey) {
+		if let Some(row) = self.rows.remove(key) {
+			self.lru.remove(&row.last_used);
+		}
+	}
+}
+
+pub struct EntityCache {
+	ttl: Duration,
+	size_max: usize,
+	state: Mutex<State>,
+	hits: AtomicU64,
+	misses: AtomicU64,
+}
+
+impl EntityCache {
+	pub(in crate::model) fn new(ttl: Duration, size_max: usize) -> Self {
+		EntityCache {
+			ttl,
+			size_max,
+			state: Mutex::default(),
+			hits: AtomicU64::new(0),
+			misses: AtomicU64::new(0),
+		}
+	}
+
+	pub(in crate::model) fn from_config() -> Self {
+		Self::new(
+			Duration::from_secs_f64(config().CACHE_TTL_SEC),
+			config().CACHE_SIZE_MAX,
+		)
+	}
+
+	pub(in crate::model) fn is_enabled(&self) -> bool {
+		self.size_max > 0
+	}
+
+	pub(in crate::model) fn get<E>(&self, table: &str, id: i64, scope: Option<&str>) -> Option<E>
+	where
+		E: Clone + 'static,
+	{
+		let mut state = self.lock();
+
+		let key = (table.to_string(), id);
+		let variant = (TypeId::of::<E>(), scope.map(str::to_string));
+		let value = match state.rows.get_mut(&key) {
+			Some(row) => match row.variants.get(&variant) {
+				Some((value, at)) if at.elapsed() < self.ttl => value.downcast_ref::<E>().cloned(),
+				Some(_) => {
+					row.variants.remove(&variant);
+					None
+				}
+				None => None,
+			},
+			None => None,
+		};
+		if value.is_some() {
+			state.touch(&key);
+		}
+
+		let counter = if value.is_some() {
+			&self.hits
+		} else {
+			&self.misses
+		};
+		counter.fetch_add(1, Ordering::Relaxed);
+
+		value
+	}
+
+	/// To be taken before reading the row from the db, for `put`.
+	pub(in crate::model) fn generation(&self) -> u64 {
+		self.lock().generation
+	}
+
+	/// Note: Ignored when there was an invalidation since `generation`,
+	///       as `value` may have been read before it.
+	pub(in crate::model) fn put<E>(
+		&self,
+		table: &str,
+		id: i64,
+		scope: Option<&str>,
+		generation: u64,
+		value: E,
+	) where
+		E: Send + Sync + 'static,
+	{
+		let mut state = self.lock();
+		if state.generation != generation {
+			return;
+		}
+
+		let key = (table.to_string(), id);
+		if !state.rows.contains_key(&key) && state.rows.len() >= self.size_max {
+			if let Some((_, lru_key)) = state.lru.pop_first() {
+				state.rows.remove(&lru_key);
+			}
+		}
+
+		let row = state.rows.entry(key.clone()).or_insert_with(|| CachedRow {
+			variants: HashMap::new(),
+			last_used: 0,
+		});
+		let variant = (TypeId::of::<E>(), scope.map(str::to_string));
+		row.variants
+			.insert(variant, (Arc::new(value), Instant::now()));
+		state.touch(&key);
+	}
+
+	pub(in crate::model) fn invalidate(&self, table: &str, id: i64) {
+		let mut state = self.lock();
+		state.generation += 1;
+		state.remove(&(table.to_string(), id));
+	}
+
+	pub(in crate::model) fn clear(&self) {
+		let mut state = self.lock();
+		state.generation += 1;
+		state.rows.clear();
+		state.lru.clear();
+	}
+
+	pub(in crate::model) fn stats(&self) -> CacheStats {
+		CacheStats {
+			hits: self.hits.load(Ordering::Relaxed),
+			misses: self.misses.load(Ordering::Relaxed),
+			entries: self.lock().rows.len(),
+		}
+	}
+
+	// Note: The state is always consistent, even if a thread panicked with the lock.
+	fn lock(&self) -> MutexGuard<'_, State> {
+		self.state.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+/// A listener of the `entity_changed` notifications, for `listen_job`.
+/// Note: With a connection of its own (not from the pool).
+pub(in crate::model) async fn new_listener(db_url: &str) -> Result<PgListener> {
+	let listen = async {
+		let mut listener = PgListener::connect(db_url).await?;
+		listener.listen(NOTIFY_CHANNEL).await?;
+		Ok::<_, sqlx::Error>(listener)
+	};
+
+	listen
+		.await
+		.map_err(|ex| Error::CacheListenFailed(ex.to_string()))
+}
+
+/// Invalidate the `cache` rows of the notifications, forever (to be spawned).
+/// Note: The whole cache is cleared when the connection is lost,
+///       as the notifications sent in the meantime are missed.
+pub(in crate::model) async fn listen_job(cache: Arc<EntityCache>, mut listener: PgListener) {
+	loop {
+		match listener.try_recv().await {
+			Ok(Some(notification)) => {
+				let payload = notification.payload();
+				match payload
+					.split_once(':')
+					.map(|(table, id)| (table, id.parse()))
+				{
+					Some((table, Ok(id))) => cache.invalidate(table, id),
+					_ => warn!(" {:<12} - invalid notification '{payload}'", "CACHE"),
+				}
+			}
+			Ok(None) => {
+				debug!(" {:<12} - listener reconnecting, cache cleared", "CACHE");
+				cache.clear();
+			}
+			Err(ex) => {
+				error!(" {:<12} - listener failed: {ex}", "CACHE");
+				cache.clear();
+				tokio::time::sleep(Duration::from_secs(1)).await;
+			}
+		}
+	}
+}
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::_dev_utils;
+	use crate::ctx::Ctx;
+	use crate::model::task::{TaskBmc, TaskForUpdate};
+	use crate::model::user::{UserBmc, UserForLogin};
+	use crate::model::ModelManager;
+	use anyhow::Result;
+	use serial_test::serial;
+
+	#[derive(Clone, Debug, PartialEq)]
+	struct FxEntity(&'static str);
+
+	#[test]
+	fn test_get_put_invalidate_ok() -> Result<()> {
+		let cache = EntityCache::new(Duration::from_secs(60), 10);
+
+		let generation = cache.generation();
+		assert_eq!(cache.get::<FxEntity>("task", 1, None), None);
+		cache.put("task", 1, None, generation, FxEntity("task 1"));
+		assert_eq!(cache.get("task", 1, None), Some(FxEntity("task 1")));
+
+		// Not for another type, or another workspace scope.
+		assert_eq!(cache.get::<String>("task", 1, None), None);
+		assert_eq!(cache.get::<FxEntity>("task", 1, Some("ws 2")), None);
+
+		cache.invalidate("task", 1);
+		assert_eq!(cache.get::<FxEntity>("task", 1, None), None);
+
+		// Not put when invalidated since the generation (the value may be stale).
+		cache.put("task", 1, None, generation, FxEntity("task 1"));
+		assert_eq!(cache.get::<FxEntity>("task", 1, None), None);
+
+		let stats = cache.stats();
+		assert_eq!((stats.hits, stats.misses, stats.entries), (1, 5, 0));
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_ttl_and_size_max_ok() -> Result<()> {
+		let cache = EntityCache::new(Duration::ZERO, 10);
+		cache.put("task", 1, None, cache.generation(), FxEntity("task 1"));
+		assert_eq!(cache.get::<FxEntity>("task", 1, None), None);
+
+		let cache = EntityCache::new(Duration::from_secs(60), 2);
+		for id in 1..=2 {
+			cache.put("task", id, None, cache.generation(), FxEntity("task"));
+		}
+		// -- The task 1 is used, so the task 2 is the least recently used.
+		cache.get::<FxEntity>("task", 1, None);
+		cache.put("task", 3, None, cache.generation(), FxEntity("task"));
+
+		assert!(cache.get::<FxEntity>("task", 1, None).is_some());
+		assert!(cache.get::<FxEntity>("task", 2, None).is_none());
+		assert!(cache.get::<FxEntity>("task", 3, None).is_some());
+		assert_eq!(cache.stats().entries, 2);
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_user_read_through_ok() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+		let user_id = _dev_utils::seed_user(&ctx, &mm, "test_user_read_through_ok").await?;
+		// Note: Two instances, each with its own cache, started after the user commit
+		//       (not to be notified of it).
+		let mm_1 = ModelManager::new().await?;
+		let mm_2 = ModelManager::new().await?;
+
+		// -- Exec & Check
+		let user: UserForLogin = UserBmc::get(&ctx, &mm_2, user_id).await?;
+		let _: UserForLogin = UserBmc::get(&ctx, &mm_2, user_id).await?;
+		let stats = mm_2.cache_stats();
+		assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 1));
+
+		// Also in a transaction, but for the rows it wrote.
+		let txn_mm = mm_2.new_with_ctx_txn(&ctx).await?;
+		let _: UserForLogin = UserBmc::get(&ctx, &txn_mm, user_id).await?;
+		assert_eq!(mm_2.cache_stats().hits, 2);
+		UserBmc::update_pwd(&ctx, &txn_mm, user_id, "test_user_read_through_ok txn").await?;
+		let user_txn: UserForLogin = UserBmc::get(&ctx, &txn_mm, user_id).await?;
+		assert_ne!(user_txn.pwd, user.pwd);
+		txn_mm.rollback().await?;
+		let user_2: UserForLogin = UserBmc::get(&ctx, &mm_2, user_id).await?;
+		assert_eq!(user_2.pwd, user.pwd);
+
+		// Invalidated by the update, in the other instance once notified.
+		UserBmc::update_pwd(&ctx, &mm_1, user_id, "test_user_read_through_ok pwd").await?;
+		let mut notified = false;
+		for _ in 0..100 {
+			let user_2: UserForLogin = UserBmc::get(&ctx, &mm_2, user_id).await?;
+			if user_2.pwd != user.pwd {
+				notified = true;
+				break;
+			}
+			tokio::time::sleep(Duration::from_millis(20)).await;
+		}
+		assert!(notified);
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_task_bulk_invalidate_ok() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+		let tasks = _dev_utils::seed_test(&ctx, &mm, &["test_task_bulk_invalidate_ok"]).await?;
+		let ids = [tasks[0].id];
+
+		// -- Exec & Check
+		TaskBmc::get(&ctx, &mm, ids[0]).await?;
+		let task_u = TaskForUpdate {
+			title: Some("test_task_bulk_invalidate_ok updated".to_string()),
+			..Default::default()
+		};
+		TaskBmc::update_many(&ctx, &mm, &ids, task_u).await?;
+		let task = TaskBmc::get(&ctx, &mm, ids[0]).await?;
+		assert_eq!(task.title, "test_task_bulk_invalidate_ok updated");
+
+		TaskBmc::delete_many(&ctx, &mm, &ids).await?;
+		let result = TaskBmc::get(&ctx, &mm, ids[0]).await;
+		assert!(matches!(result, Err(Error::EntityNotFound { .. })));
+
+		TaskBmc::restore(&ctx, &mm, ids[0]).await?;
+		TaskBmc::get(&ctx, &mm, ids[0]).await?;
+
+		// -- Clean
+		TaskBmc::delete(&ctx, &mm, ids[0]).await?;
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
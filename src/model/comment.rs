@@ -0,0 +1,325 @@
+use lazy_regex::regex;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use sqlx::prelude::FromRow;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::ctx::Ctx;
+use crate::model::error::{Error, Result};
+
+use super::common::DbBmc;
+use super::fields::{Fields, HasFields};
+use super::store::{with_db, Backend, SqlValue};
+use super::task::TaskBmc;
+use super::user::{User, UserBmc};
+use super::{common, FilterNode, FilterNodes, ListOptions, ModelManager, OpValsInt64};
+
+// Model: Comment struct
+#[serde_as]
+#[derive(Clone, Debug, Serialize, FromRow, Fields)]
+pub struct Comment {
+	pub id: i64,
+	pub task_id: i64,
+	pub author_id: i64,
+	/// Markdown, with `@username` mentions
+	pub content: String,
+
+	// -- Timestamps
+	pub cid: i64,
+	#[serde_as(as = "Rfc3339")]
+	pub ctime: OffsetDateTime,
+	pub mid: i64,
+	#[serde_as(as = "Rfc3339")]
+	pub mtime: OffsetDateTime,
+}
+
+// Comment entity for creating method
+// Note: The author is always the ctx user.
+#[derive(Deserialize, Fields)]
+pub struct CommentForCreate {
+	pub task_id: i64,
+	pub content: String,
+}
+
+// Comment entity for updating method
+#[derive(Default, Deserialize, Fields)]
+pub struct CommentForUpdate {
+	pub content: Option<String>,
+}
+
+// Comment filter for list method
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CommentFilter {
+	pub id: Option<OpValsInt64>,
+	pub task_id: Option<OpValsInt64>,
+	pub author_id: Option<OpValsInt64>,
+}
+
+impl FilterNodes for CommentFilter {
+	fn filter_nodes(self) -> Vec<FilterNode> {
+		let mut nodes = Vec::new();
+		if let Some(id) = self.id {
+			nodes.extend(id.into_filter_nodes("id"));
+		}
+		if let Some(task_id) = self.task_id {
+			nodes.extend(task_id.into_filter_nodes("task_id"));
+		}
+		if let Some(author_id) = self.author_id {
+			nodes.extend(author_id.into_filter_nodes("author_id"));
+		}
+		nodes
+	}
+}
+
+// Comment Backend Model Controller
+pub struct CommentBmc;
+
+impl CommentBmc {
+	pub async fn create(ctx: &Ctx, mm: &ModelManager, comment_c: CommentForCreate) -> Result<i64> {
+		let task = TaskBmc::get(ctx, mm, comment_c.task_id).await?;
+		let content = comment_c.content.clone();
+
+		let mut fields = comment_c.not_none_fields();
+		fields.push(("author_id", ctx.user_id()).into());
+		fields.push(("workspace_id", task.workspace_id).into());
+		let id = common::create_fields::<Self>(ctx, mm, fields).await?;
+
+		Self::sync_mentions(ctx, mm, id, &content).await?;
+
+		Ok(id)
+	}
+
+	/// Only the author can update the comment.
+	pub async fn update(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: i64,
+		comment_u: CommentForUpdate,
+	) -> Result<()> {
+		Self::get_as_author(ctx, mm, id).await?;
+		let content = comment_u.content.clone();
+
+		common::update::<Self, _>(ctx, mm, id, comment_u).await?;
+
+		if let Some(content) = content {
+			Self::sync_mentions(ctx, mm, id, &content).await?;
+		}
+
+		Ok(())
+	}
+
+	pub async fn list(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		filter: Option<CommentFilter>,
+		list_options: Option<ListOptions>,
+	) -> Result<Vec<Comment>> {
+		common::list::<Self, _, _>(ctx, mm, filter, list_options).await
+	}
+
+	pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Comment> {
+		common::get::<Self, _>(ctx, mm, id).await
+	}
+
+	/// Only the author can delete the comment.
+	pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+		Self::get_as_author(ctx, mm, id).await?;
+
+		common::delete::<Self, Comment>(ctx, mm, id).await
+	}
+
+	/// The ids of the users mentioned in the comment
+	/// (e.g., for the notifications).
+	pub async fn list_mentioned_user_ids(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: i64,
+	) -> Result<Vec<i64>> {
+		let user_ids: Vec<(i64,)> = with_db!(ctx, mm, |db| sqlx::query_as(
+			"SELECT user_id FROM mention WHERE comment_id = $1 ORDER BY user_id"
+		)
+		.bind(id)
+		.fetch_all(db)
+		.await?);
+
+		Ok(user_ids.into_iter().map(|(user_id,)| user_id).collect())
+	}
+
+	async fn get_as_author(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Comment> {
+		let comment = Self::get(ctx, mm, id).await?;
+		if comment.author_id != ctx.user_id() {
+			return Err(Error::CommentNotAuthor { id });
+		}
+
+		Ok(comment)
+	}
+
+	/// Replace the mentions of the comment with the users mentioned in `content`.
+	/// Unknown usernames are ignored.
+	async fn sync_mentions(ctx: &Ctx, mm: &ModelManager, id: i64, content: &str) -> Result<()> {
+		let mut user_ids = Vec::new();
+		for username in parse_mentions(content) {
+			let user: Option<User> = UserBmc::first_by_username(ctx, mm, username).await?;
+			if let Some(user) = user {
+				user_ids.push(user.id);
+			}
+		}
+
+		let user_ids = SqlValue::from(user_ids);
+		match mm.backend() {
+			// Note: Single statement, so that the mentions are replaced at once.
+			Backend::Postgres => {
+				with_db!(ctx, mm, |db| sqlx::query(
+					"WITH removed AS ( \
+						DELETE FROM mention WHERE comment_id = $1 AND NOT (user_id = ANY($2)) \
+					) \
+					INSERT INTO mention (comment_id, user_id) SELECT $1, unnest($2::bigint[]) \
+					ON CONFLICT DO NOTHING",
+				)
+				.bind(id)
+				.bind(&user_ids)
+				.execute(db)
+				.await?
+				.rows_affected());
+			}
+			// Note: No data-modifying CTE in SQLite, the two statements in a transaction.
+			Backend::Sqlite => {
+				let txn_mm = common::own_txn(ctx, mm).await?;
+				let mm = txn_mm.as_ref().unwrap_or(mm);
+
+				with_db!(ctx, mm, |db| sqlx::query(
+					"DELETE FROM mention WHERE comment_id = $1 \
+					 AND user_id NOT IN (SELECT value FROM json_each($2))",
+				)
+				.bind(id)
+				.bind(&user_ids)
+				.execute(db)
+				.await?
+				.rows_affected());
+				with_db!(ctx, mm, |db| sqlx::query(
+					"INSERT INTO mention (comment_id, user_id) \
+					 SELECT $1, value FROM json_each($2) WHERE TRUE ON CONFLICT DO NOTHING",
+				)
+				.bind(id)
+				.bind(&user_ids)
+				.execute(db)
+				.await?
+				.rows_affected());
+
+				common::commit_own_txn(txn_mm).await?;
+			}
+		}
+
+		Ok(())
+	}
+}
+
+// Impl Trait Dbmc for Comment model
+impl DbBmc for CommentBmc {
+	const TABLE: &'static str = "comment";
+	const WORKSPACE_SCOPED: bool = true;
+}
+
+/// The distinct `@username` mentions of the content, in order of appearance.
+/// Note: A `@` preceded by a word character is not a mention (e.g., `me@mail.com`).
+fn parse_mentions(content: &str) -> Vec<String> {
+	let mut usernames: Vec<String> = Vec::new();
+	for caps in regex!(r"(?:^|[^\w@.])@(\w+(?:[.-]\w+)*)").captures_iter(content) {
+		let username = caps[1].to_string();
+		if !usernames.contains(&username) {
+			usernames.push(username);
+		}
+	}
+
+	usernames
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(unused)]
+	use crate::_dev_utils;
+
+	use super::*;
+	use anyhow::Result;
+	use serial_test::serial;
+
+	#[test]
+	fn test_parse_mentions_ok() -> Result<()> {
+		let usernames = parse_mentions(
+			"@sau can you check with @john.doe, and @sau again? Not me@mail.com nor @@x.",
+		);
+
+		assert_eq!(usernames, ["sau", "john.doe"]);
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_create_mentions_ok() -> Result<()> {
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+		let fx_task_id =
+			_dev_utils::seed_test(&ctx, &mm, &["test_create_mentions_ok"]).await?[0].id;
+		let fx_user: User = UserBmc::first_by_username(&ctx, &mm, "sau".to_string())
+			.await?
+			.expect("sau should be seeded");
+
+		let comment_c = CommentForCreate {
+			task_id: fx_task_id,
+			content: "Ping @sau and @unknown_user".to_string(),
+		};
+		let id = CommentBmc::create(&ctx, &mm, comment_c).await?;
+
+		let comment = CommentBmc::get(&ctx, &mm, id).await?;
+		assert_eq!(comment.author_id, ctx.user_id());
+		let user_ids = CommentBmc::list_mentioned_user_ids(&ctx, &mm, id).await?;
+		assert_eq!(user_ids, [fx_user.id]);
+
+		// -- Editing the content updates the mentions.
+		let comment_u = CommentForUpdate {
+			content: Some("No more mention".to_string()),
+		};
+		CommentBmc::update(&ctx, &mm, id, comment_u).await?;
+		let user_ids = CommentBmc::list_mentioned_user_ids(&ctx, &mm, id).await?;
+		assert!(user_ids.is_empty());
+
+		// -- Clean
+		CommentBmc::delete(&ctx, &mm, id).await?;
+		TaskBmc::delete(&ctx, &mm, fx_task_id).await?;
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_update_delete_err_not_author() -> Result<()> {
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+		let other_ctx = Ctx::new(1000)?;
+		let fx_task_id =
+			_dev_utils::seed_test(&ctx, &mm, &["test_update_delete_err_not_author"]).await?[0].id;
+		let comment_c = CommentForCreate {
+			task_id: fx_task_id,
+			content: "test_update_delete_err_not_author".to_string(),
+		};
+		let id = CommentBmc::create(&ctx, &mm, comment_c).await?;
+
+		let comment_u = CommentForUpdate {
+			content: Some("edited".to_string()),
+		};
+		let result = CommentBmc::update(&other_ctx, &mm, id, comment_u).await;
+		assert!(matches!(result, Err(Error::CommentNotAuthor { id: err_id }) if err_id == id));
+
+		let result = CommentBmc::delete(&other_ctx, &mm, id).await;
+		assert!(matches!(result, Err(Error::CommentNotAuthor { id: err_id }) if err_id == id));
+
+		// -- The author can.
+		CommentBmc::delete(&ctx, &mm, id).await?;
+		TaskBmc::delete(&ctx, &mm, fx_task_id).await?;
+
+		Ok(())
+	}
+}
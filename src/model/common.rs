@@ -1,110 +1,1178 @@
-use sqlb::HasFields;
-use sqlx::{postgres::PgRow, FromRow, PgExecutor, Row};
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+use serde_json::Value;
+use time::OffsetDateTime;
 
 use crate::ctx::Ctx;
+use crate::utils::now_utc;
 
+use super::audit::{self, AuditBmc, AuditOp};
+use super::fields::{Field, HasFields};
+use super::filter::{FilterNodes, ListOptions, OrderBy, LIST_LIMIT_DEFAULT, LIST_LIMIT_MAX};
+use super::page::{Cursor, Page, PageOptions};
+use super::store::{id_row_json, with_db, Backend, DbRow, Sql};
 use super::ModelManager;
 
 use crate::model::{Error, Result};
 
 pub trait DbBmc {
 	const TABLE: &'static str;
+
+	/// When `true`, the table has a `deleted_at` column, and `delete` only sets it.
+	/// Deleted rows are then excluded from `get`, `list`, `update` and `delete`,
+	/// until they are restored or purged.
+	const SOFT_DELETE: bool = false;
+
+	/// When `true`, the table has a `version` column, bumped on every update.
+	/// (see `update_with_version`)
+	const VERSIONED: bool = false;
+
+	/// When `true`, the table has a `workspace_id` column (the tenant), set on create from
+	/// the ctx workspace. The rows of other workspaces are then excluded from all the
+	/// queries of `common` (see `workspace_cond`).
+	const WORKSPACE_SCOPED: bool = false;
+
+	/// When `true`, `get` reads through the entity cache (see `model::cache`).
+	/// The table must have the `notify_entity_changed` trigger, and the writes outside of
+	/// `common` must `invalidate_cached` the rows (or the other reads are stale until
+	/// the notification of their commit).
+	const CACHED: bool = false;
 }
 
-pub async fn get<MC, E>(_ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<E>
+pub async fn get<MC, E>(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<E>
 where
 	MC: DbBmc,
-	E: for<'r> FromRow<'r, PgRow> + Unpin + Send,
-	E: HasFields,
+	E: DbRow,
+	E: HasFields + Clone + Sync + 'static,
 {
-	let res = sqlb::select()
-		.table(MC::TABLE)
-		.and_where("id", "=", id)
-		.columns(E::field_names())
-		.fetch_optional(mm.db())
-		.await?
-		.ok_or(Error::EntityNotFound {
+	let cache = mm.cache_for(MC::TABLE, id).filter(|_| MC::CACHED);
+	let scope = workspace_cond::<MC>(ctx);
+	let generation = match cache {
+		Some(cache) => match cache.get::<E>(MC::TABLE, id, scope.as_deref()) {
+			Some(entity) => return Ok(entity),
+			None => Some(cache.generation()),
+		},
+		None => None,
+	};
+
+	let mut sql = select_sql::<MC>(mm, E::field_names());
+	sql.push(" AND \"id\" = ").push_bind(id);
+	push_not_deleted::<MC>(&mut sql);
+	push_workspace_cond::<MC>(ctx, &mut sql);
+	let res: E = with_db!(ctx, mm, |db| sql.query_as().fetch_optional(db).await?).ok_or(
+		Error::EntityNotFound {
 			entity: MC::TABLE,
 			id,
-		})?;
+		},
+	)?;
+
+	if let (Some(cache), Some(generation)) = (cache, generation) {
+		cache.put(MC::TABLE, id, scope.as_deref(), generation, res.clone());
+	}
 
 	Ok(res)
 }
 
-pub async fn list<MC, E>(_ctx: &Ctx, mm: &ModelManager) -> Result<Vec<E>>
+pub async fn list<MC, E, F>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	filter: Option<F>,
+	list_options: Option<ListOptions>,
+) -> Result<Vec<E>>
+where
+	MC: DbBmc,
+	E: DbRow,
+	E: HasFields,
+	F: FilterNodes,
+{
+	list_with_deleted::<MC, E, F>(ctx, mm, filter, list_options, false).await
+}
+
+/// Same as `list`, for the deleted rows only (i.e., the trash).
+/// Only for the `DbBmc::SOFT_DELETE` tables.
+pub async fn list_deleted<MC, E, F>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	filter: Option<F>,
+	list_options: Option<ListOptions>,
+) -> Result<Vec<E>>
 where
 	MC: DbBmc,
-	E: for<'r> FromRow<'r, PgRow> + Unpin + Send,
+	E: DbRow,
 	E: HasFields,
+	F: FilterNodes,
 {
-	let res = sqlb::select()
-		.table(MC::TABLE)
-		.columns(E::field_names())
-		.order_by("id")
-		.fetch_all(mm.db())
-		.await?;
+	list_with_deleted::<MC, E, F>(ctx, mm, filter, list_options, true).await
+}
+
+async fn list_with_deleted<MC, E, F>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	filter: Option<F>,
+	list_options: Option<ListOptions>,
+	deleted: bool,
+) -> Result<Vec<E>>
+where
+	MC: DbBmc,
+	E: DbRow,
+	E: HasFields,
+	F: FilterNodes,
+{
+	let mut sql = select_sql::<MC>(mm, E::field_names());
+	if MC::SOFT_DELETE {
+		match deleted {
+			false => sql.push(" AND \"deleted_at\" IS NULL"),
+			true => sql.push(" AND \"deleted_at\" IS NOT NULL"),
+		};
+	}
+	push_workspace_cond::<MC>(ctx, &mut sql);
+	push_filter(&mut sql, filter);
+
+	// -- Add the order bys, limit and offset.
+	list_options
+		.unwrap_or_default()
+		.push_to_sql(&mut sql, E::field_names())?;
+
+	let res = with_db!(ctx, mm, |db| sql.query_as().fetch_all(db).await?);
 
 	Ok(res)
 }
 
-pub async fn create<MC, E>(_ctx: &Ctx, mm: &ModelManager, data: E) -> Result<i64>
+/// Keyset (cursor) paginated version of `list`.
+/// See `model::page` for the cursor format and the keyset condition.
+pub async fn list_page<MC, E, F>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	filter: Option<F>,
+	page_options: Option<PageOptions>,
+) -> Result<Page<E>>
+where
+	MC: DbBmc,
+	E: DbRow,
+	E: HasFields + Serialize,
+	F: FilterNodes,
+{
+	let PageOptions {
+		limit,
+		order_by,
+		cursor,
+	} = page_options.unwrap_or_default();
+
+	let order_by = order_by.unwrap_or(OrderBy::Asc("id".to_string()));
+	if !E::field_names().contains(&order_by.column()) {
+		return Err(Error::ListOrderByUnknownColumn(
+			order_by.column().to_string(),
+		));
+	}
+	let limit = limit.unwrap_or(LIST_LIMIT_DEFAULT).clamp(1, LIST_LIMIT_MAX);
+	let cursor = cursor
+		.map(|cursor| Cursor::decode(MC::TABLE, &cursor, &order_by))
+		.transpose()?;
+
+	// -- Build the query.
+	let mut sql = select_sql::<MC>(mm, E::field_names());
+	push_not_deleted::<MC>(&mut sql);
+	push_workspace_cond::<MC>(ctx, &mut sql);
+	push_filter(&mut sql, filter);
+
+	if let Some(cursor) = cursor {
+		sql.push(" AND ");
+		cursor
+			.push_keyset_cond(ctx, mm, &mut sql, MC::TABLE)
+			.await?;
+	}
+
+	let (column, dir) = match &order_by {
+		OrderBy::Asc(column) => (column, "ASC"),
+		OrderBy::Desc(column) => (column, "DESC"),
+	};
+	if column == "id" {
+		sql.push(format!(" ORDER BY \"id\" {dir}"));
+	} else {
+		// Note: The nulls last as with Postgres (see `page::Cursor::push_keyset_cond`).
+		let nulls = if dir == "ASC" { "LAST" } else { "FIRST" };
+		sql.push(format!(
+			" ORDER BY \"{column}\" {dir} NULLS {nulls}, \"id\" {dir}"
+		));
+	}
+	// Note: One more item to know if there is a next page.
+	sql.push(" LIMIT ").push_bind(limit + 1);
+
+	// -- Exec and build the page.
+	let mut items: Vec<E> = with_db!(ctx, mm, |db| sql.query_as().fetch_all(db).await?);
+
+	let next_cursor = if items.len() as i64 > limit {
+		items.truncate(limit as usize);
+		items
+			.last()
+			.map(|last| Cursor::from_item(last, &order_by)?.encode(MC::TABLE))
+			.transpose()?
+	} else {
+		None
+	};
+
+	Ok(Page { items, next_cursor })
+}
+
+pub async fn create<MC, E>(ctx: &Ctx, mm: &ModelManager, data: E) -> Result<i64>
 where
 	MC: DbBmc,
 	E: HasFields,
 {
-	let fields = data.not_none_fields();
+	create_fields::<MC>(ctx, mm, data.not_none_fields()).await
+}
+
+/// Same as `create`, for the Bmcs that need to add computed fields
+/// to the data fields (e.g., `TaskBmc` with `done_at`).
+pub async fn create_fields<MC>(ctx: &Ctx, mm: &ModelManager, mut fields: Vec<Field>) -> Result<i64>
+where
+	MC: DbBmc,
+{
+	let names = field_names(&fields);
+	add_workspace_for_create::<MC>(&mut fields, ctx);
+	add_timestamps_for_create(&mut fields, ctx.user_id());
+
+	let txn_mm = own_txn(ctx, mm).await?;
+	let mm = txn_mm.as_ref().unwrap_or(mm);
 
-	let (id,) = sqlb::insert()
-		.table(MC::TABLE)
-		.data(fields)
-		.returning(&["id"])
-		.fetch_one::<_, (i64,)>(mm.db())
-		.await?;
+	let mut sql = insert_sql::<MC>(mm, &fields);
+	push_returning_row_json::<MC>(mm, &mut sql);
+	let (id, new) = with_db!(ctx, mm, |db| id_row_json(
+		&sql.query().fetch_one(db).await?
+	)?);
+
+	let diff = audit::diff(None, Some(&new), Some(&names));
+	AuditBmc::log(ctx, mm, MC::TABLE, AuditOp::Create, vec![(id, diff)]).await?;
+
+	commit_own_txn(txn_mm).await?;
 
 	Ok(id)
 }
 
-pub async fn delete<MC, E>(_ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()>
+/// Note: For the `DbBmc::SOFT_DELETE` tables, only sets `deleted_at`
+///       (see `restore` and `purge_deleted`).
+pub async fn delete<MC, E>(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()>
 where
 	MC: DbBmc,
 	E: HasFields,
 {
-	let row_effected = sqlb::delete()
-		.table(MC::TABLE)
-		.and_where("id", "=", id)
-		.exec(mm.db())
-		.await?;
+	let txn_mm = own_txn(ctx, mm).await?;
+	let mm = txn_mm.as_ref().unwrap_or(mm);
+
+	let deleted = if MC::SOFT_DELETE {
+		let mut fields = vec![("deleted_at", now_utc()).into()];
+		add_timestamps_for_update(&mut fields, ctx.user_id());
+		add_version_bump::<MC>(&mut fields);
 
-	if row_effected == 0 {
+		let mut sql = update_sql::<MC>(mm, &fields, id);
+		sql.push(" AND \"deleted_at\" IS NULL");
+		push_workspace_cond::<MC>(ctx, &mut sql);
+		let names = ["deleted_at".to_string()];
+		exec_update_audited::<MC>(ctx, mm, id, AuditOp::Delete, &names, sql).await?
+	} else {
+		let mut sql = Sql::new(
+			mm.backend(),
+			format!("DELETE FROM \"{}\" WHERE \"id\" = ", MC::TABLE),
+		);
+		sql.push_bind(id);
+		push_workspace_cond::<MC>(ctx, &mut sql);
+		push_returning_row_json::<MC>(mm, &mut sql);
+		let old = with_db!(ctx, mm, |db| sql
+			.query()
+			.fetch_optional(db)
+			.await?
+			.map(|row| id_row_json(&row))
+			.transpose()?);
+
+		if let Some((_, old)) = &old {
+			let diff = audit::diff(Some(old), None, None);
+			AuditBmc::log(ctx, mm, MC::TABLE, AuditOp::Delete, vec![(id, diff)]).await?;
+		}
+		old.is_some()
+	};
+
+	if !deleted {
 		return Err(Error::EntityNotFound {
 			entity: MC::TABLE,
 			id,
 		});
 	}
+	invalidate_cached::<MC>(mm, id);
+
+	commit_own_txn(txn_mm).await?;
 
 	Ok(())
 }
 
-pub async fn update<MC, E>(_ctx: &Ctx, mm: &ModelManager, id: i64, data: E) -> Result<()>
+pub async fn update<MC, E>(ctx: &Ctx, mm: &ModelManager, id: i64, data: E) -> Result<()>
+where
+	MC: DbBmc,
+	E: HasFields,
+{
+	update_fields::<MC>(ctx, mm, id, data.not_none_fields()).await
+}
+
+/// Same as `update`, but fails with `Error::VersionConflict` when the row
+/// `version` is not `version` anymore (i.e., it was updated by someone else).
+/// Only for the `DbBmc::VERSIONED` tables.
+pub async fn update_with_version<MC, E>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	id: i64,
+	version: i64,
+	data: E,
+) -> Result<()>
 where
 	MC: DbBmc,
 	E: HasFields,
 {
-	let fields = data.not_none_fields();
+	update_fields_with_version::<MC>(ctx, mm, id, Some(version), data.not_none_fields()).await
+}
 
-	let row_effected = sqlb::update()
-		.table(MC::TABLE)
-		.data(fields)
-		.and_where("id", "=", id)
-		.exec(mm.db())
-		.await?;
+/// Same as `update`, for the Bmcs that need to add computed fields
+/// to the data fields (e.g., `TaskBmc` with `done_at`).
+pub async fn update_fields<MC>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	id: i64,
+	fields: Vec<Field>,
+) -> Result<()>
+where
+	MC: DbBmc,
+{
+	update_fields_with_version::<MC>(ctx, mm, id, None, fields).await
+}
+
+/// Same as `update_fields`, with the optional expected `version`
+/// (see `update_with_version`).
+pub async fn update_fields_with_version<MC>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	id: i64,
+	version: Option<i64>,
+	mut fields: Vec<Field>,
+) -> Result<()>
+where
+	MC: DbBmc,
+{
+	let names = field_names(&fields);
+	add_timestamps_for_update(&mut fields, ctx.user_id());
+	add_version_bump::<MC>(&mut fields);
+
+	let txn_mm = own_txn(ctx, mm).await?;
+	let mm = txn_mm.as_ref().unwrap_or(mm);
+
+	let mut sql = update_sql::<MC>(mm, &fields, id);
+	push_not_deleted::<MC>(&mut sql);
+	if let Some(version) = version {
+		sql.push(" AND \"version\" = ").push_bind(version);
+	}
+	push_workspace_cond::<MC>(ctx, &mut sql);
+	let updated = exec_update_audited::<MC>(ctx, mm, id, AuditOp::Update, &names, sql).await?;
+
+	if !updated {
+		// -- Tell a version conflict from a missing row.
+		if let Some(version) = version {
+			let mut sql = select_sql::<MC>(mm, &["version"]);
+			sql.push(" AND \"id\" = ").push_bind(id);
+			push_not_deleted::<MC>(&mut sql);
+			push_workspace_cond::<MC>(ctx, &mut sql);
+			let current: Option<(i64,)> =
+				with_db!(ctx, mm, |db| sql.query_as().fetch_optional(db).await?);
+
+			if let Some((current,)) = current {
+				return Err(Error::VersionConflict {
+					entity: MC::TABLE,
+					id,
+					expected: version,
+					current,
+				});
+			}
+		}
 
-	if row_effected == 0 {
 		return Err(Error::EntityNotFound {
 			entity: MC::TABLE,
 			id,
 		});
 	}
+	invalidate_cached::<MC>(mm, id);
+
+	commit_own_txn(txn_mm).await?;
 
 	Ok(())
 }
+
+/// Lock the row `id` for the rest of the transaction, and fail with `Error::VersionConflict`
+/// when its `version` is not `version` anymore (e.g., before a delete at `version`).
+/// Only for the `DbBmc::VERSIONED` tables.
+/// Note: Must run in a transaction.
+pub async fn lock_with_version<MC>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	id: i64,
+	version: i64,
+) -> Result<()>
+where
+	MC: DbBmc,
+{
+	let mut sql = format!("SELECT version FROM \"{}\" WHERE id = $1", MC::TABLE);
+	if MC::SOFT_DELETE {
+		sql.push_str(" AND deleted_at IS NULL");
+	}
+	if let Some(cond) = workspace_cond::<MC>(ctx) {
+		sql.push_str(&format!(" AND {cond}"));
+	}
+	sql.push_str(mm.backend().for_update());
+	let current: Option<(i64,)> = with_db!(ctx, mm, |db| sqlx::query_as(&sql)
+		.bind(id)
+		.fetch_optional(db)
+		.await?);
+
+	match current {
+		None => Err(Error::EntityNotFound {
+			entity: MC::TABLE,
+			id,
+		}),
+		Some((current,)) if current != version => Err(Error::VersionConflict {
+			entity: MC::TABLE,
+			id,
+			expected: version,
+			current,
+		}),
+		Some(_) => Ok(()),
+	}
+}
+
+/// Exec the update `sql` of the row `id`, and write its `op` audit row
+/// with the diff of the `names` fields. Returns `false` when no row was updated.
+/// Note: Must run in a transaction (see `own_txn`), as the row is locked first.
+async fn exec_update_audited<MC>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	id: i64,
+	op: AuditOp,
+	names: &[String],
+	mut sql: Sql,
+) -> Result<bool>
+where
+	MC: DbBmc,
+{
+	let old = rows_json_for_update::<MC>(ctx, mm, &[id])
+		.await?
+		.remove(&id);
+
+	push_returning_row_json::<MC>(mm, &mut sql);
+	let new = with_db!(ctx, mm, |db| sql
+		.query()
+		.fetch_optional(db)
+		.await?
+		.map(|row| id_row_json(&row))
+		.transpose()?);
+
+	let Some((_, new)) = new else {
+		return Ok(false);
+	};
+	let diff = audit::diff(old.as_ref(), Some(&new), Some(names));
+	AuditBmc::log(ctx, mm, MC::TABLE, op, vec![(id, diff)]).await?;
+
+	Ok(true)
+}
+
+// region:    --- Bulk
+
+/// The max number of items of a bulk operation (e.g., `create_many`).
+/// Note: Keeps the multi-row insert well under the postgres bind parameters limit.
+pub const BULK_LIMIT_MAX: usize = 1000;
+
+/// The ids of a bulk operation, in the order of the request, without duplicates.
+#[derive(Debug, Default, Serialize)]
+pub struct BulkResult {
+	/// The ids of the rows which were updated/deleted.
+	pub ok_ids: Vec<i64>,
+	/// The ids with no (not deleted) row.
+	pub not_found_ids: Vec<i64>,
+}
+
+impl BulkResult {
+	pub(in crate::model) fn from_ids(ids: &[i64], affected_ids: &[i64]) -> Self {
+		let affected_ids: HashSet<i64> = affected_ids.iter().copied().collect();
+		let mut seen = HashSet::new();
+
+		let mut res = BulkResult::default();
+		for id in ids.iter().filter(|id| seen.insert(**id)) {
+			if affected_ids.contains(id) {
+				res.ok_ids.push(*id);
+			} else {
+				res.not_found_ids.push(*id);
+			}
+		}
+
+		res
+	}
+}
+
+/// Create all the entities with one multi-row insert (all or none).
+/// Returns the new ids, in the order of `data`.
+pub async fn create_many<MC, E>(ctx: &Ctx, mm: &ModelManager, data: Vec<E>) -> Result<Vec<i64>>
+where
+	MC: DbBmc,
+	E: HasFields,
+{
+	let rows = data.into_iter().map(|e| e.not_none_fields()).collect();
+
+	create_many_fields::<MC>(ctx, mm, rows).await
+}
+
+/// Same as `create_many`, for the Bmcs that need to add computed fields
+/// to the data fields (e.g., `TaskBmc` with `done_at`).
+pub async fn create_many_fields<MC>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	mut rows: Vec<Vec<Field>>,
+) -> Result<Vec<i64>>
+where
+	MC: DbBmc,
+{
+	check_bulk_limit(rows.len())?;
+	if rows.is_empty() {
+		return Ok(Vec::new());
+	}
+
+	let names: Vec<Vec<String>> = rows.iter().map(|fields| field_names(fields)).collect();
+	for fields in rows.iter_mut() {
+		add_workspace_for_create::<MC>(fields, ctx);
+		add_timestamps_for_create(fields, ctx.user_id());
+	}
+
+	let txn_mm = own_txn(ctx, mm).await?;
+	let mm = txn_mm.as_ref().unwrap_or(mm);
+
+	let rows = match mm.backend() {
+		Backend::Postgres => exec_insert_values::<MC>(ctx, mm, &rows).await?,
+		// Note: No `DEFAULT` in the `VALUES` of SQLite, one insert per row.
+		Backend::Sqlite => {
+			let mut new_rows = Vec::with_capacity(rows.len());
+			for fields in rows.iter() {
+				let mut sql = insert_sql::<MC>(mm, fields);
+				push_returning_row_json::<MC>(mm, &mut sql);
+				new_rows.push(with_db!(ctx, mm, |db| id_row_json(
+					&sql.query().fetch_one(db).await?
+				)?));
+			}
+			new_rows
+		}
+	};
+	let mut ids = Vec::with_capacity(rows.len());
+	let mut diffs = Vec::with_capacity(rows.len());
+	for ((id, new), names) in rows.into_iter().zip(&names) {
+		ids.push(id);
+		diffs.push((id, audit::diff(None, Some(&new), Some(names))));
+	}
+	AuditBmc::log(ctx, mm, MC::TABLE, AuditOp::Create, diffs).await?;
+
+	commit_own_txn(txn_mm).await?;
+
+	Ok(ids)
+}
+
+/// The one multi-row insert of `create_many_fields` (Postgres), with the new rows in order.
+async fn exec_insert_values<MC>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	rows: &[Vec<Field>],
+) -> Result<Vec<(i64, Value)>>
+where
+	MC: DbBmc,
+{
+	// -- The columns of all the rows. The ones missing from a row get their `DEFAULT`.
+	let mut columns: Vec<&str> = Vec::new();
+	for field in rows.iter().flatten() {
+		if !columns.contains(&field.name) {
+			columns.push(field.name);
+		}
+	}
+	let rows: Vec<Vec<Option<&Field>>> = rows
+		.iter()
+		.map(|fields| {
+			columns
+				.iter()
+				.map(|column| fields.iter().find(|f| f.name == *column))
+				.collect()
+		})
+		.collect();
+
+	// -- Build the sql.
+	let mut sql = Sql::new(
+		mm.backend(),
+		format!(
+			"INSERT INTO \"{}\" ({}) VALUES ",
+			MC::TABLE,
+			column_list(&columns)
+		),
+	);
+	for (i, row) in rows.iter().enumerate() {
+		sql.push(if i == 0 { "(" } else { ", (" });
+		for (j, field) in row.iter().enumerate() {
+			if j > 0 {
+				sql.push(", ");
+			}
+			match field {
+				Some(field) => field.push_value(&mut sql),
+				None => {
+					sql.push("DEFAULT");
+				}
+			}
+		}
+		sql.push(")");
+	}
+	// Note: Postgres returns the rows of an `INSERT ... VALUES` in the order of the values.
+	push_returning_row_json::<MC>(mm, &mut sql);
+
+	let rows = with_db!(ctx, mm, |db| sql
+		.query()
+		.fetch_all(db)
+		.await?
+		.iter()
+		.map(id_row_json)
+		.collect::<sqlx::Result<Vec<_>>>()?);
+
+	Ok(rows)
+}
+
+/// Apply the same `data` to all the `ids` with one update.
+pub async fn update_many<MC, E>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	ids: &[i64],
+	data: E,
+) -> Result<BulkResult>
+where
+	MC: DbBmc,
+	E: HasFields,
+{
+	update_many_fields::<MC>(ctx, mm, ids, data.not_none_fields()).await
+}
+
+/// Same as `update_many`, for the Bmcs that need to add computed fields
+/// to the data fields (e.g., `TaskBmc` with `done_at`).
+pub async fn update_many_fields<MC>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	ids: &[i64],
+	fields: Vec<Field>,
+) -> Result<BulkResult>
+where
+	MC: DbBmc,
+{
+	exec_update_many::<MC>(ctx, mm, AuditOp::Update, ids, fields).await
+}
+
+/// The one update of `update_many_fields`, audited as `op`
+/// (e.g., `AuditOp::Delete` for the soft `delete_many`).
+async fn exec_update_many<MC>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	op: AuditOp,
+	ids: &[i64],
+	mut fields: Vec<Field>,
+) -> Result<BulkResult>
+where
+	MC: DbBmc,
+{
+	check_bulk_limit(ids.len())?;
+	if ids.is_empty() {
+		return Ok(BulkResult::default());
+	}
+
+	let names = field_names(&fields);
+	add_timestamps_for_update(&mut fields, ctx.user_id());
+	add_version_bump::<MC>(&mut fields);
+
+	// -- Build the sql.
+	let mut sql = set_sql::<MC>(mm, &fields);
+	sql.push(" WHERE ").push_in_list("\"id\"", ids);
+	push_not_deleted::<MC>(&mut sql);
+	push_workspace_cond::<MC>(ctx, &mut sql);
+	push_returning_row_json::<MC>(mm, &mut sql);
+
+	// -- Exec, with the audit rows.
+	let txn_mm = own_txn(ctx, mm).await?;
+	let mm = txn_mm.as_ref().unwrap_or(mm);
+
+	let mut olds = rows_json_for_update::<MC>(ctx, mm, ids).await?;
+	let rows = with_db!(ctx, mm, |db| sql
+		.query()
+		.fetch_all(db)
+		.await?
+		.iter()
+		.map(id_row_json)
+		.collect::<sqlx::Result<Vec<_>>>()?);
+
+	let mut affected_ids = Vec::with_capacity(rows.len());
+	let mut diffs = Vec::with_capacity(rows.len());
+	for (id, new) in rows.into_iter() {
+		affected_ids.push(id);
+		diffs.push((
+			id,
+			audit::diff(olds.remove(&id).as_ref(), Some(&new), Some(&names)),
+		));
+		invalidate_cached::<MC>(mm, id);
+	}
+	AuditBmc::log(ctx, mm, MC::TABLE, op, diffs).await?;
+
+	commit_own_txn(txn_mm).await?;
+
+	Ok(BulkResult::from_ids(ids, &affected_ids))
+}
+
+/// Apply each patch to its id, in a transaction (the one of `mm` if any),
+/// so that they are all applied or none.
+/// Note: The ids not found are reported, not an error.
+pub async fn update_each<MC, E>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	patches: Vec<(i64, E)>,
+) -> Result<BulkResult>
+where
+	MC: DbBmc,
+	E: HasFields,
+{
+	let patches = patches
+		.into_iter()
+		.map(|(id, data)| (id, data.not_none_fields()))
+		.collect();
+
+	update_each_fields::<MC>(ctx, mm, patches).await
+}
+
+/// Same as `update_each`, for the Bmcs that need to add computed fields
+/// to the data fields (e.g., `TaskBmc` with `done_at`).
+pub async fn update_each_fields<MC>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	patches: Vec<(i64, Vec<Field>)>,
+) -> Result<BulkResult>
+where
+	MC: DbBmc,
+{
+	check_bulk_limit(patches.len())?;
+
+	let txn_mm = own_txn(ctx, mm).await?;
+	let exec_mm = txn_mm.as_ref().unwrap_or(mm);
+
+	let ids: Vec<i64> = patches.iter().map(|(id, _)| *id).collect();
+	let mut affected_ids = Vec::new();
+	for (id, fields) in patches {
+		match update_fields::<MC>(ctx, exec_mm, id, fields).await {
+			Ok(()) => affected_ids.push(id),
+			Err(Error::EntityNotFound { .. }) => (),
+			// Note: The transaction is rolled back when `txn_mm` is dropped.
+			Err(err) => return Err(err),
+		}
+	}
+
+	commit_own_txn(txn_mm).await?;
+
+	Ok(BulkResult::from_ids(&ids, &affected_ids))
+}
+
+/// Delete all the `ids` with one statement.
+/// Note: For the `DbBmc::SOFT_DELETE` tables, only sets `deleted_at` (see `delete`).
+pub async fn delete_many<MC>(ctx: &Ctx, mm: &ModelManager, ids: &[i64]) -> Result<BulkResult>
+where
+	MC: DbBmc,
+{
+	if MC::SOFT_DELETE {
+		let fields = vec![("deleted_at", now_utc()).into()];
+		return exec_update_many::<MC>(ctx, mm, AuditOp::Delete, ids, fields).await;
+	}
+
+	check_bulk_limit(ids.len())?;
+	if ids.is_empty() {
+		return Ok(BulkResult::default());
+	}
+
+	let txn_mm = own_txn(ctx, mm).await?;
+	let mm = txn_mm.as_ref().unwrap_or(mm);
+
+	let mut sql = Sql::new(
+		mm.backend(),
+		format!("DELETE FROM \"{}\" WHERE ", MC::TABLE),
+	);
+	sql.push_in_list("\"id\"", ids);
+	push_workspace_cond::<MC>(ctx, &mut sql);
+	push_returning_row_json::<MC>(mm, &mut sql);
+	let olds = with_db!(ctx, mm, |db| sql
+		.query()
+		.fetch_all(db)
+		.await?
+		.iter()
+		.map(id_row_json)
+		.collect::<sqlx::Result<Vec<_>>>()?);
+	let affected_ids: Vec<i64> = olds.iter().map(|(id, _)| *id).collect();
+	for id in affected_ids.iter() {
+		invalidate_cached::<MC>(mm, *id);
+	}
+	let diffs = olds
+		.into_iter()
+		.map(|(id, old)| (id, audit::diff(Some(&old), None, None)))
+		.collect();
+	AuditBmc::log(ctx, mm, MC::TABLE, AuditOp::Delete, diffs).await?;
+
+	commit_own_txn(txn_mm).await?;
+
+	Ok(BulkResult::from_ids(ids, &affected_ids))
+}
+
+pub(in crate::model) fn check_bulk_limit(len: usize) -> Result<()> {
+	if len > BULK_LIMIT_MAX {
+		return Err(Error::BulkLimitExceeded {
+			max: BULK_LIMIT_MAX,
+			actual: len,
+		});
+	}
+
+	Ok(())
+}
+
+// endregion: --- Bulk
+
+// region:    --- Soft Delete
+
+/// Restore a deleted row of a `DbBmc::SOFT_DELETE` table.
+pub async fn restore<MC>(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()>
+where
+	MC: DbBmc,
+{
+	let mut fields = vec![("deleted_at", None::<OffsetDateTime>).into()];
+	add_timestamps_for_update(&mut fields, ctx.user_id());
+	add_version_bump::<MC>(&mut fields);
+
+	let txn_mm = own_txn(ctx, mm).await?;
+	let mm = txn_mm.as_ref().unwrap_or(mm);
+
+	let mut sql = update_sql::<MC>(mm, &fields, id);
+	sql.push(" AND \"deleted_at\" IS NOT NULL");
+	push_workspace_cond::<MC>(ctx, &mut sql);
+	let names = ["deleted_at".to_string()];
+	let restored = exec_update_audited::<MC>(ctx, mm, id, AuditOp::Restore, &names, sql).await?;
+
+	if !restored {
+		return Err(Error::EntityNotFound {
+			entity: MC::TABLE,
+			id,
+		});
+	}
+	invalidate_cached::<MC>(mm, id);
+
+	commit_own_txn(txn_mm).await?;
+
+	Ok(())
+}
+
+/// Permanently delete the rows of a `DbBmc::SOFT_DELETE` table
+/// deleted before `deleted_before`. Returns the number of purged rows.
+/// Note: Not audited, the rows were already audited when deleted.
+pub async fn purge_deleted<MC>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	deleted_before: OffsetDateTime,
+) -> Result<u64>
+where
+	MC: DbBmc,
+{
+	let mut sql = Sql::new(
+		mm.backend(),
+		format!("DELETE FROM \"{}\" WHERE \"deleted_at\" < ", MC::TABLE),
+	);
+	sql.push_bind(deleted_before).push(" RETURNING \"id\"");
+	let ids: Vec<(i64,)> = with_db!(ctx, mm, |db| sql.query_as().fetch_all(db).await?);
+	for (id,) in ids.iter() {
+		invalidate_cached::<MC>(mm, *id);
+	}
+
+	Ok(ids.len() as u64)
+}
+
+fn push_not_deleted<MC>(sql: &mut Sql)
+where
+	MC: DbBmc,
+{
+	if MC::SOFT_DELETE {
+		sql.push(" AND \"deleted_at\" IS NULL");
+	}
+}
+
+// endregion: --- Soft Delete
+
+// region:    --- Audit Utils
+
+/// `RETURNING "id", <row json>` (see `store::Backend::row_json`), read with `id_row_json`,
+/// for the audit diffs.
+fn push_returning_row_json<MC>(mm: &ModelManager, sql: &mut Sql)
+where
+	MC: DbBmc,
+{
+	sql.push(format!(
+		" RETURNING \"id\", {}",
+		mm.backend().row_json(MC::TABLE)
+	));
+}
+
+/// The json of the `ids` rows by id, locked until the end of the transaction.
+async fn rows_json_for_update<MC>(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	ids: &[i64],
+) -> Result<HashMap<i64, Value>>
+where
+	MC: DbBmc,
+{
+	let backend = mm.backend();
+	let mut sql = Sql::new(
+		backend,
+		format!(
+			"SELECT \"id\", {} FROM \"{}\" WHERE ",
+			backend.row_json(MC::TABLE),
+			MC::TABLE
+		),
+	);
+	sql.push_in_list("\"id\"", ids).push(backend.for_update());
+	let rows = with_db!(ctx, mm, |db| sql
+		.query()
+		.fetch_all(db)
+		.await?
+		.iter()
+		.map(id_row_json)
+		.collect::<sqlx::Result<Vec<_>>>()?);
+
+	Ok(rows.into_iter().collect())
+}
+
+fn field_names(fields: &[Field]) -> Vec<String> {
+	fields.iter().map(|field| field.name.to_string()).collect()
+}
+
+// endregion: --- Audit Utils
+
+// region:    --- Txn Utils
+
+/// A new transaction for the writes of more than one statement
+/// (e.g., the change and its audit row), unless `mm` already has one.
+/// Note: Use the returned `ModelManager` when `Some`, and `commit_own_txn` it.
+///       The transaction is rolled back if it is dropped on an error.
+pub(in crate::model) async fn own_txn(
+	ctx: &Ctx,
+	mm: &ModelManager,
+) -> Result<Option<ModelManager>> {
+	match mm.txn() {
+		Some(_) => Ok(None),
+		None => Ok(Some(mm.new_with_ctx_txn(ctx).await?)),
+	}
+}
+
+pub(in crate::model) async fn commit_own_txn(txn_mm: Option<ModelManager>) -> Result<()> {
+	if let Some(txn_mm) = txn_mm {
+		txn_mm.commit().await?;
+	}
+
+	Ok(())
+}
+
+// endregion: --- Txn Utils
+
+// region:    --- Cache Utils
+
+/// Invalidate the cached row `id` of the `DbBmc::CACHED` tables, for the writes
+/// of this instance (the other instances are notified on commit).
+pub(in crate::model) fn invalidate_cached<MC>(mm: &ModelManager, id: i64)
+where
+	MC: DbBmc,
+{
+	if MC::CACHED {
+		mm.cache_invalidate(MC::TABLE, id);
+	}
+}
+
+// endregion: --- Cache Utils
+
+// region:    --- Version Utils
+
+fn add_version_bump<MC>(fields: &mut Vec<Field>)
+where
+	MC: DbBmc,
+{
+	if MC::VERSIONED {
+		fields.push(Field::raw("version", "\"version\" + 1"));
+	}
+}
+
+// endregion: --- Version Utils
+
+// region:    --- Workspace Utils
+
+/// The sql condition of the `MC` rows in the ctx workspace (e.g., `("task"."workspace_id" = 1000)`),
+/// `None` when not scoped (see `DbBmc::WORKSPACE_SCOPED`).
+/// Note: The root ctx without workspace is not scoped (e.g., the trash purge).
+///       The other ctx without workspace only have the rows without workspace.
+pub(in crate::model) fn workspace_cond<MC>(ctx: &Ctx) -> Option<String>
+where
+	MC: DbBmc,
+{
+	if !MC::WORKSPACE_SCOPED {
+		return None;
+	}
+
+	let column = format!("\"{}\".\"workspace_id\"", MC::TABLE);
+	match ctx.workspace_id() {
+		Some(workspace_id) => Some(format!("({column} = {workspace_id})")),
+		None if ctx.user_id() == 0 => None,
+		None => Some(format!("({column} IS NULL)")),
+	}
+}
+
+/// Same as `workspace_cond`, `TRUE` when not scoped (e.g., for the raw sql queries).
+pub(in crate::model) fn workspace_where<MC>(ctx: &Ctx) -> String
+where
+	MC: DbBmc,
+{
+	workspace_cond::<MC>(ctx).unwrap_or_else(|| "TRUE".to_string())
+}
+
+fn push_workspace_cond<MC>(ctx: &Ctx, sql: &mut Sql)
+where
+	MC: DbBmc,
+{
+	if let Some(cond) = workspace_cond::<MC>(ctx) {
+		sql.push(format!(" AND {cond}"));
+	}
+}
+
+/// Note: Unless already set (e.g., a comment is in the workspace of its task).
+fn add_workspace_for_create<MC>(fields: &mut Vec<Field>, ctx: &Ctx)
+where
+	MC: DbBmc,
+{
+	if !MC::WORKSPACE_SCOPED || fields.iter().any(|f| f.name == "workspace_id") {
+		return;
+	}
+	if let Some(workspace_id) = ctx.workspace_id() {
+		fields.push(("workspace_id", workspace_id).into());
+	}
+}
+
+// endregion: --- Workspace Utils
+
+// region:    --- Timestamps Utils
+
+/// Add the creator/modifier ids and the creation/modification times
+/// (`cid`, `ctime`, `mid`, `mtime`) to the fields of a new entity.
+pub fn add_timestamps_for_create(fields: &mut Vec<Field>, user_id: i64) {
+	let now = now_utc();
+	fields.push(("cid", user_id).into());
+	fields.push(("ctime", now).into());
+
+	fields.push(("mid", user_id).into());
+	fields.push(("mtime", now).into());
+}
+
+/// Add the modifier id and the modification time (`mid`, `mtime`)
+/// to the fields of an updated entity.
+pub fn add_timestamps_for_update(fields: &mut Vec<Field>, user_id: i64) {
+	let now = now_utc();
+	fields.push(("mid", user_id).into());
+	fields.push(("mtime", now).into());
+}
+
+// endregion: --- Timestamps Utils
+
+// region:    --- Sql Utils
+
+/// `SELECT <columns> FROM "<table>" WHERE TRUE`, for the `AND` conditions to push.
+pub(in crate::model) fn select_sql<MC>(mm: &ModelManager, columns: &[&str]) -> Sql
+where
+	MC: DbBmc,
+{
+	Sql::new(
+		mm.backend(),
+		format!(
+			"SELECT {} FROM \"{}\" WHERE TRUE",
+			column_list(columns),
+			MC::TABLE
+		),
+	)
+}
+
+pub(in crate::model) fn insert_sql<MC>(mm: &ModelManager, fields: &[Field]) -> Sql
+where
+	MC: DbBmc,
+{
+	let names: Vec<&str> = fields.iter().map(|field| field.name).collect();
+	let mut sql = Sql::new(
+		mm.backend(),
+		format!(
+			"INSERT INTO \"{}\" ({}) VALUES (",
+			MC::TABLE,
+			column_list(&names)
+		),
+	);
+	for (i, field) in fields.iter().enumerate() {
+		if i > 0 {
+			sql.push(", ");
+		}
+		field.push_value(&mut sql);
+	}
+	sql.push(")");
+
+	sql
+}
+
+/// `UPDATE "<table>" SET <fields>`, for the where clause to push.
+fn set_sql<MC>(mm: &ModelManager, fields: &[Field]) -> Sql
+where
+	MC: DbBmc,
+{
+	let mut sql = Sql::new(mm.backend(), format!("UPDATE \"{}\" SET ", MC::TABLE));
+	for (i, field) in fields.iter().enumerate() {
+		if i > 0 {
+			sql.push(", ");
+		}
+		sql.push(format!("\"{}\" = ", field.name));
+		field.push_value(&mut sql);
+	}
+
+	sql
+}
+
+/// The update of the row `id`, for the `AND` conditions to push.
+pub(in crate::model) fn update_sql<MC>(mm: &ModelManager, fields: &[Field], id: i64) -> Sql
+where
+	MC: DbBmc,
+{
+	let mut sql = set_sql::<MC>(mm, fields);
+	sql.push(" WHERE \"id\" = ").push_bind(id);
+
+	sql
+}
+
+/// The `AND` conditions of the filter nodes.
+fn push_filter<F>(sql: &mut Sql, filter: Option<F>)
+where
+	F: FilterNodes,
+{
+	for node in filter.map(FilterNodes::filter_nodes).unwrap_or_default() {
+		sql.push(" AND ");
+		node.push_to_sql(sql);
+	}
+}
+
+pub(in crate::model) fn column_list(names: &[&str]) -> String {
+	names
+		.iter()
+		.map(|name| format!("\"{name}\""))
+		.collect::<Vec<_>>()
+		.join(", ")
+}
+
+// endregion: --- Sql Utils
@@ -3,7 +3,7 @@ use serde_with::{serde_as, DisplayFromStr};
 
 use crate::crypt;
 
-use super::store;
+use super::{blob, store};
 
 pub type Result<T> = core::result::Result<T, Error>;
 
@@ -15,11 +15,112 @@ pub enum Error {
 
 	// -- Modules
 	Store(store::Error),
+	Blob(blob::Error),
 
 	// -- Sqlx
 	SqlxError(#[serde_as(as = "DisplayFromStr")] sqlx::Error),
 
-	EntityNotFound { entity: &'static str, id: i64 },
+	// -- Txn
+	TxnNested,
+	TxnNone,
+	TxnDone,
+
+	// -- Cache
+	CacheListenFailed(String),
+
+	EntityNotFound {
+		entity: &'static str,
+		id: i64,
+	},
+	VersionConflict {
+		entity: &'static str,
+		id: i64,
+		expected: i64,
+		current: i64,
+	},
+
+	// -- Bulk
+	BulkLimitExceeded {
+		max: usize,
+		actual: usize,
+	},
+
+	// -- User
+	UserNotAdmin {
+		user_id: i64,
+	},
+
+	// -- Workspace
+	WorkspaceNotMember {
+		id: i64,
+		user_id: i64,
+	},
+
+	// -- Comment
+	CommentNotAuthor {
+		id: i64,
+	},
+
+	// -- Project
+	ProjectHasTasks {
+		id: i64,
+	},
+
+	// -- Task
+	TaskHasSubtasks {
+		id: i64,
+	},
+	TaskParentCycle {
+		id: i64,
+		parent_id: i64,
+	},
+	/// No rank between the neighbor ranks, even once spread again (should not happen).
+	TaskRankNone,
+	TaskNotRecurring {
+		id: i64,
+	},
+	TaskRruleInvalid {
+		rrule: String,
+		reason: &'static str,
+	},
+	TaskRruleTzUnknown {
+		rrule_tz: String,
+	},
+	/// A recurring task must have a `due_at`, its first occurrence.
+	TaskRruleNoDueAt,
+
+	TaskAccessDenied {
+		id: i64,
+		user_id: i64,
+	},
+	TaskAssignDenied {
+		id: i64,
+		user_id: i64,
+	},
+
+	// -- Attachment
+	AttachmentTooLarge {
+		max: u64,
+	},
+	AttachmentFilenameInvalid {
+		filename: String,
+	},
+
+	// -- Tag
+	TagNameAlreadyExists {
+		name: String,
+	},
+	TagMergeIntoSelf {
+		id: i64,
+	},
+
+	// -- List
+	ListOrderByUnknownColumn(String),
+	ListCursorInvalid,
+	ListCursorOrderByMismatch {
+		cursor: String,
+		requested: String,
+	},
 }
 
 impl From<crypt::Error> for Error {
@@ -40,6 +141,12 @@ impl From<store::Error> for Error {
 	}
 }
 
+impl From<blob::Error> for Error {
+	fn from(err: blob::Error) -> Self {
+		Self::Blob(err)
+	}
+}
+
 // region:    --- Error Boilerplate
 impl core::fmt::Display for Error {
 	fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
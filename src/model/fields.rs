@@ -0,0 +1,80 @@
+//! The fields of the entity data structs, for the `common::*` inserts and updates.
+//!
+//! - `#[derive(Fields)]` implements `HasFields` (see the `rust-web-app-macros` crate),
+//!   the column of each field being its name.
+//! - A `Field` is bound by default, or the sql expression of the Bmc
+//!   (e.g., the `"version" + 1` of the version bump), with `$?` for its value if any.
+
+use crate::model::store::{Sql, SqlValue};
+
+pub use rust_web_app_macros::Fields;
+
+pub trait HasFields {
+	/// The fields which are not `None` (e.g., the patched fields of an update).
+	fn not_none_fields(self) -> Vec<Field>;
+
+	/// All the fields, the `None` ones as `NULL`.
+	fn all_fields(self) -> Vec<Field>;
+
+	/// The columns of the fields (e.g., the select columns of the entity).
+	fn field_names() -> &'static [&'static str];
+}
+
+#[derive(Debug, Clone)]
+pub struct Field {
+	pub name: &'static str,
+	value: Option<SqlValue>,
+	/// `$?` is the bound `value`.
+	sql: &'static str,
+}
+
+impl Field {
+	pub fn new(name: &'static str, value: impl Into<SqlValue>) -> Self {
+		Field {
+			name,
+			value: Some(value.into()),
+			sql: "$?",
+		}
+	}
+
+	/// A sql expression, without bound value (e.g., `"version" + 1`).
+	pub fn raw(name: &'static str, sql: &'static str) -> Self {
+		Field {
+			name,
+			value: None,
+			sql,
+		}
+	}
+
+	/// A sql expression with `$?` for the bound `value`
+	/// (e.g., `CASE WHEN "status" = 'done' THEN "done_at" ELSE $? END`).
+	pub fn expr(name: &'static str, sql: &'static str, value: impl Into<SqlValue>) -> Self {
+		Field {
+			name,
+			value: Some(value.into()),
+			sql,
+		}
+	}
+
+	/// Push the value of the field (its sql, with its bound value if any).
+	pub(in crate::model) fn push_value(&self, sql: &mut Sql) {
+		match &self.value {
+			Some(value) => {
+				let param = sql.bind(value.clone());
+				sql.push(self.sql.replace("$?", &param));
+			}
+			None => {
+				sql.push(self.sql);
+			}
+		}
+	}
+}
+
+impl<T> From<(&'static str, T)> for Field
+where
+	T: Into<SqlValue>,
+{
+	fn from((name, value): (&'static str, T)) -> Self {
+		Field::new(name, value)
+	}
+}
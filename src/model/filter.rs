@@ -0,0 +1,395 @@
+//! Typed filters and list options for the `common::list` function.
+//!
+//! - A `XxxFilter` struct (e.g., `TaskFilter`) lists the filterable columns
+//!   of an entity with their `OpVals...` type, and implements `FilterNodes`.
+//! - `FilterNode`s are translated into sql where clauses, for the backend of the `Sql`.
+//!   Values are always bound, and the column expressions only come from
+//!   the `FilterNodes` impls, so no client data ever ends up in the SQL string.
+//! - `ListOptions` carries the limit, offset and order bys. The order by
+//!   columns are validated against the entity field names.
+
+use serde::Deserialize;
+
+use crate::model::store::{Backend, Sql};
+use crate::model::{Error, Result};
+
+/// Limit applied when the `ListOptions` does not have one.
+pub const LIST_LIMIT_DEFAULT: i64 = 300;
+/// Server side max limit, whatever the `ListOptions` asks for.
+pub const LIST_LIMIT_MAX: i64 = 1000;
+
+// region:    --- FilterNodes
+
+/// Implemented by the entity filter structs (e.g., `TaskFilter`).
+pub trait FilterNodes {
+	fn filter_nodes(self) -> Vec<FilterNode>;
+}
+
+/// A single condition on a column (or on the related ids) with its bound value.
+/// All the nodes of a filter are combined with `AND`.
+#[derive(Debug)]
+pub struct FilterNode(Cond);
+
+#[derive(Debug)]
+enum Cond {
+	/// `("<column>") <op> <val>`
+	Int64(String, &'static str, i64),
+	/// `<column as text> <op> <val>`
+	Text(String, &'static str, String),
+	/// The column text contains `val`, case insensitive.
+	Contains(String, String),
+	StartsWith(String, String),
+	Int64In(String, Vec<i64>),
+	TextIn(String, Vec<String>),
+	/// `("<column>" IS NULL) = <val>`
+	Null(String, bool),
+	/// The related ids of the query (see `OpValsIdSet`) have any/all of the ids.
+	IdsAny(String, Vec<i64>),
+	IdsAll(String, Vec<i64>),
+}
+
+impl FilterNode {
+	/// Push the condition, with its bound value (e.g., `("title"::text) = $1`).
+	pub(in crate::model) fn push_to_sql(self, sql: &mut Sql) {
+		let backend = sql.backend();
+		match self.0 {
+			Cond::Int64(column, op, val) => {
+				sql.push(format!("(\"{column}\") {op} ")).push_bind(val);
+			}
+			// Note: Compared as text, so that it works for varchar and enum columns alike.
+			Cond::Text(column, op, val) => {
+				sql.push(format!("{} {op} ", text_expr(backend, &column)))
+					.push_bind(val);
+			}
+			Cond::Contains(column, val) => {
+				let op = match backend {
+					Backend::Postgres => "ILIKE",
+					// Note: The SQLite `LIKE` is case insensitive (ascii), with no default escape char.
+					Backend::Sqlite => "LIKE",
+				};
+				sql.push(format!("{} {op} ", text_expr(backend, &column)))
+					.push_bind(format!("%{}%", escape_like(&val)));
+				if backend == Backend::Sqlite {
+					sql.push(" ESCAPE '\\'");
+				}
+			}
+			Cond::StartsWith(column, val) => match backend {
+				Backend::Postgres => {
+					sql.push(format!("{} LIKE ", text_expr(backend, &column)))
+						.push_bind(format!("{}%", escape_like(&val)));
+				}
+				// Note: Not with `LIKE`, case insensitive for SQLite.
+				Backend::Sqlite => {
+					let param = sql.bind(val);
+					sql.push(format!(
+						"substr({}, 1, length({param})) = {param}",
+						text_expr(backend, &column)
+					));
+				}
+			},
+			Cond::Int64In(column, vals) => match backend {
+				// Note: Cast, so that it works for smallint and int columns alike.
+				Backend::Postgres => {
+					sql.push(format!("(ARRAY[\"{column}\"::bigint]) <@ "))
+						.push_bind(vals);
+				}
+				Backend::Sqlite => {
+					sql.push_in_list(&format!("(\"{column}\")"), vals);
+				}
+			},
+			Cond::TextIn(column, vals) => match backend {
+				Backend::Postgres => {
+					sql.push(format!("(ARRAY[\"{column}\"::text]) <@ "))
+						.push_bind(vals);
+				}
+				Backend::Sqlite => {
+					sql.push_in_list(&text_expr(backend, &column), vals);
+				}
+			},
+			Cond::Null(column, null) => {
+				sql.push(format!("(\"{column}\" IS NULL) = "))
+					.push_bind(null);
+			}
+			Cond::IdsAny(ids_query, vals) => match backend {
+				Backend::Postgres => {
+					sql.push(format!("(ARRAY({ids_query})) && "))
+						.push_bind(vals);
+				}
+				Backend::Sqlite => {
+					let param = sql.bind(vals);
+					sql.push(format!(
+						"EXISTS (SELECT 1 FROM json_each({param}) WHERE value IN ({ids_query}))"
+					));
+				}
+			},
+			Cond::IdsAll(ids_query, vals) => match backend {
+				Backend::Postgres => {
+					sql.push(format!("(ARRAY({ids_query})) @> "))
+						.push_bind(vals);
+				}
+				Backend::Sqlite => {
+					let param = sql.bind(vals);
+					sql.push(format!(
+						"NOT EXISTS (SELECT 1 FROM json_each({param}) WHERE value NOT IN ({ids_query}))"
+					));
+				}
+			},
+		}
+	}
+}
+
+fn text_expr(backend: Backend, column: &str) -> String {
+	backend.text(&format!("\"{column}\""))
+}
+
+// endregion: --- FilterNodes
+
+// region:    --- OpVals
+
+/// Operators for an `i64` column.
+/// (e.g., `{"id": {"$gte": 1000, "$lt": 2000}}`)
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OpValsInt64 {
+	#[serde(rename = "$eq")]
+	pub eq: Option<i64>,
+	#[serde(rename = "$ne")]
+	pub ne: Option<i64>,
+	#[serde(rename = "$lt")]
+	pub lt: Option<i64>,
+	#[serde(rename = "$lte")]
+	pub lte: Option<i64>,
+	#[serde(rename = "$gt")]
+	pub gt: Option<i64>,
+	#[serde(rename = "$gte")]
+	pub gte: Option<i64>,
+	#[serde(rename = "$in")]
+	pub in_: Option<Vec<i64>>,
+	#[serde(rename = "$null")]
+	pub null: Option<bool>,
+}
+
+impl OpValsInt64 {
+	pub fn into_filter_nodes(self, column: &str) -> Vec<FilterNode> {
+		let mut nodes = Vec::new();
+
+		let cmps = [
+			("=", self.eq),
+			("<>", self.ne),
+			("<", self.lt),
+			("<=", self.lte),
+			(">", self.gt),
+			(">=", self.gte),
+		];
+		for (op, val) in cmps {
+			if let Some(val) = val {
+				nodes.push(FilterNode(Cond::Int64(column.to_string(), op, val)));
+			}
+		}
+
+		if let Some(vals) = self.in_ {
+			nodes.push(FilterNode(Cond::Int64In(column.to_string(), vals)));
+		}
+
+		if let Some(null) = self.null {
+			nodes.push(FilterNode(Cond::Null(column.to_string(), null)));
+		}
+
+		nodes
+	}
+}
+
+/// Operators for a text column.
+/// (e.g., `{"title": {"$contains": "urgent"}}`)
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OpValsString {
+	#[serde(rename = "$eq")]
+	pub eq: Option<String>,
+	#[serde(rename = "$ne")]
+	pub ne: Option<String>,
+	#[serde(rename = "$lt")]
+	pub lt: Option<String>,
+	#[serde(rename = "$gt")]
+	pub gt: Option<String>,
+	#[serde(rename = "$contains")]
+	pub contains: Option<String>,
+	#[serde(rename = "$startsWith")]
+	pub starts_with: Option<String>,
+	#[serde(rename = "$in")]
+	pub in_: Option<Vec<String>>,
+	#[serde(rename = "$null")]
+	pub null: Option<bool>,
+}
+
+impl OpValsString {
+	pub fn into_filter_nodes(self, column: &str) -> Vec<FilterNode> {
+		let mut nodes = Vec::new();
+
+		let cmps = [
+			("=", self.eq),
+			("<>", self.ne),
+			("<", self.lt),
+			(">", self.gt),
+		];
+		for (op, val) in cmps {
+			if let Some(val) = val {
+				nodes.push(FilterNode(Cond::Text(column.to_string(), op, val)));
+			}
+		}
+
+		if let Some(val) = self.contains {
+			nodes.push(FilterNode(Cond::Contains(column.to_string(), val)));
+		}
+
+		if let Some(val) = self.starts_with {
+			nodes.push(FilterNode(Cond::StartsWith(column.to_string(), val)));
+		}
+
+		if let Some(vals) = self.in_ {
+			nodes.push(FilterNode(Cond::TextIn(column.to_string(), vals)));
+		}
+
+		if let Some(null) = self.null {
+			nodes.push(FilterNode(Cond::Null(column.to_string(), null)));
+		}
+
+		nodes
+	}
+}
+
+/// Operators for the ids of the entities related through a join table.
+/// (e.g., `{"tags": {"$all": [1000, 1001]}}`)
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OpValsIdSet {
+	/// Related to at least one of the ids (OR).
+	#[serde(rename = "$any")]
+	pub any: Option<Vec<i64>>,
+	/// Related to all of the ids (AND).
+	#[serde(rename = "$all")]
+	pub all: Option<Vec<i64>>,
+}
+
+impl OpValsIdSet {
+	/// `ids_query` is the SQL query of the related ids
+	/// (e.g., `SELECT tag_id FROM task_tag WHERE task_id = "task"."id"`).
+	pub fn into_filter_nodes(self, ids_query: &str) -> Vec<FilterNode> {
+		let mut nodes = Vec::new();
+
+		if let Some(vals) = self.any {
+			nodes.push(FilterNode(Cond::IdsAny(ids_query.to_string(), vals)));
+		}
+
+		if let Some(vals) = self.all {
+			nodes.push(FilterNode(Cond::IdsAll(ids_query.to_string(), vals)));
+		}
+
+		nodes
+	}
+}
+
+/// Escape the `LIKE` wildcards, so that `$contains` and `$startsWith`
+/// match the value literally.
+fn escape_like(val: &str) -> String {
+	val.replace('\\', "\\\\")
+		.replace('%', "\\%")
+		.replace('_', "\\_")
+}
+
+// endregion: --- OpVals
+
+// region:    --- ListOptions
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct ListOptions {
+	pub limit: Option<i64>,
+	pub offset: Option<i64>,
+	pub order_bys: Option<Vec<OrderBy>>,
+}
+
+/// Deserialized from the column name, prefixed with `!` for descending order.
+/// (e.g., `"title"`, `"!ctime"`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(from = "String")]
+pub enum OrderBy {
+	Asc(String),
+	Desc(String),
+}
+
+impl From<String> for OrderBy {
+	fn from(val: String) -> Self {
+		match val.strip_prefix('!') {
+			Some(column) => OrderBy::Desc(column.to_string()),
+			None => OrderBy::Asc(val),
+		}
+	}
+}
+
+impl OrderBy {
+	pub fn column(&self) -> &str {
+		match self {
+			OrderBy::Asc(column) | OrderBy::Desc(column) => column,
+		}
+	}
+}
+
+/// Note: Same notation as the deserialized order bys.
+impl core::fmt::Display for OrderBy {
+	fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
+		match self {
+			OrderBy::Asc(column) => write!(fmt, "{column}"),
+			OrderBy::Desc(column) => write!(fmt, "!{column}"),
+		}
+	}
+}
+
+impl ListOptions {
+	/// Push the order bys, limit and offset of the select.
+	/// - Order by columns must be part of `field_names`.
+	/// - `id` is always added as the last order by, so that the order is stable.
+	pub(in crate::model) fn push_to_sql(self, sql: &mut Sql, field_names: &[&str]) -> Result<()> {
+		// -- Limit & Offset
+		let (limit, offset) = self.limit_offset();
+
+		// -- Order bys
+		let mut order_bys = self.order_bys.unwrap_or_default();
+		for order_by in order_bys.iter() {
+			if !field_names.contains(&order_by.column()) {
+				return Err(Error::ListOrderByUnknownColumn(
+					order_by.column().to_string(),
+				));
+			}
+		}
+		if !order_bys.iter().any(|o| o.column() == "id") {
+			order_bys.push(OrderBy::Asc("id".to_string()));
+		}
+		// Note: The nulls last as with Postgres (SQLite sorts them first).
+		let order_bys = order_bys
+			.iter()
+			.map(|order_by| match order_by {
+				OrderBy::Asc(column) => format!("\"{column}\" ASC NULLS LAST"),
+				OrderBy::Desc(column) => format!("\"{column}\" DESC NULLS FIRST"),
+			})
+			.collect::<Vec<_>>()
+			.join(", ");
+		sql.push(format!(" ORDER BY {order_bys}"));
+		sql.push(" LIMIT ").push_bind(limit);
+		sql.push(" OFFSET ").push_bind(offset);
+
+		Ok(())
+	}
+
+	/// The limit (default `LIST_LIMIT_DEFAULT`, max `LIST_LIMIT_MAX`) and the offset.
+	pub(in crate::model) fn limit_offset(&self) -> (i64, i64) {
+		let limit = self
+			.limit
+			.unwrap_or(LIST_LIMIT_DEFAULT)
+			.clamp(0, LIST_LIMIT_MAX);
+		let offset = self.offset.unwrap_or(0).max(0);
+
+		(limit, offset)
+	}
+}
+
+// endregion: --- ListOptions
@@ -3,7 +3,7 @@
 //! - All application code data access must go through the model layer.
 //! - The `ModelManager` holds the internal states/resources needed
 //! 	by `ModelController` to access data.
-//! 	(e.g., db_pool, S3 client, redis client).
+//!   (e.g., db_pool, blob store (local or S3), redis client).
 //! - ModelController (e.g. `TaskBmc`, `ProjectBmc`) implement CRUD
 //! 	and other data access methods on a given "entity"
 //! 	(e.g., `Task`, `Project`)
@@ -13,35 +13,336 @@
 //! - `ModelManager` are designed to pass as an argument
 //! 	to all `ModelController` functions.
 //!  - So that we can protect the visibility of those resources only to the modern layer.
+//! - The store is Postgres, or SQLite for a single instance (e.g., local dev, tests),
+//!   selected by the scheme of `SERVICE_DB_URL` (see `store::check_db_url`).
 
 // region:    --- Modules
+mod cache;
 mod common;
 mod error;
+mod fields;
+mod filter;
+mod page;
+mod rank;
+mod rrule;
 mod store;
 
+pub mod attachment;
+pub mod audit;
+pub mod blob;
+pub mod comment;
+pub mod project;
+pub mod tag;
 pub mod task;
+pub mod task_series;
+pub mod trash;
 pub mod user;
+pub mod workspace;
 
-use store::{new_db_pool, Db};
+use std::collections::HashSet;
+use std::sync::Arc;
 
+use crate::config::config;
+use crate::ctx::Ctx;
+use blob::BlobStore;
+use cache::EntityCache;
+use store::{new_db_pool, Backend, Db, DbTxn, Txn};
+use tokio::sync::Mutex;
+
+pub use self::cache::CacheStats;
+pub use self::common::{BulkResult, BULK_LIMIT_MAX};
 pub use self::error::{Error, Result};
+pub use self::filter::{
+	FilterNode, FilterNodes, ListOptions, OpValsIdSet, OpValsInt64, OpValsString, OrderBy,
+	LIST_LIMIT_MAX,
+};
+pub use self::page::{Page, PageOptions};
 
 // endregion: --- Modules
 
+/// The cached rows written by a transaction (see `ModelManager::cache_for`),
+/// shared by the clones of its `ModelManager`.
+type TxnWritten = Arc<std::sync::Mutex<HashSet<(&'static str, i64)>>>;
+
 #[derive(Clone)]
 pub struct ModelManager {
 	db: Db,
+	txn: Option<Txn>,
+	blob: Arc<dyn BlobStore>,
+	cache: Arc<EntityCache>,
+	txn_written: TxnWritten,
 }
 
 impl ModelManager {
+	/// Note: Applies the pending schema migrations first (see `migrate`).
 	pub async fn new() -> Result<Self> {
-		let db = new_db_pool().await?;
-		Ok(ModelManager { db })
+		Self::new_for_db_url(&config().DB_URL).await
+	}
+
+	/// Same as `new`, for the db of `db_url` (e.g., a SQLite db of the tests).
+	pub async fn new_for_db_url(db_url: &str) -> Result<Self> {
+		// Note: The SQLite pool is created first, as it keeps an in-memory db alive.
+		let db = match store::check_db_url(db_url)? {
+			Backend::Postgres => {
+				store::migrate(db_url).await?;
+				new_db_pool(db_url).await?
+			}
+			Backend::Sqlite => {
+				let db = new_db_pool(db_url).await?;
+				store::migrate(db_url).await?;
+				db
+			}
+		};
+		let blob = blob::new_blob_store()?;
+
+		// Note: Without the notifications of the other instances for SQLite (single instance).
+		let cache = Arc::new(EntityCache::from_config());
+		if cache.is_enabled() && db.backend() == Backend::Postgres {
+			let listener = cache::new_listener(db_url).await?;
+			tokio::spawn(cache::listen_job(cache.clone(), listener));
+		}
+
+		Ok(ModelManager {
+			db,
+			txn: None,
+			blob,
+			cache,
+			txn_written: TxnWritten::default(),
+		})
+	}
+
+	/// Apply the pending schema migrations (`src/sql/migrations/`, or `src/sql/migrations_sqlite/`),
+	/// and return their versions.
+	/// Fails with `store::Error::MigrationUnknown` or `MigrationModified` when the db
+	/// has migrations this binary does not know, or knows with another sql.
+	pub async fn migrate() -> Result<Vec<i64>> {
+		Ok(store::migrate(&config().DB_URL).await?)
+	}
+
+	/// A new `ModelManager` sharing the db pool, with its own transaction,
+	/// with the row level security of `ctx` for all its queries (e.g., the queries of a web request).
+	/// All the Bmc calls made with it (and its clones) run in the transaction,
+	/// until `commit` or `rollback`. If neither is called, the transaction
+	/// is rolled back when the last clone is dropped.
+	/// A query missing its workspace condition still only sees the rows of the ctx workspace,
+	/// for the workspace scoped tables and the join tables of their rows
+	/// (`task_tag`, `task_watcher`, `mention`), and only the tags of the ctx user.
+	/// Note: Nested transactions are not supported, and fail with `Error::TxnNested`.
+	/// Note: Not for the `audit` table (root ctx only), its queries filter it.
+	/// Note: The root ctx uses the bypass role (see `store::set_local_ctx`).
+	/// Note: SQLite has no row level security.
+	pub async fn new_with_ctx_txn(&self, ctx: &Ctx) -> Result<ModelManager> {
+		if self.txn.is_some() {
+			return Err(Error::TxnNested);
+		}
+
+		let mut txn = self.db.begin().await?;
+		if let DbTxn::Postgres(txn) = &mut txn {
+			store::set_local_ctx(txn, ctx).await?;
+		}
+
+		Ok(ModelManager {
+			db: self.db.clone(),
+			txn: Some(Arc::new(Mutex::new(Some(txn)))),
+			blob: self.blob.clone(),
+			cache: self.cache.clone(),
+			txn_written: TxnWritten::default(),
+		})
+	}
+
+	/// Note: The rows written by the transaction are invalidated again once committed,
+	///       as they may have been cached (with their old values) in the meantime
+	///       by the reads outside of the transaction.
+	pub async fn commit(&self) -> Result<()> {
+		self.take_txn().await?.commit().await?;
+
+		let written = std::mem::take(&mut *self.lock_txn_written());
+		for (table, id) in written {
+			self.cache.invalidate(table, id);
+		}
+
+		Ok(())
+	}
+
+	pub async fn rollback(&self) -> Result<()> {
+		self.take_txn().await?.rollback().await?;
+		Ok(())
+	}
+
+	async fn take_txn(&self) -> Result<DbTxn> {
+		let txn = self.txn.as_ref().ok_or(Error::TxnNone)?;
+		let txn = txn.lock().await.take().ok_or(Error::TxnDone)?;
+		Ok(txn)
 	}
 
 	// Return a reference to db pool connection
 	// that only visible for model crate
+	// Note: Use `store::with_db!`, so that the transaction is used when there is one.
 	pub(in crate::model) fn db(&self) -> &Db {
 		&self.db
 	}
+
+	pub(in crate::model) fn backend(&self) -> Backend {
+		self.db.backend()
+	}
+
+	pub(in crate::model) fn txn(&self) -> Option<&Txn> {
+		self.txn.as_ref()
+	}
+
+	/// Note: Not part of the transaction, if any.
+	pub(in crate::model) fn blob(&self) -> &dyn BlobStore {
+		self.blob.as_ref()
+	}
+
+	/// The entity cache for the row `id` of `table` (see `model::cache`).
+	/// `None` when the cache is disabled, or when the transaction wrote the row
+	/// (it must read its own writes).
+	pub(in crate::model) fn cache_for(&self, table: &str, id: i64) -> Option<&EntityCache> {
+		if !self.cache.is_enabled() {
+			return None;
+		}
+		if self.txn.is_some() && self.lock_txn_written().contains(&(table, id)) {
+			return None;
+		}
+
+		Some(self.cache.as_ref())
+	}
+
+	/// Note: Also in a transaction, as the row is changed (the other instances
+	///       invalidate it on commit, see `cache::listen_job`).
+	pub(in crate::model) fn cache_invalidate(&self, table: &'static str, id: i64) {
+		self.cache.invalidate(table, id);
+		if self.txn.is_some() {
+			self.lock_txn_written().insert((table, id));
+		}
+	}
+
+	/// The hits and misses of the entity cache, shared by the clones (e.g., for monitoring).
+	pub fn cache_stats(&self) -> CacheStats {
+		self.cache.stats()
+	}
+
+	// Note: The set is always consistent, even if a thread panicked with the lock.
+	fn lock_txn_written(&self) -> std::sync::MutexGuard<'_, HashSet<(&'static str, i64)>> {
+		self.txn_written
+			.lock()
+			.unwrap_or_else(std::sync::PoisonError::into_inner)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(unused)]
+	use crate::_dev_utils;
+	use crate::ctx::Ctx;
+	use crate::model::project::{ProjectBmc, ProjectForCreate};
+	use crate::model::task::{TaskBmc, TaskForCreate};
+
+	use super::*;
+	use anyhow::Result;
+	use serial_test::serial;
+
+	#[serial]
+	#[tokio::test]
+	async fn test_txn_commit_ok() -> Result<()> {
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+
+		let txn_mm = mm.new_with_ctx_txn(&ctx).await?;
+		let project_id = _dev_utils::seed_project(&ctx, &txn_mm, "test_txn_commit_ok").await?;
+		let task_ids =
+			_dev_utils::seed_tasks_for_project(&ctx, &txn_mm, project_id, &["test_txn_commit_ok"])
+				.await?;
+
+		// -- Not visible outside of the transaction before the commit.
+		let result = ProjectBmc::get(&ctx, &mm, project_id).await;
+		assert!(matches!(result, Err(Error::EntityNotFound { .. })));
+
+		txn_mm.commit().await?;
+
+		ProjectBmc::get(&ctx, &mm, project_id).await?;
+		TaskBmc::get(&ctx, &mm, task_ids[0]).await?;
+
+		// -- Clean
+		TaskBmc::delete(&ctx, &mm, task_ids[0]).await?;
+		ProjectBmc::delete(&ctx, &mm, project_id).await?;
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_txn_rollback_on_err_ok() -> Result<()> {
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+
+		let txn_mm = mm.new_with_ctx_txn(&ctx).await?;
+		let project_id =
+			_dev_utils::seed_project(&ctx, &txn_mm, "test_txn_rollback_on_err_ok").await?;
+		let task_c = TaskForCreate {
+			title: "test_txn_rollback_on_err_ok".to_string(),
+			project_id: Some(project_id),
+			..Default::default()
+		};
+		let task_id = TaskBmc::create(&ctx, &txn_mm, task_c).await?;
+		// Fails, priority is out of the 0..=3 range.
+		let task_c = TaskForCreate {
+			title: "test_txn_rollback_on_err_ok".to_string(),
+			project_id: Some(project_id),
+			priority: Some(10),
+			..Default::default()
+		};
+		let result = TaskBmc::create(&ctx, &txn_mm, task_c).await;
+		assert!(result.is_err());
+
+		txn_mm.rollback().await?;
+
+		// -- Nothing was created.
+		let result = ProjectBmc::get(&ctx, &mm, project_id).await;
+		assert!(matches!(result, Err(Error::EntityNotFound { .. })));
+		let result = TaskBmc::get(&ctx, &mm, task_id).await;
+		assert!(matches!(result, Err(Error::EntityNotFound { .. })));
+
+		// -- The transaction is done.
+		let result = ProjectBmc::get(&ctx, &txn_mm, project_id).await;
+		assert!(matches!(result, Err(Error::TxnDone)));
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_txn_drop_rollback_ok() -> Result<()> {
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+
+		let project_id = {
+			let txn_mm = mm.new_with_ctx_txn(&ctx).await?;
+			_dev_utils::seed_project(&ctx, &txn_mm, "test_txn_drop_rollback_ok").await?
+		};
+
+		let result = ProjectBmc::get(&ctx, &mm, project_id).await;
+		assert!(matches!(result, Err(Error::EntityNotFound { .. })));
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_txn_err_nested() -> Result<()> {
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+
+		let txn_mm = mm.new_with_ctx_txn(&ctx).await?;
+		let result = txn_mm.new_with_ctx_txn(&ctx).await;
+		assert!(matches!(result, Err(Error::TxnNested)));
+
+		let result = mm.commit().await;
+		assert!(matches!(result, Err(Error::TxnNone)));
+
+		txn_mm.rollback().await?;
+
+		Ok(())
+	}
 }
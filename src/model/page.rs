@@ -0,0 +1,218 @@
+//! Keyset (cursor) pagination for the `common::list_page` function.
+//!
+//! - The cursor holds the order by, and the sort key (order by column value and `id`)
+//!   of the last item of the page.
+//! - It is handed to the client as an opaque `<base64url json>.<base64url hmac>` string.
+//!   The hmac is salted with the entity table, so a cursor can only be used for
+//!   the entity (and the order by) it was created for.
+//! - The next page is selected with a row comparison on `(column, id)`, so it stays
+//!   correct and fast whatever the offset, and under concurrent inserts.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::config::config;
+use crate::crypt::{encrypt_into_b64u, verify_b64u, EncryptContent};
+use crate::ctx::Ctx;
+use crate::model::store::{with_db, Backend, Sql, SqlValue};
+use crate::model::{Error, ModelManager, OrderBy, Result};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct PageOptions {
+	pub limit: Option<i64>,
+	/// Defaults to `id` ascending.
+	pub order_by: Option<OrderBy>,
+	/// The `next_cursor` of the previous page. `None` for the first page.
+	pub cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Page<E> {
+	pub items: Vec<E>,
+	/// `None` when this is the last page.
+	pub next_cursor: Option<String>,
+}
+
+// region:    --- Cursor
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(in crate::model) struct Cursor {
+	/// The order by in the `OrderBy` notation (e.g., `!ctime`).
+	order_by: String,
+	/// The value of the order by column of the last item (JSON serialized).
+	val: Value,
+	/// The id of the last item.
+	id: i64,
+}
+
+impl Cursor {
+	/// Build the cursor from the last item of a page.
+	/// Note: Relies on the entity serializing its columns under their own names.
+	pub fn from_item<E: Serialize>(item: &E, order_by: &OrderBy) -> Result<Self> {
+		let item = serde_json::to_value(item).map_err(|_| Error::ListCursorInvalid)?;
+
+		let val = item.get(order_by.column()).cloned().unwrap_or(Value::Null);
+		let id = item
+			.get("id")
+			.and_then(Value::as_i64)
+			.ok_or(Error::ListCursorInvalid)?;
+
+		Ok(Cursor {
+			order_by: order_by.to_string(),
+			val,
+			id,
+		})
+	}
+
+	pub fn encode(&self, table: &str) -> Result<String> {
+		let content = serde_json::to_string(self).map_err(|_| Error::ListCursorInvalid)?;
+		let content = base64_url::encode(&content);
+		let sign = sign_content(table, &content)?;
+
+		Ok(format!("{content}.{sign}"))
+	}
+
+	/// Decode and validate the cursor string.
+	/// Fails if the signature does not match, or if the cursor
+	/// was created for another order by.
+	pub fn decode(table: &str, cursor: &str, order_by: &OrderBy) -> Result<Self> {
+		let (content, sign) = cursor.split_once('.').ok_or(Error::ListCursorInvalid)?;
+
+		verify_sign(table, content, sign)?;
+
+		let content = base64_url::decode(content).map_err(|_| Error::ListCursorInvalid)?;
+		let cursor: Cursor =
+			serde_json::from_slice(&content).map_err(|_| Error::ListCursorInvalid)?;
+
+		if cursor.order_by != order_by.to_string() {
+			return Err(Error::ListCursorOrderByMismatch {
+				cursor: cursor.order_by,
+				requested: order_by.to_string(),
+			});
+		}
+
+		Ok(cursor)
+	}
+
+	/// Push the condition selecting the rows after this cursor.
+	///
+	/// The cursor value is cast to the column type by `jsonb_populate_record`
+	/// (for SQLite, bound as the declared column type, see `sqlite_column_val`),
+	/// so that the same condition works for any column type.
+	/// NULLs are sorted last in ascending order, and first in descending order
+	/// (PostgreSQL default, explicit in the order by of `common::list_page`).
+	pub async fn push_keyset_cond(
+		&self,
+		ctx: &Ctx,
+		mm: &ModelManager,
+		sql: &mut Sql,
+		table: &str,
+	) -> Result<()> {
+		let (column, desc) = match self.order_by.strip_prefix('!') {
+			Some(column) => (column, true),
+			None => (self.order_by.as_str(), false),
+		};
+		let cmp = if desc { "<" } else { ">" };
+
+		// -- Ordered by id only.
+		if column == "id" {
+			sql.push(format!("\"id\" {cmp} ")).push_bind(self.id);
+			return Ok(());
+		}
+
+		match (&self.val, desc) {
+			// Last item was NULL, only the next NULLs are still to come.
+			(Value::Null, false) => {
+				sql.push(format!("(\"{column}\" IS NULL AND \"id\" {cmp} "))
+					.push_bind(self.id)
+					.push(")");
+			}
+			// Last item was NULL, the next NULLs and all the non NULLs are still to come.
+			(Value::Null, true) => {
+				sql.push(format!("((\"{column}\" IS NULL AND \"id\" {cmp} "))
+					.push_bind(self.id)
+					.push(format!(") OR \"{column}\" IS NOT NULL)"));
+			}
+			// Last item had a value, NULLs are still to come only in ascending order.
+			(val, desc) => {
+				sql.push(format!("((\"{column}\", \"id\") {cmp} ("));
+				match sql.backend() {
+					Backend::Postgres => {
+						let mut record = Map::new();
+						record.insert(column.to_string(), val.clone());
+						sql.push(format!("(jsonb_populate_record(NULL::\"{table}\", "))
+							.push_bind(Value::Object(record))
+							.push(format!(")).\"{column}\""));
+					}
+					Backend::Sqlite => {
+						sql.push_bind(sqlite_column_val(ctx, mm, table, column, val).await?);
+					}
+				}
+				sql.push(", ").push_bind(self.id).push(")");
+				if !desc {
+					sql.push(format!(" OR \"{column}\" IS NULL"));
+				}
+				sql.push(")");
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// The cursor `val` of the `column` of `table`, as stored by SQLite for its declared type
+/// (e.g., a `TIMESTAMP` as the fixed width text of `SqlValue::DateTime`).
+async fn sqlite_column_val(
+	ctx: &Ctx,
+	mm: &ModelManager,
+	table: &str,
+	column: &str,
+	val: &Value,
+) -> Result<SqlValue> {
+	let mut sql = Sql::new(Backend::Sqlite, "SELECT \"type\" FROM pragma_table_info(");
+	sql.push_bind(table)
+		.push(") WHERE \"name\" = ")
+		.push_bind(column);
+	let column_type: Option<String> =
+		with_db!(ctx, mm, |db| sql.query_scalar().fetch_optional(db).await?);
+
+	let val = match (column_type.as_deref(), val) {
+		(Some("TIMESTAMP"), Value::String(val)) => {
+			let val = OffsetDateTime::parse(val, &Rfc3339).map_err(|_| Error::ListCursorInvalid)?;
+			SqlValue::from(val)
+		}
+		(_, Value::Bool(val)) => SqlValue::from(*val),
+		(_, Value::Number(val)) => SqlValue::from(val.as_i64().ok_or(Error::ListCursorInvalid)?),
+		(_, Value::String(val)) => SqlValue::from(val.as_str()),
+		_ => return Err(Error::ListCursorInvalid),
+	};
+
+	Ok(val)
+}
+
+fn sign_content(table: &str, content: &str) -> Result<String> {
+	let sign = encrypt_into_b64u(&config().TOKEN_KEY, &sign_encrypt_content(table, content))?;
+
+	Ok(sign)
+}
+
+/// Note: In constant time (see `crypt::verify_b64u`).
+fn verify_sign(table: &str, content: &str, sign: &str) -> Result<()> {
+	verify_b64u(
+		&config().TOKEN_KEY,
+		&sign_encrypt_content(table, content),
+		sign,
+	)
+	.map_err(|_| Error::ListCursorInvalid)
+}
+
+fn sign_encrypt_content(table: &str, content: &str) -> EncryptContent {
+	EncryptContent {
+		content: content.to_string(),
+		salt: format!("cursor-{table}"),
+	}
+}
+
+// endregion: --- Cursor
@@ -0,0 +1,395 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_with::serde_as;
+use sqlx::prelude::FromRow;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::config::config;
+use crate::ctx::Ctx;
+use crate::model::error::{Error, Result};
+
+use super::audit::{self, AuditBmc, AuditOp};
+use super::common::DbBmc;
+use super::fields::Fields;
+use super::store::{id_row_json, with_db, Backend};
+use super::task::TaskBmc;
+use super::{
+	common, FilterNode, FilterNodes, ListOptions, ModelManager, OpValsInt64, OpValsString,
+};
+
+// Model: Project struct
+#[serde_as]
+#[derive(Clone, Debug, Serialize, FromRow, Fields)]
+pub struct Project {
+	pub id: i64,
+	/// The tenant, `None` for the rows of a ctx without workspace
+	/// (see `DbBmc::WORKSPACE_SCOPED`).
+	pub workspace_id: Option<i64>,
+	pub name: String,
+
+	// -- Timestamps
+	pub cid: i64,
+	#[serde_as(as = "Rfc3339")]
+	pub ctime: OffsetDateTime,
+	pub mid: i64,
+	#[serde_as(as = "Rfc3339")]
+	pub mtime: OffsetDateTime,
+	/// Bumped on every update (see `ProjectBmc::update_with_version`).
+	pub version: i64,
+}
+
+// Project entity for creating method
+#[derive(Deserialize, Fields)]
+pub struct ProjectForCreate {
+	pub name: String,
+}
+
+// Project entity for updating method
+#[derive(Deserialize, Fields)]
+pub struct ProjectForUpdate {
+	pub name: Option<String>,
+}
+
+// Project filter for list method
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectFilter {
+	pub id: Option<OpValsInt64>,
+	pub name: Option<OpValsString>,
+}
+
+impl FilterNodes for ProjectFilter {
+	fn filter_nodes(self) -> Vec<FilterNode> {
+		let mut nodes = Vec::new();
+		if let Some(id) = self.id {
+			nodes.extend(id.into_filter_nodes("id"));
+		}
+		if let Some(name) = self.name {
+			nodes.extend(name.into_filter_nodes("name"));
+		}
+		nodes
+	}
+}
+
+/// What `ProjectBmc::delete` does with the tasks of the project.
+/// (configured with `SERVICE_PROJECT_DELETE_POLICY`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectDeletePolicy {
+	/// Delete the tasks with the project.
+	Cascade,
+	/// Fail with `Error::ProjectHasTasks` while the project still has tasks
+	/// (not counting the tasks in the trash, which are purged).
+	Reject,
+}
+
+impl FromStr for ProjectDeletePolicy {
+	type Err = ();
+
+	fn from_str(val: &str) -> core::result::Result<Self, Self::Err> {
+		match val {
+			"cascade" => Ok(Self::Cascade),
+			"reject" => Ok(Self::Reject),
+			_ => Err(()),
+		}
+	}
+}
+
+// Project Backend Model Controller
+pub struct ProjectBmc;
+
+impl ProjectBmc {
+	pub async fn create(ctx: &Ctx, mm: &ModelManager, project_c: ProjectForCreate) -> Result<i64> {
+		common::create::<Self, ProjectForCreate>(ctx, mm, project_c).await
+	}
+
+	pub async fn update(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: i64,
+		project_u: ProjectForUpdate,
+	) -> Result<()> {
+		common::update::<Self, ProjectForUpdate>(ctx, mm, id, project_u).await
+	}
+
+	/// Same as `update`, but fails with `Error::VersionConflict`
+	/// if the project is not at `version` anymore.
+	pub async fn update_with_version(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: i64,
+		version: i64,
+		project_u: ProjectForUpdate,
+	) -> Result<()> {
+		common::update_with_version::<Self, ProjectForUpdate>(ctx, mm, id, version, project_u).await
+	}
+
+	pub async fn list(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		filter: Option<ProjectFilter>,
+		list_options: Option<ListOptions>,
+	) -> Result<Vec<Project>> {
+		common::list::<Self, _, _>(ctx, mm, filter, list_options).await
+	}
+
+	pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Project> {
+		common::get::<Self, _>(ctx, mm, id).await
+	}
+
+	/// Delete the project following the configured `ProjectDeletePolicy`.
+	pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+		Self::delete_with_policy(ctx, mm, id, config().PROJECT_DELETE_POLICY).await
+	}
+
+	/// Same as `delete`, but fails with `Error::VersionConflict`
+	/// if the project is not at `version` anymore.
+	pub async fn delete_with_version(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: i64,
+		version: i64,
+	) -> Result<()> {
+		let txn_mm = common::own_txn(ctx, mm).await?;
+		let mm = txn_mm.as_ref().unwrap_or(mm);
+
+		common::lock_with_version::<Self>(ctx, mm, id, version).await?;
+		Self::delete(ctx, mm, id).await?;
+
+		common::commit_own_txn(txn_mm).await?;
+
+		Ok(())
+	}
+
+	pub async fn delete_with_policy(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: i64,
+		policy: ProjectDeletePolicy,
+	) -> Result<()> {
+		let tasks_where = match policy {
+			// Note: The `task.project_id` foreign key rejects the delete
+			//       while the project still has tasks. The tasks in the trash
+			//       are purged with the project.
+			ProjectDeletePolicy::Reject => "project_id = $1 AND deleted_at IS NOT NULL",
+			ProjectDeletePolicy::Cascade => "project_id = $1",
+		};
+
+		let task_workspace_cond = common::workspace_where::<TaskBmc>(ctx);
+		let workspace_cond = common::workspace_where::<Self>(ctx);
+
+		let has_tasks_err = |err| match err {
+			sqlx::Error::Database(db_err) if db_err.is_foreign_key_violation() => {
+				Error::ProjectHasTasks { id }
+			}
+			err => Error::SqlxError(err),
+		};
+		let tasks_sql = format!("DELETE FROM task WHERE {tasks_where} AND {task_workspace_cond}");
+		let project_sql = format!("DELETE FROM project WHERE id = $1 AND {workspace_cond}");
+
+		let txn_mm = common::own_txn(ctx, mm).await?;
+		let mm = txn_mm.as_ref().unwrap_or(mm);
+
+		let rows: Vec<(String, i64, Value)> = match mm.backend() {
+			// Note: Single statement, so that the tasks and the project
+			//       are deleted together or not at all. Returns the deleted rows for the audit.
+			Backend::Postgres => {
+				let sql = format!(
+					"WITH deleted_tasks AS ( \
+						{tasks_sql} RETURNING id, to_jsonb(task.*) AS row \
+					), \
+					deleted_project AS ( \
+						{project_sql} RETURNING id, to_jsonb(project.*) AS row \
+					) \
+					SELECT 'task', id, row FROM deleted_tasks \
+					UNION ALL SELECT 'project', id, row FROM deleted_project"
+				);
+				with_db!(ctx, mm, |db| sqlx::query_as(&sql)
+					.bind(id)
+					.fetch_all(db)
+					.await)
+				.map_err(has_tasks_err)?
+			}
+			// Note: No data-modifying CTE in SQLite, the two deletes in the transaction.
+			Backend::Sqlite => {
+				let mut rows = Vec::new();
+				for (entity, sql) in [(TaskBmc::TABLE, tasks_sql), (Self::TABLE, project_sql)] {
+					let sql = format!("{sql} RETURNING \"id\", *");
+					let deleted = with_db!(ctx, mm, |db| sqlx::query(&sql)
+						.bind(id)
+						.fetch_all(db)
+						.await
+						.map_err(has_tasks_err)?
+						.iter()
+						.map(id_row_json)
+						.collect::<sqlx::Result<Vec<_>>>()?);
+					rows.extend(
+						deleted
+							.into_iter()
+							.map(|(id, row)| (entity.to_string(), id, row)),
+					);
+				}
+				rows
+			}
+		};
+
+		let (projects, tasks): (Vec<_>, Vec<_>) = rows
+			.into_iter()
+			.partition(|(entity, ..)| entity == Self::TABLE);
+		if projects.is_empty() {
+			return Err(Error::EntityNotFound {
+				entity: Self::TABLE,
+				id,
+			});
+		}
+
+		for (_, id, _) in tasks.iter() {
+			common::invalidate_cached::<TaskBmc>(mm, *id);
+		}
+		for (entity, rows) in [(TaskBmc::TABLE, tasks), (Self::TABLE, projects)] {
+			let diffs = rows
+				.into_iter()
+				.map(|(_, id, old)| (id, audit::diff(Some(&old), None, None)))
+				.collect();
+			AuditBmc::log(ctx, mm, entity, AuditOp::Delete, diffs).await?;
+		}
+
+		common::commit_own_txn(txn_mm).await?;
+
+		Ok(())
+	}
+}
+
+// Impl Trait Dbmc for Project model
+impl DbBmc for ProjectBmc {
+	const TABLE: &'static str = "project";
+	const VERSIONED: bool = true;
+	const WORKSPACE_SCOPED: bool = true;
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(unused)]
+	use crate::_dev_utils;
+	use crate::model::task::{TaskBmc, TaskForCreate};
+
+	use super::*;
+	use anyhow::Result;
+	use serial_test::serial;
+
+	#[serial]
+	#[tokio::test]
+	async fn test_create_ok() -> Result<()> {
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+		let fx_name = "test_create_ok project";
+		let project_c = ProjectForCreate {
+			name: fx_name.to_string(),
+		};
+
+		let id = ProjectBmc::create(&ctx, &mm, project_c).await?;
+
+		let project = ProjectBmc::get(&ctx, &mm, id).await?;
+		assert_eq!(project.name, fx_name);
+
+		ProjectBmc::delete(&ctx, &mm, id).await?;
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_list_by_project_ok() -> Result<()> {
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+		let fx_project_id = _dev_utils::seed_project(&ctx, &mm, "test_list_by_project_ok").await?;
+		let fx_titles = ["test_list_by_project_ok 01", "test_list_by_project_ok 02"];
+		_dev_utils::seed_tasks_for_project(&ctx, &mm, fx_project_id, &fx_titles).await?;
+		// Task without project, should not be listed.
+		let other_task_id =
+			_dev_utils::seed_test(&ctx, &mm, &["test_list_by_project_ok 03"]).await?[0].id;
+
+		let tasks = TaskBmc::list_by_project(&ctx, &mm, fx_project_id, None).await?;
+		let titles: Vec<&str> = tasks.iter().map(|t| t.title.as_str()).collect();
+		assert_eq!(titles, fx_titles);
+
+		// -- Clean
+		ProjectBmc::delete_with_policy(&ctx, &mm, fx_project_id, ProjectDeletePolicy::Cascade)
+			.await?;
+		TaskBmc::delete(&ctx, &mm, other_task_id).await?;
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_delete_reject_err_has_tasks() -> Result<()> {
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+		let fx_project_id =
+			_dev_utils::seed_project(&ctx, &mm, "test_delete_reject_err_has_tasks").await?;
+		let fx_task_ids = _dev_utils::seed_tasks_for_project(
+			&ctx,
+			&mm,
+			fx_project_id,
+			&["test_delete_reject_err_has_tasks 01"],
+		)
+		.await?;
+
+		let result =
+			ProjectBmc::delete_with_policy(&ctx, &mm, fx_project_id, ProjectDeletePolicy::Reject)
+				.await;
+		assert!(matches!(
+			result,
+			Err(Error::ProjectHasTasks { id }) if id == fx_project_id
+		));
+		// Project and task are still there.
+		ProjectBmc::get(&ctx, &mm, fx_project_id).await?;
+		TaskBmc::get(&ctx, &mm, fx_task_ids[0]).await?;
+
+		// -- Once the tasks are deleted, the project can be deleted.
+		TaskBmc::delete(&ctx, &mm, fx_task_ids[0]).await?;
+		ProjectBmc::delete_with_policy(&ctx, &mm, fx_project_id, ProjectDeletePolicy::Reject)
+			.await?;
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_delete_cascade_ok() -> Result<()> {
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+		let fx_project_id = _dev_utils::seed_project(&ctx, &mm, "test_delete_cascade_ok").await?;
+		let fx_task_ids = _dev_utils::seed_tasks_for_project(
+			&ctx,
+			&mm,
+			fx_project_id,
+			&["test_delete_cascade_ok 01", "test_delete_cascade_ok 02"],
+		)
+		.await?;
+
+		ProjectBmc::delete_with_policy(&ctx, &mm, fx_project_id, ProjectDeletePolicy::Cascade)
+			.await?;
+
+		let result = ProjectBmc::get(&ctx, &mm, fx_project_id).await;
+		assert!(matches!(
+			result,
+			Err(Error::EntityNotFound {
+				entity: "project",
+				..
+			})
+		));
+		for task_id in fx_task_ids {
+			let result = TaskBmc::get(&ctx, &mm, task_id).await;
+			assert!(matches!(
+				result,
+				Err(Error::EntityNotFound { entity: "task", .. })
+			));
+		}
+
+		Ok(())
+	}
+}
@@ -0,0 +1,213 @@
+//! Lexicographic rank keys, for the manual order of the rows (e.g., `TaskBmc::move_before`).
+//!
+//! - A key is a string of base 62 digits (`0-9A-Za-z`, in ascii order), compared bytewise
+//!   (i.e., a `COLLATE "C"` column), so that there is always a key between two keys.
+//! - Keys never end with `0`, so that there is always a key before a key.
+//! - The keys get longer as rows are moved between the same neighbors,
+//!   until they are spread again (see `ranks_evenly`).
+
+const DIGITS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const BASE: usize = 62;
+
+/// The max length of a new key, above which the keys should be spread again.
+pub const RANK_LEN_MAX: usize = 32;
+
+/// The length at which a key after the last key is incremented,
+/// so that appending does not make the keys longer.
+const RANK_LEN_APPEND: usize = 4;
+
+/// A key after `before` (or the first key), and before `after` (or the last key).
+/// `None` when `before` is not less than `after`, or when one of them is not a valid key
+/// (e.g., two rows with the same key).
+pub fn rank_between(before: Option<&str>, after: Option<&str>) -> Option<String> {
+	let before = match before {
+		Some(before) => to_digits(before)?,
+		None => Vec::new(),
+	};
+	let after = match after {
+		Some(after) => Some(to_digits(after)?),
+		None => None,
+	};
+
+	let digits = match after {
+		Some(after) => {
+			if before >= after {
+				return None;
+			}
+			midpoint(&before, Some(&after))
+		}
+		None if before.is_empty() => midpoint(&before, None),
+		None => increment(&before).unwrap_or_else(|| midpoint(&before, None)),
+	};
+
+	Some(to_key(&digits))
+}
+
+/// `n` keys, in order, evenly spread over the first half of the keys of their length,
+/// so that the next keys can be appended without getting longer.
+pub fn ranks_evenly(n: usize) -> Vec<String> {
+	// -- The smallest length with ~BASE keys of room between two keys.
+	let mut len = 1;
+	while (BASE as u128).pow(len) < (n as u128 + 1) * 2 * BASE as u128 {
+		len += 1;
+	}
+	let step = (BASE as u128).pow(len) / ((n as u128 + 1) * 2);
+
+	(1..=n as u128)
+		.map(|i| {
+			let mut val = i * step;
+			let mut digits = vec![0; len as usize];
+			for digit in digits.iter_mut().rev() {
+				*digit = (val % BASE as u128) as usize;
+				val /= BASE as u128;
+			}
+			to_key(&digits)
+		})
+		.collect()
+}
+
+// region:    --- Support
+
+/// A key strictly between `a` and `b` (or after `a` when `None`).
+/// Note: `a` must be less than `b`, and both without trailing `0` digits.
+fn midpoint(a: &[usize], b: Option<&[usize]>) -> Vec<usize> {
+	if let Some(b) = b {
+		// -- Keep the common prefix (a being padded with 0s).
+		let n = b
+			.iter()
+			.enumerate()
+			.take_while(|(i, digit)| a.get(*i).copied().unwrap_or(0) == **digit)
+			.count();
+		if n > 0 {
+			let mut res = b[..n].to_vec();
+			res.extend(midpoint(a.get(n..).unwrap_or(&[]), Some(&b[n..])));
+			return res;
+		}
+	}
+
+	// -- The first digits differ.
+	let digit_a = a.first().copied().unwrap_or(0);
+	let digit_b = b.map(|b| b[0]).unwrap_or(BASE);
+	if digit_b - digit_a > 1 {
+		vec![(digit_a + digit_b) / 2]
+	} else if let Some(b) = b.filter(|b| b.len() > 1) {
+		// Note: `b` first digit alone is before `b`, and after `a`.
+		vec![b[0]]
+	} else {
+		let mut res = vec![digit_a];
+		res.extend(midpoint(a.get(1..).unwrap_or(&[]), None));
+		res
+	}
+}
+
+/// `a` + 1 at the `RANK_LEN_APPEND` digit (or its last digit, when longer).
+/// `None` on overflow.
+fn increment(a: &[usize]) -> Option<Vec<usize>> {
+	let mut digits = a.to_vec();
+	digits.resize(a.len().max(RANK_LEN_APPEND), 0);
+
+	for digit in digits.iter_mut().rev() {
+		if *digit + 1 < BASE {
+			*digit += 1;
+			return Some(digits);
+		}
+		*digit = 0;
+	}
+
+	None
+}
+
+fn to_digits(key: &str) -> Option<Vec<usize>> {
+	let digits = key
+		.bytes()
+		.map(|b| DIGITS.iter().position(|d| *d == b))
+		.collect::<Option<Vec<_>>>()?;
+
+	match digits.last() {
+		Some(0) | None => None,
+		Some(_) => Some(digits),
+	}
+}
+
+/// Note: Trims the trailing `0` digits, which keeps the order of the keys.
+fn to_key(digits: &[usize]) -> String {
+	let len = digits.iter().rposition(|d| *d != 0).map_or(0, |i| i + 1);
+
+	digits[..len].iter().map(|d| DIGITS[*d] as char).collect()
+}
+
+// endregion: --- Support
+
+#[cfg(test)]
+mod tests {
+	#![allow(unused)]
+	use super::*;
+	use anyhow::Result;
+
+	#[test]
+	fn test_rank_between_ok() -> Result<()> {
+		let fx_cases: &[(Option<&str>, Option<&str>, &str)] = &[
+			(None, None, "V"),
+			(Some("V"), None, "V001"),
+			(Some("V00z"), None, "V01"),
+			(None, Some("V"), "F"),
+			(None, Some("01"), "00V"),
+			(Some("A"), Some("B"), "AV"),
+			(Some("A"), Some("Az"), "AU"),
+			(Some("Az"), Some("B1"), "B"),
+			(Some("zzzz"), None, "zzzzV"),
+		];
+
+		for (before, after, expected) in fx_cases {
+			let rank = rank_between(*before, *after).unwrap();
+			assert_eq!(&rank, expected, "between {before:?} and {after:?}");
+			assert!(before.map_or(true, |before| before < rank.as_str()));
+			assert!(after.map_or(true, |after| rank.as_str() < after));
+		}
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_rank_between_err() -> Result<()> {
+		assert_eq!(rank_between(Some("B"), Some("A")), None);
+		assert_eq!(rank_between(Some("A"), Some("A")), None);
+		// Not a key.
+		assert_eq!(rank_between(Some("A0"), None), None);
+		assert_eq!(rank_between(Some("A-"), None), None);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_rank_between_repeated_ok() -> Result<()> {
+		// -- Always moving between the same neighbors makes the keys longer.
+		let mut after = "V".to_string();
+		for _ in 0..300 {
+			let rank = rank_between(Some("U"), Some(&after)).unwrap();
+			assert!("U" < rank.as_str() && rank < after);
+			after = rank;
+		}
+		assert!(after.len() > RANK_LEN_MAX);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_ranks_evenly_ok() -> Result<()> {
+		for n in [0, 1, 10, 61, 62, 5000] {
+			let ranks = ranks_evenly(n);
+
+			assert_eq!(ranks.len(), n);
+			assert!(ranks.windows(2).all(|w| w[0] < w[1]), "n: {n}");
+			assert!(ranks.iter().all(|r| to_digits(r).is_some()), "n: {n}");
+			// Room to append after the last one.
+			if let Some(last) = ranks.last() {
+				let next = rank_between(Some(last), None).unwrap();
+				assert!(next.len() <= RANK_LEN_APPEND.max(last.len()));
+			}
+		}
+
+		Ok(())
+	}
+}
@@ -0,0 +1,889 @@
+//! RFC 5545 recurrence rules (`RRULE`), for the recurring tasks (see `TaskBmc::create`).
+//!
+//! - Supports `FREQ` (`DAILY`, `WEEKLY`, `MONTHLY`, `YEARLY`), `INTERVAL`, `COUNT`, `UNTIL`,
+//!   `BYDAY` (with an ordinal for `MONTHLY` and `YEARLY`, e.g., `-1FR`), `BYMONTHDAY`,
+//!   `BYMONTH` and `WKST`.
+//! - The occurrences are expanded in the local time of a timezone, so that they keep
+//!   the wall clock time of `DTSTART` across the DST changes. A local time in a DST gap
+//!   is moved forward by the gap, and an ambiguous local time is the first of the two
+//!   (RFC 5545 3.3.5).
+//! - `DTSTART` is always the first occurrence, and counts for `COUNT`.
+
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+use time::format_description::FormatItem;
+use time::{Date, Duration, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset, Weekday};
+use time_tz::{
+	timezones, Offset, OffsetDateTimeExt, OffsetResult, PrimitiveDateTimeExt, TimeZone, Tz,
+};
+
+/// The max number of periods in a row without occurrence,
+/// above which the rule is considered as ended (e.g., `BYMONTH=2;BYMONTHDAY=30`).
+const EMPTY_PERIODS_MAX: u32 = 10_000;
+
+/// The max number of occurrences walked by `RRule::next_after`,
+/// above which there is no next occurrence (e.g., a long `COUNT` rule, walked from `DTSTART`).
+const NEXT_AFTER_STEPS_MAX: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Freq {
+	Daily,
+	Weekly,
+	Monthly,
+	Yearly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Until {
+	Utc(OffsetDateTime),
+	/// In the local time of the timezone (also for a date, at its end).
+	Local(PrimitiveDateTime),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RRule {
+	freq: Freq,
+	interval: u32,
+	count: Option<u32>,
+	until: Option<Until>,
+	/// The weekdays, with their ordinal in the month (or year) when any (e.g., `-1` for the last).
+	by_day: Vec<(Option<i8>, Weekday)>,
+	/// The days of the month, negative from the end of the month (e.g., `-1` for the last).
+	by_month_day: Vec<i8>,
+	by_month: Vec<Month>,
+	week_start: Weekday,
+}
+
+/// The timezone from its IANA name (e.g., `Europe/Paris`).
+pub fn tz_by_name(name: &str) -> Option<&'static Tz> {
+	timezones::get_by_name(name)
+}
+
+impl RRule {
+	/// The occurrences from `dtstart` (the first one), in order, as UTC datetimes.
+	/// Note: Endless when the rule has no `COUNT` nor `UNTIL`.
+	pub fn occurrences(&self, dtstart: OffsetDateTime, tz: &'static Tz) -> Occurrences<'_> {
+		let local = dtstart.to_timezone(tz);
+
+		Occurrences {
+			rule: self,
+			tz,
+			dtstart,
+			local_start: PrimitiveDateTime::new(local.date(), local.time()),
+			period: 0,
+			empty_periods: 0,
+			pending: VecDeque::new(),
+			count: 0,
+			done: false,
+		}
+	}
+
+	/// The first occurrence after `after`, if any.
+	/// Note: Without `COUNT`, the occurrences are walked from the period before the one
+	///       of `after`, rather than from `dtstart` (e.g., a daily rule started years ago).
+	pub fn next_after(
+		&self,
+		dtstart: OffsetDateTime,
+		tz: &'static Tz,
+		after: OffsetDateTime,
+	) -> Option<OffsetDateTime> {
+		let mut occurrences = self.occurrences(dtstart, tz);
+		if self.count.is_none() && after > dtstart {
+			occurrences.skip_to(after);
+		}
+
+		occurrences
+			.take(NEXT_AFTER_STEPS_MAX)
+			.find(|at| *at > after)
+	}
+}
+
+impl FromStr for RRule {
+	/// Why the rule is not valid (or not supported).
+	type Err = &'static str;
+
+	fn from_str(val: &str) -> core::result::Result<Self, Self::Err> {
+		let val = val.trim();
+		let val = val.strip_prefix("RRULE:").unwrap_or(val);
+
+		let mut freq = None;
+		let mut rule = RRule {
+			freq: Freq::Daily,
+			interval: 1,
+			count: None,
+			until: None,
+			by_day: Vec::new(),
+			by_month_day: Vec::new(),
+			by_month: Vec::new(),
+			week_start: Weekday::Monday,
+		};
+
+		for part in val.split(';').filter(|part| !part.is_empty()) {
+			let (name, value) = part.split_once('=').ok_or("rule part without value")?;
+			match name.to_ascii_uppercase().as_str() {
+				"FREQ" => {
+					freq = Some(match value.to_ascii_uppercase().as_str() {
+						"DAILY" => Freq::Daily,
+						"WEEKLY" => Freq::Weekly,
+						"MONTHLY" => Freq::Monthly,
+						"YEARLY" => Freq::Yearly,
+						"SECONDLY" | "MINUTELY" | "HOURLY" => return Err("FREQ not supported"),
+						_ => return Err("FREQ invalid"),
+					})
+				}
+				"INTERVAL" => {
+					rule.interval = value
+						.parse()
+						.ok()
+						.filter(|interval| *interval > 0)
+						.ok_or("INTERVAL invalid")?
+				}
+				"COUNT" => {
+					rule.count = Some(
+						value
+							.parse()
+							.ok()
+							.filter(|count| *count > 0)
+							.ok_or("COUNT invalid")?,
+					)
+				}
+				"UNTIL" => rule.until = Some(parse_until(value).ok_or("UNTIL invalid")?),
+				"BYDAY" => {
+					rule.by_day = value
+						.split(',')
+						.map(parse_by_day)
+						.collect::<Option<_>>()
+						.ok_or("BYDAY invalid")?
+				}
+				"BYMONTHDAY" => {
+					rule.by_month_day = value
+						.split(',')
+						.map(|day| {
+							day.parse::<i8>()
+								.ok()
+								.filter(|day| *day != 0 && (-31..=31).contains(day))
+						})
+						.collect::<Option<_>>()
+						.ok_or("BYMONTHDAY invalid")?
+				}
+				"BYMONTH" => {
+					rule.by_month = value
+						.split(',')
+						.map(|month| {
+							month
+								.parse::<u8>()
+								.ok()
+								.and_then(|m| Month::try_from(m).ok())
+						})
+						.collect::<Option<_>>()
+						.ok_or("BYMONTH invalid")?
+				}
+				"WKST" => rule.week_start = parse_weekday(value).ok_or("WKST invalid")?,
+				"BYSETPOS" | "BYWEEKNO" | "BYYEARDAY" | "BYHOUR" | "BYMINUTE" | "BYSECOND" => {
+					return Err("rule part not supported")
+				}
+				_ => return Err("rule part unknown"),
+			}
+		}
+
+		rule.freq = freq.ok_or("FREQ missing")?;
+		if rule.count.is_some() && rule.until.is_some() {
+			return Err("COUNT and UNTIL are exclusive");
+		}
+		let has_ordinal = rule.by_day.iter().any(|(ordinal, _)| ordinal.is_some());
+		if has_ordinal && !matches!(rule.freq, Freq::Monthly | Freq::Yearly) {
+			return Err("BYDAY ordinal only for MONTHLY or YEARLY");
+		}
+		if rule.freq == Freq::Weekly && !rule.by_month_day.is_empty() {
+			return Err("BYMONTHDAY not for WEEKLY");
+		}
+
+		Ok(rule)
+	}
+}
+
+// region:    --- Occurrences
+
+/// The occurrences of a rule (see `RRule::occurrences`).
+pub struct Occurrences<'a> {
+	rule: &'a RRule,
+	tz: &'static Tz,
+	dtstart: OffsetDateTime,
+	local_start: PrimitiveDateTime,
+	/// The next period, from the period of `dtstart` (in `interval` steps).
+	period: u32,
+	empty_periods: u32,
+	/// The local occurrences of the current period, not returned yet.
+	pending: VecDeque<PrimitiveDateTime>,
+	count: u32,
+	done: bool,
+}
+
+impl Iterator for Occurrences<'_> {
+	type Item = OffsetDateTime;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.done || self.rule.count.is_some_and(|count| self.count >= count) {
+			return None;
+		}
+
+		let (local, at) = if self.count == 0 {
+			(self.local_start, self.dtstart.to_offset(UtcOffset::UTC))
+		} else {
+			let Some(local) = self.next_local() else {
+				self.done = true;
+				return None;
+			};
+			(local, local_to_utc(local, self.tz))
+		};
+
+		let is_past_until = match self.rule.until {
+			Some(Until::Utc(until)) => at > until,
+			Some(Until::Local(until)) => local > until,
+			None => false,
+		};
+		if is_past_until {
+			self.done = true;
+			return None;
+		}
+
+		self.count += 1;
+		Some(at)
+	}
+}
+
+impl Occurrences<'_> {
+	/// Skip to the period before the one of `after` (past `dtstart`),
+	/// so that the next occurrences are the ones around `after`.
+	/// Note: Only without `COUNT`, as the skipped occurrences are not counted.
+	fn skip_to(&mut self, after: OffsetDateTime) {
+		let start = self.local_start.date();
+		let date = after.to_timezone(self.tz).date();
+		let months = |date: Date| i64::from(date.year()) * 12 + i64::from(u8::from(date.month()));
+
+		let units = match self.rule.freq {
+			Freq::Daily => (date - start).whole_days(),
+			// Note: Maybe one week less than the weeks from the `WKST` of `dtstart`.
+			Freq::Weekly => (date - start).whole_days() / 7,
+			Freq::Monthly => months(date) - months(start),
+			Freq::Yearly => i64::from(date.year()) - i64::from(start.year()),
+		};
+		let period = units / i64::from(self.rule.interval) - 1;
+		if period > 0 {
+			self.period = u32::try_from(period).unwrap_or(u32::MAX);
+			// `dtstart`, before `after`, is not returned.
+			self.count = 1;
+		}
+	}
+
+	/// The next local occurrence after the local `dtstart`.
+	fn next_local(&mut self) -> Option<PrimitiveDateTime> {
+		loop {
+			if let Some(local) = self.pending.pop_front() {
+				return Some(local);
+			}
+			if self.empty_periods >= EMPTY_PERIODS_MAX {
+				return None;
+			}
+
+			let dates = self.period_dates(self.period)?;
+			self.period += 1;
+
+			let time = self.local_start.time();
+			self.pending = dates
+				.into_iter()
+				.map(|date| PrimitiveDateTime::new(date, time))
+				.filter(|local| *local > self.local_start)
+				.collect();
+			if self.pending.is_empty() {
+				self.empty_periods += 1;
+			} else {
+				self.empty_periods = 0;
+			}
+		}
+	}
+
+	/// The dates of the period, in order. `None` past the supported dates.
+	fn period_dates(&self, period: u32) -> Option<Vec<Date>> {
+		let rule = self.rule;
+		let start = self.local_start.date();
+		let step = i64::from(period) * i64::from(rule.interval);
+
+		let dates = match rule.freq {
+			Freq::Daily => {
+				let date = start.checked_add(Duration::days(step))?;
+				let is_match = rule.matches_month(date)
+					&& rule.matches_month_day(date)
+					&& (rule.by_day.is_empty()
+						|| rule.by_day.iter().any(|(_, day)| *day == date.weekday()));
+				match is_match {
+					true => vec![date],
+					false => Vec::new(),
+				}
+			}
+			Freq::Weekly => {
+				let days_from_week_start = (start.weekday().number_days_from_monday() + 7
+					- rule.week_start.number_days_from_monday())
+					% 7;
+				let week = start.checked_sub(Duration::days(i64::from(days_from_week_start)))?;
+				let week = week.checked_add(Duration::weeks(step))?;
+				(0..7)
+					.map(|i| week.checked_add(Duration::days(i)))
+					.collect::<Option<Vec<_>>>()?
+					.into_iter()
+					.filter(|date| match rule.by_day.is_empty() {
+						true => date.weekday() == start.weekday(),
+						false => rule.by_day.iter().any(|(_, day)| *day == date.weekday()),
+					})
+					.filter(|date| rule.matches_month(*date))
+					.collect()
+			}
+			Freq::Monthly => {
+				let months = i64::from(start.year()) * 12 + i64::from(u8::from(start.month()) - 1);
+				let months = months + step;
+				let year = i32::try_from(months / 12).ok()?;
+				let month = Month::try_from((months % 12) as u8 + 1).ok()?;
+				if !rule.by_month.is_empty() && !rule.by_month.contains(&month) {
+					Vec::new()
+				} else {
+					rule.month_dates(year, month, start)?
+				}
+			}
+			Freq::Yearly => {
+				let year = i32::try_from(i64::from(start.year()) + step).ok()?;
+				if rule.by_day.is_empty() && rule.by_month_day.is_empty() {
+					// -- The day of `dtstart`, in each month.
+					let months = match rule.by_month.is_empty() {
+						true => vec![start.month()],
+						false => rule.by_month.clone(),
+					};
+					months
+						.into_iter()
+						.filter_map(|month| Date::from_calendar_date(year, month, start.day()).ok())
+						.collect()
+				} else if rule.by_month.is_empty() && rule.by_month_day.is_empty() {
+					// -- The weekdays of the year (the ordinals are in the year).
+					let first = Date::from_calendar_date(year, Month::January, 1).ok()?;
+					let days = time::util::days_in_year(year);
+					let dates = (0..days)
+						.map(|i| first.checked_add(Duration::days(i64::from(i))))
+						.collect::<Option<Vec<_>>>()?;
+					rule.filter_by_day(dates)
+				} else {
+					let months = match rule.by_month.is_empty() {
+						true => (1..=12).filter_map(|m| Month::try_from(m).ok()).collect(),
+						false => rule.by_month.clone(),
+					};
+					let mut dates = Vec::new();
+					for month in months {
+						dates.extend(rule.month_dates(year, month, start)?);
+					}
+					dates
+				}
+			}
+		};
+
+		let mut dates = dates;
+		dates.sort();
+		dates.dedup();
+
+		Some(dates)
+	}
+}
+
+impl RRule {
+	/// The dates of the month matching `BYMONTHDAY` and `BYDAY` (ordinals in the month),
+	/// or the day of `start` when none.
+	fn month_dates(&self, year: i32, month: Month, start: Date) -> Option<Vec<Date>> {
+		if self.by_day.is_empty() && self.by_month_day.is_empty() {
+			// Note: Skipped when the month does not have the day (e.g., the 31st).
+			return Some(
+				Date::from_calendar_date(year, month, start.day())
+					.ok()
+					.into_iter()
+					.collect(),
+			);
+		}
+
+		let days = month.length(year);
+		let dates = (1..=days)
+			.map(|day| Date::from_calendar_date(year, month, day).ok())
+			.collect::<Option<Vec<_>>>()?;
+		let dates = dates
+			.into_iter()
+			.filter(|date| self.matches_month_day(*date))
+			.collect();
+
+		Some(self.filter_by_day(dates))
+	}
+
+	/// The `dates` (consecutive days) matching `BYDAY`, with the ordinals in `dates`.
+	fn filter_by_day(&self, dates: Vec<Date>) -> Vec<Date> {
+		if self.by_day.is_empty() {
+			return dates;
+		}
+
+		let (Some(first), Some(last)) = (dates.first().copied(), dates.last().copied()) else {
+			return dates;
+		};
+		dates
+			.into_iter()
+			.filter(|date| {
+				// The ordinal of the weekday, from the start and from the end.
+				let nth = ((*date - first).whole_days() / 7 + 1) as i8;
+				let nth_last = -(((last - *date).whole_days() / 7 + 1) as i8);
+				self.by_day.iter().any(|(ordinal, day)| {
+					*day == date.weekday()
+						&& ordinal.map_or(true, |ordinal| ordinal == nth || ordinal == nth_last)
+				})
+			})
+			.collect()
+	}
+
+	fn matches_month(&self, date: Date) -> bool {
+		self.by_month.is_empty() || self.by_month.contains(&date.month())
+	}
+
+	fn matches_month_day(&self, date: Date) -> bool {
+		let days = date.month().length(date.year()) as i8;
+		let day = date.day() as i8;
+
+		self.by_month_day.is_empty()
+			|| self
+				.by_month_day
+				.iter()
+				.any(|d| *d == day || *d == day - days - 1)
+	}
+}
+
+// endregion: --- Occurrences
+
+// region:    --- Support
+
+/// The UTC datetime of the local time in the timezone (see the module doc for the DST changes).
+pub fn local_to_utc(local: PrimitiveDateTime, tz: &'static Tz) -> OffsetDateTime {
+	let at = match local.assume_timezone(tz) {
+		OffsetResult::Some(at) | OffsetResult::Ambiguous(at, _) => at,
+		// -- In a DST gap, with the offset before the gap.
+		OffsetResult::None => {
+			let offset = tz.get_offset_utc(&(local.assume_utc() - Duration::DAY));
+			local.assume_offset(offset.to_utc())
+		}
+	};
+
+	at.to_offset(UtcOffset::UTC)
+}
+
+/// e.g., `MO`, `2TU`, `-1FR`
+fn parse_by_day(val: &str) -> Option<(Option<i8>, Weekday)> {
+	let split = val.len().checked_sub(2)?;
+	let (ordinal, day) = (val.get(..split)?, val.get(split..)?);
+	let ordinal = match ordinal {
+		"" => None,
+		ordinal => Some(
+			ordinal
+				.parse::<i8>()
+				.ok()
+				.filter(|o| *o != 0 && (-53..=53).contains(o))?,
+		),
+	};
+
+	Some((ordinal, parse_weekday(day)?))
+}
+
+fn parse_weekday(val: &str) -> Option<Weekday> {
+	let day = match val.to_ascii_uppercase().as_str() {
+		"MO" => Weekday::Monday,
+		"TU" => Weekday::Tuesday,
+		"WE" => Weekday::Wednesday,
+		"TH" => Weekday::Thursday,
+		"FR" => Weekday::Friday,
+		"SA" => Weekday::Saturday,
+		"SU" => Weekday::Sunday,
+		_ => return None,
+	};
+
+	Some(day)
+}
+
+/// e.g., `20300101` (the whole day), `20300101T090000` (local), `20300101T090000Z` (UTC)
+fn parse_until(val: &str) -> Option<Until> {
+	let parse_date = |val: &str| {
+		let format: Vec<FormatItem> =
+			time::format_description::parse_borrowed::<2>("[year][month][day]").ok()?;
+		Date::parse(val, &format).ok()
+	};
+	let parse_time = |val: &str| {
+		let format: Vec<FormatItem> =
+			time::format_description::parse_borrowed::<2>("[hour][minute][second]").ok()?;
+		Time::parse(val, &format).ok()
+	};
+
+	let until = match val.split_once('T') {
+		None => Until::Local(PrimitiveDateTime::new(parse_date(val)?, Time::MAX)),
+		Some((date, time)) => match time.strip_suffix('Z') {
+			Some(time) => Until::Utc(
+				PrimitiveDateTime::new(parse_date(date)?, parse_time(time)?).assume_utc(),
+			),
+			None => Until::Local(PrimitiveDateTime::new(parse_date(date)?, parse_time(time)?)),
+		},
+	};
+
+	Some(until)
+}
+
+// endregion: --- Support
+
+#[cfg(test)]
+mod tests {
+	#![allow(unused)]
+	use super::*;
+	use anyhow::Result;
+	use time::format_description::well_known::Rfc3339;
+
+	fn fx_occurrences(rrule: &str, tz: &str, dtstart: &str, n: usize) -> Result<Vec<String>> {
+		let rule: RRule = rrule.parse().map_err(anyhow::Error::msg)?;
+		let tz = tz_by_name(tz).ok_or_else(|| anyhow::anyhow!("unknown tz {tz}"))?;
+		let dtstart = OffsetDateTime::parse(dtstart, &Rfc3339)?;
+
+		rule.occurrences(dtstart, tz)
+			.take(n)
+			.map(|at| Ok(at.format(&Rfc3339)?))
+			.collect()
+	}
+
+	#[test]
+	fn test_parse_err() -> Result<()> {
+		let fx_cases = [
+			("", "FREQ missing"),
+			("FREQ=HOURLY", "FREQ not supported"),
+			("FREQ=WEEKLY;INTERVAL=0", "INTERVAL invalid"),
+			(
+				"FREQ=WEEKLY;COUNT=2;UNTIL=20300101",
+				"COUNT and UNTIL are exclusive",
+			),
+			(
+				"FREQ=WEEKLY;BYDAY=1MO",
+				"BYDAY ordinal only for MONTHLY or YEARLY",
+			),
+			("FREQ=MONTHLY;BYDAY=XX", "BYDAY invalid"),
+			("FREQ=MONTHLY;BYMONTHDAY=32", "BYMONTHDAY invalid"),
+			("FREQ=MONTHLY;BYSETPOS=-1", "rule part not supported"),
+			("FREQ=MONTHLY;FOO=1", "rule part unknown"),
+		];
+
+		for (rrule, expected) in fx_cases {
+			assert_eq!(rrule.parse::<RRule>(), Err(expected), "rrule: {rrule}");
+		}
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_occurrences_ok() -> Result<()> {
+		let fx_cases: &[(&str, &str, &[&str])] = &[
+			(
+				"FREQ=DAILY;INTERVAL=2;COUNT=3",
+				"2030-01-30T09:00:00Z",
+				&[
+					"2030-01-30T09:00:00Z",
+					"2030-02-01T09:00:00Z",
+					"2030-02-03T09:00:00Z",
+				],
+			),
+			(
+				"RRULE:FREQ=WEEKLY;BYDAY=MO,FR",
+				// A Wednesday, always the first occurrence.
+				"2030-01-02T09:00:00Z",
+				&[
+					"2030-01-02T09:00:00Z",
+					"2030-01-04T09:00:00Z",
+					"2030-01-07T09:00:00Z",
+					"2030-01-11T09:00:00Z",
+				],
+			),
+			(
+				"FREQ=MONTHLY;BYMONTHDAY=31",
+				"2030-01-31T09:00:00Z",
+				&[
+					"2030-01-31T09:00:00Z",
+					"2030-03-31T09:00:00Z",
+					"2030-05-31T09:00:00Z",
+					"2030-07-31T09:00:00Z",
+				],
+			),
+			(
+				"FREQ=MONTHLY;BYDAY=-1FR",
+				"2030-01-25T09:00:00Z",
+				&[
+					"2030-01-25T09:00:00Z",
+					"2030-02-22T09:00:00Z",
+					"2030-03-29T09:00:00Z",
+					"2030-04-26T09:00:00Z",
+				],
+			),
+			(
+				"FREQ=MONTHLY;INTERVAL=2;BYDAY=1MO",
+				"2030-01-07T09:00:00Z",
+				&[
+					"2030-01-07T09:00:00Z",
+					"2030-03-04T09:00:00Z",
+					"2030-05-06T09:00:00Z",
+					"2030-07-01T09:00:00Z",
+				],
+			),
+			(
+				"FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=29",
+				"2028-02-29T09:00:00Z",
+				&[
+					"2028-02-29T09:00:00Z",
+					"2032-02-29T09:00:00Z",
+					"2036-02-29T09:00:00Z",
+					"2040-02-29T09:00:00Z",
+				],
+			),
+			(
+				"FREQ=YEARLY;BYDAY=1MO",
+				"2030-01-07T09:00:00Z",
+				&[
+					"2030-01-07T09:00:00Z",
+					"2031-01-06T09:00:00Z",
+					"2032-01-05T09:00:00Z",
+					"2033-01-03T09:00:00Z",
+				],
+			),
+			(
+				"FREQ=WEEKLY;UNTIL=20300115",
+				"2030-01-01T09:00:00Z",
+				&[
+					"2030-01-01T09:00:00Z",
+					"2030-01-08T09:00:00Z",
+					"2030-01-15T09:00:00Z",
+				],
+			),
+			(
+				"FREQ=WEEKLY;UNTIL=20300115T085959Z",
+				"2030-01-01T09:00:00Z",
+				&["2030-01-01T09:00:00Z", "2030-01-08T09:00:00Z"],
+			),
+		];
+
+		for (rrule, dtstart, expected) in fx_cases {
+			let occurrences = fx_occurrences(rrule, "UTC", dtstart, 4)?;
+			assert_eq!(&occurrences, expected, "rrule: {rrule}");
+		}
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_occurrences_week_start_ok() -> Result<()> {
+		// -- Every other week, on Tuesday and Sunday, the week starting on Monday or Sunday.
+		let occurrences = fx_occurrences(
+			"FREQ=WEEKLY;INTERVAL=2;COUNT=4;BYDAY=TU,SU;WKST=MO",
+			"UTC",
+			"1997-08-05T09:00:00Z",
+			10,
+		)?;
+		assert_eq!(
+			occurrences,
+			[
+				"1997-08-05T09:00:00Z",
+				"1997-08-10T09:00:00Z",
+				"1997-08-19T09:00:00Z",
+				"1997-08-24T09:00:00Z"
+			]
+		);
+
+		let occurrences = fx_occurrences(
+			"FREQ=WEEKLY;INTERVAL=2;COUNT=4;BYDAY=TU,SU;WKST=SU",
+			"UTC",
+			"1997-08-05T09:00:00Z",
+			10,
+		)?;
+		assert_eq!(
+			occurrences,
+			[
+				"1997-08-05T09:00:00Z",
+				"1997-08-17T09:00:00Z",
+				"1997-08-19T09:00:00Z",
+				"1997-08-31T09:00:00Z"
+			]
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_occurrences_dst_ok() -> Result<()> {
+		// -- New York, DST starts on 2030-03-10, and ends on 2030-11-03.
+		let occurrences = fx_occurrences(
+			"FREQ=WEEKLY;BYDAY=SA",
+			"America/New_York",
+			"2030-03-02T09:00:00-05:00",
+			3,
+		)?;
+		assert_eq!(
+			occurrences,
+			[
+				"2030-03-02T14:00:00Z",
+				"2030-03-09T14:00:00Z",
+				"2030-03-16T13:00:00Z"
+			]
+		);
+
+		let occurrences = fx_occurrences(
+			"FREQ=DAILY",
+			"America/New_York",
+			"2030-11-01T09:00:00-04:00",
+			4,
+		)?;
+		assert_eq!(
+			occurrences,
+			[
+				"2030-11-01T13:00:00Z",
+				"2030-11-02T13:00:00Z",
+				"2030-11-03T14:00:00Z",
+				"2030-11-04T14:00:00Z"
+			]
+		);
+
+		// -- Paris, DST starts on 2030-03-31, monthly on the last Sunday.
+		let occurrences = fx_occurrences(
+			"FREQ=MONTHLY;BYDAY=-1SU",
+			"Europe/Paris",
+			"2030-02-24T10:00:00+01:00",
+			3,
+		)?;
+		assert_eq!(
+			occurrences,
+			[
+				"2030-02-24T09:00:00Z",
+				"2030-03-31T08:00:00Z",
+				"2030-04-28T08:00:00Z"
+			]
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_occurrences_dst_gap_overlap_ok() -> Result<()> {
+		// -- 02:30 does not exist on 2030-03-10 in New York, moved forward to 03:30 EDT.
+		let occurrences = fx_occurrences(
+			"FREQ=DAILY",
+			"America/New_York",
+			"2030-03-09T02:30:00-05:00",
+			3,
+		)?;
+		assert_eq!(
+			occurrences,
+			[
+				"2030-03-09T07:30:00Z",
+				"2030-03-10T07:30:00Z",
+				"2030-03-11T06:30:00Z"
+			]
+		);
+
+		// -- 01:30 happens twice on 2030-11-03 in New York, the first one (EDT).
+		let occurrences = fx_occurrences(
+			"FREQ=DAILY",
+			"America/New_York",
+			"2030-11-02T01:30:00-04:00",
+			3,
+		)?;
+		assert_eq!(
+			occurrences,
+			[
+				"2030-11-02T05:30:00Z",
+				"2030-11-03T05:30:00Z",
+				"2030-11-04T06:30:00Z"
+			]
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_next_after_ok() -> Result<()> {
+		let fx_cases: &[(&str, &str, &str)] = &[
+			(
+				"FREQ=DAILY;INTERVAL=3",
+				"America/New_York",
+				"1990-01-01T09:00:00-05:00",
+			),
+			(
+				"FREQ=WEEKLY;BYDAY=MO,FR;WKST=SU",
+				"Europe/Paris",
+				"2001-01-03T10:00:00+01:00",
+			),
+			(
+				"FREQ=MONTHLY;INTERVAL=2;BYDAY=-1FR",
+				"UTC",
+				"2010-01-29T09:00:00Z",
+			),
+			(
+				"FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=29",
+				"UTC",
+				"2028-02-29T09:00:00Z",
+			),
+			("FREQ=WEEKLY;UNTIL=20300115", "UTC", "2029-01-01T09:00:00Z"),
+			("FREQ=DAILY;COUNT=40", "UTC", "2030-01-01T09:00:00Z"),
+		];
+		let fx_afters = [
+			"2030-01-01T09:00:00Z",
+			"2030-02-01T08:59:59Z",
+			"2031-12-31T23:00:00Z",
+		];
+
+		for (rrule, tz, dtstart) in fx_cases {
+			let rule: RRule = rrule.parse().map_err(anyhow::Error::msg)?;
+			let tz = tz_by_name(tz).ok_or_else(|| anyhow::anyhow!("unknown tz {tz}"))?;
+			let dtstart = OffsetDateTime::parse(dtstart, &Rfc3339)?;
+			for after in fx_afters {
+				let after = OffsetDateTime::parse(after, &Rfc3339)?;
+				// The same as walking all the occurrences from `dtstart`.
+				let expected = rule.occurrences(dtstart, tz).find(|at| *at > after);
+				assert_eq!(
+					rule.next_after(dtstart, tz, after),
+					expected,
+					"rrule: {rrule}, after: {after}"
+				);
+			}
+		}
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_next_after_steps_max() -> Result<()> {
+		let rule: RRule = "FREQ=DAILY;COUNT=200000"
+			.parse()
+			.map_err(anyhow::Error::msg)?;
+		let dtstart = OffsetDateTime::parse("2000-01-01T09:00:00Z", &Rfc3339)?;
+		let tz = tz_by_name("UTC").ok_or_else(|| anyhow::anyhow!("unknown tz"))?;
+
+		// -- Walked from `dtstart` (a `COUNT` rule), up to the max.
+		let after = OffsetDateTime::parse("2100-01-01T09:00:00Z", &Rfc3339)?;
+		assert!(rule.next_after(dtstart, tz, after).is_some());
+		let after = OffsetDateTime::parse("2400-01-01T09:00:00Z", &Rfc3339)?;
+		assert_eq!(rule.next_after(dtstart, tz, after), None);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_occurrences_end_ok() -> Result<()> {
+		// -- Never matches, ends instead of looping.
+		let occurrences = fx_occurrences(
+			"FREQ=DAILY;BYMONTH=2;BYMONTHDAY=30",
+			"UTC",
+			"2030-01-01T09:00:00Z",
+			3,
+		)?;
+		assert_eq!(occurrences, ["2030-01-01T09:00:00Z"]);
+
+		Ok(())
+	}
+}
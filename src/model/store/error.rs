@@ -5,13 +5,46 @@ pub type Result<T> = core::result::Result<T, Error>;
 #[derive(Debug, Serialize)]
 pub enum Error {
 	FailedToCreatePool(String),
+	/// Note: Only Postgres and SQLite are supported (see `store::check_db_url`).
+	DbBackendNotSupported {
+		scheme: String,
+	},
+
+	// -- Migration
+	MigrationSqlx(String),
+	/// The bypass role of the root ctx does not exist, or is not `BYPASSRLS`
+	/// (it is created with the database, see `migration::check_bypass_role`).
+	BypassRoleMissing {
+		role: &'static str,
+	},
+	/// The login role of `SERVICE_DB_URL` is not a member of the bypass role.
+	BypassRoleNotGranted {
+		role: &'static str,
+		user: String,
+	},
+	MigrationFailed {
+		version: i64,
+		cause: String,
+	},
+	/// Applied in the db, but not embedded (e.g., the service is older than the db).
+	MigrationUnknown {
+		version: i64,
+	},
+	/// Applied in the db with another checksum (the sql was changed after release).
+	MigrationModified {
+		version: i64,
+		name: String,
+	},
+}
+
+impl Error {
+	pub(super) fn migration_sqlx(ex: sqlx::Error) -> Self {
+		Self::MigrationSqlx(ex.to_string())
+	}
 }
 
 impl core::fmt::Display for Error {
-	fn fmt(
-		&self,
-		fmt: &mut core::fmt::Formatter,
-	) -> core::result::Result<(), core::fmt::Error> {
+	fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
 		write!(fmt, "{self:?}")
 	}
 }
@@ -0,0 +1,307 @@
+//! Versioned schema migrations, embedded in the binary (`src/sql/migrations/`,
+//! and `src/sql/migrations_sqlite/` for the same schema on SQLite).
+//!
+//! - Applied in order, each in its own transaction, and recorded
+//!   in the `schema_migrations` table with the sha256 of their sql.
+//! - Concurrent runs (e.g., two instances starting) are serialized
+//!   with a postgres advisory lock. A SQLite db has a single instance.
+//! - A migration is never changed once released: the service refuses to start
+//!   when the db has a migration that is unknown or with another checksum.
+
+use super::{check_db_url, sqlite_connect_options, Backend, Error, Result, BYPASS_ROLE};
+use crate::utils::sql_script;
+use sha2::{Digest, Sha256};
+use sqlx::{Connection, Executor, PgConnection, SqliteConnection};
+use tracing::info;
+
+struct Migration {
+	version: i64,
+	name: &'static str,
+	sql: &'static str,
+}
+
+impl Migration {
+	fn checksum(&self) -> String {
+		hex::encode(Sha256::digest(self.sql.as_bytes()))
+	}
+}
+
+/// Note: Append only, with increasing versions.
+/// Note: A change of the schema is also made to `SQLITE_MIGRATIONS`.
+const MIGRATIONS: &[Migration] = &[
+	Migration {
+		version: 1,
+		name: "create_scheme",
+		sql: include_str!("../../sql/migrations/0001_create_scheme.sql"),
+	},
+	Migration {
+		version: 2,
+		name: "entity_changed_notify",
+		sql: include_str!("../../sql/migrations/0002_entity_changed_notify.sql"),
+	},
+];
+
+/// The migrations of the SQLite schema, the same as `MIGRATIONS` without what SQLite does
+/// not have (e.g., the row level security, the notifications of the entity cache).
+/// Note: Append only, with increasing versions (their own, not the ones of `MIGRATIONS`).
+const SQLITE_MIGRATIONS: &[Migration] = &[Migration {
+	version: 1,
+	name: "create_scheme",
+	sql: include_str!("../../sql/migrations_sqlite/0001_create_scheme.sql"),
+}];
+
+/// Key of the advisory lock of the migrations (any constant unique to the app, "migr").
+const LOCK_KEY: i64 = 0x6d69_6772;
+
+/// Apply the pending `$migrations` with the connection `$conn` (of any backend),
+/// and return their versions.
+/// Note: A macro, expanded for the connection type of each backend.
+macro_rules! apply_pending {
+	($conn:expr, $migrations:expr) => {{
+		let conn = $conn;
+		let migrations: &[Migration] = $migrations;
+
+		// Note: `CURRENT_TIMESTAMP`, as SQLite has no `now()`.
+		conn.execute(
+			"CREATE TABLE IF NOT EXISTS schema_migrations ( \
+				version BIGINT PRIMARY KEY, \
+				name VARCHAR(128) NOT NULL, \
+				checksum VARCHAR(64) NOT NULL, \
+				applied_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP \
+			)",
+		)
+		.await
+		.map_err(Error::migration_sqlx)?;
+
+		// -- Check the applied migrations.
+		let applied: Vec<(i64, String)> =
+			sqlx::query_as("SELECT version, checksum FROM schema_migrations ORDER BY version")
+				.fetch_all(&mut *conn)
+				.await
+				.map_err(Error::migration_sqlx)?;
+		for (version, checksum) in applied.iter() {
+			let migration = migrations
+				.iter()
+				.find(|m| m.version == *version)
+				.ok_or(Error::MigrationUnknown { version: *version })?;
+			if migration.checksum() != *checksum {
+				return Err(Error::MigrationModified {
+					version: *version,
+					name: migration.name.to_string(),
+				});
+			}
+		}
+
+		// -- Apply the pending ones.
+		let mut versions = Vec::new();
+		for migration in migrations
+			.iter()
+			.filter(|m| !applied.iter().any(|(version, _)| *version == m.version))
+		{
+			let Migration { version, name, sql } = migration;
+			let failed = |ex: sqlx::Error| Error::MigrationFailed {
+				version: *version,
+				cause: ex.to_string(),
+			};
+
+			let mut txn = conn.begin().await.map_err(failed)?;
+			sql_script::exec_script(&mut *txn, &format!("{version:04}_{name}.sql"), sql)
+				.await
+				.map_err(|ex| Error::MigrationFailed {
+					version: *version,
+					cause: ex.to_string(),
+				})?;
+			sqlx::query(
+				"INSERT INTO schema_migrations (version, name, checksum) VALUES ($1, $2, $3)",
+			)
+			.bind(version)
+			.bind(name)
+			.bind(migration.checksum())
+			.execute(&mut *txn)
+			.await
+			.map_err(failed)?;
+			txn.commit().await.map_err(failed)?;
+
+			info!(
+				"{:<12} - migration {version:04}_{name} applied",
+				"MIGRATION"
+			);
+			versions.push(*version);
+		}
+
+		Ok(versions)
+	}};
+}
+
+/// Apply the pending migrations of the db of `db_url`, and return their versions.
+/// Note: With a connection of its own, with the login role of `db_url`
+///       (the owner of the tables).
+pub async fn migrate(db_url: &str) -> Result<Vec<i64>> {
+	match check_db_url(db_url)? {
+		Backend::Postgres => migrate_postgres(db_url).await,
+		Backend::Sqlite => {
+			let options = sqlite_connect_options(db_url).map_err(Error::migration_sqlx)?;
+			let mut conn = SqliteConnection::connect_with(&options)
+				.await
+				.map_err(Error::migration_sqlx)?;
+
+			apply_sqlite_pending(&mut conn).await
+		}
+	}
+}
+
+async fn apply_sqlite_pending(conn: &mut SqliteConnection) -> Result<Vec<i64>> {
+	apply_pending!(conn, SQLITE_MIGRATIONS)
+}
+
+async fn apply_postgres_pending(conn: &mut PgConnection) -> Result<Vec<i64>> {
+	apply_pending!(conn, MIGRATIONS)
+}
+
+async fn migrate_postgres(db_url: &str) -> Result<Vec<i64>> {
+	let mut conn = PgConnection::connect(db_url)
+		.await
+		.map_err(Error::migration_sqlx)?;
+
+	sqlx::query("SELECT pg_advisory_lock($1)")
+		.bind(LOCK_KEY)
+		.execute(&mut conn)
+		.await
+		.map_err(Error::migration_sqlx)?;
+
+	let result = match check_bypass_role(&mut conn).await {
+		Ok(()) => apply_postgres_pending(&mut conn).await,
+		Err(ex) => Err(ex),
+	};
+
+	// Note: Also released when the connection is closed.
+	let _ = sqlx::query("SELECT pg_advisory_unlock($1)")
+		.bind(LOCK_KEY)
+		.execute(&mut conn)
+		.await;
+
+	result
+}
+
+/// Fails unless the bypass role exists, with `BYPASSRLS`, and is granted to the login role.
+/// Note: Not created by a migration, as `BYPASSRLS` needs a superuser
+///       (and the first migration already grants it privileges).
+async fn check_bypass_role(conn: &mut PgConnection) -> Result<()> {
+	let role: Option<(bool, bool, String)> = sqlx::query_as(
+		"SELECT rolbypassrls, pg_has_role(current_user, oid, 'MEMBER'), current_user::text \
+		FROM pg_roles WHERE rolname = $1",
+	)
+	.bind(BYPASS_ROLE)
+	.fetch_optional(&mut *conn)
+	.await
+	.map_err(Error::migration_sqlx)?;
+
+	match role {
+		Some((true, true, _)) => Ok(()),
+		Some((true, false, user)) => Err(Error::BypassRoleNotGranted {
+			role: BYPASS_ROLE,
+			user,
+		}),
+		_ => Err(Error::BypassRoleMissing { role: BYPASS_ROLE }),
+	}
+}
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::_dev_utils;
+	use crate::config;
+	use anyhow::Result;
+	use serial_test::serial;
+
+	#[serial]
+	#[tokio::test]
+	async fn test_migrate_ok() -> Result<()> {
+		// -- Setup & Fixtures
+		_dev_utils::init_test().await;
+
+		// -- Exec
+		let versions = migrate(&config::config().DB_URL).await?;
+
+		// -- Check
+		// All applied already (by the dev db init).
+		assert!(versions.is_empty());
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_migrate_sqlite_ok() -> Result<()> {
+		// -- Setup & Fixtures
+		let fx_path = std::env::temp_dir().join("test_migrate_sqlite_ok.db");
+		let _ = std::fs::remove_file(&fx_path);
+		let fx_url = format!("sqlite://{}", fx_path.display());
+
+		// -- Exec & Check
+		let versions = migrate(&fx_url).await?;
+		assert_eq!(versions, [1]);
+		let versions = migrate(&fx_url).await?;
+		assert!(versions.is_empty());
+
+		// -- Clean
+		std::fs::remove_file(&fx_path)?;
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_migrate_err_unknown_or_modified() -> Result<()> {
+		// -- Setup & Fixtures
+		_dev_utils::init_test().await;
+		let mut conn = PgConnection::connect(&config::config().DB_URL).await?;
+
+		// -- Exec & Check
+		sqlx::query("UPDATE schema_migrations SET checksum = 'modified' WHERE version = 1")
+			.execute(&mut conn)
+			.await?;
+		let result = migrate(&config::config().DB_URL).await;
+		sqlx::query("UPDATE schema_migrations SET checksum = $1 WHERE version = 1")
+			.bind(MIGRATIONS[0].checksum())
+			.execute(&mut conn)
+			.await?;
+		assert!(matches!(
+			result,
+			Err(Error::MigrationModified { version: 1, .. })
+		));
+
+		sqlx::query(
+			"INSERT INTO schema_migrations (version, name, checksum) VALUES (9999, 'x', 'x')",
+		)
+		.execute(&mut conn)
+		.await?;
+		let result = migrate(&config::config().DB_URL).await;
+		sqlx::query("DELETE FROM schema_migrations WHERE version = 9999")
+			.execute(&mut conn)
+			.await?;
+		assert!(matches!(
+			result,
+			Err(Error::MigrationUnknown { version: 9999 })
+		));
+
+		// -- Clean
+		assert!(migrate(&config::config().DB_URL).await?.is_empty());
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_check_bypass_role_ok() -> Result<()> {
+		// -- Setup & Fixtures
+		_dev_utils::init_test().await;
+		let mut conn = PgConnection::connect(&config::config().DB_URL).await?;
+
+		// -- Exec & Check
+		check_bypass_role(&mut conn).await?;
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
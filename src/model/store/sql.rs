@@ -0,0 +1,425 @@
+//! The sql of the model layer, for both backends (see `store::Db`).
+//!
+//! - `Backend` has the sql expressions which differ between Postgres and SQLite
+//!   (e.g., `Backend::in_list` for `= ANY($1)`).
+//! - `SqlValue` is a bound value of any column type, encoded for Postgres and SQLite.
+//!   For SQLite, the times are stored as fixed width UTC text (so that they compare
+//!   as text), the uuids as blobs, and the lists as json arrays (see `Backend::in_list`).
+//! - `Sql` is the sql string with its `$n` bound values, built at runtime for a backend,
+//!   and executed with `with_db!` (e.g., `sql.query_as().fetch_all(db)`).
+//! - `RowJson` reads a whole row as json (see `Backend::row_json`), for the audit diffs.
+
+use std::borrow::Cow;
+use std::num::NonZeroU8;
+
+use serde_json::{Map, Value};
+use sqlx::database::HasArguments;
+use sqlx::encode::IsNull;
+use sqlx::postgres::{PgArgumentBuffer, PgRow, PgTypeInfo};
+use sqlx::query::{Query, QueryAs, QueryScalar};
+use sqlx::sqlite::{SqliteArgumentValue, SqliteRow, SqliteTypeInfo};
+use sqlx::{
+	Column, ColumnIndex, Database, Decode, Encode, FromRow, Postgres, Row, Sqlite, Type, TypeInfo,
+	ValueRef,
+};
+use time::format_description::well_known::iso8601::{Config, EncodedConfig, TimePrecision};
+use time::format_description::well_known::Iso8601;
+use time::{OffsetDateTime, UtcOffset};
+use uuid::Uuid;
+
+// region:    --- Backend
+
+/// The backend of the db, from the scheme of `SERVICE_DB_URL` (see `store::check_db_url`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+	Postgres,
+	Sqlite,
+}
+
+impl Backend {
+	/// `<expr>` is one of the values of the bound list `param`
+	/// (a `SqlValue::Int64s` or `SqlValue::Strings`).
+	pub fn in_list(self, expr: &str, param: &str) -> String {
+		match self {
+			Backend::Postgres => format!("{expr} = ANY({param})"),
+			Backend::Sqlite => format!("{expr} IN (SELECT value FROM json_each({param}))"),
+		}
+	}
+
+	/// The select expression of the whole row of `table`, read with `RowJson`.
+	/// Note: Postgres builds the json, SQLite returns the columns (converted by `RowJson`).
+	pub fn row_json(self, table: &str) -> String {
+		match self {
+			Backend::Postgres => format!("to_jsonb(\"{table}\".*)"),
+			Backend::Sqlite => "*".to_string(),
+		}
+	}
+
+	/// The lock of the selected rows until the end of the transaction.
+	/// Note: Empty for SQLite, its transactions hold the db write lock (see `Db::begin`).
+	pub fn for_update(self) -> &'static str {
+		match self {
+			Backend::Postgres => " FOR UPDATE",
+			Backend::Sqlite => "",
+		}
+	}
+
+	/// `<expr>` as text (e.g., an enum or a uuid column, compared as text).
+	pub fn text(self, expr: &str) -> String {
+		match self {
+			Backend::Postgres => format!("({expr}::text)"),
+			// Note: The uuids are blobs, as text in their hyphenated form.
+			Backend::Sqlite => format!(
+				"(CASE WHEN typeof({expr}) = 'blob' AND length({expr}) = 16 \
+				 THEN lower(substr(hex({expr}), 1, 8) || '-' || substr(hex({expr}), 9, 4) || '-' \
+				 || substr(hex({expr}), 13, 4) || '-' || substr(hex({expr}), 17, 4) || '-' \
+				 || substr(hex({expr}), 21)) ELSE CAST({expr} AS TEXT) END)"
+			),
+		}
+	}
+}
+
+// endregion: --- Backend
+
+// region:    --- SqlValue
+
+/// A bound value, with its type when NULL (e.g., `Int64(None)`).
+#[derive(Debug, Clone)]
+pub enum SqlValue {
+	Bool(Option<bool>),
+	Int16(Option<i16>),
+	Int32(Option<i32>),
+	Int64(Option<i64>),
+	String(Option<String>),
+	DateTime(Option<OffsetDateTime>),
+	Uuid(Option<Uuid>),
+	Json(Option<Value>),
+	/// The label of a postgres enum (e.g., `task_status`), text for SQLite (see `sql_enum!`).
+	Enum(Option<String>, PgTypeInfo),
+	/// A `bigint[]` for Postgres, a json array for SQLite.
+	Int64s(Vec<i64>),
+	/// A `text[]` for Postgres, a json array for SQLite.
+	Strings(Vec<String>),
+}
+
+macro_rules! impl_from_for_sql_value {
+	($($ty:ty => $variant:ident),* $(,)?) => {
+		$(
+			impl From<$ty> for SqlValue {
+				fn from(val: $ty) -> Self {
+					SqlValue::$variant(Some(val.into()))
+				}
+			}
+
+			impl From<Option<$ty>> for SqlValue {
+				fn from(val: Option<$ty>) -> Self {
+					SqlValue::$variant(val.map(Into::into))
+				}
+			}
+		)*
+	};
+}
+
+impl_from_for_sql_value!(
+	bool => Bool,
+	i16 => Int16,
+	i32 => Int32,
+	i64 => Int64,
+	String => String,
+	&str => String,
+	OffsetDateTime => DateTime,
+	Uuid => Uuid,
+	Value => Json,
+);
+
+impl From<Vec<i64>> for SqlValue {
+	fn from(vals: Vec<i64>) -> Self {
+		SqlValue::Int64s(vals)
+	}
+}
+
+impl From<&[i64]> for SqlValue {
+	fn from(vals: &[i64]) -> Self {
+		SqlValue::Int64s(vals.to_vec())
+	}
+}
+
+impl From<Vec<String>> for SqlValue {
+	fn from(vals: Vec<String>) -> Self {
+		SqlValue::Strings(vals)
+	}
+}
+
+/// The `SqlValue` conversions of an enum stored as a postgres enum (text for SQLite),
+/// with its `sqlx::Type` and its `strum_macros::AsRefStr` label.
+/// (e.g., `sql_enum!(TaskStatus);`)
+macro_rules! sql_enum {
+	($ty:ty) => {
+		impl From<$ty> for $crate::model::store::SqlValue {
+			fn from(val: $ty) -> Self {
+				Some(val).into()
+			}
+		}
+
+		impl From<Option<$ty>> for $crate::model::store::SqlValue {
+			fn from(val: Option<$ty>) -> Self {
+				$crate::model::store::SqlValue::Enum(
+					val.map(|val| val.as_ref().to_string()),
+					<$ty as sqlx::Type<sqlx::Postgres>>::type_info(),
+				)
+			}
+		}
+	};
+}
+pub(in crate::model) use sql_enum;
+
+/// The SQLite text of the times, UTC with microseconds (e.g., `2024-01-31T08:00:00.000000Z`),
+/// so that they compare as text.
+const SQLITE_DATETIME_FORMAT: EncodedConfig = Config::DEFAULT
+	.set_time_precision(TimePrecision::Second {
+		decimal_digits: NonZeroU8::new(6),
+	})
+	.encode();
+
+pub(in crate::model) fn sqlite_datetime(val: OffsetDateTime) -> String {
+	// Note: The fixed format can not fail for the years 0 to 9999.
+	val.to_offset(UtcOffset::UTC)
+		.format(&Iso8601::<SQLITE_DATETIME_FORMAT>)
+		.unwrap_or_default()
+}
+
+impl Type<Postgres> for SqlValue {
+	// Note: Not used, the actual type is the one of the variant (see `produces`).
+	fn type_info() -> PgTypeInfo {
+		<str as Type<Postgres>>::type_info()
+	}
+}
+
+impl<'q> Encode<'q, Postgres> for SqlValue {
+	fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> IsNull {
+		match self {
+			SqlValue::Bool(val) => Encode::<Postgres>::encode_by_ref(val, buf),
+			SqlValue::Int16(val) => Encode::<Postgres>::encode_by_ref(val, buf),
+			SqlValue::Int32(val) => Encode::<Postgres>::encode_by_ref(val, buf),
+			SqlValue::Int64(val) => Encode::<Postgres>::encode_by_ref(val, buf),
+			SqlValue::String(val) => Encode::<Postgres>::encode_by_ref(val, buf),
+			SqlValue::DateTime(val) => Encode::<Postgres>::encode_by_ref(val, buf),
+			SqlValue::Uuid(val) => Encode::<Postgres>::encode_by_ref(val, buf),
+			SqlValue::Json(val) => Encode::<Postgres>::encode_by_ref(val, buf),
+			// Note: The binary format of an enum is its label.
+			SqlValue::Enum(val, _) => Encode::<Postgres>::encode_by_ref(val, buf),
+			SqlValue::Int64s(vals) => Encode::<Postgres>::encode_by_ref(vals, buf),
+			SqlValue::Strings(vals) => Encode::<Postgres>::encode_by_ref(vals, buf),
+		}
+	}
+
+	fn produces(&self) -> Option<PgTypeInfo> {
+		let type_info = match self {
+			SqlValue::Bool(_) => <bool as Type<Postgres>>::type_info(),
+			SqlValue::Int16(_) => <i16 as Type<Postgres>>::type_info(),
+			SqlValue::Int32(_) => <i32 as Type<Postgres>>::type_info(),
+			SqlValue::Int64(_) => <i64 as Type<Postgres>>::type_info(),
+			SqlValue::String(_) => <String as Type<Postgres>>::type_info(),
+			SqlValue::DateTime(_) => <OffsetDateTime as Type<Postgres>>::type_info(),
+			SqlValue::Uuid(_) => <Uuid as Type<Postgres>>::type_info(),
+			SqlValue::Json(_) => <Value as Type<Postgres>>::type_info(),
+			SqlValue::Enum(_, type_info) => type_info.clone(),
+			SqlValue::Int64s(_) => <Vec<i64> as Type<Postgres>>::type_info(),
+			SqlValue::Strings(_) => <Vec<String> as Type<Postgres>>::type_info(),
+		};
+
+		Some(type_info)
+	}
+}
+
+impl Type<Sqlite> for SqlValue {
+	// Note: Not used, SQLite binds any value to any parameter.
+	fn type_info() -> SqliteTypeInfo {
+		<str as Type<Sqlite>>::type_info()
+	}
+}
+
+impl<'q> Encode<'q, Sqlite> for SqlValue {
+	fn encode_by_ref(&self, args: &mut Vec<SqliteArgumentValue<'q>>) -> IsNull {
+		let arg = match self {
+			SqlValue::Bool(val) => val.map(|val| SqliteArgumentValue::Int(val.into())),
+			SqlValue::Int16(val) => val.map(|val| SqliteArgumentValue::Int(val.into())),
+			SqlValue::Int32(val) => val.map(SqliteArgumentValue::Int),
+			SqlValue::Int64(val) => val.map(SqliteArgumentValue::Int64),
+			SqlValue::String(val) | SqlValue::Enum(val, _) => val
+				.as_ref()
+				.map(|val| SqliteArgumentValue::Text(Cow::Owned(val.clone()))),
+			SqlValue::DateTime(val) => {
+				val.map(|val| SqliteArgumentValue::Text(Cow::Owned(sqlite_datetime(val))))
+			}
+			SqlValue::Uuid(val) => {
+				val.map(|val| SqliteArgumentValue::Blob(Cow::Owned(val.as_bytes().to_vec())))
+			}
+			SqlValue::Json(val) => val
+				.as_ref()
+				.map(|val| SqliteArgumentValue::Text(Cow::Owned(val.to_string()))),
+			SqlValue::Int64s(vals) => Some(SqliteArgumentValue::Text(Cow::Owned(
+				Value::from(vals.clone()).to_string(),
+			))),
+			SqlValue::Strings(vals) => Some(SqliteArgumentValue::Text(Cow::Owned(
+				Value::from(vals.clone()).to_string(),
+			))),
+		};
+
+		match arg {
+			Some(arg) => {
+				args.push(arg);
+				IsNull::No
+			}
+			None => IsNull::Yes,
+		}
+	}
+}
+
+// endregion: --- SqlValue
+
+// region:    --- Sql
+
+/// A sql string of `backend` with its bound values, as `$1`, `$2`, ... in the order
+/// of `push_bind`.
+#[derive(Debug, Clone)]
+pub struct Sql {
+	backend: Backend,
+	sql: String,
+	binds: Vec<SqlValue>,
+}
+
+type DbArguments<'q, DB> = <DB as HasArguments<'q>>::Arguments;
+
+impl Sql {
+	pub fn new(backend: Backend, sql: impl Into<String>) -> Self {
+		Sql {
+			backend,
+			sql: sql.into(),
+			binds: Vec::new(),
+		}
+	}
+
+	pub fn backend(&self) -> Backend {
+		self.backend
+	}
+
+	pub fn push(&mut self, sql: impl AsRef<str>) -> &mut Self {
+		self.sql.push_str(sql.as_ref());
+		self
+	}
+
+	/// Push the `$n` parameter of `val`.
+	pub fn push_bind(&mut self, val: impl Into<SqlValue>) -> &mut Self {
+		let param = self.bind(val);
+		self.sql.push_str(&param);
+		self
+	}
+
+	/// Bind `val`, and return its `$n` parameter, to be pushed by the caller
+	/// (e.g., in an expression of `Backend`).
+	pub fn bind(&mut self, val: impl Into<SqlValue>) -> String {
+		self.binds.push(val.into());
+		format!("${}", self.binds.len())
+	}
+
+	/// Push `<expr>` is one of `vals` (see `Backend::in_list`).
+	pub fn push_in_list(&mut self, expr: &str, vals: impl Into<SqlValue>) -> &mut Self {
+		let param = self.bind(vals);
+		let cond = self.backend.in_list(expr, &param);
+		self.push(cond)
+	}
+
+	pub fn query<'q, DB>(&'q self) -> Query<'q, DB, DbArguments<'q, DB>>
+	where
+		DB: Database,
+		SqlValue: Encode<'q, DB> + Type<DB>,
+	{
+		self.binds
+			.iter()
+			.fold(sqlx::query(&self.sql), |query, val| query.bind(val))
+	}
+
+	pub fn query_as<'q, DB, O>(&'q self) -> QueryAs<'q, DB, O, DbArguments<'q, DB>>
+	where
+		DB: Database,
+		SqlValue: Encode<'q, DB> + Type<DB>,
+		O: for<'r> FromRow<'r, DB::Row>,
+	{
+		self.binds
+			.iter()
+			.fold(sqlx::query_as(&self.sql), |query, val| query.bind(val))
+	}
+
+	pub fn query_scalar<'q, DB, O>(&'q self) -> QueryScalar<'q, DB, O, DbArguments<'q, DB>>
+	where
+		DB: Database,
+		SqlValue: Encode<'q, DB> + Type<DB>,
+		(O,): for<'r> FromRow<'r, DB::Row>,
+	{
+		self.binds
+			.iter()
+			.fold(sqlx::query_scalar(&self.sql), |query, val| query.bind(val))
+	}
+}
+
+// endregion: --- Sql
+
+// region:    --- RowJson
+
+/// A row selected with `Backend::row_json` (e.g., `RETURNING "id", <row_json>`).
+pub trait RowJson {
+	/// The json object of the row, from its column `start` (e.g., `1` after the `"id"`).
+	fn row_json(&self, start: usize) -> sqlx::Result<Value>;
+}
+
+impl RowJson for PgRow {
+	fn row_json(&self, start: usize) -> sqlx::Result<Value> {
+		self.try_get(start)
+	}
+}
+
+/// Note: From the column types of the table, so the `BOOLEAN` columns are json booleans
+///       and the uuid blobs are in their hyphenated form (as with Postgres).
+impl RowJson for SqliteRow {
+	fn row_json(&self, start: usize) -> sqlx::Result<Value> {
+		let mut obj = Map::new();
+		for column in self.columns().iter().skip(start) {
+			let i = column.ordinal();
+			let raw = self.try_get_raw(i)?;
+			let val = if raw.is_null() {
+				Value::Null
+			} else if column.type_info().name() == "BOOLEAN" {
+				Value::Bool(self.try_get(i)?)
+			} else {
+				match raw.type_info().name() {
+					"INTEGER" => Value::from(self.try_get::<i64, _>(i)?),
+					"REAL" => Value::from(self.try_get::<f64, _>(i)?),
+					"BLOB" => {
+						let bytes: Vec<u8> = self.try_get(i)?;
+						match Uuid::from_slice(&bytes) {
+							Ok(uuid) => Value::from(uuid.to_string()),
+							Err(_) => Value::from(hex::encode(bytes)),
+						}
+					}
+					_ => Value::from(self.try_get::<String, _>(i)?),
+				}
+			};
+			obj.insert(column.name().to_string(), val);
+		}
+
+		Ok(Value::Object(obj))
+	}
+}
+
+/// The `"id"` and the row json of a `RETURNING "id", <row_json>` row.
+pub fn id_row_json<R>(row: &R) -> sqlx::Result<(i64, Value)>
+where
+	R: Row + RowJson,
+	usize: ColumnIndex<R>,
+	i64: for<'r> Decode<'r, R::Database> + Type<R::Database>,
+{
+	Ok((row.try_get(0)?, row.row_json(1)?))
+}
+
+// endregion: --- RowJson
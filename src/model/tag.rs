@@ -0,0 +1,385 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use sqlx::prelude::FromRow;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::ctx::Ctx;
+use crate::model::error::{Error, Result};
+
+use super::common::DbBmc;
+use super::fields::{Fields, HasFields};
+use super::store::{with_db, Backend, Sql};
+use super::{
+	common, FilterNode, FilterNodes, ListOptions, ModelManager, OpValsInt64, OpValsString,
+};
+
+// Model: Tag struct
+#[serde_as]
+#[derive(Clone, Debug, Serialize, FromRow, Fields)]
+pub struct Tag {
+	pub id: i64,
+	pub owner_id: i64,
+	pub name: String,
+
+	// -- Timestamps
+	pub cid: i64,
+	#[serde_as(as = "Rfc3339")]
+	pub ctime: OffsetDateTime,
+	pub mid: i64,
+	#[serde_as(as = "Rfc3339")]
+	pub mtime: OffsetDateTime,
+}
+
+// Tag entity for creating method
+// Note: The owner is always the ctx user.
+#[derive(Deserialize, Fields)]
+pub struct TagForCreate {
+	pub name: String,
+}
+
+// Tag filter for list method
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TagFilter {
+	pub id: Option<OpValsInt64>,
+	pub name: Option<OpValsString>,
+}
+
+impl FilterNodes for TagFilter {
+	fn filter_nodes(self) -> Vec<FilterNode> {
+		let mut nodes = Vec::new();
+		if let Some(id) = self.id {
+			nodes.extend(id.into_filter_nodes("id"));
+		}
+		if let Some(name) = self.name {
+			nodes.extend(name.into_filter_nodes("name"));
+		}
+		nodes
+	}
+}
+
+/// The client filter, restricted to the tags of the ctx user.
+struct OwnedTagFilter {
+	owner_id: i64,
+	filter: Option<TagFilter>,
+}
+
+impl FilterNodes for OwnedTagFilter {
+	fn filter_nodes(self) -> Vec<FilterNode> {
+		let owner_id = OpValsInt64 {
+			eq: Some(self.owner_id),
+			..Default::default()
+		};
+		let mut nodes = owner_id.into_filter_nodes("owner_id");
+		if let Some(filter) = self.filter {
+			nodes.extend(filter.filter_nodes());
+		}
+		nodes
+	}
+}
+
+/// A tag with the id of one of its tasks (see `TagBmc::list_by_task_ids`).
+#[derive(FromRow)]
+struct TaskTag {
+	task_id: i64,
+	#[sqlx(flatten)]
+	tag: Tag,
+}
+
+// Tag Backend Model Controller
+// Note: All the methods only see the tags of the ctx user.
+pub struct TagBmc;
+
+impl TagBmc {
+	pub async fn create(ctx: &Ctx, mm: &ModelManager, tag_c: TagForCreate) -> Result<i64> {
+		let name = tag_c.name.clone();
+
+		let mut fields = tag_c.not_none_fields();
+		fields.push(("owner_id", ctx.user_id()).into());
+
+		common::create_fields::<Self>(ctx, mm, fields)
+			.await
+			.map_err(|err| map_name_conflict(err, name))
+	}
+
+	pub async fn rename(ctx: &Ctx, mm: &ModelManager, id: i64, name: &str) -> Result<()> {
+		Self::get(ctx, mm, id).await?;
+
+		let fields = vec![("name", name.to_string()).into()];
+		common::update_fields::<Self>(ctx, mm, id, fields)
+			.await
+			.map_err(|err| map_name_conflict(err, name.to_string()))
+	}
+
+	pub async fn list(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		filter: Option<TagFilter>,
+		list_options: Option<ListOptions>,
+	) -> Result<Vec<Tag>> {
+		let filter = OwnedTagFilter {
+			owner_id: ctx.user_id(),
+			filter,
+		};
+
+		common::list::<Self, _, _>(ctx, mm, Some(filter), list_options).await
+	}
+
+	pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Tag> {
+		let mut sql = common::select_sql::<Self>(mm, Tag::field_names());
+		sql.push(" AND \"id\" = ").push_bind(id);
+		sql.push(" AND \"owner_id\" = ").push_bind(ctx.user_id());
+
+		with_db!(ctx, mm, |db| sql.query_as().fetch_optional(db).await?).ok_or(
+			Error::EntityNotFound {
+				entity: Self::TABLE,
+				id,
+			},
+		)
+	}
+
+	/// Delete the tag, and remove it from its tasks.
+	pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+		Self::get(ctx, mm, id).await?;
+
+		common::delete::<Self, Tag>(ctx, mm, id).await
+	}
+
+	/// Move the tasks of the `from_id` tag to the `into_id` tag,
+	/// and delete the `from_id` tag.
+	pub async fn merge(ctx: &Ctx, mm: &ModelManager, from_id: i64, into_id: i64) -> Result<()> {
+		if from_id == into_id {
+			return Err(Error::TagMergeIntoSelf { id: from_id });
+		}
+		Self::get(ctx, mm, from_id).await?;
+		Self::get(ctx, mm, into_id).await?;
+
+		let move_sql = "INSERT INTO task_tag (task_id, tag_id) \
+			SELECT task_id, $2 FROM task_tag WHERE tag_id = $1 \
+			ON CONFLICT DO NOTHING";
+		let delete_sql = "DELETE FROM tag WHERE id = $1";
+
+		match mm.backend() {
+			// Note: Single statement, so that the tasks are moved and the tag deleted
+			//       together or not at all. The `task_tag` rows of the `from_id` tag
+			//       are deleted by the foreign key cascade.
+			Backend::Postgres => {
+				let sql = format!("WITH moved AS ({move_sql}) {delete_sql}");
+				with_db!(ctx, mm, |db| sqlx::query(&sql)
+					.bind(from_id)
+					.bind(into_id)
+					.execute(db)
+					.await?
+					.rows_affected());
+			}
+			// Note: No data-modifying CTE in SQLite, the two statements in a transaction.
+			Backend::Sqlite => {
+				let txn_mm = common::own_txn(ctx, mm).await?;
+				let mm = txn_mm.as_ref().unwrap_or(mm);
+
+				with_db!(ctx, mm, |db| sqlx::query(move_sql)
+					.bind(from_id)
+					.bind(into_id)
+					.execute(db)
+					.await?
+					.rows_affected());
+				with_db!(ctx, mm, |db| sqlx::query(delete_sql)
+					.bind(from_id)
+					.execute(db)
+					.await?
+					.rows_affected());
+
+				common::commit_own_txn(txn_mm).await?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Fail with `EntityNotFound` on the first id which is not a tag of the ctx user.
+	pub(in crate::model) async fn ensure_owned(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		ids: &[i64],
+	) -> Result<()> {
+		let mut sql = Sql::new(mm.backend(), "SELECT id FROM tag WHERE ");
+		sql.push_in_list("id", ids)
+			.push(" AND owner_id = ")
+			.push_bind(ctx.user_id());
+		let owned: Vec<(i64,)> = with_db!(ctx, mm, |db| sql.query_as().fetch_all(db).await?);
+
+		match ids
+			.iter()
+			.find(|id| !owned.iter().any(|(owned_id,)| owned_id == *id))
+		{
+			Some(id) => Err(Error::EntityNotFound {
+				entity: Self::TABLE,
+				id: *id,
+			}),
+			None => Ok(()),
+		}
+	}
+
+	/// The tags of each task, ordered by name, in a single query.
+	pub(in crate::model) async fn list_by_task_ids(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		task_ids: &[i64],
+	) -> Result<HashMap<i64, Vec<Tag>>> {
+		let columns = Tag::field_names()
+			.iter()
+			.map(|name| format!("tag.\"{name}\""))
+			.collect::<Vec<_>>()
+			.join(", ");
+		let mut sql = Sql::new(
+			mm.backend(),
+			format!(
+				"SELECT task_tag.task_id, {columns} FROM task_tag \
+				 JOIN tag ON tag.id = task_tag.tag_id WHERE "
+			),
+		);
+		sql.push_in_list("task_tag.task_id", task_ids)
+			.push(" AND tag.owner_id = ")
+			.push_bind(ctx.user_id())
+			.push(" ORDER BY tag.name, tag.id");
+
+		let task_tags: Vec<TaskTag> = with_db!(ctx, mm, |db| sql.query_as().fetch_all(db).await?);
+
+		let mut tags_by_task: HashMap<i64, Vec<Tag>> = HashMap::new();
+		for TaskTag { task_id, tag } in task_tags {
+			tags_by_task.entry(task_id).or_default().push(tag);
+		}
+
+		Ok(tags_by_task)
+	}
+}
+
+// Impl Trait Dbmc for Tag model
+impl DbBmc for TagBmc {
+	const TABLE: &'static str = "tag";
+}
+
+/// Map the `(owner_id, name)` unique violation to `TagNameAlreadyExists`.
+fn map_name_conflict(err: Error, name: String) -> Error {
+	match err {
+		Error::SqlxError(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => {
+			Error::TagNameAlreadyExists { name }
+		}
+		err => err,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(unused)]
+	use crate::_dev_utils;
+	use crate::model::task::TaskBmc;
+
+	use super::*;
+	use anyhow::Result;
+	use serial_test::serial;
+
+	#[serial]
+	#[tokio::test]
+	async fn test_create_err_name_already_exists() -> Result<()> {
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+		let fx_name = "test_create_err_name_already_exists";
+		let id = _dev_utils::seed_tag(&ctx, &mm, fx_name).await?;
+
+		let result = TagBmc::create(
+			&ctx,
+			&mm,
+			TagForCreate {
+				name: fx_name.to_string(),
+			},
+		)
+		.await;
+		assert!(matches!(
+			result,
+			Err(Error::TagNameAlreadyExists { name }) if name == fx_name
+		));
+
+		// -- Same name, other owner is ok.
+		let other_ctx = Ctx::new(1000)?;
+		let other_id = _dev_utils::seed_tag(&other_ctx, &mm, fx_name).await?;
+
+		TagBmc::delete(&ctx, &mm, id).await?;
+		TagBmc::delete(&other_ctx, &mm, other_id).await?;
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_get_err_other_owner() -> Result<()> {
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+		let id = _dev_utils::seed_tag(&ctx, &mm, "test_get_err_other_owner").await?;
+
+		let other_ctx = Ctx::new(1000)?;
+		let result = TagBmc::get(&other_ctx, &mm, id).await;
+		assert!(matches!(
+			result,
+			Err(Error::EntityNotFound { entity: "tag", .. })
+		));
+		let tags = TagBmc::list(&other_ctx, &mm, None, None).await?;
+		assert!(tags.iter().all(|t| t.id != id));
+
+		TagBmc::delete(&ctx, &mm, id).await?;
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_rename_ok() -> Result<()> {
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+		let id = _dev_utils::seed_tag(&ctx, &mm, "test_rename_ok").await?;
+
+		TagBmc::rename(&ctx, &mm, id, "test_rename_ok renamed").await?;
+
+		let tag = TagBmc::get(&ctx, &mm, id).await?;
+		assert_eq!(tag.name, "test_rename_ok renamed");
+
+		TagBmc::delete(&ctx, &mm, id).await?;
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_merge_ok() -> Result<()> {
+		let mm = _dev_utils::init_test().await;
+		let ctx = Ctx::root_ctx();
+		let fx_from_id = _dev_utils::seed_tag(&ctx, &mm, "test_merge_ok from").await?;
+		let fx_into_id = _dev_utils::seed_tag(&ctx, &mm, "test_merge_ok into").await?;
+		let tasks =
+			_dev_utils::seed_test(&ctx, &mm, &["test_merge_ok 01", "test_merge_ok 02"]).await?;
+		// Task 01 has both tags, task 02 only the merged one.
+		TaskBmc::set_tags(&ctx, &mm, tasks[0].id, &[fx_from_id, fx_into_id]).await?;
+		TaskBmc::set_tags(&ctx, &mm, tasks[1].id, &[fx_from_id]).await?;
+
+		TagBmc::merge(&ctx, &mm, fx_from_id, fx_into_id).await?;
+
+		let result = TagBmc::get(&ctx, &mm, fx_from_id).await;
+		assert!(matches!(result, Err(Error::EntityNotFound { .. })));
+		for task in tasks.iter() {
+			let task = TaskBmc::get_with_tags(&ctx, &mm, task.id).await?;
+			let tag_ids: Vec<i64> = task.tags.iter().map(|t| t.id).collect();
+			assert_eq!(tag_ids, [fx_into_id]);
+		}
+
+		// -- Clean
+		for task in tasks {
+			TaskBmc::delete(&ctx, &mm, task.id).await?;
+		}
+		TagBmc::delete(&ctx, &mm, fx_into_id).await?;
+
+		Ok(())
+	}
+}
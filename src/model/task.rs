@@ -1,30 +1,267 @@
+use std::str::FromStr;
+
+use lazy_regex::regex;
 use serde::{Deserialize, Serialize};
-use sqlb::Fields;
+use serde_with::serde_as;
 use sqlx::prelude::FromRow;
+use time::format_description::well_known::Rfc3339;
+use time::{OffsetDateTime, PrimitiveDateTime};
+use time_tz::{OffsetDateTimeExt, Tz};
 
+use crate::config::config;
 use crate::ctx::Ctx;
 use crate::model::error::{Error, Result};
+use crate::utils::now_utc;
 
 use super::common::DbBmc;
-use super::{common, ModelManager};
+use super::fields::{Field, Fields, HasFields};
+use super::rank::{self, RANK_LEN_MAX};
+use super::rrule;
+use super::store::{sql_enum, with_db, xact_lock, Backend, Sql, SqlValue};
+use super::tag::{Tag, TagBmc};
+use super::task_series::{
+	self, TaskSeries, TaskSeriesBmc, TaskSeriesForCreate, TaskSeriesForUpdate,
+};
+use super::user::{User, UserBmc};
+use super::{
+	common, BulkResult, FilterNode, FilterNodes, ListOptions, ModelManager, OpValsIdSet,
+	OpValsInt64, OpValsString, OrderBy, Page, PageOptions, BULK_LIMIT_MAX,
+};
+
+// Task status, stored as the `task_status` postgres enum
+#[derive(
+	Clone,
+	Copy,
+	Debug,
+	Default,
+	PartialEq,
+	Eq,
+	Serialize,
+	Deserialize,
+	sqlx::Type,
+	strum_macros::AsRefStr,
+)]
+#[sqlx(type_name = "task_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum TaskStatus {
+	#[default]
+	Open,
+	InProgress,
+	Done,
+	Cancelled,
+}
+
+sql_enum!(TaskStatus);
 
 // Model: Task struct
+#[serde_as]
 #[derive(Clone, Debug, Serialize, FromRow, Fields)]
 pub struct Task {
 	pub id: i64,
+	/// The tenant, `None` for the rows of a ctx without workspace
+	/// (see `DbBmc::WORKSPACE_SCOPED`).
+	pub workspace_id: Option<i64>,
+	pub project_id: Option<i64>,
+	/// The parent task, for a subtask (see `TaskBmc::move_subtree`).
+	pub parent_id: Option<i64>,
 	pub title: String,
+	/// Markdown
+	pub description: Option<String>,
+	pub status: TaskStatus,
+	/// From 0 (none) to 3 (highest)
+	pub priority: i16,
+	#[serde_as(as = "Option<Rfc3339>")]
+	pub due_at: Option<OffsetDateTime>,
+	/// Set when the status moves to `done`, cleared when it moves out of it.
+	#[serde_as(as = "Option<Rfc3339>")]
+	pub done_at: Option<OffsetDateTime>,
+	/// The manual order, with `"order_bys": ["sort_rank"]` (see `TaskBmc::move_before`).
+	pub sort_rank: String,
+	/// The series of a recurring task (see `TaskBmc::update_series`).
+	pub series_id: Option<i64>,
+	/// The scheduled time of this occurrence, kept when its `due_at` is edited.
+	#[serde_as(as = "Option<Rfc3339>")]
+	pub occurrence_at: Option<OffsetDateTime>,
+	/// See `TaskBmc::assign`.
+	pub assignee_id: Option<i64>,
+
+	// -- Timestamps
+	pub cid: i64,
+	#[serde_as(as = "Rfc3339")]
+	pub ctime: OffsetDateTime,
+	pub mid: i64,
+	#[serde_as(as = "Rfc3339")]
+	pub mtime: OffsetDateTime,
+	/// Set when the task is in the trash (see `TaskBmc::delete`).
+	#[serde_as(as = "Option<Rfc3339>")]
+	pub deleted_at: Option<OffsetDateTime>,
+	/// Bumped on every update (see `TaskBmc::update_with_version`).
+	pub version: i64,
 }
 
 // Task entity for creating method
-#[derive(Deserialize, Fields)]
+#[serde_as]
+#[derive(Default, Deserialize, Fields)]
 pub struct TaskForCreate {
 	pub title: String,
+	pub project_id: Option<i64>,
+	pub parent_id: Option<i64>,
+	pub description: Option<String>,
+	pub status: Option<TaskStatus>,
+	pub priority: Option<i16>,
+	#[serde_as(as = "Option<Rfc3339>")]
+	pub due_at: Option<OffsetDateTime>,
+	/// RFC 5545 RRULE (e.g., `FREQ=WEEKLY;BYDAY=MO`) of a recurring task,
+	/// with `due_at` as its first occurrence.
+	#[field(skip)]
+	pub rrule: Option<String>,
+	/// IANA timezone of the occurrences (`UTC` by default).
+	#[field(skip)]
+	pub rrule_tz: Option<String>,
 }
 
 // Task entity for updating method
-#[derive(Deserialize, Fields)]
+#[serde_as]
+#[derive(Default, Deserialize, Fields)]
 pub struct TaskForUpdate {
 	pub title: Option<String>,
+	pub project_id: Option<i64>,
+	pub description: Option<String>,
+	pub status: Option<TaskStatus>,
+	pub priority: Option<i16>,
+	#[serde_as(as = "Option<Rfc3339>")]
+	pub due_at: Option<OffsetDateTime>,
+}
+
+// Task filter for list method
+// (e.g., `{"title": {"$contains": "urgent"}, "id": {"$gte": 1000}}`)
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskFilter {
+	pub id: Option<OpValsInt64>,
+	pub project_id: Option<OpValsInt64>,
+	/// e.g., `{"$null": true}` for the top level tasks.
+	pub parent_id: Option<OpValsInt64>,
+	pub title: Option<OpValsString>,
+	pub status: Option<OpValsString>,
+	pub priority: Option<OpValsInt64>,
+	pub series_id: Option<OpValsInt64>,
+	pub assignee_id: Option<OpValsInt64>,
+	/// Tag ids, with `$any` (OR) or `$all` (AND).
+	pub tags: Option<OpValsIdSet>,
+	/// Watcher user ids, with `$any` (OR) or `$all` (AND).
+	pub watchers: Option<OpValsIdSet>,
+}
+
+impl FilterNodes for TaskFilter {
+	fn filter_nodes(self) -> Vec<FilterNode> {
+		let mut nodes = Vec::new();
+		if let Some(id) = self.id {
+			nodes.extend(id.into_filter_nodes("id"));
+		}
+		if let Some(project_id) = self.project_id {
+			nodes.extend(project_id.into_filter_nodes("project_id"));
+		}
+		if let Some(parent_id) = self.parent_id {
+			nodes.extend(parent_id.into_filter_nodes("parent_id"));
+		}
+		if let Some(title) = self.title {
+			nodes.extend(title.into_filter_nodes("title"));
+		}
+		if let Some(status) = self.status {
+			nodes.extend(status.into_filter_nodes("status"));
+		}
+		if let Some(priority) = self.priority {
+			nodes.extend(priority.into_filter_nodes("priority"));
+		}
+		if let Some(series_id) = self.series_id {
+			nodes.extend(series_id.into_filter_nodes("series_id"));
+		}
+		if let Some(assignee_id) = self.assignee_id {
+			nodes.extend(assignee_id.into_filter_nodes("assignee_id"));
+		}
+		if let Some(tags) = self.tags {
+			nodes.extend(
+				tags.into_filter_nodes(
+					"SELECT tag_id FROM task_tag WHERE task_id = \"task\".\"id\"",
+				),
+			);
+		}
+		if let Some(watchers) = self.watchers {
+			nodes.extend(watchers.into_filter_nodes(
+				"SELECT user_id FROM task_watcher WHERE task_id = \"task\".\"id\"",
+			));
+		}
+		nodes
+	}
+}
+
+/// Task read model with its tags (see `TaskBmc::list_with_tags`).
+#[derive(Clone, Debug, Serialize)]
+pub struct TaskWithTags {
+	#[serde(flatten)]
+	pub task: Task,
+	pub tags: Vec<Tag>,
+}
+
+/// Task search result (see `TaskBmc::search`).
+#[derive(Clone, Debug, Serialize, FromRow)]
+pub struct TaskSearchHit {
+	#[serde(flatten)]
+	#[sqlx(flatten)]
+	pub task: Task,
+	/// How well the task matches the query, higher is better.
+	pub rank: f32,
+	/// The fragments of the title and description matching the query, html escaped,
+	/// with the matched words within `<mark></mark>` (see `mark_snippet`).
+	pub snippet: String,
+}
+
+/// A task of a subtree (see `TaskBmc::get_subtree`).
+#[derive(Clone, Debug, Serialize, FromRow)]
+pub struct TaskInTree {
+	#[serde(flatten)]
+	#[sqlx(flatten)]
+	pub task: Task,
+	/// 0 for the root of the subtree, 1 for its subtasks, ...
+	pub depth: i32,
+}
+
+/// The completion counts of the subtasks of a task, at any depth
+/// (see `TaskBmc::list_rollups`).
+/// Note: The cancelled subtasks are not counted.
+#[derive(Clone, Debug, Serialize, FromRow)]
+pub struct TaskRollup {
+	pub id: i64,
+	pub total: i64,
+	pub done: i64,
+}
+
+/// What `TaskBmc::delete` does with the subtasks of the task.
+/// (configured with `SERVICE_TASK_DELETE_POLICY`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskDeletePolicy {
+	/// Delete the subtasks, at any depth, with the task.
+	Cascade,
+	/// Move the subtasks to the parent of the task (or to the top level).
+	Reparent,
+	/// Fail with `Error::TaskHasSubtasks` while the task still has subtasks
+	/// (not counting the subtasks in the trash).
+	Reject,
+}
+
+impl FromStr for TaskDeletePolicy {
+	type Err = ();
+
+	fn from_str(val: &str) -> core::result::Result<Self, Self::Err> {
+		match val {
+			"cascade" => Ok(Self::Cascade),
+			"reparent" => Ok(Self::Reparent),
+			"reject" => Ok(Self::Reject),
+			_ => Err(()),
+		}
+	}
 }
 
 // Task Backend Model Controller
@@ -32,150 +269,3019 @@ pub struct TaskBmc;
 
 impl TaskBmc {
 	// i64: id of entity
-	pub async fn create(ctx: &Ctx, mm: &ModelManager, task_c: TaskForCreate) -> Result<i64> {
-		common::create::<Self, TaskForCreate>(ctx, mm, task_c).await
+	/// Note: The new task is ranked last.
+	pub async fn create(ctx: &Ctx, mm: &ModelManager, mut task_c: TaskForCreate) -> Result<i64> {
+		let txn_mm = common::own_txn(ctx, mm).await?;
+		let mm = txn_mm.as_ref().unwrap_or(mm);
+
+		let series_fields = Self::create_series(ctx, mm, &mut task_c).await?;
+		let is_recurring = !series_fields.is_empty();
+		let mut fields = fields_for_create(task_c);
+		fields.extend(series_fields);
+		let rank = Self::new_rank(ctx, mm, ctx.workspace_id(), None, RankPos::Last).await?;
+		fields.push(("sort_rank", rank).into());
+		let id = common::create_fields::<Self>(ctx, mm, fields).await?;
+		if is_recurring {
+			Self::create_next_occurrences(ctx, mm, &[id]).await?;
+		}
+
+		common::commit_own_txn(txn_mm).await?;
+
+		Ok(id)
 	}
 
+	/// Note: Moving an occurrence of a recurring task to done creates its next occurrence
+	///       (see `create_next_occurrences`).
 	pub async fn update(
 		ctx: &Ctx,
 		mm: &ModelManager,
 		id: i64,
 		task_u: TaskForUpdate,
 	) -> Result<()> {
-		common::update::<Self, TaskForUpdate>(ctx, mm, id, task_u).await
+		Self::exec_update(ctx, mm, id, None, task_u).await
+	}
+
+	/// Same as `update`, but fails with `Error::VersionConflict`
+	/// if the task is not at `version` anymore.
+	pub async fn update_with_version(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: i64,
+		version: i64,
+		task_u: TaskForUpdate,
+	) -> Result<()> {
+		Self::exec_update(ctx, mm, id, Some(version), task_u).await
+	}
+
+	async fn exec_update(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: i64,
+		version: Option<i64>,
+		task_u: TaskForUpdate,
+	) -> Result<()> {
+		let is_done = task_u.status == Some(TaskStatus::Done);
+		let fields = fields_for_update(task_u);
+
+		let txn_mm = common::own_txn(ctx, mm).await?;
+		let mm = txn_mm.as_ref().unwrap_or(mm);
+
+		common::update_fields_with_version::<Self>(ctx, mm, id, version, fields).await?;
+		if is_done {
+			Self::create_next_occurrences(ctx, mm, &[id]).await?;
+		}
+
+		common::commit_own_txn(txn_mm).await?;
+
+		Ok(())
+	}
+
+	pub async fn list(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		filter: Option<TaskFilter>,
+		list_options: Option<ListOptions>,
+	) -> Result<Vec<Task>> {
+		common::list::<Self, _, _>(ctx, mm, filter, list_options).await
+	}
+
+	pub async fn list_by_project(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		project_id: i64,
+		list_options: Option<ListOptions>,
+	) -> Result<Vec<Task>> {
+		let filter = TaskFilter {
+			project_id: Some(OpValsInt64 {
+				eq: Some(project_id),
+				..Default::default()
+			}),
+			..Default::default()
+		};
+
+		Self::list(ctx, mm, Some(filter), list_options).await
+	}
+
+	/// List the tasks having any (OR) or all (AND) of the tags.
+	pub async fn list_by_tags(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		tags: OpValsIdSet,
+		list_options: Option<ListOptions>,
+	) -> Result<Vec<Task>> {
+		let filter = TaskFilter {
+			tags: Some(tags),
+			..Default::default()
+		};
+
+		Self::list(ctx, mm, Some(filter), list_options).await
+	}
+
+	/// Same as `list`, with the tags of each task.
+	/// Note: The tags of all the tasks are fetched with one query.
+	pub async fn list_with_tags(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		filter: Option<TaskFilter>,
+		list_options: Option<ListOptions>,
+	) -> Result<Vec<TaskWithTags>> {
+		let tasks = Self::list(ctx, mm, filter, list_options).await?;
+
+		let task_ids: Vec<i64> = tasks.iter().map(|t| t.id).collect();
+		let mut tags_by_task = TagBmc::list_by_task_ids(ctx, mm, &task_ids).await?;
+
+		let tasks = tasks
+			.into_iter()
+			.map(|task| TaskWithTags {
+				tags: tags_by_task.remove(&task.id).unwrap_or_default(),
+				task,
+			})
+			.collect();
+
+		Ok(tasks)
 	}
 
-	pub async fn list(ctx: &Ctx, mm: &ModelManager) -> Result<Vec<Task>> {
-		common::list::<Self, _>(ctx, mm).await
+	pub async fn list_page(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		filter: Option<TaskFilter>,
+		page_options: Option<PageOptions>,
+	) -> Result<Page<Task>> {
+		common::list_page::<Self, _, _>(ctx, mm, filter, page_options).await
 	}
 
 	pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Task> {
 		common::get::<Self, _>(ctx, mm, id).await
 	}
 
+	pub async fn get_with_tags(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<TaskWithTags> {
+		let task = Self::get(ctx, mm, id).await?;
+		let tags = TagBmc::list_by_task_ids(ctx, mm, &[id])
+			.await?
+			.remove(&id)
+			.unwrap_or_default();
+
+		Ok(TaskWithTags { task, tags })
+	}
+
+	/// The task, when the ctx user can access it: its creator, its assignee
+	/// or an admin (or the root ctx).
+	/// Fails with `Error::TaskAccessDenied` otherwise.
+	/// Note: Only enforced for the attachments and the assignment of the task, so far.
+	pub async fn get_accessible(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Task> {
+		let task = Self::get(ctx, mm, id).await?;
+		Self::ensure_access(ctx, mm, &task).await?;
+
+		Ok(task)
+	}
+
+	async fn ensure_access(ctx: &Ctx, mm: &ModelManager, task: &Task) -> Result<()> {
+		let user_id = ctx.user_id();
+		if task.cid == user_id || task.assignee_id == Some(user_id) {
+			return Ok(());
+		}
+
+		match UserBmc::ensure_admin(ctx, mm).await {
+			Err(Error::UserNotAdmin { user_id }) => Err(Error::TaskAccessDenied {
+				id: task.id,
+				user_id,
+			}),
+			result => result,
+		}
+	}
+
+	/// Move the task to the trash, following the configured `TaskDeletePolicy`.
+	/// It is purged after `SERVICE_TRASH_RETENTION_SEC`.
 	pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
-		common::delete::<Self, Task>(ctx, mm, id).await
+		Self::delete_with_policy(ctx, mm, id, config().TASK_DELETE_POLICY).await
+	}
+
+	/// Same as `delete`, but fails with `Error::VersionConflict`
+	/// if the task is not at `version` anymore.
+	pub async fn delete_with_version(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: i64,
+		version: i64,
+	) -> Result<()> {
+		let txn_mm = common::own_txn(ctx, mm).await?;
+		let mm = txn_mm.as_ref().unwrap_or(mm);
+
+		common::lock_with_version::<Self>(ctx, mm, id, version).await?;
+		Self::delete(ctx, mm, id).await?;
+
+		common::commit_own_txn(txn_mm).await?;
+
+		Ok(())
+	}
+
+	/// Note: With `TaskDeletePolicy::Cascade`, the subtasks are restored one by one
+	///       (`restore` only restores the task).
+	pub async fn delete_with_policy(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: i64,
+		policy: TaskDeletePolicy,
+	) -> Result<()> {
+		let txn_mm = common::own_txn(ctx, mm).await?;
+		let mm = txn_mm.as_ref().unwrap_or(mm);
+
+		Self::exec_delete(ctx, mm, id, policy).await?;
+
+		common::commit_own_txn(txn_mm).await?;
+
+		Ok(())
+	}
+
+	/// Returns the ids of the deleted tasks (the subtasks first, for a cascade).
+	/// Note: Must run in a transaction.
+	async fn exec_delete(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: i64,
+		policy: TaskDeletePolicy,
+	) -> Result<Vec<i64>> {
+		let task = Self::get(ctx, mm, id).await?;
+		let child_ids = Self::child_ids(ctx, mm, id).await?;
+
+		let mut deleted_ids = Vec::new();
+		if !child_ids.is_empty() {
+			match policy {
+				TaskDeletePolicy::Reject => return Err(Error::TaskHasSubtasks { id }),
+				TaskDeletePolicy::Reparent => {
+					let fields = vec![("parent_id", task.parent_id).into()];
+					common::update_many_fields::<Self>(ctx, mm, &child_ids, fields).await?;
+				}
+				TaskDeletePolicy::Cascade => {
+					let descendant_ids = Self::descendant_ids(ctx, mm, id).await?;
+					for ids in descendant_ids.chunks(BULK_LIMIT_MAX) {
+						common::delete_many::<Self>(ctx, mm, ids).await?;
+					}
+					deleted_ids = descendant_ids;
+				}
+			}
+		}
+
+		common::delete::<Self, Task>(ctx, mm, id).await?;
+		deleted_ids.push(id);
+
+		Ok(deleted_ids)
 	}
 }
 
-// Impl Trait Dbmc for Task model
-impl DbBmc for TaskBmc {
-	const TABLE: &'static str = "task";
+// region:    --- Search
+
+impl TaskBmc {
+	/// Full-text search on the title and description of the tasks, best match first.
+	/// The query has the web search syntax (`"quoted phrase"`, `or`, `-negation`),
+	/// plus the prefix terms (e.g., `deplo*`, `-draft*`), which are always AND-ed.
+	/// Note: The `list_options` order bys only break the rank ties.
+	pub async fn search(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		query: &str,
+		list_options: Option<ListOptions>,
+	) -> Result<Vec<TaskSearchHit>> {
+		let (websearch, prefixes) = parse_search_query(query);
+		if websearch.is_empty() && prefixes.is_empty() {
+			return Ok(Vec::new());
+		}
+
+		let list_options = list_options.unwrap_or_default();
+		let (limit, offset) = list_options.limit_offset();
+		let order_bys = list_options.order_bys.unwrap_or_default();
+		for order_by in order_bys.iter() {
+			if !Task::field_names().contains(&order_by.column()) {
+				return Err(Error::ListOrderByUnknownColumn(
+					order_by.column().to_string(),
+				));
+			}
+		}
+
+		// -- Build the query.
+		let columns = Task::field_names()
+			.iter()
+			.map(|name| format!("task.\"{name}\""))
+			.collect::<Vec<_>>()
+			.join(", ");
+		let mut sql = match mm.backend() {
+			Backend::Postgres => {
+				let mut sql = Sql::new(
+					Backend::Postgres,
+					format!(
+						"SELECT {columns}, \
+						 ts_rank(task.search_tsv, q.query) AS rank, \
+						 ts_headline('english', concat_ws(E'\\n', task.title, task.description), q.query, \
+						 'StartSel={SNIPPET_MARK_START}, StopSel={SNIPPET_MARK_END}, \
+						 MaxFragments=2, MaxWords=24, MinWords=8') \
+						 AS snippet \
+						 FROM task, (SELECT "
+					),
+				);
+				// Note: The empty parts are left out, so that postgres does not warn about them.
+				let has_websearch = !websearch.is_empty();
+				if has_websearch {
+					sql.push("websearch_to_tsquery('english', ")
+						.push_bind(websearch)
+						.push(")");
+				}
+				if !prefixes.is_empty() {
+					if has_websearch {
+						sql.push(" && ");
+					}
+					sql.push("to_tsquery('english', ")
+						.push_bind(prefixes.join(" & "))
+						.push(")");
+				}
+				sql.push(
+					") AS q(query) \
+					 WHERE task.search_tsv @@ q.query AND task.deleted_at IS NULL",
+				);
+				sql
+			}
+			// Note: The title weights more than the description, as the postgres `A` and `B`
+			//       weights (`ts_rank` defaults).
+			Backend::Sqlite => {
+				let Some(fts_query) = fts5_query(&websearch, &prefixes) else {
+					return Ok(Vec::new());
+				};
+
+				let mut sql = Sql::new(
+					Backend::Sqlite,
+					format!(
+						"SELECT {columns}, \
+						 -bm25(task_fts, 1.0, 0.4) AS rank, \
+						 snippet(task_fts, -1, '{SNIPPET_MARK_START}', '{SNIPPET_MARK_END}', ' ... ', 24) \
+						 AS snippet \
+						 FROM task_fts JOIN task ON task.id = task_fts.rowid \
+						 WHERE task_fts MATCH "
+					),
+				);
+				sql.push_bind(fts_query)
+					.push(" AND task.deleted_at IS NULL");
+				sql
+			}
+		};
+		if let Some(cond) = common::workspace_cond::<Self>(ctx) {
+			sql.push(format!(" AND {cond}"));
+		}
+		sql.push(" ORDER BY rank DESC");
+		for order_by in order_bys.iter() {
+			match order_by {
+				OrderBy::Asc(column) => sql.push(format!(", task.\"{column}\" ASC NULLS LAST")),
+				OrderBy::Desc(column) => sql.push(format!(", task.\"{column}\" DESC NULLS FIRST")),
+			};
+		}
+		sql.push(", task.id LIMIT ")
+			.push_bind(limit)
+			.push(" OFFSET ")
+			.push_bind(offset);
+
+		let mut hits: Vec<TaskSearchHit> =
+			with_db!(ctx, mm, |db| sql.query_as().fetch_all(db).await?);
+		for hit in hits.iter_mut() {
+			hit.snippet = mark_snippet(&hit.snippet);
+		}
+
+		Ok(hits)
+	}
 }
 
-#[cfg(test)]
-mod tests {
-	#![allow(unused)]
-	use crate::_dev_utils;
+/// The marks of the matched words in the snippets of the db, as control characters,
+/// so that the snippets can be html escaped before they get their `<mark>` tags.
+const SNIPPET_MARK_START: char = '\u{2}';
+const SNIPPET_MARK_END: char = '\u{3}';
 
-	use super::*;
-	use anyhow::Result;
-	use serial_test::serial;
+/// The snippet of the db, html escaped, with its marks as `<mark></mark>`.
+/// Note: The text of a task with the mark characters only gets extra `<mark>` tags.
+fn mark_snippet(snippet: &str) -> String {
+	let mut marked = String::with_capacity(snippet.len());
+	for c in snippet.chars() {
+		match c {
+			SNIPPET_MARK_START => marked.push_str("<mark>"),
+			SNIPPET_MARK_END => marked.push_str("</mark>"),
+			'&' => marked.push_str("&amp;"),
+			'<' => marked.push_str("&lt;"),
+			'>' => marked.push_str("&gt;"),
+			'"' => marked.push_str("&quot;"),
+			'\'' => marked.push_str("&#39;"),
+			c => marked.push(c),
+		}
+	}
 
-	#[serial]
-	#[tokio::test]
-	async fn test_create_ok() -> Result<()> {
-		let mm = _dev_utils::init_test().await;
-		let ctx = Ctx::root_ctx();
-		let fx_title = "test_create_ok title";
-		let task_c = TaskForCreate {
-			title: fx_title.to_string(),
+	marked
+}
+
+/// Split the search query into its web search part and its prefix terms
+/// (as `to_tsquery` terms, e.g., `deplo:*`, `!draft:*`).
+/// Note: The terms within a quoted phrase are never prefix terms.
+fn parse_search_query(query: &str) -> (String, Vec<String>) {
+	let mut websearch = Vec::new();
+	let mut prefixes = Vec::new();
+	let mut in_phrase = false;
+
+	for term in query.split_whitespace() {
+		let prefix = match in_phrase {
+			false => regex!(r"^(-?)(\w+)\*$").captures(term),
+			true => None,
 		};
+		match prefix {
+			Some(caps) => {
+				let not = if &caps[1] == "-" { "!" } else { "" };
+				prefixes.push(format!("{not}{}:*", &caps[2]));
+			}
+			None => websearch.push(term),
+		}
 
-		let id = TaskBmc::create(&ctx, &mm, task_c).await.unwrap();
+		if term.matches('"').count() % 2 == 1 {
+			in_phrase = !in_phrase;
+		}
+	}
 
-		let task = TaskBmc::get(&ctx, &mm, id).await.unwrap();
-		assert_eq!(task.title, fx_title);
+	(websearch.join(" "), prefixes)
+}
 
-		TaskBmc::delete(&ctx, &mm, id).await.unwrap();
+/// The FTS5 query of the parsed search query (see `parse_search_query`), for SQLite:
+/// the web search as its `or` groups, AND-ed with the prefix terms.
+/// `None` when it has no (positive) terms.
+/// Note: As FTS5 has no unary `NOT`, the negations only exclude from the terms
+///       of their group, and a group with only negations is left out.
+fn fts5_query(websearch: &str, prefixes: &[String]) -> Option<String> {
+	// -- The `or` groups of the web search, with their positive and negated terms.
+	let mut groups = vec![(Vec::new(), Vec::new())];
+	let mut chars = websearch.chars().peekable();
+	while let Some(mut c) = chars.next() {
+		if c.is_whitespace() {
+			continue;
+		}
+		let not = c == '-' && chars.peek().is_some_and(|c| !c.is_whitespace());
+		if not {
+			c = chars.next().unwrap_or(c);
+		}
+		let phrase = c == '"';
+		let term: String = match phrase {
+			true => chars.by_ref().take_while(|c| *c != '"').collect(),
+			false => std::iter::once(c)
+				.chain(chars.by_ref().take_while(|c| !c.is_whitespace()))
+				.collect(),
+		};
 
-		Ok(())
+		if !not && !phrase && term.eq_ignore_ascii_case("or") {
+			groups.push((Vec::new(), Vec::new()));
+		} else if term.chars().any(char::is_alphanumeric) {
+			let (pos, neg) = groups.last_mut()?;
+			match not {
+				false => pos.push(fts5_string(&term)),
+				true => neg.push(fts5_string(&term)),
+			}
+		}
 	}
+	let ors: Vec<String> = groups
+		.into_iter()
+		.filter(|(pos, _)| !pos.is_empty())
+		.map(|(pos, neg)| fts5_and(&pos, &neg))
+		.collect();
 
-	#[serial]
-	#[tokio::test]
-	async fn test_get_err_not_found() -> Result<()> {
-		let mm = _dev_utils::init_test().await;
-		let ctx = Ctx::root_ctx();
-		let id = 100;
+	// -- The prefix terms (e.g., `deplo:*`, `!draft:*`).
+	let mut pos = Vec::new();
+	let mut neg = Vec::new();
+	if !ors.is_empty() {
+		pos.push(format!("({})", ors.join(" OR ")));
+	}
+	for prefix in prefixes.iter() {
+		let term = prefix.trim_end_matches(":*");
+		match term.strip_prefix('!') {
+			Some(term) => neg.push(format!("{}*", fts5_string(term))),
+			None => pos.push(format!("{}*", fts5_string(term))),
+		}
+	}
+	if pos.is_empty() {
+		return None;
+	}
 
-		let result = TaskBmc::get(&ctx, &mm, id).await;
-		assert!(matches!(
-			result,
-			Err(Error::EntityNotFound { entity: "task", id })
-		));
+	Some(fts5_and(&pos, &neg))
+}
 
-		Ok(())
+fn fts5_and(pos: &[String], neg: &[String]) -> String {
+	let mut and = pos.join(" AND ");
+	for term in neg {
+		and.push_str(&format!(" NOT {term}"));
 	}
 
-	#[serial]
-	#[tokio::test]
-	async fn test_delete_err_not_found() -> Result<()> {
-		let mm = _dev_utils::init_test().await;
-		let ctx = Ctx::root_ctx();
-		let id = 100;
+	format!("({and})")
+}
 
-		let result = TaskBmc::delete(&ctx, &mm, id).await;
-		assert!(matches!(
-			result,
-			Err(Error::EntityNotFound { entity: "task", id })
-		));
+/// Note: A string is a phrase of its tokens, whatever its punctuation.
+fn fts5_string(term: &str) -> String {
+	format!("\"{}\"", term.replace('"', "\"\""))
+}
 
-		Ok(())
+// endregion: --- Search
+
+// region:    --- Bulk
+
+impl TaskBmc {
+	/// Create all the tasks at once. Returns their ids, in the same order.
+	pub async fn create_many(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		tasks_c: Vec<TaskForCreate>,
+	) -> Result<Vec<i64>> {
+		common::check_bulk_limit(tasks_c.len())?;
+
+		let txn_mm = common::own_txn(ctx, mm).await?;
+		let mm = txn_mm.as_ref().unwrap_or(mm);
+
+		// -- Ranked last, in the order of `tasks_c`.
+		let mut rows = Vec::with_capacity(tasks_c.len());
+		let mut rank: Option<String> = None;
+		let mut has_recurring = false;
+		for mut task_c in tasks_c {
+			let next_rank = match rank {
+				None => Self::new_rank(ctx, mm, ctx.workspace_id(), None, RankPos::Last).await?,
+				Some(rank) => rank::rank_between(Some(&rank), None).ok_or(Error::TaskRankNone)?,
+			};
+			let series_fields = Self::create_series(ctx, mm, &mut task_c).await?;
+			has_recurring |= !series_fields.is_empty();
+			let mut fields = fields_for_create(task_c);
+			fields.extend(series_fields);
+			fields.push(("sort_rank", next_rank.clone()).into());
+			rows.push(fields);
+			rank = Some(next_rank);
+		}
+		let ids = common::create_many_fields::<Self>(ctx, mm, rows).await?;
+		if has_recurring {
+			Self::create_next_occurrences(ctx, mm, &ids).await?;
+		}
+
+		common::commit_own_txn(txn_mm).await?;
+
+		Ok(ids)
 	}
 
-	#[serial]
-	#[tokio::test]
-	async fn test_list_ok() -> Result<()> {
-		let mm = _dev_utils::init_test().await;
-		let ctx = Ctx::root_ctx();
-		let list = ["Sau 1", "Sau 2"];
-		let list_task = _dev_utils::seed_test(&ctx, &mm, &list).await?;
+	/// Apply the same update to all the tasks.
+	pub async fn update_many(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		ids: &[i64],
+		task_u: TaskForUpdate,
+	) -> Result<BulkResult> {
+		let is_done = task_u.status == Some(TaskStatus::Done);
 
-		let result = TaskBmc::list(&ctx, &mm).await?;
-		assert!(matches!(list_task, result));
+		let txn_mm = common::own_txn(ctx, mm).await?;
+		let mm = txn_mm.as_ref().unwrap_or(mm);
 
-		Ok(())
+		let res =
+			common::update_many_fields::<Self>(ctx, mm, ids, fields_for_update(task_u)).await?;
+		if is_done {
+			Self::create_next_occurrences(ctx, mm, ids).await?;
+		}
+
+		common::commit_own_txn(txn_mm).await?;
+
+		Ok(res)
 	}
 
-	#[serial]
-	#[tokio::test]
-	async fn test_update_err_not_found() -> Result<()> {
-		let mm = _dev_utils::init_test().await;
-		let ctx = Ctx::root_ctx();
-		let id = 100;
-		let task_u = TaskForUpdate {
-			title: Some("Sau".to_string()),
-		};
+	/// Apply each update to its task (all or none).
+	pub async fn update_each(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		patches: Vec<(i64, TaskForUpdate)>,
+	) -> Result<BulkResult> {
+		let done_ids: Vec<i64> = patches
+			.iter()
+			.filter(|(_, task_u)| task_u.status == Some(TaskStatus::Done))
+			.map(|(id, _)| *id)
+			.collect();
+		let patches = patches
+			.into_iter()
+			.map(|(id, task_u)| (id, fields_for_update(task_u)))
+			.collect();
 
-		let result = TaskBmc::update(&ctx, &mm, id, task_u).await;
-		assert!(matches!(
-			result,
-			Err(Error::EntityNotFound { entity: "task", id })
-		));
+		let txn_mm = common::own_txn(ctx, mm).await?;
+		let mm = txn_mm.as_ref().unwrap_or(mm);
 
-		Ok(())
+		let res = common::update_each_fields::<Self>(ctx, mm, patches).await?;
+		if !done_ids.is_empty() {
+			Self::create_next_occurrences(ctx, mm, &done_ids).await?;
+		}
+
+		common::commit_own_txn(txn_mm).await?;
+
+		Ok(res)
 	}
 
-	#[serial]
-	#[tokio::test]
-	async fn test_update_ok() -> Result<()> {
-		let mm = _dev_utils::init_test().await;
-		let ctx = Ctx::root_ctx();
-		let task_c = TaskForCreate {
-			title: "Sau".to_string(),
-		};
-		let id = TaskBmc::create(&ctx, &mm, task_c).await?;
+	/// Move all the tasks to the trash.
+	/// Delete the tasks one by one, following the configured `TaskDeletePolicy`,
+	/// in a transaction (all or none).
+	/// Note: With `TaskDeletePolicy::Reject`, the subtasks must come before their parent.
+	pub async fn delete_many(ctx: &Ctx, mm: &ModelManager, ids: &[i64]) -> Result<BulkResult> {
+		common::check_bulk_limit(ids.len())?;
+		let policy = config().TASK_DELETE_POLICY;
 
-		let after_title = "Sau after edited";
-		let task_u = TaskForUpdate {
-			title: Some(after_title.to_string()),
+		let txn_mm = common::own_txn(ctx, mm).await?;
+		let mm = txn_mm.as_ref().unwrap_or(mm);
+
+		let mut deleted_ids = Vec::new();
+		for &id in ids {
+			// Note: Already deleted (e.g., by the cascade of its parent) when not found.
+			if deleted_ids.contains(&id) {
+				continue;
+			}
+			match Self::exec_delete(ctx, mm, id, policy).await {
+				Ok(ids) => deleted_ids.extend(ids),
+				Err(Error::EntityNotFound { .. }) => (),
+				Err(err) => return Err(err),
+			}
+		}
+
+		common::commit_own_txn(txn_mm).await?;
+
+		Ok(BulkResult::from_ids(ids, &deleted_ids))
+	}
+}
+
+// endregion: --- Bulk
+
+// region:    --- Subtasks
+
+impl TaskBmc {
+	/// The task and its subtasks at any depth, depth first
+	/// (each subtask after its parent, the siblings by id).
+	/// Note: The subtasks in the trash are skipped, with their own subtasks.
+	pub async fn get_subtree(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Vec<TaskInTree>> {
+		// Note: The subtasks are in the workspace of their parent
+		//       (see `task_parent_id_workspace_id_fkey`).
+		let workspace_cond = common::workspace_where::<Self>(ctx);
+		// Note: The SQLite path is the text of the zero padded ids, so that it sorts the same.
+		let (root_path, path) = match mm.backend() {
+			Backend::Postgres => ("ARRAY[task.id]", "subtree.path || task.id"),
+			Backend::Sqlite => (
+				"printf('%020d', task.id)",
+				"subtree.path || printf('/%020d', task.id)",
+			),
 		};
-		TaskBmc::update(&ctx, &mm, id, task_u).await?;
+		let sql = format!(
+			"WITH RECURSIVE subtree AS ( \
+				SELECT task.*, 0 AS depth, {root_path} AS path \
+				FROM task WHERE id = $1 AND deleted_at IS NULL AND {workspace_cond} \
+				UNION ALL \
+				SELECT task.*, subtree.depth + 1, {path} \
+				FROM task JOIN subtree ON task.parent_id = subtree.id \
+				WHERE task.deleted_at IS NULL \
+			) \
+			SELECT * FROM subtree ORDER BY path"
+		);
+		let tasks: Vec<TaskInTree> = with_db!(ctx, mm, |db| sqlx::query_as(&sql)
+			.bind(id)
+			.fetch_all(db)
+			.await?);
 
-		let task = TaskBmc::get(&ctx, &mm, id).await?;
-		assert_eq!(after_title, task.title);
+		if tasks.is_empty() {
+			return Err(Error::EntityNotFound {
+				entity: Self::TABLE,
+				id,
+			});
+		}
+
+		Ok(tasks)
+	}
+
+	/// Move the task, with its subtasks, under `parent_id` (or to the top level when `None`).
+	/// Fails with `Error::TaskParentCycle` when `parent_id` is in the subtree of the task.
+	pub async fn move_subtree(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: i64,
+		parent_id: Option<i64>,
+	) -> Result<()> {
+		let txn_mm = common::own_txn(ctx, mm).await?;
+		let mm = txn_mm.as_ref().unwrap_or(mm);
+
+		// Note: The `task_parent_no_cycle` trigger only sees the committed moves,
+		//       so that two concurrent moves could make a cycle without this lock.
+		xact_lock(ctx, mm, "task.parent_id", None).await?;
+
+		if let Some(parent_id) = parent_id {
+			// The parent must not be in the trash.
+			Self::get(ctx, mm, parent_id).await?;
+		}
+
+		let fields = vec![("parent_id", parent_id).into()];
+		common::update_fields::<Self>(ctx, mm, id, fields)
+			.await
+			.map_err(|err| match (err, parent_id) {
+				// Note: The SQLite trigger raises the constraint name as its message.
+				(Error::SqlxError(sqlx::Error::Database(db_err)), Some(parent_id))
+					if db_err.constraint() == Some("task_parent_no_cycle")
+						|| db_err.message() == "task_parent_no_cycle" =>
+				{
+					Error::TaskParentCycle { id, parent_id }
+				}
+				(err, _) => err,
+			})?;
+
+		common::commit_own_txn(txn_mm).await?;
 
 		Ok(())
 	}
+
+	/// The completion counts of the subtasks of each task, at any depth.
+	/// Note: The ids not found (or in the trash) are skipped.
+	pub async fn list_rollups(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		ids: &[i64],
+	) -> Result<Vec<TaskRollup>> {
+		common::check_bulk_limit(ids.len())?;
+
+		let workspace_cond = common::workspace_where::<Self>(ctx);
+		let in_ids = mm.backend().in_list("id", "$1");
+		let sql = format!(
+			"WITH RECURSIVE subtree AS ( \
+				SELECT id AS root_id, id, status FROM task \
+				WHERE {in_ids} AND deleted_at IS NULL AND {workspace_cond} \
+				UNION ALL \
+				SELECT subtree.root_id, task.id, task.status \
+				FROM task JOIN subtree ON task.parent_id = subtree.id \
+				WHERE task.deleted_at IS NULL \
+			) \
+			SELECT root_id AS id, \
+				count(*) FILTER (WHERE id <> root_id AND status <> 'cancelled') AS total, \
+				count(*) FILTER (WHERE id <> root_id AND status = 'done') AS done \
+			FROM subtree GROUP BY root_id ORDER BY root_id"
+		);
+		let rollups = with_db!(ctx, mm, |db| sqlx::query_as(&sql)
+			.bind(SqlValue::from(ids))
+			.fetch_all(db)
+			.await?);
+
+		Ok(rollups)
+	}
+
+	pub async fn get_rollup(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<TaskRollup> {
+		Self::list_rollups(ctx, mm, &[id])
+			.await?
+			.pop()
+			.ok_or(Error::EntityNotFound {
+				entity: Self::TABLE,
+				id,
+			})
+	}
+
+	/// The ids of the subtasks (not in the trash), locked until the end of the transaction.
+	async fn child_ids(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Vec<i64>> {
+		let sql = format!(
+			"SELECT id FROM task WHERE parent_id = $1 AND deleted_at IS NULL ORDER BY id{}",
+			mm.backend().for_update()
+		);
+		let ids: Vec<(i64,)> = with_db!(ctx, mm, |db| sqlx::query_as(&sql)
+			.bind(id)
+			.fetch_all(db)
+			.await?);
+
+		Ok(ids.into_iter().map(|(id,)| id).collect())
+	}
+
+	/// The ids of the subtasks at any depth (not in the trash), the deepest first.
+	async fn descendant_ids(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Vec<i64>> {
+		let ids: Vec<(i64,)> = with_db!(ctx, mm, |db| sqlx::query_as(
+			"WITH RECURSIVE subtree AS ( \
+				SELECT id, 0 AS depth FROM task WHERE id = $1 \
+				UNION ALL \
+				SELECT task.id, subtree.depth + 1 \
+				FROM task JOIN subtree ON task.parent_id = subtree.id \
+				WHERE task.deleted_at IS NULL \
+			) \
+			SELECT id FROM subtree WHERE depth > 0 ORDER BY depth DESC, id",
+		)
+		.bind(id)
+		.fetch_all(db)
+		.await?);
+
+		Ok(ids.into_iter().map(|(id,)| id).collect())
+	}
+}
+
+// endregion: --- Subtasks
+
+// region:    --- Rank
+
+/// Where to rank a task (see `TaskBmc::new_rank`).
+#[derive(Debug, Clone, Copy)]
+enum RankPos {
+	Last,
+	Before(i64),
+	After(i64),
+}
+
+impl TaskBmc {
+	/// Move the task just before the `before_id` task, in the rank order.
+	/// Note: Only updates the moved task (unless the ranks have to be spread again).
+	pub async fn move_before(ctx: &Ctx, mm: &ModelManager, id: i64, before_id: i64) -> Result<()> {
+		Self::move_to(ctx, mm, id, RankPos::Before(before_id)).await
+	}
+
+	/// Move the task just after the `after_id` task, in the rank order.
+	/// Note: Only updates the moved task (unless the ranks have to be spread again).
+	pub async fn move_after(ctx: &Ctx, mm: &ModelManager, id: i64, after_id: i64) -> Result<()> {
+		Self::move_to(ctx, mm, id, RankPos::After(after_id)).await
+	}
+
+	async fn move_to(ctx: &Ctx, mm: &ModelManager, id: i64, pos: RankPos) -> Result<()> {
+		if let RankPos::Before(target_id) | RankPos::After(target_id) = pos {
+			if target_id == id {
+				return Ok(());
+			}
+		}
+
+		// Note: The ranks are per workspace, the target must be in the workspace of the task.
+		let workspace_id = Self::get(ctx, mm, id).await?.workspace_id;
+		if let RankPos::Before(target_id) | RankPos::After(target_id) = pos {
+			let target = Self::get(ctx, mm, target_id).await?;
+			if target.workspace_id != workspace_id {
+				return Err(Error::EntityNotFound {
+					entity: Self::TABLE,
+					id: target_id,
+				});
+			}
+		}
+
+		let txn_mm = common::own_txn(ctx, mm).await?;
+		let mm = txn_mm.as_ref().unwrap_or(mm);
+
+		// Note: So that the neighbor ranks of the workspace do not change
+		//       until the move is committed.
+		xact_lock(ctx, mm, "task.sort_rank", workspace_id).await?;
+
+		let rank = Self::new_rank(ctx, mm, workspace_id, Some(id), pos).await?;
+		common::update_fields::<Self>(ctx, mm, id, vec![("sort_rank", rank).into()]).await?;
+
+		common::commit_own_txn(txn_mm).await?;
+
+		Ok(())
+	}
+
+	/// A rank at `pos` among the tasks of `workspace_id` (not in the trash),
+	/// ignoring the `moved_id` task.
+	/// The ranks are spread again when there is no rank at `pos` (e.g., two tasks with the
+	/// same rank after concurrent creates), or when it gets longer than `RANK_LEN_MAX`.
+	/// Note: Must run in a transaction.
+	async fn new_rank(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		workspace_id: Option<i64>,
+		moved_id: Option<i64>,
+		pos: RankPos,
+	) -> Result<String> {
+		let (before, after) = Self::rank_neighbors(ctx, mm, workspace_id, moved_id, pos).await?;
+		let rank = rank::rank_between(before.as_deref(), after.as_deref());
+		if let Some(rank) = rank.filter(|rank| rank.len() <= RANK_LEN_MAX) {
+			return Ok(rank);
+		}
+
+		Self::rebalance_ranks(ctx, mm, workspace_id).await?;
+
+		let (before, after) = Self::rank_neighbors(ctx, mm, workspace_id, moved_id, pos).await?;
+		rank::rank_between(before.as_deref(), after.as_deref()).ok_or(Error::TaskRankNone)
+	}
+
+	/// The ranks before and after `pos` (`None` for the first/last position),
+	/// among the tasks of `workspace_id` not in the trash.
+	async fn rank_neighbors(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		workspace_id: Option<i64>,
+		moved_id: Option<i64>,
+		pos: RankPos,
+	) -> Result<(Option<String>, Option<String>)> {
+		let moved_id = moved_id.unwrap_or_default();
+
+		let neighbors = match pos {
+			RankPos::Last => {
+				let last: Option<(String,)> = with_db!(ctx, mm, |db| {
+					sqlx::query_as(
+						"SELECT sort_rank FROM task \
+					 WHERE id <> $1 AND workspace_id IS NOT DISTINCT FROM $2 AND deleted_at IS NULL \
+					 ORDER BY sort_rank DESC, id DESC LIMIT 1",
+					)
+					.bind(moved_id)
+					.bind(workspace_id)
+					.fetch_optional(db)
+					.await?
+				});
+				(last.map(|(rank,)| rank), None)
+			}
+			RankPos::Before(target_id) | RankPos::After(target_id) => {
+				let target: Option<(String,)> = with_db!(ctx, mm, |db| sqlx::query_as(
+					"SELECT sort_rank FROM task WHERE id = $1 AND deleted_at IS NULL"
+				)
+				.bind(target_id)
+				.fetch_optional(db)
+				.await?);
+				let (target_rank,) = target.ok_or(Error::EntityNotFound {
+					entity: Self::TABLE,
+					id: target_id,
+				})?;
+
+				// Note: The neighbor, in the (sort_rank, id) order of the lists.
+				let sql = match pos {
+					RankPos::Before(_) => {
+						"SELECT sort_rank FROM task WHERE (sort_rank, id) < ($1, $2) AND id <> $3 \
+						 AND workspace_id IS NOT DISTINCT FROM $4 AND deleted_at IS NULL \
+						 ORDER BY sort_rank DESC, id DESC LIMIT 1"
+					}
+					_ => {
+						"SELECT sort_rank FROM task WHERE (sort_rank, id) > ($1, $2) AND id <> $3 \
+						 AND workspace_id IS NOT DISTINCT FROM $4 AND deleted_at IS NULL \
+						 ORDER BY sort_rank, id LIMIT 1"
+					}
+				};
+				let neighbor: Option<(String,)> = with_db!(ctx, mm, |db| sqlx::query_as(sql)
+					.bind(&target_rank)
+					.bind(target_id)
+					.bind(moved_id)
+					.bind(workspace_id)
+					.fetch_optional(db)
+					.await?);
+				let neighbor = neighbor.map(|(rank,)| rank);
+
+				match pos {
+					RankPos::Before(_) => (neighbor, Some(target_rank)),
+					_ => (Some(target_rank), neighbor),
+				}
+			}
+		};
+
+		Ok(neighbors)
+	}
+
+	/// Spread the ranks of the tasks of `workspace_id` evenly, keeping their order.
+	/// Note: Only updates `sort_rank` (no version bump, no audit), as the order does not change.
+	/// Note: Not the tasks in the trash, a restored task keeps its rank
+	///       (ordered by id among the tasks with the same rank).
+	async fn rebalance_ranks(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		workspace_id: Option<i64>,
+	) -> Result<()> {
+		let sql = format!(
+			"SELECT id FROM task WHERE workspace_id IS NOT DISTINCT FROM $1 AND deleted_at IS NULL \
+			 ORDER BY sort_rank, id{}",
+			mm.backend().for_update()
+		);
+		let ids: Vec<(i64,)> = with_db!(ctx, mm, |db| sqlx::query_as(&sql)
+			.bind(workspace_id)
+			.fetch_all(db)
+			.await?);
+		let ids: Vec<i64> = ids.into_iter().map(|(id,)| id).collect();
+		let ranks = rank::ranks_evenly(ids.len());
+
+		// Note: The ids and the ranks are zipped by their position.
+		let ranks_sql = match mm.backend() {
+			Backend::Postgres => "unnest($1::bigint[], $2::text[]) AS r(id, sort_rank)",
+			Backend::Sqlite => {
+				"(SELECT i.value AS id, s.value AS sort_rank \
+				 FROM json_each($1) AS i JOIN json_each($2) AS s ON s.key = i.key) AS r"
+			}
+		};
+		let sql = format!(
+			"UPDATE task SET sort_rank = r.sort_rank FROM {ranks_sql} WHERE task.id = r.id"
+		);
+		with_db!(ctx, mm, |db| sqlx::query(&sql)
+			.bind(SqlValue::from(&ids[..]))
+			.bind(SqlValue::from(ranks))
+			.execute(db)
+			.await?
+			.rows_affected());
+		for id in ids.iter() {
+			common::invalidate_cached::<Self>(mm, *id);
+		}
+
+		Ok(())
+	}
+}
+
+// endregion: --- Rank
+
+// region:    --- Recurrence
+
+/// A done occurrence of a recurring task (see `TaskBmc::create_next_occurrences`).
+#[derive(FromRow)]
+struct DoneOccurrence {
+	series_id: i64,
+	workspace_id: Option<i64>,
+	parent_id: Option<i64>,
+	assignee_id: Option<i64>,
+	occurrence_at: OffsetDateTime,
+}
+
+impl TaskBmc {
+	/// The series of the recurring task.
+	pub async fn get_series(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<TaskSeries> {
+		let series_id = Self::series_id(ctx, mm, id).await?;
+
+		TaskSeriesBmc::get(ctx, mm, series_id).await
+	}
+
+	/// Edit this occurrence of the recurring task only,
+	/// the series and its next occurrences do not change.
+	pub async fn update_occurrence(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: i64,
+		task_u: TaskForUpdate,
+	) -> Result<()> {
+		Self::series_id(ctx, mm, id).await?;
+
+		Self::update(ctx, mm, id, task_u).await
+	}
+
+	/// Edit the whole series of the recurring task: the template of its next occurrences,
+	/// and its open occurrences (neither done nor cancelled).
+	/// A new rule applies after the open occurrence (i.e., the series restarts from it,
+	/// for `COUNT`). With a new timezone, the occurrences keep their local time.
+	pub async fn update_series(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: i64,
+		series_u: TaskSeriesForUpdate,
+	) -> Result<()> {
+		let series_id = Self::series_id(ctx, mm, id).await?;
+
+		let txn_mm = common::own_txn(ctx, mm).await?;
+		let mm = txn_mm.as_ref().unwrap_or(mm);
+
+		let series = TaskSeriesBmc::get_for_update(ctx, mm, series_id).await?;
+		let (_, old_tz) = series.schedule()?;
+		let is_schedule_changed = series_u.rrule.is_some() || series_u.rrule_tz.is_some();
+		let (_, tz) = task_series::parse_schedule(
+			series_u.rrule.as_deref().unwrap_or(&series.rrule),
+			series_u.rrule_tz.as_deref().unwrap_or(&series.rrule_tz),
+		)?;
+
+		let sql = format!(
+			"SELECT id, occurrence_at FROM task \
+			 WHERE series_id = $1 AND status NOT IN ('done', 'cancelled') AND deleted_at IS NULL \
+			 ORDER BY occurrence_at{}",
+			mm.backend().for_update()
+		);
+		let open: Vec<(i64, OffsetDateTime)> = with_db!(ctx, mm, |db| sqlx::query_as(&sql)
+			.bind(series_id)
+			.fetch_all(db)
+			.await?);
+
+		// -- The template, to the open occurrences.
+		let task_u = TaskForUpdate {
+			title: series_u.title.clone(),
+			description: series_u.description.clone(),
+			priority: series_u.priority,
+			..Default::default()
+		};
+		let template_fields = task_u.not_none_fields();
+		let open_ids: Vec<i64> = open.iter().map(|(id, _)| *id).collect();
+		if !template_fields.is_empty() && !open_ids.is_empty() {
+			common::update_many_fields::<Self>(ctx, mm, &open_ids, template_fields).await?;
+		}
+
+		// -- The schedule, restarting from the (first) open occurrence, or the last one.
+		let mut fields = series_u.not_none_fields();
+		if is_schedule_changed {
+			let last: (Option<OffsetDateTime>,) = with_db!(ctx, mm, |db| sqlx::query_as(
+				"SELECT max(occurrence_at) FROM task WHERE series_id = $1"
+			)
+			.bind(series_id)
+			.fetch_one(db)
+			.await?);
+			let start = open.first().map(|(_, at)| *at).or(last.0);
+			let start = start.unwrap_or(series.dtstart);
+			fields.push(("dtstart", to_tz_local(start, old_tz, tz)).into());
+
+			if old_tz != tz {
+				for (id, occurrence_at) in open {
+					let at = to_tz_local(occurrence_at, old_tz, tz);
+					let fields = vec![("occurrence_at", at).into(), ("due_at", at).into()];
+					common::update_fields::<Self>(ctx, mm, id, fields).await?;
+				}
+			}
+		}
+		common::update_fields::<TaskSeriesBmc>(ctx, mm, series_id, fields).await?;
+
+		common::commit_own_txn(txn_mm).await?;
+
+		Ok(())
+	}
+
+	/// The series of the task, fails with `Error::TaskNotRecurring` when none.
+	async fn series_id(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<i64> {
+		let task = Self::get(ctx, mm, id).await?;
+
+		task.series_id.ok_or(Error::TaskNotRecurring { id })
+	}
+
+	/// With `rrule`, create the series of the new task, the task being its first occurrence.
+	/// Returns the series fields of the task.
+	/// Note: Must run in a transaction.
+	async fn create_series(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		task_c: &mut TaskForCreate,
+	) -> Result<Vec<Field>> {
+		let Some(rrule) = task_c.rrule.take() else {
+			return Ok(Vec::new());
+		};
+		let rrule_tz = task_c.rrule_tz.take().unwrap_or_else(|| "UTC".to_string());
+		let dtstart = task_c.due_at.ok_or(Error::TaskRruleNoDueAt)?;
+		task_series::parse_schedule(&rrule, &rrule_tz)?;
+
+		let series_c = TaskSeriesForCreate {
+			rrule,
+			rrule_tz,
+			dtstart,
+			title: task_c.title.clone(),
+			description: task_c.description.clone(),
+			priority: task_c.priority,
+			project_id: task_c.project_id,
+		};
+		let series_id = TaskSeriesBmc::create(ctx, mm, series_c).await?;
+
+		Ok(vec![
+			("series_id", series_id).into(),
+			("occurrence_at", dtstart).into(),
+		])
+	}
+
+	/// Create the next occurrence of the done occurrences of `ids`, from their series
+	/// template (keeping their workspace, parent and assignee), unless the series already has a later occurrence (e.g., done again).
+	/// The next occurrence is the first one of the rule after the done occurrence
+	/// (so, already overdue when the occurrence is done late).
+	/// Note: Must run in a transaction.
+	async fn create_next_occurrences(ctx: &Ctx, mm: &ModelManager, ids: &[i64]) -> Result<()> {
+		let mut sql = Sql::new(
+			mm.backend(),
+			"SELECT series_id, workspace_id, parent_id, assignee_id, occurrence_at FROM task WHERE ",
+		);
+		sql.push_in_list("id", ids).push(
+			" AND status = 'done' AND series_id IS NOT NULL \
+			 AND deleted_at IS NULL ORDER BY occurrence_at",
+		);
+		let done: Vec<DoneOccurrence> =
+			with_db!(ctx, mm, |db| sql.query_as().fetch_all(db).await?);
+
+		for done in done {
+			let DoneOccurrence {
+				series_id,
+				workspace_id,
+				parent_id,
+				assignee_id,
+				occurrence_at,
+			} = done;
+			let series = TaskSeriesBmc::get_for_update(ctx, mm, series_id).await?;
+
+			// Note: The occurrences in the trash count, so that a deleted one is not recreated.
+			let (has_later,): (bool,) = with_db!(ctx, mm, |db| {
+				sqlx::query_as(
+				"SELECT EXISTS (SELECT 1 FROM task WHERE series_id = $1 AND occurrence_at > $2)"
+			)
+			.bind(series_id)
+			.bind(SqlValue::from(occurrence_at))
+			.fetch_one(db)
+			.await?
+			});
+			if has_later {
+				continue;
+			}
+
+			let (rule, tz) = series.schedule()?;
+			let next_at = rule.next_after(series.dtstart, tz, occurrence_at);
+			let Some(next_at) = next_at else {
+				continue;
+			};
+
+			let task_c = TaskForCreate {
+				title: series.title,
+				project_id: series.project_id,
+				parent_id,
+				description: series.description,
+				priority: Some(series.priority),
+				due_at: Some(next_at),
+				..Default::default()
+			};
+			let mut fields = fields_for_create(task_c);
+			fields.push(("series_id", series_id).into());
+			fields.push(("occurrence_at", next_at).into());
+			fields.push(("assignee_id", assignee_id).into());
+			fields.push(("workspace_id", workspace_id).into());
+			let rank = Self::new_rank(ctx, mm, workspace_id, None, RankPos::Last).await?;
+			fields.push(("sort_rank", rank).into());
+			common::create_fields::<Self>(ctx, mm, fields).await?;
+		}
+
+		Ok(())
+	}
+}
+
+/// The time with the same local time in the `to_tz` timezone as in the `from_tz` one.
+fn to_tz_local(at: OffsetDateTime, from_tz: &'static Tz, to_tz: &'static Tz) -> OffsetDateTime {
+	let local = at.to_timezone(from_tz);
+
+	rrule::local_to_utc(PrimitiveDateTime::new(local.date(), local.time()), to_tz)
+}
+
+// endregion: --- Recurrence
+
+// region:    --- Trash
+
+impl TaskBmc {
+	pub async fn list_deleted(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		filter: Option<TaskFilter>,
+		list_options: Option<ListOptions>,
+	) -> Result<Vec<Task>> {
+		common::list_deleted::<Self, _, _>(ctx, mm, filter, list_options).await
+	}
+
+	pub async fn restore(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+		common::restore::<Self>(ctx, mm, id).await
+	}
+
+	pub async fn purge_deleted(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		deleted_before: OffsetDateTime,
+	) -> Result<u64> {
+		common::purge_deleted::<Self>(ctx, mm, deleted_before).await
+	}
+}
+
+// endregion: --- Trash
+
+// region:    --- Tags
+
+impl TaskBmc {
+	/// Replace the tags of the task with `tag_ids`.
+	pub async fn set_tags(ctx: &Ctx, mm: &ModelManager, id: i64, tag_ids: &[i64]) -> Result<()> {
+		Self::get(ctx, mm, id).await?;
+		TagBmc::ensure_owned(ctx, mm, tag_ids).await?;
+
+		let tag_ids = SqlValue::from(tag_ids);
+		match mm.backend() {
+			// Note: Single statement, so that the tags are replaced at once.
+			Backend::Postgres => {
+				with_db!(ctx, mm, |db| sqlx::query(
+					"WITH removed AS ( \
+						DELETE FROM task_tag WHERE task_id = $1 AND NOT (tag_id = ANY($2)) \
+					) \
+					INSERT INTO task_tag (task_id, tag_id) SELECT $1, unnest($2::bigint[]) \
+					ON CONFLICT DO NOTHING",
+				)
+				.bind(id)
+				.bind(&tag_ids)
+				.execute(db)
+				.await?
+				.rows_affected());
+			}
+			// Note: No data-modifying CTE in SQLite, the two statements in a transaction.
+			Backend::Sqlite => {
+				let txn_mm = common::own_txn(ctx, mm).await?;
+				let mm = txn_mm.as_ref().unwrap_or(mm);
+
+				with_db!(ctx, mm, |db| sqlx::query(
+					"DELETE FROM task_tag WHERE task_id = $1 \
+					 AND tag_id NOT IN (SELECT value FROM json_each($2))",
+				)
+				.bind(id)
+				.bind(&tag_ids)
+				.execute(db)
+				.await?
+				.rows_affected());
+				Self::insert_tags(ctx, mm, id, &tag_ids).await?;
+
+				common::commit_own_txn(txn_mm).await?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Add the tags to the task. Tags already on the task are ignored.
+	pub async fn add_tags(ctx: &Ctx, mm: &ModelManager, id: i64, tag_ids: &[i64]) -> Result<()> {
+		Self::get(ctx, mm, id).await?;
+		TagBmc::ensure_owned(ctx, mm, tag_ids).await?;
+
+		Self::insert_tags(ctx, mm, id, &SqlValue::from(tag_ids)).await
+	}
+
+	/// Remove the tags from the task. Tags not on the task are ignored.
+	pub async fn remove_tags(ctx: &Ctx, mm: &ModelManager, id: i64, tag_ids: &[i64]) -> Result<()> {
+		Self::get(ctx, mm, id).await?;
+
+		let mut sql = Sql::new(mm.backend(), "DELETE FROM task_tag WHERE task_id = ");
+		sql.push_bind(id)
+			.push(" AND ")
+			.push_in_list("tag_id", tag_ids);
+		with_db!(ctx, mm, |db| sql.query().execute(db).await?.rows_affected());
+
+		Ok(())
+	}
+
+	/// Note: The tags already on the task are ignored.
+	async fn insert_tags(ctx: &Ctx, mm: &ModelManager, id: i64, tag_ids: &SqlValue) -> Result<()> {
+		let tag_ids_sql = match mm.backend() {
+			Backend::Postgres => "unnest($2::bigint[])",
+			Backend::Sqlite => "value FROM json_each($2) WHERE TRUE",
+		};
+		let sql = format!(
+			"INSERT INTO task_tag (task_id, tag_id) SELECT $1, {tag_ids_sql} ON CONFLICT DO NOTHING"
+		);
+		with_db!(ctx, mm, |db| sqlx::query(&sql)
+			.bind(id)
+			.bind(tag_ids)
+			.execute(db)
+			.await?
+			.rows_affected());
+
+		Ok(())
+	}
+}
+
+// endregion: --- Tags
+
+// region:    --- Assignment
+
+impl TaskBmc {
+	/// Assign the task to the `user_id` user (fails with `Error::EntityNotFound` when unknown).
+	/// Allowed for the users who can access the task (see `get_accessible`),
+	/// and for any user taking an unassigned task.
+	/// Fails with `Error::TaskAssignDenied` otherwise.
+	pub async fn assign(ctx: &Ctx, mm: &ModelManager, id: i64, user_id: i64) -> Result<()> {
+		let task = Self::get(ctx, mm, id).await?;
+		UserBmc::get::<User>(ctx, mm, user_id).await?;
+
+		let is_taking = task.assignee_id.is_none() && user_id == ctx.user_id();
+		if !is_taking {
+			Self::ensure_can_assign(ctx, mm, &task).await?;
+		}
+
+		let fields = vec![("assignee_id", user_id).into()];
+		common::update_fields::<Self>(ctx, mm, id, fields).await
+	}
+
+	/// Allowed for the users who can access the task (see `get_accessible`).
+	pub async fn unassign(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+		let task = Self::get(ctx, mm, id).await?;
+		Self::ensure_can_assign(ctx, mm, &task).await?;
+
+		let fields = vec![("assignee_id", None::<i64>).into()];
+		common::update_fields::<Self>(ctx, mm, id, fields).await
+	}
+
+	/// The tasks assigned to the ctx user, matching `filter` (its `assignee_id` is ignored).
+	pub async fn list_assigned_to_me(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		filter: Option<TaskFilter>,
+		list_options: Option<ListOptions>,
+	) -> Result<Vec<Task>> {
+		let filter = TaskFilter {
+			assignee_id: Some(OpValsInt64 {
+				eq: Some(ctx.user_id()),
+				..Default::default()
+			}),
+			..filter.unwrap_or_default()
+		};
+
+		Self::list(ctx, mm, Some(filter), list_options).await
+	}
+
+	/// The ctx user watches the task. No error when already watching.
+	/// Fails with `Error::EntityNotFound` when the ctx user is not a user (e.g., the root ctx).
+	pub async fn watch(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+		Self::get(ctx, mm, id).await?;
+		UserBmc::get::<User>(ctx, mm, ctx.user_id()).await?;
+
+		with_db!(ctx, mm, |db| sqlx::query(
+			"INSERT INTO task_watcher (task_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING"
+		)
+		.bind(id)
+		.bind(ctx.user_id())
+		.execute(db)
+		.await?
+		.rows_affected());
+
+		Ok(())
+	}
+
+	/// No error when not watching.
+	pub async fn unwatch(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+		Self::get(ctx, mm, id).await?;
+
+		with_db!(ctx, mm, |db| sqlx::query(
+			"DELETE FROM task_watcher WHERE task_id = $1 AND user_id = $2"
+		)
+		.bind(id)
+		.bind(ctx.user_id())
+		.execute(db)
+		.await?
+		.rows_affected());
+
+		Ok(())
+	}
+
+	/// The ids of the users watching the task (e.g., for the notifications).
+	pub async fn list_watcher_ids(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Vec<i64>> {
+		Self::get(ctx, mm, id).await?;
+
+		let user_ids: Vec<(i64,)> = with_db!(ctx, mm, |db| sqlx::query_as(
+			"SELECT user_id FROM task_watcher WHERE task_id = $1 ORDER BY user_id"
+		)
+		.bind(id)
+		.fetch_all(db)
+		.await?);
+
+		Ok(user_ids.into_iter().map(|(user_id,)| user_id).collect())
+	}
+
+	/// The tasks watched by the ctx user, matching `filter` (its `watchers` is ignored).
+	pub async fn list_watched_by_me(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		filter: Option<TaskFilter>,
+		list_options: Option<ListOptions>,
+	) -> Result<Vec<Task>> {
+		let filter = TaskFilter {
+			watchers: Some(OpValsIdSet {
+				any: Some(vec![ctx.user_id()]),
+				..Default::default()
+			}),
+			..filter.unwrap_or_default()
+		};
+
+		Self::list(ctx, mm, Some(filter), list_options).await
+	}
+
+	async fn ensure_can_assign(ctx: &Ctx, mm: &ModelManager, task: &Task) -> Result<()> {
+		match Self::ensure_access(ctx, mm, task).await {
+			Err(Error::TaskAccessDenied { id, user_id }) => {
+				Err(Error::TaskAssignDenied { id, user_id })
+			}
+			result => result,
+		}
+	}
+}
+
+// endregion: --- Assignment
+
+// Impl Trait Dbmc for Task model
+impl DbBmc for TaskBmc {
+	const TABLE: &'static str = "task";
+	const SOFT_DELETE: bool = true;
+	const VERSIONED: bool = true;
+	const WORKSPACE_SCOPED: bool = true;
+	// Note: Read by most of the task methods (e.g., for the access checks).
+	const CACHED: bool = true;
+}
+
+/// The fields of the new task, with `done_at` when it is created done.
+fn fields_for_create(task_c: TaskForCreate) -> Vec<Field> {
+	let is_done = task_c.status == Some(TaskStatus::Done);
+
+	let mut fields = task_c.not_none_fields();
+	if is_done {
+		fields.push(("done_at", now_utc()).into());
+	}
+
+	fields
+}
+
+/// The fields of the task update, with `done_at` when the status changes.
+fn fields_for_update(task_u: TaskForUpdate) -> Vec<Field> {
+	let status = task_u.status;
+
+	let mut fields = task_u.not_none_fields();
+	match status {
+		// Note: In the SET clause, `status` and `done_at` are the values before the update,
+		//       so `done_at` is only set when the status moves to done.
+		Some(TaskStatus::Done) => fields.push(Field::expr(
+			"done_at",
+			"CASE WHEN \"status\" = 'done' THEN \"done_at\" ELSE $? END",
+			now_utc(),
+		)),
+		Some(_) => fields.push(("done_at", None::<OffsetDateTime>).into()),
+		None => (),
+	}
+
+	fields
+}
+
+#[cfg(test)]
+mod tests {
+	#![allow(unused)]
+	use crate::_dev_utils;
+
+	use super::*;
+	use anyhow::Result;
+	use serial_test::serial;
+
+	_dev_utils::test_backends!(
+		test_create_ok,
+		test_get_err_not_found,
+		test_delete_err_not_found,
+		test_list_ok,
+		test_list_by_filter_ok,
+		test_list_with_list_options_ok,
+		test_list_page_ok,
+		test_list_page_err_cursor,
+		test_list_err_order_by_unknown_column,
+		test_update_err_not_found,
+		test_update_ok,
+		test_update_timestamps_ok,
+		test_create_details_ok,
+		test_update_status_done_at_ok,
+		test_tags_ok,
+		test_set_tags_err_other_owner,
+		test_delete_restore_ok,
+		test_purge_deleted_ok,
+		test_update_with_version_ok,
+		test_delete_with_version_ok,
+		test_create_many_ok,
+		test_update_delete_many_ok,
+		test_update_each_err_rollback,
+		test_search_ok,
+		test_subtree_rollup_ok,
+		test_move_subtree_ok,
+		test_move_subtree_err_cycle,
+		test_delete_with_policy_ok,
+		test_move_before_after_ok,
+		test_move_rebalance_ok,
+		test_recurring_done_next_ok,
+		test_update_occurrence_series_ok,
+		test_recurring_err,
+		test_assign_ok,
+		test_assign_err,
+		test_watch_ok,
+	);
+
+	async fn test_create_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let fx_title = "test_create_ok title";
+		let task_c = TaskForCreate {
+			title: fx_title.to_string(),
+			..Default::default()
+		};
+
+		let id = TaskBmc::create(&ctx, &mm, task_c).await.unwrap();
+
+		let task = TaskBmc::get(&ctx, &mm, id).await.unwrap();
+		assert_eq!(task.title, fx_title);
+		assert_eq!(task.cid, ctx.user_id());
+		assert_eq!(task.mid, ctx.user_id());
+		assert_eq!(task.ctime, task.mtime);
+
+		TaskBmc::delete(&ctx, &mm, id).await.unwrap();
+
+		Ok(())
+	}
+
+	async fn test_get_err_not_found(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let id = 100;
+
+		let result = TaskBmc::get(&ctx, &mm, id).await;
+		assert!(matches!(
+			result,
+			Err(Error::EntityNotFound { entity: "task", id })
+		));
+
+		Ok(())
+	}
+
+	async fn test_delete_err_not_found(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let id = 100;
+
+		let result = TaskBmc::delete(&ctx, &mm, id).await;
+		assert!(matches!(
+			result,
+			Err(Error::EntityNotFound { entity: "task", id })
+		));
+
+		Ok(())
+	}
+
+	async fn test_list_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let list = ["Sau 1", "Sau 2"];
+		let list_task = _dev_utils::seed_test(&ctx, &mm, &list).await?;
+
+		let result = TaskBmc::list(&ctx, &mm, None, None).await?;
+		assert!(matches!(list_task, result));
+
+		Ok(())
+	}
+
+	async fn test_list_by_filter_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let fx_titles = [
+			"test_list_by_filter_ok-task 01",
+			"test_list_by_filter_ok-task 02",
+			"test_list_by_filter_ok-other 03",
+		];
+		let fx_tasks = _dev_utils::seed_test(&ctx, &mm, &fx_titles).await?;
+
+		// -- $contains
+		let filter: TaskFilter = serde_json::from_value(serde_json::json!({
+			"title": {"$contains": "test_list_by_filter_ok-task"}
+		}))?;
+		let tasks = TaskBmc::list(&ctx, &mm, Some(filter), None).await?;
+		let titles: Vec<&str> = tasks.iter().map(|t| t.title.as_str()).collect();
+		assert_eq!(titles, &fx_titles[..2]);
+
+		// -- $in & $ne
+		let filter = TaskFilter {
+			id: Some(OpValsInt64 {
+				in_: Some(fx_tasks.iter().map(|t| t.id).collect()),
+				ne: Some(fx_tasks[0].id),
+				..Default::default()
+			}),
+			..Default::default()
+		};
+		let tasks = TaskBmc::list(&ctx, &mm, Some(filter), None).await?;
+		let titles: Vec<&str> = tasks.iter().map(|t| t.title.as_str()).collect();
+		assert_eq!(titles, &fx_titles[1..]);
+
+		// -- Clean
+		for task in fx_tasks {
+			TaskBmc::delete(&ctx, &mm, task.id).await?;
+		}
+
+		Ok(())
+	}
+
+	async fn test_list_with_list_options_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let fx_titles = [
+			"test_list_with_list_options_ok 01",
+			"test_list_with_list_options_ok 02",
+			"test_list_with_list_options_ok 03",
+		];
+		let fx_tasks = _dev_utils::seed_test(&ctx, &mm, &fx_titles).await?;
+
+		let filter = TaskFilter {
+			title: Some(OpValsString {
+				starts_with: Some("test_list_with_list_options_ok".to_string()),
+				..Default::default()
+			}),
+			..Default::default()
+		};
+		let list_options: ListOptions = serde_json::from_value(serde_json::json!({
+			"order_bys": ["!title"],
+			"offset": 1,
+			"limit": 1
+		}))?;
+		let tasks = TaskBmc::list(&ctx, &mm, Some(filter), Some(list_options)).await?;
+		let titles: Vec<&str> = tasks.iter().map(|t| t.title.as_str()).collect();
+		assert_eq!(titles, ["test_list_with_list_options_ok 02"]);
+
+		// -- Clean
+		for task in fx_tasks {
+			TaskBmc::delete(&ctx, &mm, task.id).await?;
+		}
+
+		Ok(())
+	}
+
+	async fn test_list_page_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let fx_titles = [
+			"test_list_page_ok 01",
+			"test_list_page_ok 02",
+			"test_list_page_ok 03",
+			"test_list_page_ok 04",
+			"test_list_page_ok 05",
+		];
+		let fx_tasks = _dev_utils::seed_test(&ctx, &mm, &fx_titles).await?;
+		let filter = || TaskFilter {
+			title: Some(OpValsString {
+				starts_with: Some("test_list_page_ok".to_string()),
+				..Default::default()
+			}),
+			..Default::default()
+		};
+
+		// -- Exec: Walk through all the pages.
+		let mut titles: Vec<String> = Vec::new();
+		let mut cursor = None;
+		let mut page_count = 0;
+		loop {
+			let page_options = PageOptions {
+				limit: Some(2),
+				order_by: Some("!title".to_string().into()),
+				cursor,
+			};
+			let page = TaskBmc::list_page(&ctx, &mm, Some(filter()), Some(page_options)).await?;
+			page_count += 1;
+			titles.extend(page.items.into_iter().map(|t| t.title));
+			cursor = page.next_cursor;
+			if cursor.is_none() {
+				break;
+			}
+		}
+
+		// -- Check
+		let mut fx_titles_desc = fx_titles.to_vec();
+		fx_titles_desc.reverse();
+		assert_eq!(page_count, 3);
+		assert_eq!(titles, fx_titles_desc);
+
+		// -- Clean
+		for task in fx_tasks {
+			TaskBmc::delete(&ctx, &mm, task.id).await?;
+		}
+
+		Ok(())
+	}
+
+	async fn test_list_page_err_cursor(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let fx_titles = [
+			"test_list_page_err_cursor 01",
+			"test_list_page_err_cursor 02",
+		];
+		let fx_tasks = _dev_utils::seed_test(&ctx, &mm, &fx_titles).await?;
+
+		let page_options = PageOptions {
+			limit: Some(1),
+			..Default::default()
+		};
+		let page = TaskBmc::list_page(&ctx, &mm, None, Some(page_options)).await?;
+		let cursor = page.next_cursor.expect("should have a next_cursor");
+
+		// -- Check: Forged cursor.
+		let (_, sign) = cursor.split_once('.').unwrap();
+		let forged = format!(
+			"{}.{sign}",
+			base64_url::encode(r#"{"order_by":"id","val":0,"id":0}"#)
+		);
+		let page_options = PageOptions {
+			cursor: Some(forged),
+			..Default::default()
+		};
+		let result = TaskBmc::list_page(&ctx, &mm, None, Some(page_options)).await;
+		assert!(matches!(result, Err(Error::ListCursorInvalid)));
+
+		// -- Check: Cursor used with another order by.
+		let page_options = PageOptions {
+			order_by: Some("title".to_string().into()),
+			cursor: Some(cursor),
+			..Default::default()
+		};
+		let result = TaskBmc::list_page(&ctx, &mm, None, Some(page_options)).await;
+		assert!(matches!(
+			result,
+			Err(Error::ListCursorOrderByMismatch { .. })
+		));
+
+		// -- Clean
+		for task in fx_tasks {
+			TaskBmc::delete(&ctx, &mm, task.id).await?;
+		}
+
+		Ok(())
+	}
+
+	async fn test_list_err_order_by_unknown_column(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let list_options = ListOptions {
+			order_bys: Some(vec!["title; DROP TABLE task".to_string().into()]),
+			..Default::default()
+		};
+
+		let result = TaskBmc::list(&ctx, &mm, None, Some(list_options)).await;
+		assert!(matches!(result, Err(Error::ListOrderByUnknownColumn(_))));
+
+		Ok(())
+	}
+
+	async fn test_update_err_not_found(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let id = 100;
+		let task_u = TaskForUpdate {
+			title: Some("Sau".to_string()),
+			..Default::default()
+		};
+
+		let result = TaskBmc::update(&ctx, &mm, id, task_u).await;
+		assert!(matches!(
+			result,
+			Err(Error::EntityNotFound { entity: "task", id })
+		));
+
+		Ok(())
+	}
+
+	async fn test_update_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let task_c = TaskForCreate {
+			title: "Sau".to_string(),
+			..Default::default()
+		};
+		let id = TaskBmc::create(&ctx, &mm, task_c).await?;
+
+		let after_title = "Sau after edited";
+		let task_u = TaskForUpdate {
+			title: Some(after_title.to_string()),
+			..Default::default()
+		};
+		TaskBmc::update(&ctx, &mm, id, task_u).await?;
+
+		let task = TaskBmc::get(&ctx, &mm, id).await?;
+		assert_eq!(after_title, task.title);
+
+		Ok(())
+	}
+
+	async fn test_update_timestamps_ok(mm: ModelManager) -> Result<()> {
+		let root_ctx = Ctx::root_ctx();
+		let ctx = Ctx::new(1000)?;
+		let task_c = TaskForCreate {
+			title: "test_update_timestamps_ok".to_string(),
+			..Default::default()
+		};
+		let id = TaskBmc::create(&root_ctx, &mm, task_c).await?;
+
+		let task_u = TaskForUpdate {
+			title: Some("test_update_timestamps_ok edited".to_string()),
+			..Default::default()
+		};
+		TaskBmc::update(&ctx, &mm, id, task_u).await?;
+
+		let task = TaskBmc::get(&ctx, &mm, id).await?;
+		assert_eq!(task.cid, root_ctx.user_id());
+		assert_eq!(task.mid, ctx.user_id());
+		assert!(task.mtime > task.ctime);
+
+		TaskBmc::delete(&ctx, &mm, id).await?;
+
+		Ok(())
+	}
+
+	async fn test_create_details_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let fx_due_at = OffsetDateTime::parse("2030-01-15T09:00:00Z", &Rfc3339)?;
+		let task_c = TaskForCreate {
+			title: "test_create_details_ok".to_string(),
+			description: Some("Some **markdown**".to_string()),
+			priority: Some(2),
+			due_at: Some(fx_due_at),
+			..Default::default()
+		};
+
+		let id = TaskBmc::create(&ctx, &mm, task_c).await?;
+
+		let task = TaskBmc::get(&ctx, &mm, id).await?;
+		assert_eq!(task.description.as_deref(), Some("Some **markdown**"));
+		assert_eq!(task.status, TaskStatus::Open);
+		assert_eq!(task.priority, 2);
+		assert_eq!(task.due_at, Some(fx_due_at));
+		assert!(task.done_at.is_none());
+
+		// -- Filter by status and priority.
+		let filter: TaskFilter = serde_json::from_value(serde_json::json!({
+			"title": {"$eq": "test_create_details_ok"},
+			"status": {"$in": ["open", "in_progress"]},
+			"priority": {"$gte": 2}
+		}))?;
+		let tasks = TaskBmc::list(&ctx, &mm, Some(filter), None).await?;
+		assert_eq!(tasks.len(), 1);
+
+		TaskBmc::delete(&ctx, &mm, id).await?;
+
+		Ok(())
+	}
+
+	async fn test_update_status_done_at_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let task_c = TaskForCreate {
+			title: "test_update_status_done_at_ok".to_string(),
+			..Default::default()
+		};
+		let id = TaskBmc::create(&ctx, &mm, task_c).await?;
+		let status_u = |status| TaskForUpdate {
+			status: Some(status),
+			..Default::default()
+		};
+
+		// -- Moving to done sets done_at.
+		TaskBmc::update(&ctx, &mm, id, status_u(TaskStatus::Done)).await?;
+		let task = TaskBmc::get(&ctx, &mm, id).await?;
+		assert_eq!(task.status, TaskStatus::Done);
+		let done_at = task.done_at.expect("done_at should be set");
+
+		// -- Updating an already done task keeps done_at.
+		TaskBmc::update(&ctx, &mm, id, status_u(TaskStatus::Done)).await?;
+		let task = TaskBmc::get(&ctx, &mm, id).await?;
+		assert_eq!(task.done_at, Some(done_at));
+
+		// -- Reopening clears done_at.
+		TaskBmc::update(&ctx, &mm, id, status_u(TaskStatus::InProgress)).await?;
+		let task = TaskBmc::get(&ctx, &mm, id).await?;
+		assert_eq!(task.status, TaskStatus::InProgress);
+		assert!(task.done_at.is_none());
+
+		TaskBmc::delete(&ctx, &mm, id).await?;
+
+		Ok(())
+	}
+
+	async fn test_tags_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let fx_tag_a = _dev_utils::seed_tag(&ctx, &mm, "test_tags_ok a").await?;
+		let fx_tag_b = _dev_utils::seed_tag(&ctx, &mm, "test_tags_ok b").await?;
+		let tasks = _dev_utils::seed_test(
+			&ctx,
+			&mm,
+			&["test_tags_ok 01", "test_tags_ok 02", "test_tags_ok 03"],
+		)
+		.await?;
+		let ids: Vec<i64> = tasks.iter().map(|t| t.id).collect();
+
+		// -- Set, add and remove.
+		TaskBmc::set_tags(&ctx, &mm, ids[0], &[fx_tag_a, fx_tag_b]).await?;
+		TaskBmc::add_tags(&ctx, &mm, ids[1], &[fx_tag_a]).await?;
+		TaskBmc::add_tags(&ctx, &mm, ids[2], &[fx_tag_a, fx_tag_b]).await?;
+		TaskBmc::remove_tags(&ctx, &mm, ids[2], &[fx_tag_a]).await?;
+
+		// -- List with tags.
+		let filter = TaskFilter {
+			id: Some(OpValsInt64 {
+				in_: Some(ids.clone()),
+				..Default::default()
+			}),
+			..Default::default()
+		};
+		let tasks = TaskBmc::list_with_tags(&ctx, &mm, Some(filter), None).await?;
+		let tag_ids: Vec<Vec<i64>> = tasks
+			.iter()
+			.map(|t| t.tags.iter().map(|tag| tag.id).collect())
+			.collect();
+		assert_eq!(
+			tag_ids,
+			[vec![fx_tag_a, fx_tag_b], vec![fx_tag_a], vec![fx_tag_b]]
+		);
+
+		// -- List by tags, OR and AND.
+		let any = OpValsIdSet {
+			any: Some(vec![fx_tag_a, fx_tag_b]),
+			..Default::default()
+		};
+		let tasks = TaskBmc::list_by_tags(&ctx, &mm, any, None).await?;
+		assert_eq!(tasks.iter().map(|t| t.id).collect::<Vec<_>>(), ids);
+
+		let all = OpValsIdSet {
+			all: Some(vec![fx_tag_a, fx_tag_b]),
+			..Default::default()
+		};
+		let tasks = TaskBmc::list_by_tags(&ctx, &mm, all, None).await?;
+		assert_eq!(tasks.iter().map(|t| t.id).collect::<Vec<_>>(), [ids[0]]);
+
+		// -- Set replaces.
+		TaskBmc::set_tags(&ctx, &mm, ids[0], &[fx_tag_b]).await?;
+		let task = TaskBmc::get_with_tags(&ctx, &mm, ids[0]).await?;
+		assert_eq!(task.tags.len(), 1);
+		assert_eq!(task.tags[0].id, fx_tag_b);
+
+		// -- Clean
+		for id in ids {
+			TaskBmc::delete(&ctx, &mm, id).await?;
+		}
+		TagBmc::delete(&ctx, &mm, fx_tag_a).await?;
+		TagBmc::delete(&ctx, &mm, fx_tag_b).await?;
+
+		Ok(())
+	}
+
+	async fn test_set_tags_err_other_owner(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let other_ctx = Ctx::new(1000)?;
+		let fx_tag_id =
+			_dev_utils::seed_tag(&other_ctx, &mm, "test_set_tags_err_other_owner").await?;
+		let fx_task_id =
+			_dev_utils::seed_test(&ctx, &mm, &["test_set_tags_err_other_owner"]).await?[0].id;
+
+		let result = TaskBmc::set_tags(&ctx, &mm, fx_task_id, &[fx_tag_id]).await;
+		assert!(matches!(
+			result,
+			Err(Error::EntityNotFound { entity: "tag", id }) if id == fx_tag_id
+		));
+
+		TaskBmc::delete(&ctx, &mm, fx_task_id).await?;
+		TagBmc::delete(&other_ctx, &mm, fx_tag_id).await?;
+
+		Ok(())
+	}
+
+	async fn test_delete_restore_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let fx_title = "test_delete_restore_ok";
+		let id = _dev_utils::seed_test(&ctx, &mm, &[fx_title]).await?[0].id;
+		let title_filter = || TaskFilter {
+			title: Some(OpValsString {
+				eq: Some(fx_title.to_string()),
+				..Default::default()
+			}),
+			..Default::default()
+		};
+
+		// -- Deleted, only in the trash.
+		TaskBmc::delete(&ctx, &mm, id).await?;
+		let result = TaskBmc::get(&ctx, &mm, id).await;
+		assert!(matches!(result, Err(Error::EntityNotFound { .. })));
+		let tasks = TaskBmc::list(&ctx, &mm, Some(title_filter()), None).await?;
+		assert!(tasks.is_empty());
+		let tasks = TaskBmc::list_deleted(&ctx, &mm, Some(title_filter()), None).await?;
+		assert_eq!(tasks.len(), 1);
+		assert!(tasks[0].deleted_at.is_some());
+		// Already deleted.
+		let result = TaskBmc::delete(&ctx, &mm, id).await;
+		assert!(matches!(result, Err(Error::EntityNotFound { .. })));
+
+		// -- Restored.
+		TaskBmc::restore(&ctx, &mm, id).await?;
+		let task = TaskBmc::get(&ctx, &mm, id).await?;
+		assert!(task.deleted_at.is_none());
+		let result = TaskBmc::restore(&ctx, &mm, id).await;
+		assert!(matches!(result, Err(Error::EntityNotFound { .. })));
+
+		// -- Clean
+		TaskBmc::delete(&ctx, &mm, id).await?;
+
+		Ok(())
+	}
+
+	async fn test_purge_deleted_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let ids: Vec<i64> = _dev_utils::seed_test(
+			&ctx,
+			&mm,
+			&["test_purge_deleted_ok 01", "test_purge_deleted_ok 02"],
+		)
+		.await?
+		.iter()
+		.map(|t| t.id)
+		.collect();
+		TaskBmc::delete(&ctx, &mm, ids[0]).await?;
+
+		// -- Nothing deleted before the retention.
+		TaskBmc::purge_deleted(&Ctx::root_ctx(), &mm, now_utc() - time::Duration::days(1)).await?;
+		TaskBmc::restore(&ctx, &mm, ids[0]).await?;
+		TaskBmc::delete(&ctx, &mm, ids[0]).await?;
+
+		// -- Purged, the task not in the trash is kept.
+		let purged = TaskBmc::purge_deleted(&Ctx::root_ctx(), &mm, now_utc()).await?;
+		assert!(purged >= 1);
+		let result = TaskBmc::restore(&ctx, &mm, ids[0]).await;
+		assert!(matches!(result, Err(Error::EntityNotFound { .. })));
+		TaskBmc::get(&ctx, &mm, ids[1]).await?;
+
+		// -- Clean
+		TaskBmc::delete(&ctx, &mm, ids[1]).await?;
+
+		Ok(())
+	}
+
+	async fn test_update_with_version_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let task = _dev_utils::seed_test(&ctx, &mm, &["test_update_with_version_ok"])
+			.await?
+			.remove(0);
+		let title_u = |title: &str| TaskForUpdate {
+			title: Some(title.to_string()),
+			..Default::default()
+		};
+
+		// -- Update at the current version, bumps the version.
+		TaskBmc::update_with_version(&ctx, &mm, task.id, task.version, title_u("edited 01"))
+			.await?;
+		let task_01 = TaskBmc::get(&ctx, &mm, task.id).await?;
+		assert_eq!(task_01.version, task.version + 1);
+
+		// -- Update at the previous version, conflicts.
+		let result =
+			TaskBmc::update_with_version(&ctx, &mm, task.id, task.version, title_u("edited 02"))
+				.await;
+		assert!(matches!(
+			result,
+			Err(Error::VersionConflict { expected, current, .. })
+				if expected == task.version && current == task_01.version
+		));
+		let task_02 = TaskBmc::get(&ctx, &mm, task.id).await?;
+		assert_eq!(task_02.title, "edited 01");
+
+		// -- Unknown task, not found.
+		let result = TaskBmc::update_with_version(&ctx, &mm, 100, 0, title_u("edited 03")).await;
+		assert!(matches!(result, Err(Error::EntityNotFound { .. })));
+
+		TaskBmc::delete(&ctx, &mm, task.id).await?;
+
+		Ok(())
+	}
+
+	async fn test_delete_with_version_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let task = _dev_utils::seed_test(&ctx, &mm, &["test_delete_with_version_ok"])
+			.await?
+			.remove(0);
+		let task_u = TaskForUpdate {
+			title: Some("edited".to_string()),
+			..Default::default()
+		};
+		TaskBmc::update(&ctx, &mm, task.id, task_u).await?;
+
+		// -- Delete at the previous version, conflicts.
+		let result = TaskBmc::delete_with_version(&ctx, &mm, task.id, task.version).await;
+		assert!(matches!(
+			result,
+			Err(Error::VersionConflict { expected, current, .. })
+				if expected == task.version && current == task.version + 1
+		));
+		TaskBmc::get(&ctx, &mm, task.id).await?;
+
+		// -- Delete at the current version.
+		TaskBmc::delete_with_version(&ctx, &mm, task.id, task.version + 1).await?;
+		let result = TaskBmc::get(&ctx, &mm, task.id).await;
+		assert!(matches!(result, Err(Error::EntityNotFound { .. })));
+
+		// -- In the trash, not found.
+		let result = TaskBmc::delete_with_version(&ctx, &mm, task.id, task.version + 1).await;
+		assert!(matches!(result, Err(Error::EntityNotFound { .. })));
+
+		Ok(())
+	}
+
+	async fn test_create_many_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let tasks_c = vec![
+			TaskForCreate {
+				title: "test_create_many_ok 01".to_string(),
+				..Default::default()
+			},
+			TaskForCreate {
+				title: "test_create_many_ok 02".to_string(),
+				status: Some(TaskStatus::Done),
+				priority: Some(2),
+				..Default::default()
+			},
+		];
+
+		let ids = TaskBmc::create_many(&ctx, &mm, tasks_c).await?;
+
+		assert_eq!(ids.len(), 2);
+		// The missing columns of a row get their default.
+		let task = TaskBmc::get(&ctx, &mm, ids[0]).await?;
+		assert_eq!(task.title, "test_create_many_ok 01");
+		assert_eq!(task.status, TaskStatus::Open);
+		assert_eq!(task.priority, 0);
+		let task = TaskBmc::get(&ctx, &mm, ids[1]).await?;
+		assert_eq!(task.title, "test_create_many_ok 02");
+		assert_eq!(task.priority, 2);
+		assert!(task.done_at.is_some());
+
+		// -- Clean
+		TaskBmc::delete_many(&ctx, &mm, &ids).await?;
+
+		Ok(())
+	}
+
+	async fn test_update_delete_many_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let fx_ids: Vec<i64> = _dev_utils::seed_test(
+			&ctx,
+			&mm,
+			&[
+				"test_update_delete_many_ok 01",
+				"test_update_delete_many_ok 02",
+			],
+		)
+		.await?
+		.into_iter()
+		.map(|t| t.id)
+		.collect();
+
+		// -- Same update for all.
+		let ids = [fx_ids[0], 100_000, fx_ids[1], fx_ids[0]];
+		let task_u = TaskForUpdate {
+			status: Some(TaskStatus::Done),
+			..Default::default()
+		};
+		let res = TaskBmc::update_many(&ctx, &mm, &ids, task_u).await?;
+		assert_eq!(res.ok_ids, fx_ids);
+		assert_eq!(res.not_found_ids, [100_000]);
+		for id in fx_ids.iter() {
+			let task = TaskBmc::get(&ctx, &mm, *id).await?;
+			assert_eq!(task.status, TaskStatus::Done);
+			assert!(task.done_at.is_some());
+		}
+
+		// -- One update per task.
+		let patches = vec![
+			(
+				fx_ids[0],
+				TaskForUpdate {
+					title: Some("test_update_delete_many_ok 01 updated".to_string()),
+					..Default::default()
+				},
+			),
+			(
+				fx_ids[1],
+				TaskForUpdate {
+					priority: Some(3),
+					..Default::default()
+				},
+			),
+		];
+		let res = TaskBmc::update_each(&ctx, &mm, patches).await?;
+		assert_eq!(res.ok_ids, fx_ids);
+		let task = TaskBmc::get(&ctx, &mm, fx_ids[0]).await?;
+		assert_eq!(task.title, "test_update_delete_many_ok 01 updated");
+		let task = TaskBmc::get(&ctx, &mm, fx_ids[1]).await?;
+		assert_eq!(task.priority, 3);
+
+		// -- Delete, the second time they are not found.
+		let res = TaskBmc::delete_many(&ctx, &mm, &fx_ids).await?;
+		assert_eq!(res.ok_ids, fx_ids);
+		let res = TaskBmc::delete_many(&ctx, &mm, &fx_ids).await?;
+		assert!(res.ok_ids.is_empty());
+		assert_eq!(res.not_found_ids, fx_ids);
+
+		Ok(())
+	}
+
+	async fn test_update_each_err_rollback(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let fx_title = "test_update_each_err_rollback";
+		let fx_id = _dev_utils::seed_test(&ctx, &mm, &[fx_title]).await?[0].id;
+
+		let patches = vec![
+			(
+				fx_id,
+				TaskForUpdate {
+					title: Some("rolled back".to_string()),
+					..Default::default()
+				},
+			),
+			// Fails, priority is out of the 0..=3 range.
+			(
+				fx_id,
+				TaskForUpdate {
+					priority: Some(10),
+					..Default::default()
+				},
+			),
+		];
+		let result = TaskBmc::update_each(&ctx, &mm, patches).await;
+
+		assert!(result.is_err());
+		let task = TaskBmc::get(&ctx, &mm, fx_id).await?;
+		assert_eq!(task.title, fx_title);
+
+		// -- Clean
+		TaskBmc::delete(&ctx, &mm, fx_id).await?;
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_mark_snippet_ok() -> Result<()> {
+		let snippet = mark_snippet("\u{2}Fix\u{3} <b>the</b> \"bug\" & 'it'");
+
+		assert_eq!(
+			snippet,
+			"<mark>Fix</mark> &lt;b&gt;the&lt;/b&gt; &quot;bug&quot; &amp; &#39;it&#39;"
+		);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_parse_search_query_ok() -> Result<()> {
+		let (websearch, prefixes) =
+			parse_search_query(r#""fix the bug*" -urgent or deplo* -draft* docs"#);
+
+		assert_eq!(websearch, r#""fix the bug*" -urgent or docs"#);
+		assert_eq!(prefixes, ["deplo:*", "!draft:*"]);
+
+		Ok(())
+	}
+
+	async fn test_search_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let tasks_c = vec![
+			TaskForCreate {
+				title: "Zanzibar deployment".to_string(),
+				description: Some("Roll out the zanzibar release".to_string()),
+				..Default::default()
+			},
+			TaskForCreate {
+				title: "Write the docs".to_string(),
+				description: Some("About the zanzibar deployment, draft".to_string()),
+				..Default::default()
+			},
+			TaskForCreate {
+				title: "Zanzibar retro".to_string(),
+				..Default::default()
+			},
+		];
+		let mut fx_ids = TaskBmc::create_many(&ctx, &mm, tasks_c).await?;
+
+		// -- Title matches rank first.
+		let hits = TaskBmc::search(&ctx, &mm, "zanzibar deployment", None).await?;
+		let ids: Vec<i64> = hits.iter().map(|h| h.task.id).collect();
+		assert_eq!(ids, [fx_ids[0], fx_ids[1]]);
+		assert!(hits[0].rank > hits[1].rank);
+		assert!(hits[0].snippet.contains("<mark>Zanzibar</mark>"));
+
+		// -- Phrase.
+		let hits = TaskBmc::search(&ctx, &mm, r#""zanzibar release""#, None).await?;
+		let ids: Vec<i64> = hits.iter().map(|h| h.task.id).collect();
+		assert_eq!(ids, [fx_ids[0]]);
+
+		// -- Prefix and negation.
+		let hits = TaskBmc::search(&ctx, &mm, "zanzib* -dra*", None).await?;
+		let mut ids: Vec<i64> = hits.iter().map(|h| h.task.id).collect();
+		ids.sort();
+		assert_eq!(ids, [fx_ids[0], fx_ids[2]]);
+		let hits = TaskBmc::search(&ctx, &mm, "zanzibar -retro", None).await?;
+		assert!(hits.iter().all(|h| h.task.id != fx_ids[2]));
+
+		// -- No terms, no hits.
+		let hits = TaskBmc::search(&ctx, &mm, "  ", None).await?;
+		assert!(hits.is_empty());
+
+		// -- The snippet is html escaped, but for its marks.
+		let task_c = TaskForCreate {
+			title: "Quokka a<b & \"c\" wombat".to_string(),
+			..Default::default()
+		};
+		fx_ids.push(TaskBmc::create(&ctx, &mm, task_c).await?);
+		let hits = TaskBmc::search(&ctx, &mm, "quokka wombat", None).await?;
+		assert_eq!(hits.len(), 1);
+		let snippet = &hits[0].snippet;
+		assert!(
+			snippet.contains("<mark>Quokka</mark> a&lt;b &amp; &quot;c&quot; <mark>wombat</mark>"),
+			"snippet: {snippet}"
+		);
+
+		// -- Deleted tasks are not found.
+		TaskBmc::delete_many(&ctx, &mm, &fx_ids).await?;
+		let hits = TaskBmc::search(&ctx, &mm, "zanzibar", None).await?;
+		assert!(hits.is_empty());
+
+		Ok(())
+	}
+
+	async fn test_subtree_rollup_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let fx_root_id =
+			_dev_utils::seed_test(&ctx, &mm, &["test_subtree_rollup_ok root"]).await?[0].id;
+		let fx_ids = _dev_utils::seed_subtasks(
+			&ctx,
+			&mm,
+			fx_root_id,
+			&["test_subtree_rollup_ok a", "test_subtree_rollup_ok b"],
+		)
+		.await?;
+		let fx_a1_id =
+			_dev_utils::seed_subtasks(&ctx, &mm, fx_ids[0], &["test_subtree_rollup_ok a1"]).await?
+				[0];
+		let fx_status = [
+			(fx_ids[0], TaskStatus::Done),
+			(fx_ids[1], TaskStatus::Cancelled),
+		];
+		for (id, status) in fx_status {
+			let task_u = TaskForUpdate {
+				status: Some(status),
+				..Default::default()
+			};
+			TaskBmc::update(&ctx, &mm, id, task_u).await?;
+		}
+
+		// -- Check subtree, depth first.
+		let tree = TaskBmc::get_subtree(&ctx, &mm, fx_root_id).await?;
+		let tree: Vec<(i64, i32)> = tree.iter().map(|t| (t.task.id, t.depth)).collect();
+		assert_eq!(
+			tree,
+			[
+				(fx_root_id, 0),
+				(fx_ids[0], 1),
+				(fx_a1_id, 2),
+				(fx_ids[1], 1)
+			]
+		);
+
+		// -- Check rollups, without the cancelled subtask.
+		let rollups = TaskBmc::list_rollups(&ctx, &mm, &[fx_root_id, fx_ids[0], fx_a1_id]).await?;
+		let rollups: Vec<(i64, i64, i64)> =
+			rollups.iter().map(|r| (r.id, r.total, r.done)).collect();
+		assert_eq!(
+			rollups,
+			[(fx_root_id, 2, 1), (fx_ids[0], 1, 0), (fx_a1_id, 0, 0)]
+		);
+
+		// -- Clean
+		TaskBmc::delete_with_policy(&ctx, &mm, fx_root_id, TaskDeletePolicy::Cascade).await?;
+
+		Ok(())
+	}
+
+	async fn test_move_subtree_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let fx_ids = _dev_utils::seed_test(
+			&ctx,
+			&mm,
+			&["test_move_subtree_ok 01", "test_move_subtree_ok 02"],
+		)
+		.await?;
+		let fx_child_id =
+			_dev_utils::seed_subtasks(&ctx, &mm, fx_ids[0].id, &["test_move_subtree_ok child"])
+				.await?[0];
+
+		TaskBmc::move_subtree(&ctx, &mm, fx_ids[0].id, Some(fx_ids[1].id)).await?;
+
+		let tree = TaskBmc::get_subtree(&ctx, &mm, fx_ids[1].id).await?;
+		let tree: Vec<i64> = tree.iter().map(|t| t.task.id).collect();
+		assert_eq!(tree, [fx_ids[1].id, fx_ids[0].id, fx_child_id]);
+
+		// -- Back to the top level.
+		TaskBmc::move_subtree(&ctx, &mm, fx_ids[0].id, None).await?;
+		assert_eq!(TaskBmc::get(&ctx, &mm, fx_ids[0].id).await?.parent_id, None);
+
+		// -- Clean
+		for task in fx_ids {
+			TaskBmc::delete_with_policy(&ctx, &mm, task.id, TaskDeletePolicy::Cascade).await?;
+		}
+
+		Ok(())
+	}
+
+	async fn test_move_subtree_err_cycle(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let fx_root_id =
+			_dev_utils::seed_test(&ctx, &mm, &["test_move_subtree_err_cycle"]).await?[0].id;
+		let fx_child_id = _dev_utils::seed_subtasks(
+			&ctx,
+			&mm,
+			fx_root_id,
+			&["test_move_subtree_err_cycle child"],
+		)
+		.await?[0];
+		let fx_grandchild_id = _dev_utils::seed_subtasks(
+			&ctx,
+			&mm,
+			fx_child_id,
+			&["test_move_subtree_err_cycle grandchild"],
+		)
+		.await?[0];
+
+		for parent_id in [fx_root_id, fx_grandchild_id] {
+			let result = TaskBmc::move_subtree(&ctx, &mm, fx_root_id, Some(parent_id)).await;
+			assert!(
+				matches!(
+					result,
+					Err(Error::TaskParentCycle { id, parent_id: p_id })
+						if id == fx_root_id && p_id == parent_id
+				),
+				"parent_id {parent_id}: {result:?}"
+			);
+		}
+		assert_eq!(TaskBmc::get(&ctx, &mm, fx_root_id).await?.parent_id, None);
+
+		// -- Clean
+		TaskBmc::delete_with_policy(&ctx, &mm, fx_root_id, TaskDeletePolicy::Cascade).await?;
+
+		Ok(())
+	}
+
+	async fn test_delete_with_policy_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let fx_root_id =
+			_dev_utils::seed_test(&ctx, &mm, &["test_delete_with_policy_ok"]).await?[0].id;
+		let fx_id =
+			_dev_utils::seed_subtasks(&ctx, &mm, fx_root_id, &["test_delete_with_policy_ok 01"])
+				.await?[0];
+		let fx_child_ids = _dev_utils::seed_subtasks(
+			&ctx,
+			&mm,
+			fx_id,
+			&[
+				"test_delete_with_policy_ok 01.a",
+				"test_delete_with_policy_ok 01.b",
+			],
+		)
+		.await?;
+
+		// -- Check reject.
+		let result = TaskBmc::delete_with_policy(&ctx, &mm, fx_id, TaskDeletePolicy::Reject).await;
+		assert!(matches!(result, Err(Error::TaskHasSubtasks { id }) if id == fx_id));
+		TaskBmc::get(&ctx, &mm, fx_id).await?;
+
+		// -- Check reparent, the subtasks move up to the root.
+		TaskBmc::delete_with_policy(&ctx, &mm, fx_id, TaskDeletePolicy::Reparent).await?;
+		for id in fx_child_ids.iter() {
+			assert_eq!(
+				TaskBmc::get(&ctx, &mm, *id).await?.parent_id,
+				Some(fx_root_id)
+			);
+		}
+
+		// -- Check cascade.
+		TaskBmc::delete_with_policy(&ctx, &mm, fx_root_id, TaskDeletePolicy::Cascade).await?;
+		for id in fx_child_ids {
+			let result = TaskBmc::get(&ctx, &mm, id).await;
+			assert!(matches!(result, Err(Error::EntityNotFound { .. })));
+		}
+
+		Ok(())
+	}
+
+	async fn test_move_before_after_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let fx_titles = &[
+			"test_move_before_after_ok 01",
+			"test_move_before_after_ok 02",
+			"test_move_before_after_ok 03",
+		];
+		let fx_ids: Vec<i64> = _dev_utils::seed_test(&ctx, &mm, fx_titles)
+			.await?
+			.into_iter()
+			.map(|t| t.id)
+			.collect();
+
+		// -- Check ranked last, in the creation order.
+		assert_eq!(
+			fx_ranked_ids(&ctx, &mm, "test_move_before_after_ok").await?,
+			fx_ids
+		);
+
+		TaskBmc::move_before(&ctx, &mm, fx_ids[2], fx_ids[0]).await?;
+		assert_eq!(
+			fx_ranked_ids(&ctx, &mm, "test_move_before_after_ok").await?,
+			[fx_ids[2], fx_ids[0], fx_ids[1]]
+		);
+
+		TaskBmc::move_after(&ctx, &mm, fx_ids[0], fx_ids[1]).await?;
+		assert_eq!(
+			fx_ranked_ids(&ctx, &mm, "test_move_before_after_ok").await?,
+			[fx_ids[2], fx_ids[1], fx_ids[0]]
+		);
+
+		// -- Check only the moved task was updated.
+		let task = TaskBmc::get(&ctx, &mm, fx_ids[1]).await?;
+		assert_eq!(task.version, 0);
+
+		// -- Clean
+		for id in fx_ids {
+			TaskBmc::delete(&ctx, &mm, id).await?;
+		}
+
+		Ok(())
+	}
+
+	async fn test_move_rebalance_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let fx_titles = &[
+			"test_move_rebalance_ok 01",
+			"test_move_rebalance_ok 02",
+			"test_move_rebalance_ok 03",
+		];
+		let fx_ids: Vec<i64> = _dev_utils::seed_test(&ctx, &mm, fx_titles)
+			.await?
+			.into_iter()
+			.map(|t| t.id)
+			.collect();
+		// Not rebalanced: a task in the trash, and a task of another workspace.
+		let trash_id =
+			_dev_utils::seed_test(&ctx, &mm, &["test_move_rebalance_ok trash"]).await?[0].id;
+		TaskBmc::delete(&ctx, &mm, trash_id).await?;
+		let user_id = _dev_utils::seed_user(&ctx, &mm, "test_move_rebalance_ok").await?;
+		let user_ctx = Ctx::new(user_id)?;
+		let workspace_c = crate::model::workspace::WorkspaceForCreate {
+			name: "test_move_rebalance_ok".to_string(),
+		};
+		let workspace_id =
+			crate::model::workspace::WorkspaceBmc::create(&user_ctx, &mm, workspace_c).await?;
+		let task_c = TaskForCreate {
+			title: "other - test_move_rebalance_ok".to_string(),
+			..Default::default()
+		};
+		let other_id =
+			TaskBmc::create(&user_ctx.with_workspace_id(workspace_id), &mm, task_c).await?;
+		let fx_other_ranks = fx_ranks(&mm, &[trash_id, other_id]).await?;
+
+		// -- Always moving between the same neighbors.
+		for i in 0..200 {
+			let (id, after_id) = match i % 2 {
+				0 => (fx_ids[2], fx_ids[0]),
+				_ => (fx_ids[1], fx_ids[0]),
+			};
+			TaskBmc::move_after(&ctx, &mm, id, after_id).await?;
+		}
+
+		// -- Check the ranks stay short.
+		for id in fx_ids.iter() {
+			let task = TaskBmc::get(&ctx, &mm, *id).await?;
+			assert!(
+				task.sort_rank.len() <= RANK_LEN_MAX,
+				"sort_rank: {}",
+				task.sort_rank
+			);
+		}
+		assert_eq!(
+			fx_ranked_ids(&ctx, &mm, "test_move_rebalance_ok").await?,
+			[fx_ids[0], fx_ids[1], fx_ids[2]]
+		);
+
+		// -- Check same ranks (e.g., concurrent creates) are spread again.
+		let task = TaskBmc::get(&ctx, &mm, fx_ids[0]).await?;
+		with_db!(&ctx, mm, |db| sqlx::query(
+			"UPDATE task SET sort_rank = $1 WHERE id = $2"
+		)
+		.bind(&task.sort_rank)
+		.bind(fx_ids[1])
+		.execute(db)
+		.await?
+		.rows_affected());
+		TaskBmc::move_after(&ctx, &mm, fx_ids[2], fx_ids[0]).await?;
+		assert_eq!(
+			fx_ranked_ids(&ctx, &mm, "test_move_rebalance_ok").await?,
+			[fx_ids[0], fx_ids[2], fx_ids[1]]
+		);
+		assert_eq!(fx_ranks(&mm, &[trash_id, other_id]).await?, fx_other_ranks);
+
+		// -- Clean
+		for id in fx_ids {
+			TaskBmc::delete(&ctx, &mm, id).await?;
+		}
+
+		Ok(())
+	}
+
+	/// The ranks of the tasks (in the trash too, of any workspace), in the order of `ids`.
+	async fn fx_ranks(mm: &ModelManager, ids: &[i64]) -> Result<Vec<String>> {
+		let ctx = Ctx::root_ctx();
+		let mut ranks = Vec::new();
+		for id in ids {
+			let (rank,): (String,) = with_db!(&ctx, mm, |db| sqlx::query_as(
+				"SELECT sort_rank FROM task WHERE id = $1"
+			)
+			.bind(id)
+			.fetch_one(db)
+			.await?);
+			ranks.push(rank);
+		}
+
+		Ok(ranks)
+	}
+
+	/// The ids of the tasks with the title prefix, in the rank order.
+	async fn test_recurring_done_next_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let fx_title = "test_recurring_done_next_ok";
+		// Weekly on Monday at 09:00 New York time, DST starts on 2030-03-10.
+		let task_c = TaskForCreate {
+			title: fx_title.to_string(),
+			priority: Some(2),
+			due_at: Some(OffsetDateTime::parse(
+				"2030-03-04T09:00:00-05:00",
+				&Rfc3339,
+			)?),
+			rrule: Some("FREQ=WEEKLY;BYDAY=MO;COUNT=3".to_string()),
+			rrule_tz: Some("America/New_York".to_string()),
+			..Default::default()
+		};
+		let id = TaskBmc::create(&ctx, &mm, task_c).await?;
+		let series_id = TaskBmc::get(&ctx, &mm, id).await?.series_id.unwrap();
+		let done_u = |status| TaskForUpdate {
+			status: Some(status),
+			..Default::default()
+		};
+
+		// -- Done creates the next occurrence, after the DST change.
+		TaskBmc::update(&ctx, &mm, id, done_u(TaskStatus::Done)).await?;
+		let tasks = fx_occurrences(&ctx, &mm, series_id).await?;
+		assert_eq!(tasks.len(), 2);
+		assert_eq!(tasks[0].id, id);
+		let next = &tasks[1];
+		assert_eq!(next.title, fx_title);
+		assert_eq!(next.priority, 2);
+		assert_eq!(next.status, TaskStatus::Open);
+		assert_eq!(
+			next.occurrence_at,
+			Some(OffsetDateTime::parse("2030-03-11T13:00:00Z", &Rfc3339)?)
+		);
+		assert_eq!(next.due_at, next.occurrence_at);
+
+		// -- Done again (once reopened) does not create another one.
+		TaskBmc::update(&ctx, &mm, id, done_u(TaskStatus::Open)).await?;
+		TaskBmc::update(&ctx, &mm, id, done_u(TaskStatus::Done)).await?;
+		assert_eq!(fx_occurrences(&ctx, &mm, series_id).await?.len(), 2);
+
+		// -- The last occurrence (COUNT=3), with the bulk update.
+		TaskBmc::update_many(&ctx, &mm, &[tasks[1].id], done_u(TaskStatus::Done)).await?;
+		let tasks = fx_occurrences(&ctx, &mm, series_id).await?;
+		assert_eq!(tasks.len(), 3);
+		assert_eq!(
+			tasks[2].occurrence_at,
+			Some(OffsetDateTime::parse("2030-03-18T13:00:00Z", &Rfc3339)?)
+		);
+		TaskBmc::update(&ctx, &mm, tasks[2].id, done_u(TaskStatus::Done)).await?;
+		assert_eq!(fx_occurrences(&ctx, &mm, series_id).await?.len(), 3);
+
+		// -- Clean
+		for task in tasks {
+			TaskBmc::delete(&ctx, &mm, task.id).await?;
+		}
+
+		Ok(())
+	}
+
+	async fn test_update_occurrence_series_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let fx_title = "test_update_occurrence_series_ok";
+		let task_c = TaskForCreate {
+			title: fx_title.to_string(),
+			due_at: Some(OffsetDateTime::parse(
+				"2030-01-07T09:00:00+01:00",
+				&Rfc3339,
+			)?),
+			rrule: Some("FREQ=WEEKLY".to_string()),
+			rrule_tz: Some("Europe/Paris".to_string()),
+			..Default::default()
+		};
+		let id = TaskBmc::create(&ctx, &mm, task_c).await?;
+		let series_id = TaskBmc::get(&ctx, &mm, id).await?.series_id.unwrap();
+
+		// -- This occurrence only.
+		let task_u = TaskForUpdate {
+			title: Some(format!("{fx_title} - moved")),
+			due_at: Some(OffsetDateTime::parse(
+				"2030-01-08T09:00:00+01:00",
+				&Rfc3339,
+			)?),
+			..Default::default()
+		};
+		TaskBmc::update_occurrence(&ctx, &mm, id, task_u).await?;
+		let task = TaskBmc::get(&ctx, &mm, id).await?;
+		assert_eq!(task.title, format!("{fx_title} - moved"));
+		assert_eq!(
+			task.occurrence_at,
+			Some(OffsetDateTime::parse("2030-01-07T08:00:00Z", &Rfc3339)?)
+		);
+		assert_eq!(TaskBmc::get_series(&ctx, &mm, id).await?.title, fx_title);
+
+		// -- The whole series, with the open occurrence, and a new rule.
+		let series_u = TaskSeriesForUpdate {
+			title: Some(format!("{fx_title} - series")),
+			rrule: Some("FREQ=DAILY".to_string()),
+			..Default::default()
+		};
+		TaskBmc::update_series(&ctx, &mm, id, series_u).await?;
+		let task = TaskBmc::get(&ctx, &mm, id).await?;
+		assert_eq!(task.title, format!("{fx_title} - series"));
+		let series = TaskBmc::get_series(&ctx, &mm, id).await?;
+		assert_eq!(series.rrule, "FREQ=DAILY");
+		assert_eq!(series.dtstart, task.occurrence_at.unwrap());
+
+		// -- The next occurrence follows the new rule, from the series template.
+		let done_u = TaskForUpdate {
+			status: Some(TaskStatus::Done),
+			..Default::default()
+		};
+		TaskBmc::update(&ctx, &mm, id, done_u).await?;
+		let tasks = fx_occurrences(&ctx, &mm, series_id).await?;
+		assert_eq!(tasks.len(), 2);
+		assert_eq!(tasks[1].title, format!("{fx_title} - series"));
+		assert_eq!(
+			tasks[1].occurrence_at,
+			Some(OffsetDateTime::parse("2030-01-08T08:00:00Z", &Rfc3339)?)
+		);
+
+		// -- A new timezone keeps the local time of the open occurrence.
+		let series_u = TaskSeriesForUpdate {
+			rrule_tz: Some("America/New_York".to_string()),
+			..Default::default()
+		};
+		TaskBmc::update_series(&ctx, &mm, id, series_u).await?;
+		let task = TaskBmc::get(&ctx, &mm, tasks[1].id).await?;
+		assert_eq!(
+			task.occurrence_at,
+			Some(OffsetDateTime::parse("2030-01-08T14:00:00Z", &Rfc3339)?)
+		);
+		assert_eq!(task.due_at, task.occurrence_at);
+
+		// -- Clean
+		for task in tasks {
+			TaskBmc::delete(&ctx, &mm, task.id).await?;
+		}
+
+		Ok(())
+	}
+
+	async fn test_recurring_err(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let fx_due_at = OffsetDateTime::parse("2030-01-07T09:00:00Z", &Rfc3339)?;
+		let task_c = |due_at, rrule: &str, rrule_tz: &str| TaskForCreate {
+			title: "test_recurring_err".to_string(),
+			due_at,
+			rrule: Some(rrule.to_string()),
+			rrule_tz: Some(rrule_tz.to_string()),
+			..Default::default()
+		};
+
+		let res = TaskBmc::create(&ctx, &mm, task_c(None, "FREQ=DAILY", "UTC")).await;
+		assert!(matches!(res, Err(Error::TaskRruleNoDueAt)));
+		let res = TaskBmc::create(&ctx, &mm, task_c(Some(fx_due_at), "FREQ=HOURLY", "UTC")).await;
+		assert!(matches!(
+			res,
+			Err(Error::TaskRruleInvalid {
+				reason: "FREQ not supported",
+				..
+			})
+		));
+		let res = TaskBmc::create(
+			&ctx,
+			&mm,
+			task_c(Some(fx_due_at), "FREQ=DAILY", "Mars/Base"),
+		)
+		.await;
+		assert!(matches!(res, Err(Error::TaskRruleTzUnknown { .. })));
+
+		// -- Not recurring.
+		let id = _dev_utils::seed_test(&ctx, &mm, &["test_recurring_err"]).await?[0].id;
+		let res = TaskBmc::update_series(&ctx, &mm, id, TaskSeriesForUpdate::default()).await;
+		assert!(matches!(res, Err(Error::TaskNotRecurring { id: err_id }) if err_id == id));
+		let res = TaskBmc::update_occurrence(&ctx, &mm, id, TaskForUpdate::default()).await;
+		assert!(matches!(res, Err(Error::TaskNotRecurring { .. })));
+
+		// -- Clean
+		TaskBmc::delete(&ctx, &mm, id).await?;
+
+		Ok(())
+	}
+
+	async fn test_assign_ok(mm: ModelManager) -> Result<()> {
+		// -- Setup & Fixtures
+		let ctx = Ctx::root_ctx();
+		let user_1 = _dev_utils::seed_user(&ctx, &mm, "test_assign_ok-01").await?;
+		let user_2 = _dev_utils::seed_user(&ctx, &mm, "test_assign_ok-02").await?;
+		let (ctx_1, ctx_2) = (Ctx::new(user_1)?, Ctx::new(user_2)?);
+		let id = _dev_utils::seed_test(&ctx, &mm, &["test_assign_ok"]).await?[0].id;
+		let fx_filter = || TaskFilter {
+			title: Some(OpValsString {
+				eq: Some("test_assign_ok".to_string()),
+				..Default::default()
+			}),
+			..Default::default()
+		};
+
+		// -- Exec & Check
+		// Any user can take an unassigned task.
+		TaskBmc::assign(&ctx_1, &mm, id, user_1).await?;
+		assert_eq!(TaskBmc::get(&ctx, &mm, id).await?.assignee_id, Some(user_1));
+		// The assignee can hand it over (and then access it).
+		TaskBmc::assign(&ctx_1, &mm, id, user_2).await?;
+		TaskBmc::get_accessible(&ctx_2, &mm, id).await?;
+
+		let tasks = TaskBmc::list_assigned_to_me(&ctx_2, &mm, Some(fx_filter()), None).await?;
+		assert_eq!(tasks.iter().map(|t| t.id).collect::<Vec<_>>(), [id]);
+		let tasks = TaskBmc::list_assigned_to_me(&ctx_1, &mm, Some(fx_filter()), None).await?;
+		assert!(tasks.is_empty());
+
+		TaskBmc::unassign(&ctx_2, &mm, id).await?;
+		assert_eq!(TaskBmc::get(&ctx, &mm, id).await?.assignee_id, None);
+
+		// -- Clean
+		TaskBmc::delete(&ctx, &mm, id).await?;
+
+		Ok(())
+	}
+
+	async fn test_assign_err(mm: ModelManager) -> Result<()> {
+		// -- Setup & Fixtures
+		let ctx = Ctx::root_ctx();
+		let user_1 = _dev_utils::seed_user(&ctx, &mm, "test_assign_err-01").await?;
+		let user_2 = _dev_utils::seed_user(&ctx, &mm, "test_assign_err-02").await?;
+		let ctx_2 = Ctx::new(user_2)?;
+		let id = _dev_utils::seed_test(&ctx, &mm, &["test_assign_err"]).await?[0].id;
+		TaskBmc::assign(&ctx, &mm, id, user_1).await?;
+
+		// -- Check
+		// Neither the creator, nor the assignee, nor an admin.
+		let result = TaskBmc::assign(&ctx_2, &mm, id, user_2).await;
+		assert!(matches!(
+			result,
+			Err(Error::TaskAssignDenied { user_id, .. }) if user_id == user_2
+		));
+		let result = TaskBmc::unassign(&ctx_2, &mm, id).await;
+		assert!(matches!(result, Err(Error::TaskAssignDenied { .. })));
+		let result = TaskBmc::get_accessible(&ctx_2, &mm, id).await;
+		assert!(matches!(result, Err(Error::TaskAccessDenied { .. })));
+
+		let result = TaskBmc::assign(&ctx, &mm, id, 9999).await;
+		assert!(matches!(
+			result,
+			Err(Error::EntityNotFound {
+				entity: "user",
+				id: 9999
+			})
+		));
+		assert_eq!(TaskBmc::get(&ctx, &mm, id).await?.assignee_id, Some(user_1));
+
+		// -- Clean
+		TaskBmc::delete(&ctx, &mm, id).await?;
+
+		Ok(())
+	}
+
+	async fn test_watch_ok(mm: ModelManager) -> Result<()> {
+		// -- Setup & Fixtures
+		let ctx = Ctx::root_ctx();
+		let user_1 = _dev_utils::seed_user(&ctx, &mm, "test_watch_ok-01").await?;
+		let user_2 = _dev_utils::seed_user(&ctx, &mm, "test_watch_ok-02").await?;
+		let (ctx_1, ctx_2) = (Ctx::new(user_1)?, Ctx::new(user_2)?);
+		let ids: Vec<i64> =
+			_dev_utils::seed_test(&ctx, &mm, &["test_watch_ok 01", "test_watch_ok 02"])
+				.await?
+				.iter()
+				.map(|t| t.id)
+				.collect();
+
+		// -- Exec
+		TaskBmc::watch(&ctx_1, &mm, ids[0]).await?;
+		TaskBmc::watch(&ctx_1, &mm, ids[0]).await?;
+		TaskBmc::watch(&ctx_2, &mm, ids[0]).await?;
+		TaskBmc::watch(&ctx_2, &mm, ids[1]).await?;
+
+		// -- Check
+		assert_eq!(
+			TaskBmc::list_watcher_ids(&ctx, &mm, ids[0]).await?,
+			[user_1, user_2]
+		);
+		let filter = TaskFilter {
+			title: Some(OpValsString {
+				starts_with: Some("test_watch_ok".to_string()),
+				..Default::default()
+			}),
+			..Default::default()
+		};
+		let tasks = TaskBmc::list_watched_by_me(&ctx_2, &mm, Some(filter), None).await?;
+		assert_eq!(tasks.iter().map(|t| t.id).collect::<Vec<_>>(), ids);
+
+		TaskBmc::unwatch(&ctx_1, &mm, ids[0]).await?;
+		assert_eq!(
+			TaskBmc::list_watcher_ids(&ctx, &mm, ids[0]).await?,
+			[user_2]
+		);
+
+		// -- Clean
+		for id in ids {
+			TaskBmc::delete(&ctx, &mm, id).await?;
+		}
+
+		Ok(())
+	}
+
+	async fn fx_occurrences(ctx: &Ctx, mm: &ModelManager, series_id: i64) -> Result<Vec<Task>> {
+		let filter = TaskFilter {
+			series_id: Some(OpValsInt64 {
+				eq: Some(series_id),
+				..Default::default()
+			}),
+			..Default::default()
+		};
+		let list_options = ListOptions {
+			order_bys: Some(vec![OrderBy::Asc("occurrence_at".to_string())]),
+			..Default::default()
+		};
+
+		Ok(TaskBmc::list(ctx, mm, Some(filter), Some(list_options)).await?)
+	}
+
+	async fn fx_ranked_ids(ctx: &Ctx, mm: &ModelManager, title_prefix: &str) -> Result<Vec<i64>> {
+		let filter = TaskFilter {
+			title: Some(OpValsString {
+				starts_with: Some(title_prefix.to_string()),
+				..Default::default()
+			}),
+			..Default::default()
+		};
+		let list_options = ListOptions {
+			order_bys: Some(vec![OrderBy::Asc("sort_rank".to_string())]),
+			..Default::default()
+		};
+		let tasks = TaskBmc::list(ctx, mm, Some(filter), Some(list_options)).await?;
+
+		Ok(tasks.into_iter().map(|t| t.id).collect())
+	}
 }
@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use sqlx::prelude::FromRow;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use time_tz::Tz;
+
+use crate::ctx::Ctx;
+use crate::model::error::{Error, Result};
+
+use super::common::DbBmc;
+use super::fields::Fields;
+use super::rrule::{self, RRule};
+use super::store::with_db;
+use super::{common, ModelManager};
+
+// Model: TaskSeries struct
+/// The schedule of a recurring task, and the template of its next occurrences
+/// (see `TaskBmc::update_series`).
+#[serde_as]
+#[derive(Clone, Debug, Serialize, FromRow, Fields)]
+pub struct TaskSeries {
+	pub id: i64,
+	/// RFC 5545 RRULE (e.g., `FREQ=WEEKLY;BYDAY=MO`, see `model::rrule`).
+	pub rrule: String,
+	/// IANA timezone of the occurrences (e.g., `Europe/Paris`).
+	pub rrule_tz: String,
+	/// The first occurrence. Its local time is the time of all the occurrences.
+	#[serde_as(as = "Rfc3339")]
+	pub dtstart: OffsetDateTime,
+
+	// -- Template of the next occurrences
+	pub title: String,
+	pub description: Option<String>,
+	pub priority: i16,
+	pub project_id: Option<i64>,
+
+	// -- Timestamps
+	pub cid: i64,
+	#[serde_as(as = "Rfc3339")]
+	pub ctime: OffsetDateTime,
+	pub mid: i64,
+	#[serde_as(as = "Rfc3339")]
+	pub mtime: OffsetDateTime,
+}
+
+// TaskSeries entity for creating method (see `TaskBmc::create`)
+#[derive(Fields)]
+pub(in crate::model) struct TaskSeriesForCreate {
+	pub rrule: String,
+	pub rrule_tz: String,
+	pub dtstart: OffsetDateTime,
+	pub title: String,
+	pub description: Option<String>,
+	pub priority: Option<i16>,
+	pub project_id: Option<i64>,
+}
+
+// TaskSeries entity for updating method (see `TaskBmc::update_series`)
+#[derive(Default, Deserialize, Fields)]
+pub struct TaskSeriesForUpdate {
+	pub title: Option<String>,
+	pub description: Option<String>,
+	pub priority: Option<i16>,
+	pub rrule: Option<String>,
+	pub rrule_tz: Option<String>,
+}
+
+impl TaskSeries {
+	/// The rule and timezone of the series.
+	pub(in crate::model) fn schedule(&self) -> Result<(RRule, &'static Tz)> {
+		parse_schedule(&self.rrule, &self.rrule_tz)
+	}
+}
+
+/// Fails with `Error::TaskRruleInvalid` or `Error::TaskRruleTzUnknown`.
+pub(in crate::model) fn parse_schedule(
+	rrule: &str,
+	rrule_tz: &str,
+) -> Result<(RRule, &'static Tz)> {
+	let rule = rrule.parse().map_err(|reason| Error::TaskRruleInvalid {
+		rrule: rrule.to_string(),
+		reason,
+	})?;
+	let tz = rrule::tz_by_name(rrule_tz).ok_or_else(|| Error::TaskRruleTzUnknown {
+		rrule_tz: rrule_tz.to_string(),
+	})?;
+
+	Ok((rule, tz))
+}
+
+// TaskSeries Backend Model Controller
+// Note: The series are created and updated through `TaskBmc`.
+pub struct TaskSeriesBmc;
+
+impl TaskSeriesBmc {
+	pub(in crate::model) async fn create(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		series_c: TaskSeriesForCreate,
+	) -> Result<i64> {
+		common::create::<Self, _>(ctx, mm, series_c).await
+	}
+
+	pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<TaskSeries> {
+		common::get::<Self, _>(ctx, mm, id).await
+	}
+
+	/// The series, locked until the end of the transaction
+	/// (e.g., so that its next occurrence is only created once).
+	pub(in crate::model) async fn get_for_update(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		id: i64,
+	) -> Result<TaskSeries> {
+		let sql = format!(
+			"SELECT * FROM task_series WHERE id = $1{}",
+			mm.backend().for_update()
+		);
+		let series = with_db!(ctx, mm, |db| sqlx::query_as(&sql)
+			.bind(id)
+			.fetch_optional(db)
+			.await?);
+
+		series.ok_or(Error::EntityNotFound {
+			entity: Self::TABLE,
+			id,
+		})
+	}
+}
+
+// Impl Trait Dbmc for TaskSeries model
+impl DbBmc for TaskSeriesBmc {
+	const TABLE: &'static str = "task_series";
+	const WORKSPACE_SCOPED: bool = true;
+}
@@ -0,0 +1,45 @@
+//! Background purge of the soft deleted rows (see `DbBmc::SOFT_DELETE`).
+//!
+//! - Rows deleted more than `SERVICE_TRASH_RETENTION_SEC` ago are permanently deleted.
+//! - The purge runs every `SERVICE_TRASH_PURGE_INTERVAL_SEC`.
+//! - The attachments of the purged tasks are deleted, with their content,
+//!   and the contents without attachment (see `AttachmentBmc::purge_orphans`).
+
+use std::time::Duration;
+
+use tracing::{debug, error};
+
+use crate::config::config;
+use crate::ctx::Ctx;
+use crate::model::attachment::AttachmentBmc;
+use crate::model::task::TaskBmc;
+use crate::model::{ModelManager, Result};
+use crate::utils::now_utc;
+
+/// Run the purge forever (to be spawned at startup).
+pub async fn purge_job(mm: ModelManager) {
+	let mut interval =
+		tokio::time::interval(Duration::from_secs_f64(config().TRASH_PURGE_INTERVAL_SEC));
+
+	loop {
+		interval.tick().await;
+
+		match purge(&mm).await {
+			Ok(count) => debug!(" {:<12} - purged {count} rows", "TRASH"),
+			Err(ex) => error!(" {:<12} - purge failed: {ex}", "TRASH"),
+		}
+	}
+}
+
+/// Purge the rows of all the soft delete tables, past the retention.
+/// Returns the number of purged rows.
+/// Note: With the root ctx, the purge is for all the workspaces.
+pub async fn purge(mm: &ModelManager) -> Result<u64> {
+	let ctx = Ctx::root_ctx();
+	let deleted_before = now_utc() - time::Duration::seconds_f64(config().TRASH_RETENTION_SEC);
+
+	let count = TaskBmc::purge_deleted(&ctx, mm, deleted_before).await?;
+	let count = count + AttachmentBmc::purge_orphans(&ctx, mm, now_utc()).await?;
+
+	Ok(count)
+}
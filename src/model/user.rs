@@ -2,20 +2,33 @@ use super::{Error, Result};
 use crate::crypt::{pwd, EncryptContent};
 use crate::ctx::Ctx;
 use serde::{Deserialize, Serialize};
-use sqlb::{Fields, HasFields};
-use sqlx::{postgres::PgRow, prelude::FromRow};
+use serde_with::serde_as;
+use sqlx::prelude::FromRow;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
 use tracing::debug;
 use uuid::Uuid;
 
 use super::{
 	common::{self, DbBmc},
+	fields::{Fields, HasFields},
+	store::{with_db, DbRow},
 	ModelManager,
 };
 
+#[serde_as]
 #[derive(Debug, Clone, Serialize, FromRow, Fields)]
 pub struct User {
 	pub id: i64,
 	pub username: String,
+
+	// -- Timestamps
+	pub cid: i64,
+	#[serde_as(as = "Rfc3339")]
+	pub ctime: OffsetDateTime,
+	pub mid: i64,
+	#[serde_as(as = "Rfc3339")]
+	pub mtime: OffsetDateTime,
 }
 
 // Entity for API Call
@@ -33,7 +46,7 @@ struct UserForInsert {
 	username: String,
 }
 
-#[derive(FromRow, Fields, Debug)]
+#[derive(Clone, FromRow, Fields, Debug)]
 pub struct UserForLogin {
 	pub id: i64,
 	pub username: String,
@@ -43,7 +56,7 @@ pub struct UserForLogin {
 	pub token_salt: Uuid,
 }
 
-#[derive(Debug, FromRow, Fields)]
+#[derive(Debug, Clone, FromRow, Fields)]
 pub struct UserForAuth {
 	pub id: i64,
 	pub username: String,
@@ -52,7 +65,7 @@ pub struct UserForAuth {
 	pub token_salt: Uuid,
 }
 
-pub trait UserBy: for<'r> FromRow<'r, PgRow> + Unpin + Send + HasFields {}
+pub trait UserBy: DbRow + Sync + Clone + HasFields + 'static {}
 
 impl UserBy for User {}
 impl UserBy for UserForLogin {}
@@ -62,9 +75,31 @@ pub struct UserBmc;
 
 impl DbBmc for UserBmc {
 	const TABLE: &'static str = "user";
+	// Note: To be read on every request, once `mw_ctx_resolve` resolves the user
+	//       of the auth token (it still uses a placeholder ctx).
+	const CACHED: bool = true;
 }
 
 impl UserBmc {
+	/// Note: Not audited, as the password updates (the row holds the password salts).
+	pub async fn create(ctx: &Ctx, mm: &ModelManager, user_c: UserForCreate) -> Result<i64> {
+		let UserForCreate { username, pwd } = user_c;
+		let mut fields = UserForInsert { username }.not_none_fields();
+		common::add_timestamps_for_create(&mut fields, ctx.user_id());
+
+		let txn_mm = common::own_txn(ctx, mm).await?;
+		let mm = txn_mm.as_ref().unwrap_or(mm);
+
+		let mut sql = common::insert_sql::<Self>(mm, &fields);
+		sql.push(" RETURNING \"id\"");
+		let (id,): (i64,) = with_db!(ctx, mm, |db| sql.query_as().fetch_one(db).await?);
+		Self::update_pwd(ctx, mm, id, &pwd).await?;
+
+		common::commit_own_txn(txn_mm).await?;
+
+		Ok(id)
+	}
+
 	pub async fn get<E>(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<E>
 	where
 		E: UserBy,
@@ -80,15 +115,32 @@ impl UserBmc {
 	where
 		E: UserBy,
 	{
-		let user = sqlb::select()
-			.table(Self::TABLE)
-			.columns(E::field_names())
-			.and_where("username", "=", username)
-			.fetch_optional(mm.db())
-			.await?;
+		let mut sql = common::select_sql::<Self>(mm, E::field_names());
+		sql.push(" AND \"username\" = ").push_bind(username);
+		let user = with_db!(ctx, mm, |db| sql.query_as().fetch_optional(db).await?);
 		Ok(user)
 	}
 
+	/// Fails with `Error::UserNotAdmin` unless the ctx user is an admin (or the root ctx).
+	pub async fn ensure_admin(ctx: &Ctx, mm: &ModelManager) -> Result<()> {
+		let user_id = ctx.user_id();
+		if user_id == 0 {
+			return Ok(());
+		}
+
+		let is_admin: Option<(bool,)> = with_db!(ctx, mm, |db| sqlx::query_as(
+			"SELECT \"is_admin\" FROM \"user\" WHERE \"id\" = $1"
+		)
+		.bind(user_id)
+		.fetch_optional(db)
+		.await?);
+
+		match is_admin {
+			Some((true,)) => Ok(()),
+			_ => Err(Error::UserNotAdmin { user_id }),
+		}
+	}
+
 	pub async fn update_pwd(ctx: &Ctx, mm: &ModelManager, id: i64, raw_pwd: &str) -> Result<()> {
 		let user: UserForLogin = Self::get(ctx, mm, id).await?;
 		let ec_content = EncryptContent {
@@ -98,12 +150,11 @@ impl UserBmc {
 
 		let pwd = pwd::encrypt_pwd(&ec_content)?;
 
-		let row_effected = sqlb::update()
-			.table(Self::TABLE)
-			.and_where("id", "=", id)
-			.data(vec![("pwd", pwd.to_string()).into()])
-			.exec(mm.db())
-			.await?;
+		let mut fields = vec![("pwd", pwd.to_string()).into()];
+		common::add_timestamps_for_update(&mut fields, ctx.user_id());
+
+		let sql = common::update_sql::<Self>(mm, &fields, id);
+		let row_effected = with_db!(ctx, mm, |db| sql.query().execute(db).await?.rows_affected());
 
 		if row_effected == 0 {
 			return Err(Error::EntityNotFound {
@@ -111,6 +162,121 @@ impl UserBmc {
 				id,
 			});
 		}
+		common::invalidate_cached::<Self>(mm, id);
+
+		Ok(())
+	}
+}
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	#![allow(unused)]
+	use crate::_dev_utils;
+
+	use super::*;
+	use anyhow::Result;
+	use serial_test::serial;
+
+	_dev_utils::test_backends!(
+		test_create_ok,
+		test_create_err_duplicate,
+		test_first_by_username_ok,
+		test_update_pwd_ok,
+		test_update_pwd_err_not_found,
+		test_ensure_admin,
+	);
+
+	async fn test_create_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let fx_username = "test_create_ok-user-01";
+
+		let id = _dev_utils::seed_user(&ctx, &mm, fx_username).await?;
+
+		let user: User = UserBmc::get(&ctx, &mm, id).await?;
+		assert_eq!(user.username, fx_username);
+		assert_eq!(user.cid, ctx.user_id());
+		let user: UserForLogin = UserBmc::get(&ctx, &mm, id).await?;
+		assert!(user.pwd.is_some());
+		assert_ne!(user.pwd_salt, user.token_salt);
+
+		Ok(())
+	}
+
+	async fn test_create_err_duplicate(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let fx_username = "test_create_err_duplicate-user-01";
+		_dev_utils::seed_user(&ctx, &mm, fx_username).await?;
+
+		let result = _dev_utils::seed_user(&ctx, &mm, fx_username).await;
+
+		assert!(matches!(result, Err(Error::SqlxError(_))));
+		let users: Option<User> =
+			UserBmc::first_by_username(&ctx, &mm, fx_username.to_string()).await?;
+		assert!(users.is_some());
+
+		Ok(())
+	}
+
+	async fn test_first_by_username_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+
+		let user: Option<UserForLogin> =
+			UserBmc::first_by_username(&ctx, &mm, "sau".to_string()).await?;
+		let user = user.ok_or(anyhow::anyhow!("user 'sau' not seeded"))?;
+		assert_eq!(user.id, 1000);
+		assert!(user.pwd.is_some());
+
+		let user: Option<UserForAuth> =
+			UserBmc::first_by_username(&ctx, &mm, "test_first_by_username_ok".to_string()).await?;
+		assert!(user.is_none());
+
+		Ok(())
+	}
+
+	async fn test_update_pwd_ok(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let id = _dev_utils::seed_user(&ctx, &mm, "test_update_pwd_ok-user-01").await?;
+		let user: UserForLogin = UserBmc::get(&ctx, &mm, id).await?;
+
+		UserBmc::update_pwd(&ctx, &mm, id, "test_update_pwd_ok pwd").await?;
+
+		let user_u: UserForLogin = UserBmc::get(&ctx, &mm, id).await?;
+		assert!(user_u.pwd.is_some());
+		assert_ne!(user_u.pwd, user.pwd);
+		assert_eq!(user_u.token_salt, user.token_salt);
+
+		Ok(())
+	}
+
+	async fn test_update_pwd_err_not_found(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let id = 100;
+
+		let result = UserBmc::update_pwd(&ctx, &mm, id, "test_update_pwd_err_not_found").await;
+
+		assert!(matches!(
+			result,
+			Err(Error::EntityNotFound {
+				entity: "user",
+				id: 100
+			})
+		));
+
+		Ok(())
+	}
+
+	async fn test_ensure_admin(mm: ModelManager) -> Result<()> {
+		let ctx = Ctx::root_ctx();
+		let user_id = _dev_utils::seed_user(&ctx, &mm, "test_ensure_admin-user-01").await?;
+
+		UserBmc::ensure_admin(&ctx, &mm).await?;
+		UserBmc::ensure_admin(&Ctx::new(1000)?, &mm).await?;
+		let result = UserBmc::ensure_admin(&Ctx::new(user_id)?, &mm).await;
+
+		assert!(matches!(result, Err(Error::UserNotAdmin { user_id: id }) if id == user_id));
+
 		Ok(())
 	}
 }
+// endregion: --- Tests
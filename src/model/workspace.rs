@@ -0,0 +1,499 @@
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use sqlx::prelude::FromRow;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::ctx::Ctx;
+use crate::model::error::{Error, Result};
+
+use super::common::DbBmc;
+use super::fields::{Fields, HasFields};
+use super::store::with_db;
+use super::user::{User, UserBmc};
+use super::{common, ModelManager};
+
+// Model: Workspace struct
+// Note: The tenant of the `DbBmc::WORKSPACE_SCOPED` rows.
+#[serde_as]
+#[derive(Clone, Debug, Serialize, FromRow, Fields)]
+pub struct Workspace {
+	pub id: i64,
+	pub name: String,
+
+	// -- Timestamps
+	pub cid: i64,
+	#[serde_as(as = "Rfc3339")]
+	pub ctime: OffsetDateTime,
+	pub mid: i64,
+	#[serde_as(as = "Rfc3339")]
+	pub mtime: OffsetDateTime,
+}
+
+// Workspace entity for creating method
+#[derive(Deserialize, Fields)]
+pub struct WorkspaceForCreate {
+	pub name: String,
+}
+
+// Workspace Backend Model Controller
+// Note: Except `create`, the methods fail with `Error::WorkspaceNotMember`
+//       unless the ctx user is a member of the workspace (or the root ctx).
+pub struct WorkspaceBmc;
+
+impl WorkspaceBmc {
+	/// Note: The ctx user is the first member.
+	pub async fn create(
+		ctx: &Ctx,
+		mm: &ModelManager,
+		workspace_c: WorkspaceForCreate,
+	) -> Result<i64> {
+		let txn_mm = common::own_txn(ctx, mm).await?;
+		let mm = txn_mm.as_ref().unwrap_or(mm);
+
+		let id = common::create::<Self, _>(ctx, mm, workspace_c).await?;
+		if ctx.user_id() != 0 {
+			UserBmc::get::<User>(ctx, mm, ctx.user_id()).await?;
+			Self::insert_member(ctx, mm, id, ctx.user_id()).await?;
+		}
+
+		common::commit_own_txn(txn_mm).await?;
+
+		Ok(id)
+	}
+
+	pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Workspace> {
+		Self::ensure_member(ctx, mm, id).await?;
+
+		common::get::<Self, _>(ctx, mm, id).await
+	}
+
+	/// The workspaces of the ctx user, oldest first.
+	pub async fn list_mine(ctx: &Ctx, mm: &ModelManager) -> Result<Vec<Workspace>> {
+		let columns = Workspace::field_names()
+			.iter()
+			.map(|name| format!("workspace.\"{name}\""))
+			.collect::<Vec<_>>()
+			.join(", ");
+		let sql = format!(
+			"SELECT {columns} FROM workspace \
+			 JOIN workspace_member ON workspace_member.workspace_id = workspace.id \
+			 WHERE workspace_member.user_id = $1 ORDER BY workspace.id"
+		);
+		let workspaces = with_db!(ctx, mm, |db| sqlx::query_as(&sql)
+			.bind(ctx.user_id())
+			.fetch_all(db)
+			.await?);
+
+		Ok(workspaces)
+	}
+
+	/// Note: Adding a member twice is not an error.
+	pub async fn add_member(ctx: &Ctx, mm: &ModelManager, id: i64, user_id: i64) -> Result<()> {
+		Self::ensure_member(ctx, mm, id).await?;
+		UserBmc::get::<User>(ctx, mm, user_id).await?;
+
+		Self::insert_member(ctx, mm, id, user_id).await
+	}
+
+	pub async fn remove_member(ctx: &Ctx, mm: &ModelManager, id: i64, user_id: i64) -> Result<()> {
+		Self::ensure_member(ctx, mm, id).await?;
+
+		with_db!(ctx, mm, |db| sqlx::query(
+			"DELETE FROM workspace_member WHERE workspace_id = $1 AND user_id = $2"
+		)
+		.bind(id)
+		.bind(user_id)
+		.execute(db)
+		.await?
+		.rows_affected());
+
+		Ok(())
+	}
+
+	pub async fn list_member_ids(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Vec<i64>> {
+		Self::ensure_member(ctx, mm, id).await?;
+
+		let user_ids: Vec<(i64,)> = with_db!(ctx, mm, |db| sqlx::query_as(
+			"SELECT user_id FROM workspace_member WHERE workspace_id = $1 ORDER BY user_id"
+		)
+		.bind(id)
+		.fetch_all(db)
+		.await?);
+
+		Ok(user_ids.into_iter().map(|(user_id,)| user_id).collect())
+	}
+
+	/// Note: For the ctx resolution (see `mw_ctx_resolve`), with the root ctx.
+	pub async fn is_member(ctx: &Ctx, mm: &ModelManager, id: i64, user_id: i64) -> Result<bool> {
+		let (is_member,): (bool,) = with_db!(ctx, mm, |db| {
+			sqlx::query_as(
+			"SELECT EXISTS (SELECT 1 FROM workspace_member WHERE workspace_id = $1 AND user_id = $2)"
+		)
+		.bind(id)
+		.bind(user_id)
+		.fetch_one(db)
+		.await?
+		});
+
+		Ok(is_member)
+	}
+
+	/// The workspace of the user when none is chosen: the oldest one, if any.
+	/// Note: For the ctx resolution (see `mw_ctx_resolve`), with the root ctx.
+	pub async fn default_id(ctx: &Ctx, mm: &ModelManager, user_id: i64) -> Result<Option<i64>> {
+		let id: Option<(i64,)> = with_db!(ctx, mm, |db| sqlx::query_as(
+			"SELECT workspace_id FROM workspace_member WHERE user_id = $1 \
+			 ORDER BY workspace_id LIMIT 1"
+		)
+		.bind(user_id)
+		.fetch_optional(db)
+		.await?);
+
+		Ok(id.map(|(id,)| id))
+	}
+
+	async fn ensure_member(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+		let user_id = ctx.user_id();
+		if user_id == 0 || Self::is_member(ctx, mm, id, user_id).await? {
+			return Ok(());
+		}
+
+		Err(Error::WorkspaceNotMember { id, user_id })
+	}
+
+	async fn insert_member(ctx: &Ctx, mm: &ModelManager, id: i64, user_id: i64) -> Result<()> {
+		with_db!(ctx, mm, |db| sqlx::query(
+			"INSERT INTO workspace_member (workspace_id, user_id) VALUES ($1, $2) \
+			 ON CONFLICT DO NOTHING"
+		)
+		.bind(id)
+		.bind(user_id)
+		.execute(db)
+		.await?
+		.rows_affected());
+
+		Ok(())
+	}
+}
+
+// Impl Trait Dbmc for Workspace model
+impl DbBmc for WorkspaceBmc {
+	const TABLE: &'static str = "workspace";
+}
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::_dev_utils;
+	use crate::model::comment::{CommentBmc, CommentForCreate};
+	use crate::model::project::{ProjectBmc, ProjectForCreate, ProjectForUpdate};
+	use crate::model::store::SqlValue;
+	use crate::model::tag::{TagBmc, TagForCreate};
+	use crate::model::task::{TaskBmc, TaskFilter, TaskForCreate};
+	use crate::model::OpValsInt64;
+	use anyhow::Result;
+	use serial_test::serial;
+
+	#[serial]
+	#[tokio::test]
+	async fn test_members_ok() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = _dev_utils::init_test().await;
+		let root_ctx = Ctx::root_ctx();
+		let user_1 = _dev_utils::seed_user(&root_ctx, &mm, "test_members_ok-user-01").await?;
+		let user_2 = _dev_utils::seed_user(&root_ctx, &mm, "test_members_ok-user-02").await?;
+		let (ctx_1, ctx_2) = (Ctx::new(user_1)?, Ctx::new(user_2)?);
+
+		// -- Exec
+		let id = WorkspaceBmc::create(
+			&ctx_1,
+			&mm,
+			WorkspaceForCreate {
+				name: "test_members_ok".to_string(),
+			},
+		)
+		.await?;
+
+		// -- Check
+		let result = WorkspaceBmc::get(&ctx_2, &mm, id).await;
+		assert!(matches!(
+			result,
+			Err(Error::WorkspaceNotMember { user_id, .. }) if user_id == user_2
+		));
+		let result = WorkspaceBmc::add_member(&ctx_2, &mm, id, user_2).await;
+		assert!(matches!(result, Err(Error::WorkspaceNotMember { .. })));
+		assert_eq!(
+			WorkspaceBmc::default_id(&root_ctx, &mm, user_2).await?,
+			None
+		);
+
+		WorkspaceBmc::add_member(&ctx_1, &mm, id, user_2).await?;
+		WorkspaceBmc::add_member(&ctx_1, &mm, id, user_2).await?;
+		assert_eq!(
+			WorkspaceBmc::list_member_ids(&ctx_2, &mm, id).await?,
+			[user_1, user_2]
+		);
+		assert_eq!(
+			WorkspaceBmc::get(&ctx_2, &mm, id).await?.name,
+			"test_members_ok"
+		);
+		let ids: Vec<i64> = WorkspaceBmc::list_mine(&ctx_2, &mm)
+			.await?
+			.into_iter()
+			.map(|w| w.id)
+			.collect();
+		assert_eq!(ids, [id]);
+		assert_eq!(
+			WorkspaceBmc::default_id(&root_ctx, &mm, user_2).await?,
+			Some(id)
+		);
+
+		WorkspaceBmc::remove_member(&ctx_1, &mm, id, user_2).await?;
+		assert!(!WorkspaceBmc::is_member(&root_ctx, &mm, id, user_2).await?);
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_scope_ok() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = _dev_utils::init_test().await;
+		let (ctx_1, ctx_2) = fx_tenant_ctxs(&mm, "test_scope_ok").await?;
+		let project_id = ProjectBmc::create(
+			&ctx_1,
+			&mm,
+			ProjectForCreate {
+				name: "test_scope_ok project".to_string(),
+			},
+		)
+		.await?;
+		let task_id = TaskBmc::create(
+			&ctx_1,
+			&mm,
+			TaskForCreate {
+				title: "test_scope_ok task".to_string(),
+				project_id: Some(project_id),
+				..Default::default()
+			},
+		)
+		.await?;
+
+		// -- Check
+		// The rows are in the workspace of their ctx.
+		let task = TaskBmc::get(&ctx_1, &mm, task_id).await?;
+		assert_eq!(task.workspace_id, ctx_1.workspace_id());
+		let tasks = TaskBmc::list(&ctx_1, &mm, None, None).await?;
+		assert_eq!(tasks.len(), 1);
+
+		// The other tenant can not read, update or delete them.
+		let result = TaskBmc::get(&ctx_2, &mm, task_id).await;
+		assert!(matches!(result, Err(Error::EntityNotFound { .. })));
+		let result = ProjectBmc::get(&ctx_2, &mm, project_id).await;
+		assert!(matches!(result, Err(Error::EntityNotFound { .. })));
+		let filter = TaskFilter {
+			id: Some(OpValsInt64 {
+				eq: Some(task_id),
+				..Default::default()
+			}),
+			..Default::default()
+		};
+		assert!(TaskBmc::list(&ctx_2, &mm, Some(filter), None)
+			.await?
+			.is_empty());
+		assert!(TaskBmc::list_page(&ctx_2, &mm, None, None)
+			.await?
+			.items
+			.is_empty());
+		assert!(TaskBmc::search(&ctx_2, &mm, "test_scope_ok", None)
+			.await?
+			.is_empty());
+		let project_u = ProjectForUpdate {
+			name: Some("test_scope_ok hacked".to_string()),
+		};
+		let result = ProjectBmc::update(&ctx_2, &mm, project_id, project_u).await;
+		assert!(matches!(result, Err(Error::EntityNotFound { .. })));
+		let result = ProjectBmc::delete(&ctx_2, &mm, project_id).await;
+		assert!(matches!(result, Err(Error::EntityNotFound { .. })));
+		let result = TaskBmc::delete(&ctx_2, &mm, task_id).await;
+		assert!(matches!(result, Err(Error::EntityNotFound { .. })));
+		let result = TaskBmc::delete_many(&ctx_2, &mm, &[task_id]).await?;
+		assert_eq!(result.not_found_ids, [task_id]);
+		let comment_c = CommentForCreate {
+			task_id,
+			content: "test_scope_ok comment".to_string(),
+		};
+		let result = CommentBmc::create(&ctx_2, &mm, comment_c).await;
+		assert!(matches!(result, Err(Error::EntityNotFound { .. })));
+
+		// Nor reference them.
+		let task_c = TaskForCreate {
+			title: "test_scope_ok other task".to_string(),
+			project_id: Some(project_id),
+			..Default::default()
+		};
+		let result = TaskBmc::create(&ctx_2, &mm, task_c).await;
+		assert!(matches!(result, Err(Error::SqlxError(_))));
+
+		// Nor a ctx without workspace.
+		let ctx_none = Ctx::new(ctx_1.user_id())?;
+		let result = TaskBmc::get(&ctx_none, &mm, task_id).await;
+		assert!(matches!(result, Err(Error::EntityNotFound { .. })));
+
+		// The rows are untouched.
+		assert_eq!(
+			ProjectBmc::get(&ctx_1, &mm, project_id).await?.name,
+			"test_scope_ok project"
+		);
+		TaskBmc::get(&ctx_1, &mm, task_id).await?;
+
+		Ok(())
+	}
+
+	#[serial]
+	#[tokio::test]
+	async fn test_rls_ok() -> Result<()> {
+		// -- Setup & Fixtures
+		let mm = _dev_utils::init_test().await;
+		let (ctx_1, ctx_2) = fx_tenant_ctxs(&mm, "test_rls_ok").await?;
+		let mut task_ids = Vec::new();
+		for ctx in [&ctx_1, &ctx_2] {
+			// Note: The Bmc calls work as usual under the row level security.
+			let txn_mm = mm.new_with_ctx_txn(ctx).await?;
+			let task_c = TaskForCreate {
+				title: "test_rls_ok task".to_string(),
+				..Default::default()
+			};
+			let task_id = TaskBmc::create(ctx, &txn_mm, task_c).await?;
+			TaskBmc::watch(ctx, &txn_mm, task_id).await?;
+			task_ids.push(task_id);
+			assert_eq!(TaskBmc::list(ctx, &txn_mm, None, None).await?.len(), 1);
+			txn_mm.commit().await?;
+		}
+		let (task_id_1, task_id_2) = (task_ids[0], task_ids[1]);
+
+		// -- Exec
+		// Queries missing their workspace condition (the bug RLS defends against).
+		let txn_mm = mm.new_with_ctx_txn(&ctx_1).await?;
+		let ids: Vec<(i64,)> =
+			with_db!(&ctx_1, txn_mm, |db| sqlx::query_as("SELECT id FROM task")
+				.fetch_all(db)
+				.await)?;
+		// The join tables, from the task of their rows.
+		let watched_ids: Vec<(i64,)> = with_db!(&ctx_1, txn_mm, |db| sqlx::query_as(
+			"SELECT task_id FROM task_watcher WHERE task_id = ANY($1)"
+		)
+		.bind(SqlValue::from(task_ids.as_slice()))
+		.fetch_all(db)
+		.await)?;
+		let updated = with_db!(&ctx_1, txn_mm, |db| sqlx::query(
+			"UPDATE task SET title = 'test_rls_ok hacked'"
+		)
+		.execute(db)
+		.await
+		.map(|res| res.rows_affected()))?;
+		let deleted = with_db!(&ctx_1, txn_mm, |db| sqlx::query(
+			"DELETE FROM task WHERE id = $1"
+		)
+		.bind(task_id_2)
+		.execute(db)
+		.await
+		.map(|res| res.rows_affected()))?;
+		let insert_result = with_db!(&ctx_1, txn_mm, |db| sqlx::query(
+			"INSERT INTO task (workspace_id, title, sort_rank, cid, ctime, mid, mtime) \
+			VALUES ($1, 'test_rls_ok other', 'a', 0, now(), 0, now())"
+		)
+		.bind(ctx_2.workspace_id())
+		.execute(db)
+		.await
+		.map(|res| res.rows_affected()));
+		txn_mm.rollback().await?;
+
+		// -- Check
+		assert_eq!(ids, [(task_id_1,)]);
+		assert_eq!(watched_ids, [(task_id_1,)]);
+		assert_eq!(updated, 1);
+		assert_eq!(deleted, 0);
+		// Note: 42501, the row violates the policy.
+		assert!(matches!(
+			insert_result,
+			Err(sqlx::Error::Database(db_err)) if db_err.code().as_deref() == Some("42501")
+		));
+
+		// Not for a ctx with the workspace of another user, nor without workspace.
+		for ctx in [
+			Ctx::new(ctx_1.user_id())?.with_workspace_id(ctx_2.workspace_id().unwrap_or(0)),
+			Ctx::new(ctx_1.user_id())?,
+		] {
+			let txn_mm = mm.new_with_ctx_txn(&ctx).await?;
+			let ids: Vec<(i64,)> = with_db!(&ctx, txn_mm, |db| sqlx::query_as(
+				"SELECT id FROM task WHERE id = ANY($1)"
+			)
+			.bind(SqlValue::from(task_ids.as_slice()))
+			.fetch_all(db)
+			.await)?;
+			assert!(ids.is_empty());
+		}
+
+		// Nor outside of a transaction, the pool connections having the login role.
+		let ids: Vec<(i64,)> = with_db!(&ctx_2, mm, |db| sqlx::query_as(
+			"SELECT id FROM task WHERE id = ANY($1)"
+		)
+		.bind(SqlValue::from(task_ids.as_slice()))
+		.fetch_all(db)
+		.await)?;
+		assert_eq!(ids, [(task_id_2,)]);
+
+		// The tags, per owner.
+		let tag_id = TagBmc::create(
+			&ctx_2,
+			&mm,
+			TagForCreate {
+				name: "test_rls_ok tag".to_string(),
+			},
+		)
+		.await?;
+		let tag_ids: Vec<(i64,)> = with_db!(&ctx_1, mm, |db| sqlx::query_as(
+			"SELECT id FROM tag WHERE id = $1"
+		)
+		.bind(tag_id)
+		.fetch_all(db)
+		.await)?;
+		assert!(tag_ids.is_empty());
+
+		// The root ctx bypasses it.
+		let root_ctx = Ctx::root_ctx();
+		let txn_mm = mm.new_with_ctx_txn(&root_ctx).await?;
+		let ids: Vec<(i64,)> = with_db!(&root_ctx, txn_mm, |db| sqlx::query_as(
+			"SELECT id FROM task WHERE id = ANY($1)"
+		)
+		.bind(SqlValue::from(task_ids.as_slice()))
+		.fetch_all(db)
+		.await)?;
+		assert_eq!(ids.len(), 2);
+
+		Ok(())
+	}
+
+	/// The ctxs of two users, each in their own new workspace.
+	async fn fx_tenant_ctxs(mm: &ModelManager, prefix: &str) -> Result<(Ctx, Ctx)> {
+		let root_ctx = Ctx::root_ctx();
+		let mut ctxs = Vec::new();
+		for name in ["01", "02"] {
+			let user_id = _dev_utils::seed_user(&root_ctx, mm, &format!("{prefix}-{name}")).await?;
+			let ctx = Ctx::new(user_id)?;
+			let workspace_c = WorkspaceForCreate {
+				name: format!("{prefix}-{name}"),
+			};
+			let workspace_id = WorkspaceBmc::create(&ctx, mm, workspace_c).await?;
+			ctxs.push(ctx.with_workspace_id(workspace_id));
+		}
+		let ctx_2 = ctxs.pop().unwrap_or_else(Ctx::root_ctx);
+		let ctx_1 = ctxs.pop().unwrap_or_else(Ctx::root_ctx);
+
+		Ok((ctx_1, ctx_2))
+	}
+}
+// endregion: --- Tests
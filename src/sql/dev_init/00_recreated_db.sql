@@ -3,11 +3,15 @@ SELECT pg_terminate_backend(pid) FROM pg_stat_activity
 WHERE usename = 'app_user' OR datname = 'app_db';
 DROP DATABASE IF EXISTS app_db;
 DROP USER IF EXISTS app_user;
+DROP ROLE IF EXISTS app_bypass;
 
 
 
 -- Initialize database and user
 CREATE USER app_user PASSWORD 'dev_only_pwd';
+-- The role of the root ctx (and of the pool connections), not subject to the row level security
+CREATE ROLE app_bypass NOLOGIN BYPASSRLS;
+GRANT app_bypass TO app_user;
 CREATE DATABASE app_db OWNER app_user ENCODING = 'UTF-8';
 
 
@@ -1 +1 @@
-INSERT INTO "user" (username) VALUES ('sau');
\ No newline at end of file
+INSERT INTO "user" (username, is_admin, cid, ctime, mid, mtime) VALUES ('sau', true, 0, now(), 0, now());
\ No newline at end of file
@@ -0,0 +1 @@
+INSERT OR IGNORE INTO "user" (username, is_admin, cid, ctime, mid, mtime) VALUES ('sau', true, 0, strftime('%Y-%m-%dT%H:%M:%f000Z'), 0, strftime('%Y-%m-%dT%H:%M:%f000Z'));
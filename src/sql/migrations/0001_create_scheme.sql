@@ -0,0 +1,388 @@
+--      Privileges of the bypass role (see store::set_local_ctx) on the app_user objects
+-- Note: The app_bypass role is created with the database (see dev_init/00_recreated_db.sql).
+ALTER DEFAULT PRIVILEGES GRANT SELECT, INSERT, UPDATE, DELETE ON TABLES TO app_bypass;
+ALTER DEFAULT PRIVILEGES GRANT USAGE, SELECT, UPDATE ON SEQUENCES TO app_bypass;
+
+--      User table
+CREATE TABLE "user" (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+
+    username VARCHAR(128) NOT NULL UNIQUE,
+
+    pwd varchar(128), 
+    pwd_salt uuid DEFAULT gen_random_uuid(),
+    token_salt uuid DEFAULT gen_random_uuid(),
+
+    -- Can read the audit trail
+    is_admin BOOLEAN NOT NULL DEFAULT false,
+
+    -- Timestamps
+    cid BIGINT NOT NULL,
+    ctime TIMESTAMP WITH TIME ZONE NOT NULL,
+    mid BIGINT NOT NULL,
+    mtime TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+--      Workspace table (tenant, see DbBmc::WORKSPACE_SCOPED)
+CREATE TABLE workspace (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+
+    name VARCHAR(128) NOT NULL,
+
+    -- Timestamps
+    cid BIGINT NOT NULL,
+    ctime TIMESTAMP WITH TIME ZONE NOT NULL,
+    mid BIGINT NOT NULL,
+    mtime TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+--      Workspace member table (the users who can use a workspace, see WorkspaceBmc)
+CREATE TABLE workspace_member (
+    workspace_id BIGINT NOT NULL REFERENCES workspace(id) ON DELETE CASCADE,
+    user_id BIGINT NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+
+    PRIMARY KEY (workspace_id, user_id)
+);
+
+CREATE INDEX workspace_member_user_id_idx ON workspace_member (user_id);
+
+--      Project table
+CREATE TABLE project (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+
+    -- The tenant (see DbBmc::WORKSPACE_SCOPED), NULL for the rows of a ctx without workspace
+    workspace_id BIGINT REFERENCES workspace(id),
+
+    name VARCHAR(256) NOT NULL,
+
+    -- Optimistic concurrency, bumped on every update
+    version BIGINT NOT NULL DEFAULT 0,
+
+    -- Timestamps
+    cid BIGINT NOT NULL,
+    ctime TIMESTAMP WITH TIME ZONE NOT NULL,
+    mid BIGINT NOT NULL,
+    mtime TIMESTAMP WITH TIME ZONE NOT NULL,
+
+    -- For the task foreign key, so that a task is in the workspace of its project.
+    CONSTRAINT project_id_workspace_id_key UNIQUE (id, workspace_id)
+);
+
+CREATE INDEX project_workspace_id_idx ON project (workspace_id);
+
+--      Task series table (recurring tasks, see TaskBmc::update_series)
+CREATE TABLE task_series (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+
+    -- The tenant (see DbBmc::WORKSPACE_SCOPED), NULL for the rows of a ctx without workspace
+    workspace_id BIGINT REFERENCES workspace(id),
+
+    -- RFC 5545 RRULE (see model::rrule), expanded in the local time of rrule_tz
+    rrule TEXT NOT NULL,
+    -- IANA timezone (e.g., Europe/Paris)
+    rrule_tz TEXT NOT NULL,
+    -- The first occurrence, its local time is the time of all the occurrences
+    dtstart TIMESTAMP WITH TIME ZONE NOT NULL,
+
+    -- Template of the next occurrences
+    title VARCHAR(256) NOT NULL,
+    description TEXT,
+    priority SMALLINT NOT NULL DEFAULT 0 CHECK (priority BETWEEN 0 AND 3),
+    project_id BIGINT REFERENCES project(id) ON DELETE SET NULL,
+
+    -- Timestamps
+    cid BIGINT NOT NULL,
+    ctime TIMESTAMP WITH TIME ZONE NOT NULL,
+    mid BIGINT NOT NULL,
+    mtime TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+--      Task table
+CREATE TYPE task_status AS ENUM ('open', 'in_progress', 'done', 'cancelled');
+
+CREATE TABLE task (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+
+    -- The tenant (see DbBmc::WORKSPACE_SCOPED), NULL for the rows of a ctx without workspace
+    workspace_id BIGINT REFERENCES workspace(id),
+
+    -- Note: No ON DELETE, the project delete policy is applied by ProjectBmc::delete.
+    project_id BIGINT REFERENCES project(id),
+    -- Subtasks, at any depth (see task_parent_no_cycle).
+    -- Note: The delete policy is applied by TaskBmc::delete, the purge of a task purges its subtasks.
+    parent_id BIGINT REFERENCES task(id) ON DELETE CASCADE,
+
+    title VARCHAR(256) NOT NULL,
+    description TEXT,
+    status task_status NOT NULL DEFAULT 'open',
+    -- From 0 (none) to 3 (highest)
+    priority SMALLINT NOT NULL DEFAULT 0 CHECK (priority BETWEEN 0 AND 3),
+    due_at TIMESTAMP WITH TIME ZONE,
+    -- Set by TaskBmc when the status moves to done
+    done_at TIMESTAMP WITH TIME ZONE,
+    -- Manual order (see TaskBmc::move_before), compared bytewise
+    sort_rank TEXT COLLATE "C" NOT NULL,
+    -- Recurring task, with the scheduled time of this occurrence
+    -- Note: occurrence_at is kept when due_at is edited.
+    series_id BIGINT REFERENCES task_series(id),
+    occurrence_at TIMESTAMP WITH TIME ZONE,
+    -- See TaskBmc::assign for who may assign.
+    assignee_id BIGINT REFERENCES "user"(id) ON DELETE SET NULL,
+
+    -- Optimistic concurrency, bumped on every update
+    version BIGINT NOT NULL DEFAULT 0,
+
+    -- Timestamps
+    cid BIGINT NOT NULL,
+    ctime TIMESTAMP WITH TIME ZONE NOT NULL,
+    mid BIGINT NOT NULL,
+    mtime TIMESTAMP WITH TIME ZONE NOT NULL,
+
+    -- Soft delete (NULL when not in the trash)
+    deleted_at TIMESTAMP WITH TIME ZONE,
+
+    -- Full-text search (see TaskBmc::search), the title weights more than the description
+    search_tsv TSVECTOR GENERATED ALWAYS AS (
+        setweight(to_tsvector('english', title), 'A') ||
+        setweight(to_tsvector('english', coalesce(description, '')), 'B')
+    ) STORED,
+
+    CONSTRAINT task_series_id_occurrence_at_key UNIQUE (series_id, occurrence_at),
+    -- The project and the parent are in the workspace of the task.
+    -- Note: Not checked for the rows without workspace (MATCH SIMPLE).
+    CONSTRAINT task_id_workspace_id_key UNIQUE (id, workspace_id),
+    CONSTRAINT task_project_id_workspace_id_fkey FOREIGN KEY (project_id, workspace_id)
+        REFERENCES project(id, workspace_id),
+    CONSTRAINT task_parent_id_workspace_id_fkey FOREIGN KEY (parent_id, workspace_id)
+        REFERENCES task(id, workspace_id) ON DELETE CASCADE,
+    CHECK ((series_id IS NULL) = (occurrence_at IS NULL))
+);
+
+CREATE INDEX task_workspace_id_idx ON task (workspace_id);
+CREATE INDEX task_project_id_idx ON task (project_id);
+CREATE INDEX task_parent_id_idx ON task (parent_id);
+CREATE INDEX task_sort_rank_idx ON task (sort_rank, id);
+CREATE INDEX task_deleted_at_idx ON task (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX task_search_tsv_idx ON task USING GIN (search_tsv);
+CREATE INDEX task_assignee_id_idx ON task (assignee_id);
+
+-- True when ancestor_id is the parent of task_id, at any depth.
+CREATE FUNCTION task_has_ancestor(task_id BIGINT, ancestor_id BIGINT) RETURNS BOOLEAN
+LANGUAGE sql STABLE AS $$
+    WITH RECURSIVE ancestors AS (
+        SELECT parent_id FROM task WHERE id = task_id
+        UNION
+        SELECT task.parent_id FROM task JOIN ancestors ON task.id = ancestors.parent_id
+    )
+    SELECT EXISTS (SELECT 1 FROM ancestors WHERE parent_id = ancestor_id)
+$$;
+
+-- A task can not be its own subtask, at any depth.
+-- Note: A trigger, not a check constraint, as it reads the other rows of the table.
+--       It only sees the committed moves, concurrent moves are serialized by TaskBmc::move_subtree.
+--       The error has the constraint name task_parent_no_cycle (see TaskBmc::move_subtree).
+CREATE FUNCTION task_parent_no_cycle() RETURNS TRIGGER
+LANGUAGE plpgsql AS $$
+BEGIN
+    IF NEW.parent_id = NEW.id OR task_has_ancestor(NEW.parent_id, NEW.id) THEN
+        RAISE EXCEPTION 'task % can not be a subtask of its subtask %', NEW.id, NEW.parent_id
+            USING ERRCODE = 'check_violation', CONSTRAINT = 'task_parent_no_cycle';
+    END IF;
+    RETURN NEW;
+END
+$$;
+
+CREATE TRIGGER task_parent_no_cycle BEFORE INSERT OR UPDATE OF parent_id ON task
+FOR EACH ROW WHEN (NEW.parent_id IS NOT NULL)
+EXECUTE FUNCTION task_parent_no_cycle();
+
+--      Tag table
+CREATE TABLE tag (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+
+    -- Note: Tags are per user, the owner is the creator.
+    owner_id BIGINT NOT NULL,
+    name VARCHAR(128) NOT NULL,
+
+    -- Timestamps
+    cid BIGINT NOT NULL,
+    ctime TIMESTAMP WITH TIME ZONE NOT NULL,
+    mid BIGINT NOT NULL,
+    mtime TIMESTAMP WITH TIME ZONE NOT NULL,
+
+    CONSTRAINT tag_owner_id_name_key UNIQUE (owner_id, name)
+);
+
+--      Task/Tag join table
+CREATE TABLE task_tag (
+    task_id BIGINT NOT NULL REFERENCES task(id) ON DELETE CASCADE,
+    tag_id BIGINT NOT NULL REFERENCES tag(id) ON DELETE CASCADE,
+
+    PRIMARY KEY (task_id, tag_id)
+);
+
+CREATE INDEX task_tag_tag_id_idx ON task_tag (tag_id);
+
+--      Task watcher table (users following a task, see TaskBmc::watch)
+CREATE TABLE task_watcher (
+    task_id BIGINT NOT NULL REFERENCES task(id) ON DELETE CASCADE,
+    user_id BIGINT NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+
+    PRIMARY KEY (task_id, user_id)
+);
+
+CREATE INDEX task_watcher_user_id_idx ON task_watcher (user_id);
+
+--      Comment table
+CREATE TABLE comment (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+
+    -- The tenant (see DbBmc::WORKSPACE_SCOPED), NULL for the rows of a ctx without workspace
+    workspace_id BIGINT REFERENCES workspace(id),
+
+    task_id BIGINT NOT NULL REFERENCES task(id) ON DELETE CASCADE,
+    -- Note: The author is the creator, only the author can edit or delete.
+    author_id BIGINT NOT NULL,
+    -- Markdown, with @username mentions
+    content TEXT NOT NULL,
+
+    -- Timestamps
+    cid BIGINT NOT NULL,
+    ctime TIMESTAMP WITH TIME ZONE NOT NULL,
+    mid BIGINT NOT NULL,
+    mtime TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX comment_task_id_idx ON comment (task_id);
+
+--      Mention table (users mentioned in a comment)
+CREATE TABLE mention (
+    comment_id BIGINT NOT NULL REFERENCES comment(id) ON DELETE CASCADE,
+    user_id BIGINT NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+
+    PRIMARY KEY (comment_id, user_id)
+);
+
+CREATE INDEX mention_user_id_idx ON mention (user_id);
+
+--      Attachment table (content in the blob store, see model::blob)
+CREATE TABLE attachment (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+
+    -- The tenant (see DbBmc::WORKSPACE_SCOPED), NULL for the rows of a ctx without workspace
+    workspace_id BIGINT REFERENCES workspace(id),
+
+    -- NULL once the task is purged, the content is then deleted by the trash purge.
+    task_id BIGINT REFERENCES task(id) ON DELETE SET NULL,
+    filename VARCHAR(256) NOT NULL,
+    -- Sniffed from the content, not the one declared on upload.
+    content_type VARCHAR(128) NOT NULL,
+    size BIGINT NOT NULL,
+    -- The key of the content in the blob store
+    blob_key VARCHAR(256) NOT NULL UNIQUE,
+
+    -- Timestamps
+    cid BIGINT NOT NULL,
+    ctime TIMESTAMP WITH TIME ZONE NOT NULL,
+    mid BIGINT NOT NULL,
+    mtime TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX attachment_task_id_idx ON attachment (task_id);
+
+--      Blob orphan table (see AttachmentBmc::purge_orphans)
+-- The blob store contents that may have no attachment: an upload not committed (yet),
+-- or the content of a deleted attachment. Deleted from the blob store by the trash purge,
+-- unless an attachment has it.
+CREATE TABLE blob_orphan (
+    blob_key VARCHAR(256) PRIMARY KEY,
+    -- Not purged before (e.g., while the upload may still be committed).
+    purge_after TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+--      Audit table (see model::audit)
+CREATE TYPE audit_op AS ENUM ('create', 'update', 'delete', 'restore');
+
+CREATE TABLE audit (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+
+    -- The DbBmc table and the id of the changed row
+    -- Note: No foreign key, the row can be deleted.
+    entity VARCHAR(64) NOT NULL,
+    entity_id BIGINT NOT NULL,
+    op audit_op NOT NULL,
+    -- {"<field>": {"old": .., "new": ..}} of the changed fields
+    diff JSONB NOT NULL,
+
+    -- The ctx user, and the web request (NULL outside of a web request)
+    actor_id BIGINT NOT NULL,
+    req_uuid UUID,
+    ctime TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX audit_entity_entity_id_idx ON audit (entity, entity_id);
+CREATE INDEX audit_actor_id_idx ON audit (actor_id);
+
+--      Row level security
+-- Defense in depth for the workspace scoped tables (see ModelManager::new_with_ctx_txn).
+-- True when the row of row_workspace_id is in the workspace of the transaction ctx,
+-- from the app.user_id and app.workspace_id settings (empty for a ctx without workspace).
+-- Note: No row without app.user_id. The bypass role (root ctx) is not subject to it.
+CREATE FUNCTION app_rls_visible(row_workspace_id BIGINT) RETURNS BOOLEAN
+LANGUAGE sql STABLE AS $$
+    SELECT coalesce(current_setting('app.user_id', true), '') <> ''
+        AND row_workspace_id IS NOT DISTINCT FROM
+            nullif(current_setting('app.workspace_id', true), '')::BIGINT
+        AND (row_workspace_id IS NULL OR EXISTS (
+            SELECT 1 FROM workspace_member
+            WHERE workspace_member.workspace_id = row_workspace_id
+                AND workspace_member.user_id = nullif(current_setting('app.user_id', true), '')::BIGINT
+        ))
+$$;
+
+-- Note: Forced, as app_user owns the tables.
+ALTER TABLE project ENABLE ROW LEVEL SECURITY;
+ALTER TABLE project FORCE ROW LEVEL SECURITY;
+CREATE POLICY project_workspace ON project USING (app_rls_visible(workspace_id));
+
+ALTER TABLE task_series ENABLE ROW LEVEL SECURITY;
+ALTER TABLE task_series FORCE ROW LEVEL SECURITY;
+CREATE POLICY task_series_workspace ON task_series USING (app_rls_visible(workspace_id));
+
+ALTER TABLE task ENABLE ROW LEVEL SECURITY;
+ALTER TABLE task FORCE ROW LEVEL SECURITY;
+CREATE POLICY task_workspace ON task USING (app_rls_visible(workspace_id));
+
+ALTER TABLE comment ENABLE ROW LEVEL SECURITY;
+ALTER TABLE comment FORCE ROW LEVEL SECURITY;
+CREATE POLICY comment_workspace ON comment USING (app_rls_visible(workspace_id));
+
+ALTER TABLE attachment ENABLE ROW LEVEL SECURITY;
+ALTER TABLE attachment FORCE ROW LEVEL SECURITY;
+CREATE POLICY attachment_workspace ON attachment USING (app_rls_visible(workspace_id));
+
+--      Row level security of the join tables
+-- Their rows are visible with the task (or the comment) they belong to,
+-- from the row level security of the task and comment tables.
+-- Note: Not the audit table (read by the admins only, with the root ctx).
+ALTER TABLE task_tag ENABLE ROW LEVEL SECURITY;
+ALTER TABLE task_tag FORCE ROW LEVEL SECURITY;
+CREATE POLICY task_tag_task ON task_tag
+    USING (EXISTS (SELECT 1 FROM task WHERE task.id = task_tag.task_id));
+
+ALTER TABLE task_watcher ENABLE ROW LEVEL SECURITY;
+ALTER TABLE task_watcher FORCE ROW LEVEL SECURITY;
+CREATE POLICY task_watcher_task ON task_watcher
+    USING (EXISTS (SELECT 1 FROM task WHERE task.id = task_watcher.task_id));
+
+ALTER TABLE mention ENABLE ROW LEVEL SECURITY;
+ALTER TABLE mention FORCE ROW LEVEL SECURITY;
+CREATE POLICY mention_comment ON mention
+    USING (EXISTS (SELECT 1 FROM comment WHERE comment.id = mention.comment_id));
+
+--      Row level security of the tag table
+-- Per owner, not per workspace: only the tags of the app.user_id ctx user.
+ALTER TABLE tag ENABLE ROW LEVEL SECURITY;
+ALTER TABLE tag FORCE ROW LEVEL SECURITY;
+CREATE POLICY tag_owner ON tag
+    USING (owner_id = nullif(current_setting('app.user_id', true), '')::BIGINT);
@@ -0,0 +1,17 @@
+-- Notify the updated and deleted rows of the cached tables (see DbBmc::CACHED),
+-- so that all the service instances invalidate them (see model::cache).
+-- Note: The notifications are sent on commit, with the '<table>:<id>' payload.
+CREATE FUNCTION notify_entity_changed() RETURNS TRIGGER
+LANGUAGE plpgsql AS $$
+BEGIN
+    PERFORM pg_notify('entity_changed', TG_TABLE_NAME || ':' || OLD.id);
+    RETURN NULL;
+END
+$$;
+
+CREATE TRIGGER user_entity_changed AFTER UPDATE OR DELETE ON "user"
+    FOR EACH ROW EXECUTE FUNCTION notify_entity_changed();
+
+-- The task rows are cached too (see TaskBmc, DbBmc::CACHED).
+CREATE TRIGGER task_entity_changed AFTER UPDATE OR DELETE ON task
+    FOR EACH ROW EXECUTE FUNCTION notify_entity_changed();
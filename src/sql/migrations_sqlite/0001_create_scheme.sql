@@ -0,0 +1,332 @@
+-- The SQLite schema, the same as the postgres one (src/sql/migrations/) with:
+-- - TIMESTAMP columns as fixed width UTC text (see store::SqlValue), so they compare as text.
+-- - The enums as TEXT with a CHECK, the uuids as 16 bytes BLOB, the JSONB as TEXT.
+-- - The full-text search of the tasks in the task_fts table (see TaskBmc::search).
+-- - No row level security (a single instance).
+-- Note: The ids start at 1000, as the postgres identities (see the sqlite_sequence insert).
+
+--      User table
+CREATE TABLE "user" (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+
+    username VARCHAR(128) NOT NULL UNIQUE,
+
+    pwd VARCHAR(128),
+    pwd_salt BLOB DEFAULT (randomblob(16)),
+    token_salt BLOB DEFAULT (randomblob(16)),
+
+    -- Can read the audit trail
+    is_admin BOOLEAN NOT NULL DEFAULT false,
+
+    -- Timestamps
+    cid BIGINT NOT NULL,
+    ctime TIMESTAMP NOT NULL,
+    mid BIGINT NOT NULL,
+    mtime TIMESTAMP NOT NULL
+);
+
+--      Workspace table (tenant, see DbBmc::WORKSPACE_SCOPED)
+CREATE TABLE workspace (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+
+    name VARCHAR(128) NOT NULL,
+
+    -- Timestamps
+    cid BIGINT NOT NULL,
+    ctime TIMESTAMP NOT NULL,
+    mid BIGINT NOT NULL,
+    mtime TIMESTAMP NOT NULL
+);
+
+--      Workspace member table (the users who can use a workspace, see WorkspaceBmc)
+CREATE TABLE workspace_member (
+    workspace_id BIGINT NOT NULL REFERENCES workspace(id) ON DELETE CASCADE,
+    user_id BIGINT NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+
+    PRIMARY KEY (workspace_id, user_id)
+);
+
+CREATE INDEX workspace_member_user_id_idx ON workspace_member (user_id);
+
+--      Project table
+CREATE TABLE project (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+
+    -- The tenant (see DbBmc::WORKSPACE_SCOPED), NULL for the rows of a ctx without workspace
+    workspace_id BIGINT REFERENCES workspace(id),
+
+    name VARCHAR(256) NOT NULL,
+
+    -- Optimistic concurrency, bumped on every update
+    version BIGINT NOT NULL DEFAULT 0,
+
+    -- Timestamps
+    cid BIGINT NOT NULL,
+    ctime TIMESTAMP NOT NULL,
+    mid BIGINT NOT NULL,
+    mtime TIMESTAMP NOT NULL,
+
+    -- For the task foreign key, so that a task is in the workspace of its project.
+    CONSTRAINT project_id_workspace_id_key UNIQUE (id, workspace_id)
+);
+
+CREATE INDEX project_workspace_id_idx ON project (workspace_id);
+
+--      Task series table (recurring tasks, see TaskBmc::update_series)
+CREATE TABLE task_series (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+
+    -- The tenant (see DbBmc::WORKSPACE_SCOPED), NULL for the rows of a ctx without workspace
+    workspace_id BIGINT REFERENCES workspace(id),
+
+    -- RFC 5545 RRULE (see model::rrule), expanded in the local time of rrule_tz
+    rrule TEXT NOT NULL,
+    -- IANA timezone (e.g., Europe/Paris)
+    rrule_tz TEXT NOT NULL,
+    -- The first occurrence, its local time is the time of all the occurrences
+    dtstart TIMESTAMP NOT NULL,
+
+    -- Template of the next occurrences
+    title VARCHAR(256) NOT NULL,
+    description TEXT,
+    priority SMALLINT NOT NULL DEFAULT 0 CHECK (priority BETWEEN 0 AND 3),
+    project_id BIGINT REFERENCES project(id) ON DELETE SET NULL,
+
+    -- Timestamps
+    cid BIGINT NOT NULL,
+    ctime TIMESTAMP NOT NULL,
+    mid BIGINT NOT NULL,
+    mtime TIMESTAMP NOT NULL
+);
+
+--      Task table
+CREATE TABLE task (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+
+    -- The tenant (see DbBmc::WORKSPACE_SCOPED), NULL for the rows of a ctx without workspace
+    workspace_id BIGINT REFERENCES workspace(id),
+
+    -- Note: No ON DELETE, the project delete policy is applied by ProjectBmc::delete.
+    project_id BIGINT REFERENCES project(id),
+    -- Subtasks, at any depth (see task_parent_no_cycle).
+    -- Note: The delete policy is applied by TaskBmc::delete, the purge of a task purges its subtasks.
+    parent_id BIGINT REFERENCES task(id) ON DELETE CASCADE,
+
+    title VARCHAR(256) NOT NULL,
+    description TEXT,
+    status TEXT NOT NULL DEFAULT 'open'
+        CHECK (status IN ('open', 'in_progress', 'done', 'cancelled')),
+    -- From 0 (none) to 3 (highest)
+    priority SMALLINT NOT NULL DEFAULT 0 CHECK (priority BETWEEN 0 AND 3),
+    due_at TIMESTAMP,
+    -- Set by TaskBmc when the status moves to done
+    done_at TIMESTAMP,
+    -- Manual order (see TaskBmc::move_before), compared bytewise (the BINARY collation)
+    sort_rank TEXT NOT NULL,
+    -- Recurring task, with the scheduled time of this occurrence
+    -- Note: occurrence_at is kept when due_at is edited.
+    series_id BIGINT REFERENCES task_series(id),
+    occurrence_at TIMESTAMP,
+    -- See TaskBmc::assign for who may assign.
+    assignee_id BIGINT REFERENCES "user"(id) ON DELETE SET NULL,
+
+    -- Optimistic concurrency, bumped on every update
+    version BIGINT NOT NULL DEFAULT 0,
+
+    -- Timestamps
+    cid BIGINT NOT NULL,
+    ctime TIMESTAMP NOT NULL,
+    mid BIGINT NOT NULL,
+    mtime TIMESTAMP NOT NULL,
+
+    -- Soft delete (NULL when not in the trash)
+    deleted_at TIMESTAMP,
+
+    CONSTRAINT task_series_id_occurrence_at_key UNIQUE (series_id, occurrence_at),
+    -- The project and the parent are in the workspace of the task.
+    -- Note: Not checked for the rows without workspace (as MATCH SIMPLE).
+    CONSTRAINT task_id_workspace_id_key UNIQUE (id, workspace_id),
+    CONSTRAINT task_project_id_workspace_id_fkey FOREIGN KEY (project_id, workspace_id)
+        REFERENCES project(id, workspace_id),
+    CONSTRAINT task_parent_id_workspace_id_fkey FOREIGN KEY (parent_id, workspace_id)
+        REFERENCES task(id, workspace_id) ON DELETE CASCADE,
+    CHECK ((series_id IS NULL) = (occurrence_at IS NULL))
+);
+
+CREATE INDEX task_workspace_id_idx ON task (workspace_id);
+CREATE INDEX task_project_id_idx ON task (project_id);
+CREATE INDEX task_parent_id_idx ON task (parent_id);
+CREATE INDEX task_sort_rank_idx ON task (sort_rank, id);
+CREATE INDEX task_deleted_at_idx ON task (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX task_assignee_id_idx ON task (assignee_id);
+
+-- A task can not be its own subtask, at any depth.
+-- Note: The error message is the name of the postgres constraint (see TaskBmc::move_subtree).
+CREATE TRIGGER task_parent_no_cycle BEFORE UPDATE OF parent_id ON task
+WHEN NEW.parent_id IS NOT NULL
+BEGIN
+    SELECT RAISE(ABORT, 'task_parent_no_cycle')
+    WHERE NEW.parent_id = NEW.id OR EXISTS (
+        WITH RECURSIVE ancestors(id) AS (
+            SELECT NEW.parent_id
+            UNION
+            SELECT task.parent_id FROM task JOIN ancestors ON task.id = ancestors.id
+            WHERE task.parent_id IS NOT NULL
+        )
+        SELECT 1 FROM ancestors WHERE ancestors.id = NEW.id
+    );
+END;
+
+-- Full-text search (see TaskBmc::search), the title weights more than the description
+-- (see the bm25 weights of the search).
+CREATE VIRTUAL TABLE task_fts USING fts5 (
+    title, description, content = 'task', content_rowid = 'id', tokenize = 'porter unicode61'
+);
+
+CREATE TRIGGER task_fts_insert AFTER INSERT ON task
+BEGIN
+    INSERT INTO task_fts (rowid, title, description) VALUES (NEW.id, NEW.title, NEW.description);
+END;
+
+CREATE TRIGGER task_fts_delete AFTER DELETE ON task
+BEGIN
+    INSERT INTO task_fts (task_fts, rowid, title, description)
+    VALUES ('delete', OLD.id, OLD.title, OLD.description);
+END;
+
+CREATE TRIGGER task_fts_update AFTER UPDATE OF title, description ON task
+BEGIN
+    INSERT INTO task_fts (task_fts, rowid, title, description)
+    VALUES ('delete', OLD.id, OLD.title, OLD.description);
+    INSERT INTO task_fts (rowid, title, description) VALUES (NEW.id, NEW.title, NEW.description);
+END;
+
+--      Tag table
+CREATE TABLE tag (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+
+    -- Note: Tags are per user, the owner is the creator.
+    owner_id BIGINT NOT NULL,
+    name VARCHAR(128) NOT NULL,
+
+    -- Timestamps
+    cid BIGINT NOT NULL,
+    ctime TIMESTAMP NOT NULL,
+    mid BIGINT NOT NULL,
+    mtime TIMESTAMP NOT NULL,
+
+    CONSTRAINT tag_owner_id_name_key UNIQUE (owner_id, name)
+);
+
+--      Task/Tag join table
+CREATE TABLE task_tag (
+    task_id BIGINT NOT NULL REFERENCES task(id) ON DELETE CASCADE,
+    tag_id BIGINT NOT NULL REFERENCES tag(id) ON DELETE CASCADE,
+
+    PRIMARY KEY (task_id, tag_id)
+);
+
+CREATE INDEX task_tag_tag_id_idx ON task_tag (tag_id);
+
+--      Task watcher table (users following a task, see TaskBmc::watch)
+CREATE TABLE task_watcher (
+    task_id BIGINT NOT NULL REFERENCES task(id) ON DELETE CASCADE,
+    user_id BIGINT NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+
+    PRIMARY KEY (task_id, user_id)
+);
+
+CREATE INDEX task_watcher_user_id_idx ON task_watcher (user_id);
+
+--      Comment table
+CREATE TABLE comment (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+
+    -- The tenant (see DbBmc::WORKSPACE_SCOPED), NULL for the rows of a ctx without workspace
+    workspace_id BIGINT REFERENCES workspace(id),
+
+    task_id BIGINT NOT NULL REFERENCES task(id) ON DELETE CASCADE,
+    -- Note: The author is the creator, only the author can edit or delete.
+    author_id BIGINT NOT NULL,
+    -- Markdown, with @username mentions
+    content TEXT NOT NULL,
+
+    -- Timestamps
+    cid BIGINT NOT NULL,
+    ctime TIMESTAMP NOT NULL,
+    mid BIGINT NOT NULL,
+    mtime TIMESTAMP NOT NULL
+);
+
+CREATE INDEX comment_task_id_idx ON comment (task_id);
+
+--      Mention table (users mentioned in a comment)
+CREATE TABLE mention (
+    comment_id BIGINT NOT NULL REFERENCES comment(id) ON DELETE CASCADE,
+    user_id BIGINT NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+
+    PRIMARY KEY (comment_id, user_id)
+);
+
+CREATE INDEX mention_user_id_idx ON mention (user_id);
+
+--      Attachment table (content in the blob store, see model::blob)
+CREATE TABLE attachment (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+
+    -- The tenant (see DbBmc::WORKSPACE_SCOPED), NULL for the rows of a ctx without workspace
+    workspace_id BIGINT REFERENCES workspace(id),
+
+    -- NULL once the task is purged, the content is then deleted by the trash purge.
+    task_id BIGINT REFERENCES task(id) ON DELETE SET NULL,
+    filename VARCHAR(256) NOT NULL,
+    -- Sniffed from the content, not the one declared on upload.
+    content_type VARCHAR(128) NOT NULL,
+    size BIGINT NOT NULL,
+    -- The key of the content in the blob store
+    blob_key VARCHAR(256) NOT NULL UNIQUE,
+
+    -- Timestamps
+    cid BIGINT NOT NULL,
+    ctime TIMESTAMP NOT NULL,
+    mid BIGINT NOT NULL,
+    mtime TIMESTAMP NOT NULL
+);
+
+CREATE INDEX attachment_task_id_idx ON attachment (task_id);
+
+--      Blob orphan table (see AttachmentBmc::purge_orphans)
+-- The blob store contents that may have no attachment: an upload not committed (yet),
+-- or the content of a deleted attachment. Deleted from the blob store by the trash purge,
+-- unless an attachment has it.
+CREATE TABLE blob_orphan (
+    blob_key VARCHAR(256) PRIMARY KEY,
+    -- Not purged before (e.g., while the upload may still be committed).
+    purge_after TIMESTAMP NOT NULL
+);
+
+--      Audit table (see model::audit)
+CREATE TABLE audit (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+
+    -- The DbBmc table and the id of the changed row
+    -- Note: No foreign key, the row can be deleted.
+    entity VARCHAR(64) NOT NULL,
+    entity_id BIGINT NOT NULL,
+    op TEXT NOT NULL CHECK (op IN ('create', 'update', 'delete', 'restore')),
+    -- {"<field>": {"old": .., "new": ..}} of the changed fields, as json text
+    diff TEXT NOT NULL,
+
+    -- The ctx user, and the web request (NULL outside of a web request)
+    actor_id BIGINT NOT NULL,
+    req_uuid BLOB,
+    ctime TIMESTAMP NOT NULL
+);
+
+CREATE INDEX audit_entity_entity_id_idx ON audit (entity, entity_id);
+CREATE INDEX audit_actor_id_idx ON audit (actor_id);
+
+--      Ids from 1000
+INSERT INTO sqlite_sequence (name, seq) VALUES
+    ('user', 999), ('workspace', 999), ('project', 999), ('task_series', 999), ('task', 999),
+    ('tag', 999), ('comment', 999), ('attachment', 999), ('audit', 999);
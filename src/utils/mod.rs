@@ -0,0 +1,11 @@
+// region:    --- Modules
+pub mod sql_script;
+// endregion: --- Modules
+
+use time::OffsetDateTime;
+
+// region:    --- Time
+pub fn now_utc() -> OffsetDateTime {
+	OffsetDateTime::now_utc()
+}
+// endregion: --- Time
@@ -0,0 +1,324 @@
+//! Run sql scripts (e.g., the dev init files, the migrations), statement by statement.
+//!
+//! - The script is split on the `;` outside of the string literals, quoted identifiers,
+//!   dollar-quoted bodies (e.g., `$$ ... $$`) and comments.
+//! - The statements of a SQLite trigger body (`CREATE TRIGGER ... BEGIN ...; END`)
+//!   are part of the trigger statement.
+//! - The statements with only whitespaces and comments are skipped.
+//! - A failing statement is reported with the script name and its line number.
+
+use futures_util::future::BoxFuture;
+use serde::Serialize;
+use sqlx::Executor;
+
+// region:    --- Error
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug, Serialize)]
+pub enum Error {
+	StatementFailed {
+		name: String,
+		line: usize,
+		cause: String,
+	},
+}
+
+impl core::fmt::Display for Error {
+	fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
+		write!(fmt, "{self:?}")
+	}
+}
+
+impl std::error::Error for Error {}
+// endregion: --- Error
+
+/// A statement of a script, without its `;`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Statement<'a> {
+	pub sql: &'a str,
+	/// The line of its first character (1-based).
+	pub line: usize,
+}
+
+/// Execute the statements of `script` in order, stopping at the first failing one.
+/// `name` is for the error (e.g., the file name).
+/// Note: Not in a transaction, unless `conn` is one.
+/// Note: With `Executor::execute` on the connection (a simple query), rather than
+///       `sqlx::raw_sql(..).execute(&mut *conn)`, for the future to be `Send`.
+/// Note: The connection of any backend (e.g., `PgConnection`, `SqliteConnection`),
+///       with a boxed future, as the compiler can not prove an `async fn` future `Send`
+///       through the higher-ranked executor bound.
+pub fn exec_script<'a, C>(
+	conn: &'a mut C,
+	name: &'a str,
+	script: &'a str,
+) -> BoxFuture<'a, Result<()>>
+where
+	C: Send,
+	for<'c> &'c mut C: Executor<'c>,
+{
+	Box::pin(async move {
+		for Statement { sql, line } in split_statements(script) {
+			(&mut *conn)
+				.execute(sql)
+				.await
+				.map_err(|ex| Error::StatementFailed {
+					name: name.to_string(),
+					line,
+					cause: ex.to_string(),
+				})?;
+		}
+
+		Ok(())
+	})
+}
+
+pub fn split_statements(script: &str) -> Vec<Statement<'_>> {
+	let bytes = script.as_bytes();
+	let mut statements = Vec::new();
+	// The offset and line of the first character of the current statement.
+	let mut start: Option<(usize, usize)> = None;
+	let mut line = 1;
+	let mut i = 0;
+
+	// Note: All the delimiters are ascii, so the offsets are always on char boundaries.
+	while i < bytes.len() {
+		let end = match bytes[i] {
+			b';' => {
+				// Note: Within a trigger body, the `;` ends a statement of the body.
+				if let Some((start, _)) = start {
+					if is_in_trigger_body(&script[start..i]) {
+						i += 1;
+						continue;
+					}
+				}
+				if let Some((start, line)) = start.take() {
+					let sql = script[start..i].trim_end();
+					statements.push(Statement { sql, line });
+				}
+				i += 1;
+				continue;
+			}
+			b'-' if bytes.get(i + 1) == Some(&b'-') => {
+				let end = find(bytes, i, b"\n").unwrap_or(bytes.len());
+				i = end;
+				continue;
+			}
+			b'/' if bytes.get(i + 1) == Some(&b'*') => {
+				let end = block_comment_end(bytes, i);
+				line += count_lines(&bytes[i..end]);
+				i = end;
+				continue;
+			}
+			b if b.is_ascii_whitespace() => {
+				line += count_lines(&bytes[i..i + 1]);
+				i += 1;
+				continue;
+			}
+			b'\'' => quoted_end(bytes, i, b'\'', is_escape_string(bytes, i)),
+			b'"' => quoted_end(bytes, i, b'"', false),
+			b'$' => dollar_quoted_end(bytes, i).unwrap_or(i + 1),
+			_ => i + 1,
+		};
+
+		if start.is_none() {
+			start = Some((i, line));
+		}
+		line += count_lines(&bytes[i..end]);
+		i = end;
+	}
+
+	// -- The last statement, without `;`.
+	if let Some((start, line)) = start {
+		let sql = script[start..].trim_end();
+		statements.push(Statement { sql, line });
+	}
+
+	statements
+}
+
+// region:    --- Support
+/// A SQLite `CREATE TRIGGER ... BEGIN ...` statement, before the `END` of its body.
+/// Note: The words are matched in the whole statement (e.g., also in the strings),
+///       so a statement of the body must not end with `END` (e.g., a `CASE`).
+fn is_in_trigger_body(sql: &str) -> bool {
+	let words: Vec<String> = sql
+		.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+		.filter(|word| !word.is_empty())
+		.map(|word| word.to_ascii_uppercase())
+		.collect();
+
+	let is_trigger = words.first().is_some_and(|word| word == "CREATE")
+		&& words.iter().take(3).any(|word| word == "TRIGGER");
+	is_trigger
+		&& words.iter().any(|word| word == "BEGIN")
+		&& words.last().is_some_and(|word| word != "END")
+}
+
+fn is_ident_char(b: u8) -> bool {
+	b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || !b.is_ascii()
+}
+
+fn count_lines(bytes: &[u8]) -> usize {
+	bytes.iter().filter(|b| **b == b'\n').count()
+}
+
+fn find(bytes: &[u8], from: usize, pattern: &[u8]) -> Option<usize> {
+	bytes[from..]
+		.windows(pattern.len())
+		.position(|window| window == pattern)
+		.map(|pos| from + pos)
+}
+
+/// `E'...'`, where a backslash escapes the next character.
+fn is_escape_string(bytes: &[u8], quote: usize) -> bool {
+	quote > 0
+		&& matches!(bytes[quote - 1], b'E' | b'e')
+		&& (quote < 2 || !is_ident_char(bytes[quote - 2]))
+}
+
+/// The offset after the closing quote (doubled to be escaped), or the end of the script.
+fn quoted_end(bytes: &[u8], open: usize, quote: u8, backslash_escapes: bool) -> usize {
+	let mut i = open + 1;
+	while i < bytes.len() {
+		match bytes[i] {
+			b'\\' if backslash_escapes => i += 2,
+			b if b == quote && bytes.get(i + 1) == Some(&quote) => i += 2,
+			b if b == quote => return i + 1,
+			_ => i += 1,
+		}
+	}
+
+	bytes.len()
+}
+
+/// The offset after the closing `$tag$`, or the end of the script.
+/// `None` when the `$` does not open a dollar-quoted string (e.g., `$1`, `a$b`).
+fn dollar_quoted_end(bytes: &[u8], open: usize) -> Option<usize> {
+	if open > 0 && is_ident_char(bytes[open - 1]) {
+		return None;
+	}
+	if bytes.get(open + 1).is_some_and(u8::is_ascii_digit) {
+		return None;
+	}
+
+	let mut i = open + 1;
+	while i < bytes.len() && bytes[i] != b'$' {
+		if !is_ident_char(bytes[i]) {
+			return None;
+		}
+		i += 1;
+	}
+	if i == bytes.len() {
+		return None;
+	}
+
+	let tag = &bytes[open..=i];
+	let end = find(bytes, i + 1, tag).map_or(bytes.len(), |pos| pos + tag.len());
+
+	Some(end)
+}
+
+/// The offset after the closing `*/`, or the end of the script.
+/// Note: The block comments nest.
+fn block_comment_end(bytes: &[u8], open: usize) -> usize {
+	let mut depth = 0;
+	let mut i = open;
+	while i < bytes.len() {
+		match (bytes[i], bytes.get(i + 1)) {
+			(b'/', Some(b'*')) => {
+				depth += 1;
+				i += 2;
+			}
+			(b'*', Some(b'/')) => {
+				depth -= 1;
+				i += 2;
+				if depth == 0 {
+					return i;
+				}
+			}
+			_ => i += 1,
+		}
+	}
+
+	bytes.len()
+}
+// endregion: --- Support
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use anyhow::Result;
+
+	#[test]
+	fn test_split_statements_ok() -> Result<()> {
+		let fx_script = "\
+-- The users; with a comment
+CREATE TABLE \"a;b\" (id BIGINT);
+
+INSERT INTO \"a;b\" VALUES (1); INSERT INTO t VALUES ('it''s; ok', E'\\'; ok');
+/* a block; /* nested; */ comment */
+CREATE FUNCTION f(x BIGINT) RETURNS BIGINT LANGUAGE sql AS $$
+    SELECT x + 1; -- not the end
+$$;
+CREATE FUNCTION g() RETURNS TEXT LANGUAGE sql AS $body$ SELECT '$$;' $body$;
+SELECT $1, a$b;
+;  -- empty
+SELECT 'last'";
+
+		let statements = split_statements(fx_script);
+
+		let expected = [
+			(2, "CREATE TABLE \"a;b\" (id BIGINT)"),
+			(4, "INSERT INTO \"a;b\" VALUES (1)"),
+			(4, "INSERT INTO t VALUES ('it''s; ok', E'\\'; ok')"),
+			(
+				6,
+				"CREATE FUNCTION f(x BIGINT) RETURNS BIGINT LANGUAGE sql AS $$\n    SELECT x + 1; -- not the end\n$$",
+			),
+			(
+				9,
+				"CREATE FUNCTION g() RETURNS TEXT LANGUAGE sql AS $body$ SELECT '$$;' $body$",
+			),
+			(10, "SELECT $1, a$b"),
+			(12, "SELECT 'last'"),
+		];
+		let statements: Vec<(usize, &str)> = statements.iter().map(|s| (s.line, s.sql)).collect();
+		assert_eq!(statements, expected);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_split_statements_trigger() -> Result<()> {
+		let fx_script = "\
+CREATE TRIGGER t_no_cycle BEFORE UPDATE ON t
+BEGIN
+    SELECT RAISE(ABORT, 't_no_cycle') WHERE NEW.id = NEW.parent_id;
+    DELETE FROM u WHERE id = NEW.id;
+END;
+CREATE TRIGGER t_changed AFTER UPDATE ON t FOR EACH ROW EXECUTE FUNCTION f();
+SELECT 1;";
+
+		let statements = split_statements(fx_script);
+
+		let statements: Vec<usize> = statements.iter().map(|s| s.line).collect();
+		assert_eq!(statements, [1, 6, 7]);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_split_statements_empty() -> Result<()> {
+		for fx_script in ["", " \n ", ";;", "-- only a comment;\n", "/* ; */ ;"] {
+			assert!(
+				split_statements(fx_script).is_empty(),
+				"script: {fx_script:?}"
+			);
+		}
+
+		Ok(())
+	}
+}
+// endregion: --- Tests
@@ -17,8 +17,19 @@ pub enum Error {
 	// -- CtxExtError
 	CtxExt(web::mw_auth::CtxExtError),
 
+	// -- RPC
+	RpcMethodUnknown(String),
+	RpcMissingParams { rpc_method: String },
+	RpcFailJsonParams { rpc_method: String },
+	IfMatchInvalid(String),
+	IfMatchNotSupported { rpc_method: String },
+	IfMatchFailed { entity: &'static str, id: i64 },
+
 	// Model
 	ModelError(model::Error),
+
+	// -- External Modules
+	SerdeJson(String),
 }
 
 // region:    --- Model Error
@@ -29,6 +40,14 @@ impl From<model::Error> for Error {
 }
 // endregion: --- Model Error
 
+// region:    --- External Errors
+impl From<serde_json::Error> for Error {
+	fn from(value: serde_json::Error) -> Self {
+		Self::SerdeJson(value.to_string())
+	}
+}
+// endregion: --- External Errors
+
 // region:    --- Axum IntoResponse
 impl IntoResponse for Error {
 	fn into_response(self) -> Response {
@@ -70,8 +89,74 @@ impl Error {
 			| LoginFailUsernameNotFound => (StatusCode::FORBIDDEN, ClientError::LOGIN_FAIL),
 
 			// -- Auth
+			CtxExt(web::mw_auth::CtxExtError::WorkspaceNotMember(_)) => {
+				(StatusCode::FORBIDDEN, ClientError::WORKSPACE_NOT_MEMBER)
+			}
+			CtxExt(web::mw_auth::CtxExtError::WorkspaceIdInvalid) => {
+				(StatusCode::BAD_REQUEST, ClientError::INVALID_PARAMS)
+			}
 			CtxExt(_) => (StatusCode::FORBIDDEN, ClientError::NO_AUTH),
 
+			// -- RPC
+			RpcMethodUnknown(_)
+			| RpcMissingParams { .. }
+			| RpcFailJsonParams { .. }
+			| IfMatchInvalid(_)
+			| IfMatchNotSupported { .. } => (StatusCode::BAD_REQUEST, ClientError::INVALID_PARAMS),
+			IfMatchFailed { .. } => (
+				StatusCode::PRECONDITION_FAILED,
+				ClientError::PRECONDITION_FAILED,
+			),
+
+			// -- Model
+			ModelError(model::Error::EntityNotFound { .. }) => {
+				(StatusCode::BAD_REQUEST, ClientError::ENTITY_NOT_FOUND)
+			}
+			ModelError(model::Error::VersionConflict { .. }) => {
+				(StatusCode::CONFLICT, ClientError::VERSION_CONFLICT)
+			}
+			ModelError(model::Error::UserNotAdmin { .. }) => {
+				(StatusCode::FORBIDDEN, ClientError::USER_NOT_ADMIN)
+			}
+			ModelError(model::Error::WorkspaceNotMember { .. }) => {
+				(StatusCode::FORBIDDEN, ClientError::WORKSPACE_NOT_MEMBER)
+			}
+			ModelError(model::Error::CommentNotAuthor { .. }) => {
+				(StatusCode::FORBIDDEN, ClientError::COMMENT_NOT_AUTHOR)
+			}
+			ModelError(model::Error::ProjectHasTasks { .. }) => {
+				(StatusCode::CONFLICT, ClientError::PROJECT_HAS_TASKS)
+			}
+			ModelError(model::Error::TaskHasSubtasks { .. }) => {
+				(StatusCode::CONFLICT, ClientError::TASK_HAS_SUBTASKS)
+			}
+			ModelError(model::Error::TaskAccessDenied { .. }) => {
+				(StatusCode::FORBIDDEN, ClientError::TASK_ACCESS_DENIED)
+			}
+			ModelError(model::Error::TaskAssignDenied { .. }) => {
+				(StatusCode::FORBIDDEN, ClientError::TASK_ASSIGN_DENIED)
+			}
+			ModelError(model::Error::AttachmentTooLarge { .. }) => (
+				StatusCode::PAYLOAD_TOO_LARGE,
+				ClientError::ATTACHMENT_TOO_LARGE,
+			),
+			ModelError(model::Error::TagNameAlreadyExists { .. }) => {
+				(StatusCode::CONFLICT, ClientError::TAG_NAME_ALREADY_EXISTS)
+			}
+			ModelError(
+				model::Error::BulkLimitExceeded { .. }
+				| model::Error::TaskParentCycle { .. }
+				| model::Error::TaskNotRecurring { .. }
+				| model::Error::TaskRruleInvalid { .. }
+				| model::Error::TaskRruleTzUnknown { .. }
+				| model::Error::TaskRruleNoDueAt
+				| model::Error::AttachmentFilenameInvalid { .. }
+				| model::Error::TagMergeIntoSelf { .. }
+				| model::Error::ListOrderByUnknownColumn(_)
+				| model::Error::ListCursorInvalid
+				| model::Error::ListCursorOrderByMismatch { .. },
+			) => (StatusCode::BAD_REQUEST, ClientError::INVALID_PARAMS),
+
 			// -- Fallback.
 			_ => (
 				StatusCode::INTERNAL_SERVER_ERROR,
@@ -86,6 +171,19 @@ impl Error {
 pub enum ClientError {
 	LOGIN_FAIL,
 	NO_AUTH,
+	ENTITY_NOT_FOUND,
+	VERSION_CONFLICT,
+	PRECONDITION_FAILED,
+	INVALID_PARAMS,
+	USER_NOT_ADMIN,
+	WORKSPACE_NOT_MEMBER,
+	COMMENT_NOT_AUTHOR,
+	PROJECT_HAS_TASKS,
+	TASK_HAS_SUBTASKS,
+	TASK_ACCESS_DENIED,
+	TASK_ASSIGN_DENIED,
+	ATTACHMENT_TOO_LARGE,
+	TAG_NAME_ALREADY_EXISTS,
 	SERVICE_ERROR,
 }
 // endregion: --- Client Error
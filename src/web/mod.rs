@@ -3,8 +3,10 @@
 mod error;
 pub mod mw_auth;
 pub mod mw_res_map;
+pub mod routes_attachment;
 pub mod routes_login;
 pub mod routes_static;
+pub mod rpc;
 
 pub use self::error::ClientError;
 pub use self::error::{Error, Result};
@@ -12,3 +14,6 @@ pub use self::error::{Error, Result};
 // endregion: --- Modules
 
 pub const AUTH_TOKEN: &str = "auth-token";
+
+/// The workspace of the request (see `mw_ctx_resolve`).
+pub const WORKSPACE_HEADER: &str = "x-workspace-id";
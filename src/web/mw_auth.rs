@@ -1,19 +1,20 @@
 use crate::ctx::Ctx;
+use crate::model::workspace::WorkspaceBmc;
 use crate::model::ModelManager;
-use crate::web::AUTH_TOKEN;
 use crate::web::{Error, Result};
+use crate::web::{AUTH_TOKEN, WORKSPACE_HEADER};
 use async_trait::async_trait;
 use axum::extract::{FromRequestParts, State};
 use axum::http::request::Parts;
-use axum::http::Request;
+use axum::http::{HeaderMap, Request};
 use axum::middleware::Next;
 use axum::response::Response;
 use serde::Serialize;
 use tower_cookies::{Cookie, Cookies};
+use uuid::Uuid;
 
 use tracing::debug;
 
-#[allow(dead_code)] // For now, until we have the rpc.
 pub async fn mw_ctx_require<B>(
 	ctx: Result<Ctx>,
 	req: Request<B>,
@@ -27,7 +28,7 @@ pub async fn mw_ctx_require<B>(
 }
 
 pub async fn mw_ctx_resolve<B>(
-	_mm: State<ModelManager>,
+	mm: State<ModelManager>,
 	cookies: Cookies,
 	mut req: Request<B>,
 	next: Next<B>,
@@ -37,19 +38,59 @@ pub async fn mw_ctx_resolve<B>(
 	let _auth_token = cookies.get(AUTH_TOKEN).map(|c| c.value().to_string());
 
 	// FIXME - Compute real CtxAuthResult<Ctx>.
-	let result_ctx = Ctx::new(100).map_err(|ex| CtxExtError::CtxCreateFail(ex.to_string()));
+	// Note: The request uuid is the one of the request log line (see `mw_reponse_map`).
+	let result_ctx = Ctx::new(100)
+		.map(|ctx| ctx.with_req_uuid(Uuid::new_v4()))
+		.map_err(|ex| CtxExtError::CtxCreateFail(ex.to_string()));
 
 	// Remove the cookie if something went wrong other than NoAuthTokenCookie.
 	if result_ctx.is_err() && !matches!(result_ctx, Err(CtxExtError::TokenNotInCookie)) {
 		cookies.remove(Cookie::named(AUTH_TOKEN))
 	}
 
+	let result_ctx = match result_ctx {
+		Ok(ctx) => resolve_workspace(&mm, req.headers(), ctx).await,
+		Err(ex) => Err(ex),
+	};
+
 	// Store the ctx_result in the request extension.
 	req.extensions_mut().insert(result_ctx);
 
 	Ok(next.run(req).await)
 }
 
+/// The ctx in the workspace of the `X-Workspace-Id` header (the user must be a member),
+/// else in the default workspace of the user, if any (see `WorkspaceBmc::default_id`).
+async fn resolve_workspace(mm: &ModelManager, headers: &HeaderMap, ctx: Ctx) -> CtxExtResult {
+	let root_ctx = Ctx::root_ctx();
+	let user_id = ctx.user_id();
+
+	let workspace_id = match headers.get(WORKSPACE_HEADER) {
+		Some(val) => {
+			let id = val
+				.to_str()
+				.ok()
+				.and_then(|val| val.parse::<i64>().ok())
+				.ok_or(CtxExtError::WorkspaceIdInvalid)?;
+			let is_member = WorkspaceBmc::is_member(&root_ctx, mm, id, user_id)
+				.await
+				.map_err(|ex| CtxExtError::ModelAccessError(ex.to_string()))?;
+			if !is_member {
+				return Err(CtxExtError::WorkspaceNotMember(id));
+			}
+			Some(id)
+		}
+		None => WorkspaceBmc::default_id(&root_ctx, mm, user_id)
+			.await
+			.map_err(|ex| CtxExtError::ModelAccessError(ex.to_string()))?,
+	};
+
+	Ok(match workspace_id {
+		Some(workspace_id) => ctx.with_workspace_id(workspace_id),
+		None => ctx,
+	})
+}
+
 // region:    --- Ctx Extractor
 #[async_trait]
 impl<S: Send + Sync> FromRequestParts<S> for Ctx {
@@ -76,5 +117,8 @@ pub enum CtxExtError {
 	TokenNotInCookie,
 	CtxNotInRequestExt,
 	CtxCreateFail(String),
+	ModelAccessError(String),
+	WorkspaceIdInvalid,
+	WorkspaceNotMember(i64),
 }
 // endregion: --- Ctx Extractor Result/Error
@@ -17,7 +17,11 @@ pub async fn mw_reponse_map(
 	res: Response,
 ) -> Response {
 	debug!(" {:<12} - mw_reponse_map", "RES_MAPPER");
-	let uuid = Uuid::new_v4();
+	// Note: Same uuid as the ctx one, if any, so that the log line matches the audit trail.
+	let uuid = ctx
+		.as_ref()
+		.and_then(Ctx::req_uuid)
+		.unwrap_or_else(Uuid::new_v4);
 
 	// -- Get the eventual response error.
 	let web_error = res.extensions().get::<web::Error>();
@@ -0,0 +1,122 @@
+//! Streaming upload and download of the attachment contents
+//! (the attachments are listed and deleted with the rpc methods).
+//!
+//! - `POST /api/tasks/:task_id/attachments?filename=<name>` with the content as body.
+//! - `GET /api/attachments/:id/content`
+
+use crate::config::config;
+use crate::ctx::Ctx;
+use crate::model::attachment::{Attachment, AttachmentBmc, AttachmentForCreate};
+use crate::model::{self, ModelManager};
+use crate::web::Result;
+use axum::body::StreamBody;
+use axum::extract::{BodyStream, Path, Query, State};
+use axum::http::header::{
+	CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_SECURITY_POLICY, CONTENT_TYPE,
+	X_CONTENT_TYPE_OPTIONS,
+};
+use axum::http::HeaderMap;
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures_util::TryStreamExt;
+use serde::Deserialize;
+use std::io;
+use tracing::debug;
+
+pub fn routes(mm: ModelManager) -> Router {
+	Router::new()
+		.route("/tasks/:task_id/attachments", post(upload_handler))
+		.route("/attachments/:id/content", get(download_handler))
+		.with_state(mm)
+}
+
+#[derive(Deserialize)]
+struct UploadParams {
+	filename: String,
+}
+
+async fn upload_handler(
+	State(mm): State<ModelManager>,
+	ctx: Ctx,
+	Path(task_id): Path<i64>,
+	Query(UploadParams { filename }): Query<UploadParams>,
+	headers: HeaderMap,
+	body: BodyStream,
+) -> Result<Json<Attachment>> {
+	debug!(" {:<12} - upload_handler", "HANDLER");
+
+	// Note: Rejected before reading the body when it is known to be too large.
+	//       Otherwise, the limit is checked while streaming.
+	let size_max = config().ATTACHMENT_SIZE_MAX;
+	let content_length = headers
+		.get(CONTENT_LENGTH)
+		.and_then(|val| val.to_str().ok())
+		.and_then(|val| val.parse::<u64>().ok());
+	if matches!(content_length, Some(len) if len > size_max) {
+		return Err(model::Error::AttachmentTooLarge { max: size_max }.into());
+	}
+
+	// Note: The body is stored before the transaction is opened,
+	//       so that a slow upload does not hold a pool connection.
+	let body = body.map_err(|ex| io::Error::new(io::ErrorKind::Other, ex));
+	let attachment_c = AttachmentForCreate { task_id, filename };
+	let content =
+		AttachmentBmc::put_content(&ctx, &mm, attachment_c, Box::pin(body), size_max).await?;
+
+	// Note: With the row level security of the ctx (see `rpc_handler`).
+	let mm = mm.new_with_ctx_txn(&ctx).await?;
+	let id = AttachmentBmc::create_from_content(&ctx, &mm, content).await?;
+	let attachment = AttachmentBmc::get(&ctx, &mm, id).await?;
+	mm.commit().await?;
+
+	Ok(Json(attachment))
+}
+
+async fn download_handler(
+	State(mm): State<ModelManager>,
+	ctx: Ctx,
+	Path(id): Path<i64>,
+) -> Result<impl IntoResponse> {
+	debug!(" {:<12} - download_handler", "HANDLER");
+
+	// Note: The content is streamed after the commit (not in the transaction).
+	let mm = mm.new_with_ctx_txn(&ctx).await?;
+	let (attachment, content) = AttachmentBmc::get_content(&ctx, &mm, id).await?;
+	mm.commit().await?;
+
+	// Note: Always downloaded, never rendered by the browser
+	//       (e.g., an uploaded html page is not run in the app origin).
+	let headers = [
+		(CONTENT_TYPE, attachment.content_type),
+		(CONTENT_LENGTH, attachment.size.to_string()),
+		(
+			CONTENT_DISPOSITION,
+			content_disposition(&attachment.filename),
+		),
+		(X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+		(CONTENT_SECURITY_POLICY, "sandbox".to_string()),
+	];
+
+	Ok((headers, StreamBody::new(content)))
+}
+
+/// `attachment`, with the filename as ascii fallback and utf-8 (RFC 6266).
+fn content_disposition(filename: &str) -> String {
+	let ascii: String = filename
+		.chars()
+		.map(|c| match c {
+			'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' | ' ' => c,
+			_ => '_',
+		})
+		.collect();
+	let encoded: String = filename
+		.bytes()
+		.map(|b| match b {
+			b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'-' | b'_' => (b as char).to_string(),
+			_ => format!("%{b:02X}"),
+		})
+		.collect();
+
+	format!("attachment; filename=\"{ascii}\"; filename*=UTF-8''{encoded}")
+}
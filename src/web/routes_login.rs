@@ -78,3 +78,66 @@ struct LoginPayload {
 	username: String,
 	pwd: String,
 }
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	#![allow(unused)]
+	use crate::_dev_utils;
+
+	use super::*;
+	use anyhow::Result;
+	use axum::body::Body;
+	use axum::http::{header, Request, StatusCode};
+	use tower::ServiceExt;
+	use tower_cookies::CookieManagerLayer;
+
+	// Note: On an in-memory `ModelManager` of their own, so without `#[serial]`.
+
+	#[tokio::test]
+	async fn test_api_login_ok() -> Result<()> {
+		let mm = _dev_utils::init_test_sqlite().await;
+		let app = routes(mm).layer(CookieManagerLayer::new());
+
+		let res = app.oneshot(fx_login_req("sau", "sau")?).await?;
+
+		assert_eq!(res.status(), StatusCode::OK);
+		let cookie = res
+			.headers()
+			.get(header::SET_COOKIE)
+			.and_then(|val| val.to_str().ok())
+			.unwrap_or_default();
+		assert!(cookie.starts_with(&format!("{}=", web::AUTH_TOKEN)));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_api_login_err_username() -> Result<()> {
+		let mm = _dev_utils::init_test_sqlite().await;
+		let app = routes(mm).layer(CookieManagerLayer::new());
+
+		let res = app
+			.oneshot(fx_login_req("test_api_login_err_username", "sau")?)
+			.await?;
+
+		assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+		assert!(matches!(
+			res.extensions().get::<Error>(),
+			Some(Error::LoginFailUsernameNotFound)
+		));
+		assert!(res.headers().get(header::SET_COOKIE).is_none());
+
+		Ok(())
+	}
+
+	fn fx_login_req(username: &str, pwd: &str) -> Result<Request<Body>> {
+		let body = json!({ "username": username, "pwd": pwd });
+		let req = Request::post("/api/login")
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(Body::from(body.to_string()))?;
+
+		Ok(req)
+	}
+}
+// endregion: --- Tests
@@ -0,0 +1,42 @@
+use crate::ctx::Ctx;
+use crate::model::attachment::{Attachment, AttachmentBmc};
+use crate::model::ModelManager;
+use crate::web::Result;
+
+use super::ParamsIded;
+
+// Note: The contents are uploaded and downloaded with `routes_attachment`.
+
+/// `id` is the id of the task.
+pub async fn list_task_attachments(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsIded,
+) -> Result<Vec<Attachment>> {
+	let ParamsIded { id } = params;
+
+	let attachments = AttachmentBmc::list_by_task(&ctx, &mm, id).await?;
+
+	Ok(attachments)
+}
+
+pub async fn get_attachment(ctx: Ctx, mm: ModelManager, params: ParamsIded) -> Result<Attachment> {
+	let ParamsIded { id } = params;
+
+	let attachment = AttachmentBmc::get(&ctx, &mm, id).await?;
+
+	Ok(attachment)
+}
+
+pub async fn delete_attachment(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsIded,
+) -> Result<Attachment> {
+	let ParamsIded { id } = params;
+
+	let attachment = AttachmentBmc::get(&ctx, &mm, id).await?;
+	AttachmentBmc::delete(&ctx, &mm, id).await?;
+
+	Ok(attachment)
+}
@@ -0,0 +1,23 @@
+use crate::ctx::Ctx;
+use crate::model::audit::{Audit, AuditBmc, AuditFilter};
+use crate::model::ModelManager;
+use crate::web::Result;
+
+use super::ParamsList;
+
+/// The audit trail (e.g., by `entity` and `entity_id`, or by `actor_id`).
+/// Note: Admin only (see `UserBmc::ensure_admin`).
+pub async fn list_audits(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsList<AuditFilter>,
+) -> Result<Vec<Audit>> {
+	let ParamsList {
+		filters,
+		list_options,
+	} = params;
+
+	let audits = AuditBmc::list(&ctx, &mm, filters, list_options).await?;
+
+	Ok(audits)
+}
@@ -0,0 +1,14 @@
+use serde_json::Value;
+
+use crate::ctx::Ctx;
+use crate::model::user::UserBmc;
+use crate::model::{CacheStats, ModelManager};
+use crate::web::Result;
+
+/// The hits and misses of the entity cache of this instance.
+/// Note: Admin only (see `UserBmc::ensure_admin`).
+pub async fn get_cache_stats(ctx: Ctx, mm: ModelManager, _params: Value) -> Result<CacheStats> {
+	UserBmc::ensure_admin(&ctx, &mm).await?;
+
+	Ok(mm.cache_stats())
+}
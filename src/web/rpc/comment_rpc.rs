@@ -0,0 +1,67 @@
+use crate::ctx::Ctx;
+use crate::model::comment::{
+	Comment, CommentBmc, CommentFilter, CommentForCreate, CommentForUpdate,
+};
+use crate::model::ModelManager;
+use crate::web::Result;
+
+use super::{ParamsForCreate, ParamsForUpdate, ParamsIded, ParamsList};
+
+pub async fn create_comment(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsForCreate<CommentForCreate>,
+) -> Result<Comment> {
+	let ParamsForCreate { data } = params;
+
+	let id = CommentBmc::create(&ctx, &mm, data).await?;
+	let comment = CommentBmc::get(&ctx, &mm, id).await?;
+
+	Ok(comment)
+}
+
+pub async fn list_comments(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsList<CommentFilter>,
+) -> Result<Vec<Comment>> {
+	let ParamsList {
+		filters,
+		list_options,
+	} = params;
+
+	let comments = CommentBmc::list(&ctx, &mm, filters, list_options).await?;
+
+	Ok(comments)
+}
+
+pub async fn get_comment(ctx: Ctx, mm: ModelManager, params: ParamsIded) -> Result<Comment> {
+	let ParamsIded { id } = params;
+
+	let comment = CommentBmc::get(&ctx, &mm, id).await?;
+
+	Ok(comment)
+}
+
+pub async fn update_comment(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsForUpdate<CommentForUpdate>,
+) -> Result<Comment> {
+	// Note: Comments are not versioned.
+	let ParamsForUpdate { id, data, .. } = params;
+
+	CommentBmc::update(&ctx, &mm, id, data).await?;
+	let comment = CommentBmc::get(&ctx, &mm, id).await?;
+
+	Ok(comment)
+}
+
+pub async fn delete_comment(ctx: Ctx, mm: ModelManager, params: ParamsIded) -> Result<Comment> {
+	let ParamsIded { id } = params;
+
+	let comment = CommentBmc::get(&ctx, &mm, id).await?;
+	CommentBmc::delete(&ctx, &mm, id).await?;
+
+	Ok(comment)
+}
@@ -0,0 +1,608 @@
+// region:    --- Modules
+
+mod attachment_rpc;
+mod audit_rpc;
+mod cache_rpc;
+mod comment_rpc;
+mod project_rpc;
+mod tag_rpc;
+mod task_rpc;
+mod workspace_rpc;
+
+use crate::ctx::Ctx;
+use crate::model::{self, ListOptions, ModelManager, PageOptions};
+use crate::web::{Error, Result};
+use axum::extract::State;
+use axum::http::{header, HeaderMap, HeaderValue};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::{from_value, json, to_value, Value};
+use tracing::debug;
+
+use self::attachment_rpc::{delete_attachment, get_attachment, list_task_attachments};
+use self::audit_rpc::list_audits;
+use self::cache_rpc::get_cache_stats;
+use self::comment_rpc::{
+	create_comment, delete_comment, get_comment, list_comments, update_comment,
+};
+use self::project_rpc::{
+	create_project, delete_project, get_project, list_projects, update_project,
+};
+use self::tag_rpc::{create_tag, delete_tag, list_tags, merge_tags, rename_tag};
+use self::task_rpc::{
+	add_task_tags, assign_task, create_task, create_tasks, delete_task, delete_tasks, get_task,
+	get_task_series, get_task_subtree, get_task_with_tags, list_deleted_tasks,
+	list_my_assigned_tasks, list_my_watched_tasks, list_task_rollups, list_task_watchers,
+	list_tasks, list_tasks_page, list_tasks_with_tags, move_task, move_task_after,
+	move_task_before, remove_task_tags, restore_task, search_tasks, set_task_tags, unassign_task,
+	unwatch_task, update_task, update_task_occurrence, update_task_series, update_tasks,
+	update_tasks_each, watch_task,
+};
+use self::workspace_rpc::{
+	add_workspace_member, create_workspace, list_my_workspaces, list_workspace_members,
+	remove_workspace_member,
+};
+
+// endregion: --- Modules
+
+// region:    --- RPC Types
+
+/// JSON-RPC Request Body
+#[derive(Deserialize)]
+struct RpcRequest {
+	id: Option<Value>,
+	method: String,
+	params: Option<Value>,
+}
+
+#[derive(Deserialize)]
+pub struct ParamsForCreate<D> {
+	data: D,
+}
+
+#[derive(Deserialize)]
+pub struct ParamsForUpdate<D> {
+	id: i64,
+	/// The expected version, for the versioned entities (e.g., task, project).
+	/// Set from the `If-Match` header when present.
+	version: Option<i64>,
+	data: D,
+}
+
+#[derive(Deserialize)]
+pub struct ParamsIded {
+	id: i64,
+}
+
+#[derive(Deserialize)]
+pub struct ParamsForDelete {
+	id: i64,
+	/// The expected version, for the versioned entities (e.g., task, project).
+	/// Set from the `If-Match` header when present.
+	version: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct ParamsIds {
+	ids: Vec<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct ParamsForCreateMany<D> {
+	data: Vec<D>,
+}
+
+/// The same `data` for all the `ids`.
+#[derive(Deserialize)]
+pub struct ParamsForUpdateMany<D> {
+	ids: Vec<i64>,
+	data: D,
+}
+
+/// One `data` per id.
+#[derive(Deserialize)]
+pub struct ParamsForUpdateEach<D> {
+	items: Vec<ParamsIdedData<D>>,
+}
+
+#[derive(Deserialize)]
+pub struct ParamsIdedData<D> {
+	id: i64,
+	data: D,
+}
+
+#[derive(Deserialize)]
+pub struct ParamsList<F> {
+	filters: Option<F>,
+	list_options: Option<ListOptions>,
+}
+
+#[derive(Deserialize)]
+pub struct ParamsPage<F> {
+	filters: Option<F>,
+	page_options: Option<PageOptions>,
+}
+
+// endregion: --- RPC Types
+
+/// The rpc methods with the `version` param, set from the `If-Match` header
+/// (rejected for the other methods, rather than ignored).
+const IF_MATCH_METHODS: &[&str] = &[
+	"update_project",
+	"delete_project",
+	"update_task",
+	"delete_task",
+];
+
+pub fn routes(mm: ModelManager) -> Router {
+	Router::new()
+		.route("/rpc", post(rpc_handler))
+		.with_state(mm)
+}
+
+async fn rpc_handler(
+	State(mm): State<ModelManager>,
+	ctx: Ctx,
+	headers: HeaderMap,
+	Json(rpc_req): Json<RpcRequest>,
+) -> Response {
+	// -- Create the RPC Info to be set to the response.extensions.
+	let rpc_info = RpcInfo {
+		id: rpc_req.id.clone(),
+		method: rpc_req.method.clone(),
+	};
+
+	// -- Exec & Store RpcInfo in response.
+	let mut res = match if_match_version(&headers) {
+		Ok(if_match) => _rpc_handler(ctx, mm, rpc_req, if_match)
+			.await
+			.into_response(),
+		Err(err) => err.into_response(),
+	};
+	res.extensions_mut().insert(rpc_info);
+
+	res
+}
+
+/// RPC basic information holding the id and method for further logging.
+#[derive(Debug)]
+pub struct RpcInfo {
+	pub id: Option<Value>,
+	pub method: String,
+}
+
+macro_rules! exec_rpc_fn {
+	($rpc_fn:expr, $ctx:expr, $mm:expr, $rpc_params:expr) => {{
+		let rpc_fn_name = stringify!($rpc_fn);
+
+		let params = $rpc_params.ok_or(Error::RpcMissingParams {
+			rpc_method: rpc_fn_name.to_string(),
+		})?;
+
+		let params = from_value(params).map_err(|_| Error::RpcFailJsonParams {
+			rpc_method: rpc_fn_name.to_string(),
+		})?;
+
+		$rpc_fn($ctx, $mm, params).await.map(to_value)??
+	}};
+}
+
+async fn _rpc_handler(
+	ctx: Ctx,
+	mm: ModelManager,
+	rpc_req: RpcRequest,
+	if_match: Option<i64>,
+) -> Result<Response> {
+	let RpcRequest {
+		id: rpc_id,
+		method: rpc_method,
+		params: rpc_params,
+	} = rpc_req;
+
+	debug!(" {:<12} - _rpc_handler - method: {rpc_method}", "HANDLER");
+
+	// -- The If-Match version is the expected version of the update (or delete).
+	if if_match.is_some() && !IF_MATCH_METHODS.contains(&rpc_method.as_str()) {
+		return Err(Error::IfMatchNotSupported { rpc_method });
+	}
+	let rpc_params = match (rpc_params, if_match) {
+		(Some(Value::Object(mut params)), Some(version)) => {
+			params.insert("version".to_string(), json!(version));
+			Some(Value::Object(params))
+		}
+		(rpc_params, _) => rpc_params,
+	};
+
+	// -- All the queries of the request run with the row level security of the ctx.
+	//    Rolled back on an error (when the transaction is dropped).
+	let mm = mm.new_with_ctx_txn(&ctx).await?;
+
+	let result_json = exec_rpc_method(ctx, mm.clone(), rpc_method, rpc_params)
+		.await
+		.map_err(|err| match err {
+			// Note: A conflict on the If-Match version is a failed precondition.
+			Error::ModelError(model::Error::VersionConflict { entity, id, .. })
+				if if_match.is_some() =>
+			{
+				Error::IfMatchFailed { entity, id }
+			}
+			err => err,
+		})?;
+
+	mm.commit().await?;
+
+	// -- The version of the returned entity, if any, is its ETag.
+	let etag = result_json
+		.get("version")
+		.and_then(Value::as_i64)
+		.and_then(|version| HeaderValue::from_str(&format!("\"{version}\"")).ok());
+
+	let body_response = Json(json!({
+		"id": rpc_id,
+		"result": result_json
+	}));
+
+	let res = match etag {
+		Some(etag) => ([(header::ETAG, etag)], body_response).into_response(),
+		None => body_response.into_response(),
+	};
+
+	Ok(res)
+}
+
+/// Parse the version of the `If-Match` header (e.g., `"3"` or `W/"3"`).
+/// `None` when there is no header, or for `*`.
+fn if_match_version(headers: &HeaderMap) -> Result<Option<i64>> {
+	let Some(if_match) = headers.get(header::IF_MATCH) else {
+		return Ok(None);
+	};
+
+	let if_match = if_match
+		.to_str()
+		.map_err(|_| Error::IfMatchInvalid(format!("{if_match:?}")))?
+		.trim();
+	if if_match == "*" {
+		return Ok(None);
+	}
+
+	if_match
+		.trim_start_matches("W/")
+		.trim_matches('"')
+		.parse::<i64>()
+		.map(Some)
+		.map_err(|_| Error::IfMatchInvalid(if_match.to_string()))
+}
+
+async fn exec_rpc_method(
+	ctx: Ctx,
+	mm: ModelManager,
+	rpc_method: String,
+	rpc_params: Option<Value>,
+) -> Result<Value> {
+	let result_json: Value = match rpc_method.as_str() {
+		// -- Workspace RPC methods.
+		"create_workspace" => exec_rpc_fn!(create_workspace, ctx, mm, rpc_params),
+		"list_my_workspaces" => {
+			exec_rpc_fn!(list_my_workspaces, ctx, mm, rpc_params.or(Some(json!({}))))
+		}
+		"add_workspace_member" => exec_rpc_fn!(add_workspace_member, ctx, mm, rpc_params),
+		"remove_workspace_member" => exec_rpc_fn!(remove_workspace_member, ctx, mm, rpc_params),
+		"list_workspace_members" => exec_rpc_fn!(list_workspace_members, ctx, mm, rpc_params),
+
+		// -- Project RPC methods.
+		"create_project" => exec_rpc_fn!(create_project, ctx, mm, rpc_params),
+		"list_projects" => exec_rpc_fn!(list_projects, ctx, mm, rpc_params.or(Some(json!({})))),
+		"get_project" => exec_rpc_fn!(get_project, ctx, mm, rpc_params),
+		"update_project" => exec_rpc_fn!(update_project, ctx, mm, rpc_params),
+		"delete_project" => exec_rpc_fn!(delete_project, ctx, mm, rpc_params),
+
+		// -- Task RPC methods.
+		"create_task" => exec_rpc_fn!(create_task, ctx, mm, rpc_params),
+		// Note: All the list params are optional.
+		"list_tasks" => exec_rpc_fn!(list_tasks, ctx, mm, rpc_params.or(Some(json!({})))),
+		"list_tasks_page" => {
+			exec_rpc_fn!(list_tasks_page, ctx, mm, rpc_params.or(Some(json!({}))))
+		}
+		"list_tasks_with_tags" => {
+			exec_rpc_fn!(
+				list_tasks_with_tags,
+				ctx,
+				mm,
+				rpc_params.or(Some(json!({})))
+			)
+		}
+		"search_tasks" => exec_rpc_fn!(search_tasks, ctx, mm, rpc_params),
+		"get_task" => exec_rpc_fn!(get_task, ctx, mm, rpc_params),
+		"get_task_with_tags" => exec_rpc_fn!(get_task_with_tags, ctx, mm, rpc_params),
+		"update_task" => exec_rpc_fn!(update_task, ctx, mm, rpc_params),
+		"delete_task" => exec_rpc_fn!(delete_task, ctx, mm, rpc_params),
+		"get_task_subtree" => exec_rpc_fn!(get_task_subtree, ctx, mm, rpc_params),
+		"move_task" => exec_rpc_fn!(move_task, ctx, mm, rpc_params),
+		"list_task_rollups" => exec_rpc_fn!(list_task_rollups, ctx, mm, rpc_params),
+		"move_task_before" => exec_rpc_fn!(move_task_before, ctx, mm, rpc_params),
+		"move_task_after" => exec_rpc_fn!(move_task_after, ctx, mm, rpc_params),
+		"get_task_series" => exec_rpc_fn!(get_task_series, ctx, mm, rpc_params),
+		"update_task_occurrence" => exec_rpc_fn!(update_task_occurrence, ctx, mm, rpc_params),
+		"update_task_series" => exec_rpc_fn!(update_task_series, ctx, mm, rpc_params),
+		"create_tasks" => exec_rpc_fn!(create_tasks, ctx, mm, rpc_params),
+		"update_tasks" => exec_rpc_fn!(update_tasks, ctx, mm, rpc_params),
+		"update_tasks_each" => exec_rpc_fn!(update_tasks_each, ctx, mm, rpc_params),
+		"delete_tasks" => exec_rpc_fn!(delete_tasks, ctx, mm, rpc_params),
+		"list_deleted_tasks" => {
+			exec_rpc_fn!(list_deleted_tasks, ctx, mm, rpc_params.or(Some(json!({}))))
+		}
+		"restore_task" => exec_rpc_fn!(restore_task, ctx, mm, rpc_params),
+		"set_task_tags" => exec_rpc_fn!(set_task_tags, ctx, mm, rpc_params),
+		"add_task_tags" => exec_rpc_fn!(add_task_tags, ctx, mm, rpc_params),
+		"remove_task_tags" => exec_rpc_fn!(remove_task_tags, ctx, mm, rpc_params),
+		"assign_task" => exec_rpc_fn!(assign_task, ctx, mm, rpc_params),
+		"unassign_task" => exec_rpc_fn!(unassign_task, ctx, mm, rpc_params),
+		"list_my_assigned_tasks" => {
+			exec_rpc_fn!(
+				list_my_assigned_tasks,
+				ctx,
+				mm,
+				rpc_params.or(Some(json!({})))
+			)
+		}
+		"watch_task" => exec_rpc_fn!(watch_task, ctx, mm, rpc_params),
+		"unwatch_task" => exec_rpc_fn!(unwatch_task, ctx, mm, rpc_params),
+		"list_task_watchers" => exec_rpc_fn!(list_task_watchers, ctx, mm, rpc_params),
+		"list_my_watched_tasks" => {
+			exec_rpc_fn!(
+				list_my_watched_tasks,
+				ctx,
+				mm,
+				rpc_params.or(Some(json!({})))
+			)
+		}
+
+		// -- Tag RPC methods.
+		"create_tag" => exec_rpc_fn!(create_tag, ctx, mm, rpc_params),
+		"list_tags" => exec_rpc_fn!(list_tags, ctx, mm, rpc_params.or(Some(json!({})))),
+		"rename_tag" => exec_rpc_fn!(rename_tag, ctx, mm, rpc_params),
+		"merge_tags" => exec_rpc_fn!(merge_tags, ctx, mm, rpc_params),
+		"delete_tag" => exec_rpc_fn!(delete_tag, ctx, mm, rpc_params),
+
+		// -- Comment RPC methods.
+		"create_comment" => exec_rpc_fn!(create_comment, ctx, mm, rpc_params),
+		"list_comments" => exec_rpc_fn!(list_comments, ctx, mm, rpc_params.or(Some(json!({})))),
+		"get_comment" => exec_rpc_fn!(get_comment, ctx, mm, rpc_params),
+		"update_comment" => exec_rpc_fn!(update_comment, ctx, mm, rpc_params),
+		"delete_comment" => exec_rpc_fn!(delete_comment, ctx, mm, rpc_params),
+
+		// -- Attachment RPC methods (the contents go through `routes_attachment`).
+		"list_task_attachments" => exec_rpc_fn!(list_task_attachments, ctx, mm, rpc_params),
+		"get_attachment" => exec_rpc_fn!(get_attachment, ctx, mm, rpc_params),
+		"delete_attachment" => exec_rpc_fn!(delete_attachment, ctx, mm, rpc_params),
+
+		// -- Audit RPC methods (admin only).
+		"list_audits" => exec_rpc_fn!(list_audits, ctx, mm, rpc_params.or(Some(json!({})))),
+
+		// -- Cache RPC methods (admin only).
+		"get_cache_stats" => {
+			exec_rpc_fn!(get_cache_stats, ctx, mm, rpc_params.or(Some(json!({}))))
+		}
+
+		// -- Fallback as Err.
+		_ => return Err(Error::RpcMethodUnknown(rpc_method)),
+	};
+
+	Ok(result_json)
+}
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::_dev_utils;
+	use crate::web::mw_auth::{mw_ctx_require, mw_ctx_resolve};
+	use crate::web::mw_res_map::mw_reponse_map;
+	use anyhow::Result;
+	use axum::body::{Body, HttpBody};
+	use axum::http::{Request, StatusCode};
+	use axum::middleware;
+	use tower::ServiceExt;
+	use tower_cookies::CookieManagerLayer;
+
+	// Note: On an in-memory `ModelManager` of their own, so without `#[serial]`.
+
+	#[tokio::test]
+	async fn test_rpc_task_ok() -> Result<()> {
+		let app = fx_app().await;
+
+		// -- Create, with its version as ETag.
+		let params = json!({ "data": { "title": "test_rpc_task_ok 01" } });
+		let (status, etag, body) = fx_rpc(&app, "create_task", params, None).await?;
+		assert_eq!(status, StatusCode::OK);
+		let id = body["result"]["id"].as_i64().unwrap_or_default();
+		assert_eq!(body["result"]["title"], "test_rpc_task_ok 01");
+		let version = body["result"]["version"].as_i64().unwrap_or_default();
+		assert_eq!(etag.as_deref(), Some(format!("\"{version}\"").as_str()));
+
+		// -- Update with the If-Match of the ETag.
+		let params = json!({ "id": id, "data": { "title": "test_rpc_task_ok 02" } });
+		let (status, etag_2, body) = fx_rpc(&app, "update_task", params, etag.as_deref()).await?;
+		assert_eq!(status, StatusCode::OK);
+		assert_eq!(body["result"]["title"], "test_rpc_task_ok 02");
+		assert_ne!(etag_2, etag);
+
+		// -- Get, list.
+		let (status, _, body) = fx_rpc(&app, "get_task", json!({ "id": id }), None).await?;
+		assert_eq!(status, StatusCode::OK);
+		assert_eq!(body["result"]["title"], "test_rpc_task_ok 02");
+		let (status, _, body) = fx_rpc(&app, "list_tasks", json!({}), None).await?;
+		assert_eq!(status, StatusCode::OK);
+		assert!(body["result"]
+			.as_array()
+			.is_some_and(|tasks| tasks.iter().any(|task| task["id"] == id)));
+
+		// -- Delete with the If-Match of the last ETag.
+		let params = json!({ "id": id });
+		let (status, _, _) = fx_rpc(&app, "delete_task", params, etag_2.as_deref()).await?;
+		assert_eq!(status, StatusCode::OK);
+		let (status, _, body) = fx_rpc(&app, "get_task", json!({ "id": id }), None).await?;
+		assert_eq!(status, StatusCode::BAD_REQUEST);
+		assert_eq!(body["error"]["type"], "ENTITY_NOT_FOUND");
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_rpc_task_err_if_match() -> Result<()> {
+		let app = fx_app().await;
+		let params = json!({ "data": { "title": "test_rpc_task_err_if_match 01" } });
+		let (_, etag, body) = fx_rpc(&app, "create_task", params, None).await?;
+		let id = body["result"]["id"].as_i64().unwrap_or_default();
+		let params = json!({ "id": id, "data": { "title": "test_rpc_task_err_if_match 02" } });
+		fx_rpc(&app, "update_task", params, None).await?;
+
+		// -- A stale version is a failed precondition, and nothing is changed.
+		let params = json!({ "id": id, "data": { "title": "test_rpc_task_err_if_match 03" } });
+		let (status, _, body) = fx_rpc(&app, "update_task", params, etag.as_deref()).await?;
+		assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+		assert_eq!(body["error"]["type"], "PRECONDITION_FAILED");
+		let (status, _, _) =
+			fx_rpc(&app, "delete_task", json!({ "id": id }), etag.as_deref()).await?;
+		assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+		let (_, _, body) = fx_rpc(&app, "get_task", json!({ "id": id }), None).await?;
+		assert_eq!(body["result"]["title"], "test_rpc_task_err_if_match 02");
+
+		// -- Not a version.
+		let params = json!({ "id": id, "data": {} });
+		let (status, _, body) = fx_rpc(&app, "update_task", params, Some("abc")).await?;
+		assert_eq!(status, StatusCode::BAD_REQUEST);
+		assert_eq!(body["error"]["type"], "INVALID_PARAMS");
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_rpc_project_ok() -> Result<()> {
+		let app = fx_app().await;
+
+		let params = json!({ "data": { "name": "test_rpc_project_ok 01" } });
+		let (status, etag, body) = fx_rpc(&app, "create_project", params, None).await?;
+		assert_eq!(status, StatusCode::OK);
+		let id = body["result"]["id"].as_i64().unwrap_or_default();
+
+		let params = json!({ "id": id, "data": { "name": "test_rpc_project_ok 02" } });
+		let (status, etag_2, body) =
+			fx_rpc(&app, "update_project", params, etag.as_deref()).await?;
+		assert_eq!(status, StatusCode::OK);
+		assert_eq!(body["result"]["name"], "test_rpc_project_ok 02");
+
+		// -- The stale ETag fails, the last one deletes.
+		let params = json!({ "id": id });
+		let (status, _, _) =
+			fx_rpc(&app, "delete_project", params.clone(), etag.as_deref()).await?;
+		assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+		let (status, _, _) = fx_rpc(&app, "delete_project", params, etag_2.as_deref()).await?;
+		assert_eq!(status, StatusCode::OK);
+		let (status, _, body) = fx_rpc(&app, "get_project", json!({ "id": id }), None).await?;
+		assert_eq!(status, StatusCode::BAD_REQUEST);
+		assert_eq!(body["error"]["type"], "ENTITY_NOT_FOUND");
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_rpc_comment_ok() -> Result<()> {
+		let app = fx_app().await;
+		let params = json!({ "data": { "title": "test_rpc_comment_ok" } });
+		let (_, _, body) = fx_rpc(&app, "create_task", params, None).await?;
+		let task_id = body["result"]["id"].as_i64().unwrap_or_default();
+
+		let params = json!({ "data": { "task_id": task_id, "content": "first" } });
+		let (status, etag, body) = fx_rpc(&app, "create_comment", params, None).await?;
+		assert_eq!(status, StatusCode::OK);
+		assert!(etag.is_none());
+		let id = body["result"]["id"].as_i64().unwrap_or_default();
+
+		let params = json!({ "id": id, "data": { "content": "edited" } });
+		let (status, _, body) = fx_rpc(&app, "update_comment", params, None).await?;
+		assert_eq!(status, StatusCode::OK);
+		assert_eq!(body["result"]["content"], "edited");
+
+		let params = json!({ "filters": { "task_id": { "$eq": task_id } } });
+		let (status, _, body) = fx_rpc(&app, "list_comments", params, None).await?;
+		assert_eq!(status, StatusCode::OK);
+		assert_eq!(body["result"].as_array().map(Vec::len), Some(1));
+
+		// -- Comments are not versioned, If-Match is rejected.
+		let params = json!({ "id": id, "data": { "content": "again" } });
+		let (status, _, body) = fx_rpc(&app, "update_comment", params, Some("\"1\"")).await?;
+		assert_eq!(status, StatusCode::BAD_REQUEST);
+		assert_eq!(body["error"]["type"], "INVALID_PARAMS");
+
+		let (status, _, _) = fx_rpc(&app, "delete_comment", json!({ "id": id }), None).await?;
+		assert_eq!(status, StatusCode::OK);
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_rpc_err_params() -> Result<()> {
+		let app = fx_app().await;
+
+		let (status, _, body) = fx_rpc(&app, "test_rpc_err_params", json!({}), None).await?;
+		assert_eq!(status, StatusCode::BAD_REQUEST);
+		assert_eq!(body["error"]["type"], "INVALID_PARAMS");
+
+		let params = json!({ "data": { "name": "missing the title" } });
+		let (status, _, body) = fx_rpc(&app, "create_task", params, None).await?;
+		assert_eq!(status, StatusCode::BAD_REQUEST);
+		assert_eq!(body["error"]["type"], "INVALID_PARAMS");
+
+		Ok(())
+	}
+
+	/// The rpc routes with the middlewares of `main`.
+	async fn fx_app() -> Router {
+		let mm = _dev_utils::init_test_sqlite().await;
+
+		Router::new()
+			.nest(
+				"/api",
+				routes(mm.clone()).route_layer(middleware::from_fn(mw_ctx_require)),
+			)
+			.layer(middleware::map_response(mw_reponse_map))
+			.layer(middleware::from_fn_with_state(mm, mw_ctx_resolve))
+			.layer(CookieManagerLayer::new())
+	}
+
+	/// The status, the ETag and the json body of the response of the rpc call.
+	async fn fx_rpc(
+		app: &Router,
+		method: &str,
+		params: Value,
+		if_match: Option<&str>,
+	) -> Result<(StatusCode, Option<String>, Value)> {
+		let body = json!({ "id": 1, "method": method, "params": params });
+		let mut req = Request::post("/api/rpc").header(header::CONTENT_TYPE, "application/json");
+		if let Some(if_match) = if_match {
+			req = req.header(header::IF_MATCH, if_match);
+		}
+		let req = req.body(Body::from(body.to_string()))?;
+
+		let res = app.clone().oneshot(req).await?;
+
+		let status = res.status();
+		let etag = res
+			.headers()
+			.get(header::ETAG)
+			.and_then(|val| val.to_str().ok())
+			.map(String::from);
+		let mut res_body = res.into_body();
+		let mut body = Vec::new();
+		while let Some(chunk) = res_body.data().await {
+			body.extend_from_slice(&chunk?);
+		}
+		let body = serde_json::from_slice(&body)?;
+
+		Ok((status, etag, body))
+	}
+}
+// endregion: --- Tests
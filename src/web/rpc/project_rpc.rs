@@ -0,0 +1,76 @@
+use crate::ctx::Ctx;
+use crate::model::project::{
+	Project, ProjectBmc, ProjectFilter, ProjectForCreate, ProjectForUpdate,
+};
+use crate::model::ModelManager;
+use crate::web::Result;
+
+use super::{ParamsForCreate, ParamsForDelete, ParamsForUpdate, ParamsIded, ParamsList};
+
+pub async fn create_project(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsForCreate<ProjectForCreate>,
+) -> Result<Project> {
+	let ParamsForCreate { data } = params;
+
+	let id = ProjectBmc::create(&ctx, &mm, data).await?;
+	let project = ProjectBmc::get(&ctx, &mm, id).await?;
+
+	Ok(project)
+}
+
+pub async fn list_projects(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsList<ProjectFilter>,
+) -> Result<Vec<Project>> {
+	let ParamsList {
+		filters,
+		list_options,
+	} = params;
+
+	let projects = ProjectBmc::list(&ctx, &mm, filters, list_options).await?;
+
+	Ok(projects)
+}
+
+pub async fn get_project(ctx: Ctx, mm: ModelManager, params: ParamsIded) -> Result<Project> {
+	let ParamsIded { id } = params;
+
+	let project = ProjectBmc::get(&ctx, &mm, id).await?;
+
+	Ok(project)
+}
+
+pub async fn update_project(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsForUpdate<ProjectForUpdate>,
+) -> Result<Project> {
+	let ParamsForUpdate { id, version, data } = params;
+
+	match version {
+		Some(version) => ProjectBmc::update_with_version(&ctx, &mm, id, version, data).await?,
+		None => ProjectBmc::update(&ctx, &mm, id, data).await?,
+	}
+	let project = ProjectBmc::get(&ctx, &mm, id).await?;
+
+	Ok(project)
+}
+
+pub async fn delete_project(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsForDelete,
+) -> Result<Project> {
+	let ParamsForDelete { id, version } = params;
+
+	let project = ProjectBmc::get(&ctx, &mm, id).await?;
+	match version {
+		Some(version) => ProjectBmc::delete_with_version(&ctx, &mm, id, version).await?,
+		None => ProjectBmc::delete(&ctx, &mm, id).await?,
+	}
+
+	Ok(project)
+}
@@ -0,0 +1,76 @@
+use serde::Deserialize;
+
+use crate::ctx::Ctx;
+use crate::model::tag::{Tag, TagBmc, TagFilter, TagForCreate};
+use crate::model::ModelManager;
+use crate::web::Result;
+
+use super::{ParamsForCreate, ParamsIded, ParamsList};
+
+#[derive(Deserialize)]
+pub struct ParamsForRename {
+	id: i64,
+	name: String,
+}
+
+#[derive(Deserialize)]
+pub struct ParamsForMerge {
+	from_id: i64,
+	into_id: i64,
+}
+
+pub async fn create_tag(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsForCreate<TagForCreate>,
+) -> Result<Tag> {
+	let ParamsForCreate { data } = params;
+
+	let id = TagBmc::create(&ctx, &mm, data).await?;
+	let tag = TagBmc::get(&ctx, &mm, id).await?;
+
+	Ok(tag)
+}
+
+pub async fn list_tags(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsList<TagFilter>,
+) -> Result<Vec<Tag>> {
+	let ParamsList {
+		filters,
+		list_options,
+	} = params;
+
+	let tags = TagBmc::list(&ctx, &mm, filters, list_options).await?;
+
+	Ok(tags)
+}
+
+pub async fn rename_tag(ctx: Ctx, mm: ModelManager, params: ParamsForRename) -> Result<Tag> {
+	let ParamsForRename { id, name } = params;
+
+	TagBmc::rename(&ctx, &mm, id, &name).await?;
+	let tag = TagBmc::get(&ctx, &mm, id).await?;
+
+	Ok(tag)
+}
+
+/// Returns the tag the tasks were merged into.
+pub async fn merge_tags(ctx: Ctx, mm: ModelManager, params: ParamsForMerge) -> Result<Tag> {
+	let ParamsForMerge { from_id, into_id } = params;
+
+	TagBmc::merge(&ctx, &mm, from_id, into_id).await?;
+	let tag = TagBmc::get(&ctx, &mm, into_id).await?;
+
+	Ok(tag)
+}
+
+pub async fn delete_tag(ctx: Ctx, mm: ModelManager, params: ParamsIded) -> Result<Tag> {
+	let ParamsIded { id } = params;
+
+	let tag = TagBmc::get(&ctx, &mm, id).await?;
+	TagBmc::delete(&ctx, &mm, id).await?;
+
+	Ok(tag)
+}
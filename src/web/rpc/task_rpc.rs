@@ -0,0 +1,463 @@
+use serde::Deserialize;
+
+use crate::ctx::Ctx;
+use crate::model::task::{
+	Task, TaskBmc, TaskFilter, TaskForCreate, TaskForUpdate, TaskInTree, TaskRollup, TaskSearchHit,
+	TaskWithTags,
+};
+use crate::model::task_series::{TaskSeries, TaskSeriesForUpdate};
+use crate::model::{BulkResult, ListOptions, ModelManager, Page};
+use crate::web::Result;
+
+use super::{
+	ParamsForCreate, ParamsForCreateMany, ParamsForDelete, ParamsForUpdate, ParamsForUpdateEach,
+	ParamsForUpdateMany, ParamsIded, ParamsIdedData, ParamsIds, ParamsList, ParamsPage,
+};
+
+#[derive(Deserialize)]
+pub struct ParamsTags {
+	id: i64,
+	tag_ids: Vec<i64>,
+}
+
+/// `parent_id` is `null` (or missing) to move the task to the top level.
+#[derive(Deserialize)]
+pub struct ParamsMove {
+	id: i64,
+	parent_id: Option<i64>,
+}
+
+/// The task to move next to, in the rank order.
+#[derive(Deserialize)]
+pub struct ParamsMoveNextTo {
+	id: i64,
+	target_id: i64,
+}
+
+#[derive(Deserialize)]
+pub struct ParamsAssign {
+	id: i64,
+	user_id: i64,
+}
+
+#[derive(Deserialize)]
+pub struct ParamsSearch {
+	query: String,
+	list_options: Option<ListOptions>,
+}
+
+pub async fn create_task(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsForCreate<TaskForCreate>,
+) -> Result<Task> {
+	let ParamsForCreate { data } = params;
+
+	let id = TaskBmc::create(&ctx, &mm, data).await?;
+	let task = TaskBmc::get(&ctx, &mm, id).await?;
+
+	Ok(task)
+}
+
+pub async fn list_tasks(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsList<TaskFilter>,
+) -> Result<Vec<Task>> {
+	let ParamsList {
+		filters,
+		list_options,
+	} = params;
+
+	let tasks = TaskBmc::list(&ctx, &mm, filters, list_options).await?;
+
+	Ok(tasks)
+}
+
+pub async fn list_tasks_with_tags(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsList<TaskFilter>,
+) -> Result<Vec<TaskWithTags>> {
+	let ParamsList {
+		filters,
+		list_options,
+	} = params;
+
+	let tasks = TaskBmc::list_with_tags(&ctx, &mm, filters, list_options).await?;
+
+	Ok(tasks)
+}
+
+pub async fn list_tasks_page(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsPage<TaskFilter>,
+) -> Result<Page<Task>> {
+	let ParamsPage {
+		filters,
+		page_options,
+	} = params;
+
+	let page = TaskBmc::list_page(&ctx, &mm, filters, page_options).await?;
+
+	Ok(page)
+}
+
+pub async fn search_tasks(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsSearch,
+) -> Result<Vec<TaskSearchHit>> {
+	let ParamsSearch {
+		query,
+		list_options,
+	} = params;
+
+	let hits = TaskBmc::search(&ctx, &mm, &query, list_options).await?;
+
+	Ok(hits)
+}
+
+pub async fn get_task(ctx: Ctx, mm: ModelManager, params: ParamsIded) -> Result<Task> {
+	let ParamsIded { id } = params;
+
+	let task = TaskBmc::get(&ctx, &mm, id).await?;
+
+	Ok(task)
+}
+
+pub async fn get_task_with_tags(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsIded,
+) -> Result<TaskWithTags> {
+	let ParamsIded { id } = params;
+
+	let task = TaskBmc::get_with_tags(&ctx, &mm, id).await?;
+
+	Ok(task)
+}
+
+pub async fn update_task(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsForUpdate<TaskForUpdate>,
+) -> Result<Task> {
+	let ParamsForUpdate { id, version, data } = params;
+
+	match version {
+		Some(version) => TaskBmc::update_with_version(&ctx, &mm, id, version, data).await?,
+		None => TaskBmc::update(&ctx, &mm, id, data).await?,
+	}
+	let task = TaskBmc::get(&ctx, &mm, id).await?;
+
+	Ok(task)
+}
+
+pub async fn delete_task(ctx: Ctx, mm: ModelManager, params: ParamsForDelete) -> Result<Task> {
+	let ParamsForDelete { id, version } = params;
+
+	let task = TaskBmc::get(&ctx, &mm, id).await?;
+	match version {
+		Some(version) => TaskBmc::delete_with_version(&ctx, &mm, id, version).await?,
+		None => TaskBmc::delete(&ctx, &mm, id).await?,
+	}
+
+	Ok(task)
+}
+
+// region:    --- Subtasks
+
+pub async fn get_task_subtree(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsIded,
+) -> Result<Vec<TaskInTree>> {
+	let ParamsIded { id } = params;
+
+	let tasks = TaskBmc::get_subtree(&ctx, &mm, id).await?;
+
+	Ok(tasks)
+}
+
+pub async fn move_task(ctx: Ctx, mm: ModelManager, params: ParamsMove) -> Result<Task> {
+	let ParamsMove { id, parent_id } = params;
+
+	TaskBmc::move_subtree(&ctx, &mm, id, parent_id).await?;
+	let task = TaskBmc::get(&ctx, &mm, id).await?;
+
+	Ok(task)
+}
+
+pub async fn list_task_rollups(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsIds,
+) -> Result<Vec<TaskRollup>> {
+	let ParamsIds { ids } = params;
+
+	let rollups = TaskBmc::list_rollups(&ctx, &mm, &ids).await?;
+
+	Ok(rollups)
+}
+
+// endregion: --- Subtasks
+
+// region:    --- Rank
+
+pub async fn move_task_before(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsMoveNextTo,
+) -> Result<Task> {
+	let ParamsMoveNextTo { id, target_id } = params;
+
+	TaskBmc::move_before(&ctx, &mm, id, target_id).await?;
+	let task = TaskBmc::get(&ctx, &mm, id).await?;
+
+	Ok(task)
+}
+
+pub async fn move_task_after(ctx: Ctx, mm: ModelManager, params: ParamsMoveNextTo) -> Result<Task> {
+	let ParamsMoveNextTo { id, target_id } = params;
+
+	TaskBmc::move_after(&ctx, &mm, id, target_id).await?;
+	let task = TaskBmc::get(&ctx, &mm, id).await?;
+
+	Ok(task)
+}
+
+// endregion: --- Rank
+
+// region:    --- Recurrence
+
+pub async fn get_task_series(ctx: Ctx, mm: ModelManager, params: ParamsIded) -> Result<TaskSeries> {
+	let ParamsIded { id } = params;
+
+	let series = TaskBmc::get_series(&ctx, &mm, id).await?;
+
+	Ok(series)
+}
+
+/// Edit this occurrence only (see `update_task_series` for the whole series).
+pub async fn update_task_occurrence(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsIdedData<TaskForUpdate>,
+) -> Result<Task> {
+	let ParamsIdedData { id, data } = params;
+
+	TaskBmc::update_occurrence(&ctx, &mm, id, data).await?;
+	let task = TaskBmc::get(&ctx, &mm, id).await?;
+
+	Ok(task)
+}
+
+/// `id` is the id of an occurrence of the series.
+pub async fn update_task_series(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsIdedData<TaskSeriesForUpdate>,
+) -> Result<TaskSeries> {
+	let ParamsIdedData { id, data } = params;
+
+	TaskBmc::update_series(&ctx, &mm, id, data).await?;
+	let series = TaskBmc::get_series(&ctx, &mm, id).await?;
+
+	Ok(series)
+}
+
+// endregion: --- Recurrence
+
+// region:    --- Assignment
+
+pub async fn assign_task(ctx: Ctx, mm: ModelManager, params: ParamsAssign) -> Result<Task> {
+	let ParamsAssign { id, user_id } = params;
+
+	TaskBmc::assign(&ctx, &mm, id, user_id).await?;
+	let task = TaskBmc::get(&ctx, &mm, id).await?;
+
+	Ok(task)
+}
+
+pub async fn unassign_task(ctx: Ctx, mm: ModelManager, params: ParamsIded) -> Result<Task> {
+	let ParamsIded { id } = params;
+
+	TaskBmc::unassign(&ctx, &mm, id).await?;
+	let task = TaskBmc::get(&ctx, &mm, id).await?;
+
+	Ok(task)
+}
+
+pub async fn list_my_assigned_tasks(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsList<TaskFilter>,
+) -> Result<Vec<Task>> {
+	let ParamsList {
+		filters,
+		list_options,
+	} = params;
+
+	let tasks = TaskBmc::list_assigned_to_me(&ctx, &mm, filters, list_options).await?;
+
+	Ok(tasks)
+}
+
+/// Returns the ids of the watchers of the task.
+pub async fn watch_task(ctx: Ctx, mm: ModelManager, params: ParamsIded) -> Result<Vec<i64>> {
+	let ParamsIded { id } = params;
+
+	TaskBmc::watch(&ctx, &mm, id).await?;
+	let user_ids = TaskBmc::list_watcher_ids(&ctx, &mm, id).await?;
+
+	Ok(user_ids)
+}
+
+/// Returns the ids of the watchers of the task.
+pub async fn unwatch_task(ctx: Ctx, mm: ModelManager, params: ParamsIded) -> Result<Vec<i64>> {
+	let ParamsIded { id } = params;
+
+	TaskBmc::unwatch(&ctx, &mm, id).await?;
+	let user_ids = TaskBmc::list_watcher_ids(&ctx, &mm, id).await?;
+
+	Ok(user_ids)
+}
+
+pub async fn list_task_watchers(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsIded,
+) -> Result<Vec<i64>> {
+	let ParamsIded { id } = params;
+
+	let user_ids = TaskBmc::list_watcher_ids(&ctx, &mm, id).await?;
+
+	Ok(user_ids)
+}
+
+pub async fn list_my_watched_tasks(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsList<TaskFilter>,
+) -> Result<Vec<Task>> {
+	let ParamsList {
+		filters,
+		list_options,
+	} = params;
+
+	let tasks = TaskBmc::list_watched_by_me(&ctx, &mm, filters, list_options).await?;
+
+	Ok(tasks)
+}
+
+// endregion: --- Assignment
+
+// region:    --- Bulk
+
+/// Returns the ids of the created tasks, in the order of `data`.
+pub async fn create_tasks(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsForCreateMany<TaskForCreate>,
+) -> Result<Vec<i64>> {
+	let ParamsForCreateMany { data } = params;
+
+	let ids = TaskBmc::create_many(&ctx, &mm, data).await?;
+
+	Ok(ids)
+}
+
+pub async fn update_tasks(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsForUpdateMany<TaskForUpdate>,
+) -> Result<BulkResult> {
+	let ParamsForUpdateMany { ids, data } = params;
+
+	let res = TaskBmc::update_many(&ctx, &mm, &ids, data).await?;
+
+	Ok(res)
+}
+
+pub async fn update_tasks_each(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsForUpdateEach<TaskForUpdate>,
+) -> Result<BulkResult> {
+	let ParamsForUpdateEach { items } = params;
+
+	let patches = items
+		.into_iter()
+		.map(|ParamsIdedData { id, data }| (id, data))
+		.collect();
+	let res = TaskBmc::update_each(&ctx, &mm, patches).await?;
+
+	Ok(res)
+}
+
+pub async fn delete_tasks(ctx: Ctx, mm: ModelManager, params: ParamsIds) -> Result<BulkResult> {
+	let ParamsIds { ids } = params;
+
+	let res = TaskBmc::delete_many(&ctx, &mm, &ids).await?;
+
+	Ok(res)
+}
+
+// endregion: --- Bulk
+
+pub async fn set_task_tags(ctx: Ctx, mm: ModelManager, params: ParamsTags) -> Result<TaskWithTags> {
+	let ParamsTags { id, tag_ids } = params;
+
+	TaskBmc::set_tags(&ctx, &mm, id, &tag_ids).await?;
+	let task = TaskBmc::get_with_tags(&ctx, &mm, id).await?;
+
+	Ok(task)
+}
+
+pub async fn add_task_tags(ctx: Ctx, mm: ModelManager, params: ParamsTags) -> Result<TaskWithTags> {
+	let ParamsTags { id, tag_ids } = params;
+
+	TaskBmc::add_tags(&ctx, &mm, id, &tag_ids).await?;
+	let task = TaskBmc::get_with_tags(&ctx, &mm, id).await?;
+
+	Ok(task)
+}
+
+pub async fn remove_task_tags(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsTags,
+) -> Result<TaskWithTags> {
+	let ParamsTags { id, tag_ids } = params;
+
+	TaskBmc::remove_tags(&ctx, &mm, id, &tag_ids).await?;
+	let task = TaskBmc::get_with_tags(&ctx, &mm, id).await?;
+
+	Ok(task)
+}
+
+pub async fn list_deleted_tasks(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsList<TaskFilter>,
+) -> Result<Vec<Task>> {
+	let ParamsList {
+		filters,
+		list_options,
+	} = params;
+
+	let tasks = TaskBmc::list_deleted(&ctx, &mm, filters, list_options).await?;
+
+	Ok(tasks)
+}
+
+pub async fn restore_task(ctx: Ctx, mm: ModelManager, params: ParamsIded) -> Result<Task> {
+	let ParamsIded { id } = params;
+
+	TaskBmc::restore(&ctx, &mm, id).await?;
+	let task = TaskBmc::get(&ctx, &mm, id).await?;
+
+	Ok(task)
+}
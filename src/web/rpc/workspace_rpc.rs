@@ -0,0 +1,80 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::ctx::Ctx;
+use crate::model::workspace::{Workspace, WorkspaceBmc, WorkspaceForCreate};
+use crate::model::ModelManager;
+use crate::web::Result;
+
+use super::{ParamsForCreate, ParamsIded};
+
+#[derive(Deserialize)]
+pub struct ParamsMember {
+	id: i64,
+	user_id: i64,
+}
+
+/// Note: The new workspace is chosen with the `X-Workspace-Id` header
+///       (the default one is the oldest of the user).
+pub async fn create_workspace(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsForCreate<WorkspaceForCreate>,
+) -> Result<Workspace> {
+	let ParamsForCreate { data } = params;
+
+	let id = WorkspaceBmc::create(&ctx, &mm, data).await?;
+	let workspace = WorkspaceBmc::get(&ctx, &mm, id).await?;
+
+	Ok(workspace)
+}
+
+pub async fn list_my_workspaces(
+	ctx: Ctx,
+	mm: ModelManager,
+	_params: Value,
+) -> Result<Vec<Workspace>> {
+	let workspaces = WorkspaceBmc::list_mine(&ctx, &mm).await?;
+
+	Ok(workspaces)
+}
+
+/// Returns the ids of the members of the workspace.
+pub async fn add_workspace_member(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsMember,
+) -> Result<Vec<i64>> {
+	let ParamsMember { id, user_id } = params;
+
+	WorkspaceBmc::add_member(&ctx, &mm, id, user_id).await?;
+	let user_ids = WorkspaceBmc::list_member_ids(&ctx, &mm, id).await?;
+
+	Ok(user_ids)
+}
+
+/// Returns the ids of the members of the workspace.
+pub async fn remove_workspace_member(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsMember,
+) -> Result<Vec<i64>> {
+	let ParamsMember { id, user_id } = params;
+
+	WorkspaceBmc::remove_member(&ctx, &mm, id, user_id).await?;
+	let user_ids = WorkspaceBmc::list_member_ids(&ctx, &mm, id).await?;
+
+	Ok(user_ids)
+}
+
+pub async fn list_workspace_members(
+	ctx: Ctx,
+	mm: ModelManager,
+	params: ParamsIded,
+) -> Result<Vec<i64>> {
+	let ParamsIded { id } = params;
+
+	let user_ids = WorkspaceBmc::list_member_ids(&ctx, &mm, id).await?;
+
+	Ok(user_ids)
+}